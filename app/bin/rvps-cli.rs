@@ -0,0 +1,407 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Command-line client of Reference Value Provider Service.
+//! It either drives an embedded RVPS Core over a local-fs Cache,
+//! or talks to a running RVPS server via the gRPC API.
+
+use std::{
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use reference_value_provider_service::{
+    cache::local_fs::LocalFsCache, extractors::extractor_modules::in_toto::ProvenanceBuilder,
+    Config, Core, Message, ReferenceValue, RVPSAPI,
+};
+use rvps::provenance_api::configure_core;
+use rvps::provenance_api::grpc::proto::{
+    self, reference_value_provider_service_client::ReferenceValueProviderServiceClient,
+    DeleteReferenceValueRequest, GetReferenceValueRequest, ListReferenceValuesRequest,
+    VerifyAndExtractRequest,
+};
+use tonic::{transport::Channel, Code};
+
+/// Client of Reference Value Provider Service
+#[derive(Parser)]
+#[clap(version, about)]
+struct Cli {
+    /// Address of a running RVPS gRPC API, e.g. http://127.0.0.1:50003.
+    /// If not given, an embedded RVPS Core is used.
+    #[clap(long, global = true)]
+    server: Option<String>,
+
+    /// File storing reference values of the embedded RVPS Core
+    #[clap(long, global = true, default_value = "rvps-cache.json")]
+    cache_path: PathBuf,
+
+    /// Path to the JSON config file of the embedded RVPS Core, e.g.
+    /// with the configuration of the Extractors
+    #[clap(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Output format
+    #[clap(long, short, global = true, value_enum, default_value = "table")]
+    output: OutputFormat,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Verify a provenance Message and register the extracted reference values
+    Register {
+        /// File containing the Message in JSON. Read from stdin if not
+        /// given or given as "-".
+        file: Option<PathBuf>,
+    },
+
+    /// Get a reference value by the artifact's name
+    Get { name: String },
+
+    /// List reference values
+    List {
+        /// Only list reference values whose name starts with this
+        #[clap(long)]
+        name_prefix: Option<String>,
+    },
+
+    /// Export all the reference values as a JSON array
+    Export {
+        /// File to write to. Write to stdout if not given.
+        #[clap(long, short)]
+        file: Option<PathBuf>,
+    },
+
+    /// Delete a reference value by the artifact's name
+    Delete { name: String },
+
+    /// Package a directory of in-toto metadata into a Message
+    PackageInToto {
+        /// Directory containing the .layout, .pub, .link files
+        /// and the artifacts to be inspected
+        dir: PathBuf,
+
+        /// Only package files matching the glob, e.g. "*.link".
+        /// Can be given multiple times. All files are packaged
+        /// if not given.
+        #[clap(long)]
+        include: Vec<String>,
+
+        /// Do not package files matching the glob. Can be given
+        /// multiple times.
+        #[clap(long)]
+        exclude: Vec<String>,
+
+        /// Do not normalize CRLF line separators to LF when
+        /// calculating digests
+        #[clap(long)]
+        no_line_normalization: bool,
+
+        /// File to write the Message to. Write to stdout if not given.
+        #[clap(long, short)]
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Json,
+    Table,
+}
+
+/// Where the requests go.
+enum Backend {
+    Embedded(Core<LocalFsCache>),
+    Remote(ReferenceValueProviderServiceClient<Channel>),
+}
+
+impl Backend {
+    async fn new(cli: &Cli) -> Result<Self> {
+        match &cli.server {
+            Some(server) => {
+                let client = ReferenceValueProviderServiceClient::connect(server.clone()).await?;
+                Ok(Backend::Remote(client))
+            }
+            None => {
+                let mut core = Core::new(LocalFsCache::new(&cli.cache_path)?);
+                if let Some(path) = &cli.config {
+                    let config = Config::from_file(path)
+                        .map_err(|e| anyhow!("Load config {} failed: {}", path.display(), e))?;
+                    configure_core(&mut core, config)?;
+                }
+                Ok(Backend::Embedded(core))
+            }
+        }
+    }
+
+    async fn register(&mut self, message: Message) -> Result<Vec<ReferenceValue>> {
+        match self {
+            Backend::Embedded(core) => core.verify_and_extract(message).map(|(rvs, _)| rvs),
+            Backend::Remote(client) => {
+                let request = VerifyAndExtractRequest {
+                    message: Some(proto::Message {
+                        version: message.version().clone(),
+                        r#type: message.typ().clone(),
+                        payload: message.payload().clone(),
+                    }),
+                };
+                client
+                    .verify_and_extract(request)
+                    .await?
+                    .into_inner()
+                    .reference_values
+                    .into_iter()
+                    .map(|rv| rv.try_into())
+                    .collect()
+            }
+        }
+    }
+
+    async fn get(&mut self, name: &str) -> Result<Option<ReferenceValue>> {
+        match self {
+            Backend::Embedded(core) => core.get_rv(name),
+            Backend::Remote(client) => {
+                let request = GetReferenceValueRequest { name: name.into() };
+                match client.get_reference_value(request).await {
+                    Ok(response) => response
+                        .into_inner()
+                        .reference_value
+                        .map(|rv| rv.try_into())
+                        .transpose(),
+                    Err(status) if status.code() == Code::NotFound => Ok(None),
+                    Err(status) => Err(status.into()),
+                }
+            }
+        }
+    }
+
+    async fn list(&mut self) -> Result<Vec<ReferenceValue>> {
+        let mut rvs = match self {
+            Backend::Embedded(core) => core.list_rvs()?,
+            Backend::Remote(client) => client
+                .list_reference_values(ListReferenceValuesRequest {})
+                .await?
+                .into_inner()
+                .reference_values
+                .into_iter()
+                .map(|rv| rv.try_into())
+                .collect::<Result<_>>()?,
+        };
+        rvs.sort_by(|a, b| a.name().cmp(b.name()));
+        Ok(rvs)
+    }
+
+    async fn delete(&mut self, name: &str) -> Result<Option<ReferenceValue>> {
+        match self {
+            Backend::Embedded(core) => core.delete_rv(name),
+            Backend::Remote(client) => {
+                let request = DeleteReferenceValueRequest { name: name.into() };
+                match client.delete_reference_value(request).await {
+                    Ok(response) => response
+                        .into_inner()
+                        .reference_value
+                        .map(|rv| rv.try_into())
+                        .transpose(),
+                    Err(status) if status.code() == Code::NotFound => Ok(None),
+                    Err(status) => Err(status.into()),
+                }
+            }
+        }
+    }
+}
+
+/// Print reference values as a table, one reference value per row.
+fn print_table(rvs: &[ReferenceValue]) {
+    let header = [
+        "NAME".to_string(),
+        "VERSION".to_string(),
+        "EXPIRED".to_string(),
+        "HASH-VALUE".to_string(),
+    ];
+    let rows: Vec<[String; 4]> = rvs
+        .iter()
+        .map(|rv| {
+            let hash_values: Vec<String> = rv
+                .hash_values()
+                .iter()
+                .map(|pair| format!("{}:{}", pair.alg(), pair.value()))
+                .collect();
+            [
+                rv.name().clone(),
+                rv.version().clone(),
+                rv.expired().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+                hash_values.join(","),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.clone().map(|h| h.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
+
+/// Print reference values in the given format.
+fn print_rvs(rvs: &[ReferenceValue], output: OutputFormat) -> Result<()> {
+    match output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(rvs)?),
+        OutputFormat::Table => print_table(rvs),
+    }
+    Ok(())
+}
+
+/// Print a reference value in the given format.
+fn print_rv(rv: &ReferenceValue, output: OutputFormat) -> Result<()> {
+    match output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(rv)?),
+        OutputFormat::Table => print_table(std::slice::from_ref(rv)),
+    }
+    Ok(())
+}
+
+/// Read a Message from the file, or from stdin.
+fn read_message(file: &Option<PathBuf>) -> Result<Message> {
+    let content = match file {
+        Some(path) if path.as_os_str() != "-" => fs::read_to_string(path)?,
+        _ => {
+            let mut content = String::new();
+            io::stdin().read_to_string(&mut content)?;
+            content
+        }
+    };
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Package the in-toto directory into a Message, and write it.
+fn package_in_toto(
+    dir: &PathBuf,
+    includes: &[String],
+    excludes: &[String],
+    line_normalization: bool,
+    file: &Option<PathBuf>,
+) -> Result<()> {
+    let mut builder = ProvenanceBuilder::new(dir).line_normalization(line_normalization);
+    for pattern in includes {
+        builder = builder.include(pattern)?;
+    }
+    for pattern in excludes {
+        builder = builder.exclude(pattern)?;
+    }
+
+    let content = serde_json::to_string_pretty(&builder.build_message()?)?;
+    match file {
+        Some(path) => fs::write(path, content)?,
+        None => println!("{}", content),
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init_from_env(env_logger::Env::new().default_filter_or("warn"));
+    let cli = Cli::parse();
+
+    // Packaging needs no RVPS at all
+    if let Command::PackageInToto {
+        dir,
+        include,
+        exclude,
+        no_line_normalization,
+        file,
+    } = &cli.command
+    {
+        return package_in_toto(dir, include, exclude, !no_line_normalization, file);
+    }
+
+    let mut backend = Backend::new(&cli).await?;
+
+    match &cli.command {
+        Command::Register { file } => {
+            let message = read_message(file)?;
+            let rvs = backend.register(message).await?;
+            print_rvs(&rvs, cli.output)?;
+        }
+        Command::Get { name } => {
+            let rv = backend
+                .get(name)
+                .await?
+                .ok_or_else(|| anyhow!("Reference value {} not found", name))?;
+            print_rv(&rv, cli.output)?;
+        }
+        Command::List { name_prefix } => {
+            let rvs: Vec<ReferenceValue> = backend
+                .list()
+                .await?
+                .into_iter()
+                .filter(|rv| {
+                    name_prefix
+                        .as_ref()
+                        .map_or(true, |prefix| rv.name().starts_with(prefix))
+                })
+                .collect();
+            print_rvs(&rvs, cli.output)?;
+        }
+        Command::Export { file } => {
+            let rvs = backend.list().await?;
+            let content = serde_json::to_string_pretty(&rvs)?;
+            match file {
+                Some(path) => fs::write(path, content)?,
+                None => println!("{}", content),
+            }
+        }
+        Command::Delete { name } => {
+            let rv = backend
+                .delete(name)
+                .await?
+                .ok_or_else(|| anyhow!("Reference value {} not found", name))?;
+            print_rv(&rv, cli.output)?;
+        }
+        Command::PackageInToto { .. } => unreachable!("handled without a backend"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn line_normalization(args: &[&str]) -> bool {
+        match Cli::try_parse_from(args).unwrap().command {
+            Command::PackageInToto {
+                no_line_normalization,
+                ..
+            } => !no_line_normalization,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_package_in_toto_line_normalization() {
+        assert!(line_normalization(&["rvps-cli", "package-in-toto", "dir"]));
+        assert!(!line_normalization(&[
+            "rvps-cli",
+            "package-in-toto",
+            "dir",
+            "--no-line-normalization",
+        ]));
+    }
+}
@@ -0,0 +1,110 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Reference Value Provider Service server.
+
+use std::{net::SocketAddr, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use log::info;
+use reference_value_provider_service::Config;
+use rvps::provenance_api::{grpc, new_configured_shared_core, rest, CacheType};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::watch,
+};
+
+/// Reference Value Provider Service
+#[derive(Parser)]
+#[clap(version, about)]
+struct Cli {
+    /// Address the gRPC API listens on
+    #[clap(long, default_value = "127.0.0.1:50003")]
+    grpc_addr: SocketAddr,
+
+    /// Address the REST API listens on. The REST API is
+    /// disabled if not given.
+    #[clap(long)]
+    rest_addr: Option<SocketAddr>,
+
+    /// Type of the Cache storing reference values
+    #[clap(long, value_enum, default_value = "simple")]
+    cache: CacheType,
+
+    /// Path to the file storing reference values, needed by
+    /// the local-fs Cache
+    #[clap(long)]
+    cache_path: Option<PathBuf>,
+
+    /// Path to the JSON config file, e.g. with the configuration of
+    /// the Extractors
+    #[clap(long)]
+    config: Option<PathBuf>,
+
+    /// Print the OpenAPI document of the REST API and exit
+    #[clap(long)]
+    print_openapi: bool,
+}
+
+/// Wait for SIGINT or SIGTERM.
+async fn shutdown_signal() {
+    let mut sigterm = signal(SignalKind::terminate()).expect("register SIGTERM handler");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {},
+        _ = sigterm.recv() => {},
+    }
+    info!("Shutdown signal received");
+}
+
+/// Wait until the shutdown is triggered.
+async fn wait_shutdown(mut shutdown: watch::Receiver<bool>) {
+    while !*shutdown.borrow() {
+        if shutdown.changed().await.is_err() {
+            break;
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+    let cli = Cli::parse();
+
+    if cli.print_openapi {
+        println!("{}", serde_json::to_string_pretty(&rest::openapi())?);
+        return Ok(());
+    }
+
+    let config = match &cli.config {
+        Some(path) => Config::from_file(path)
+            .map_err(|e| anyhow!("Load config {} failed: {}", path.display(), e))?,
+        None => Config::default(),
+    };
+    let core = new_configured_shared_core(cli.cache, cli.cache_path.as_deref(), config)?;
+
+    // All the front ends shut down together
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        let _ = shutdown_tx.send(true);
+    });
+
+    let grpc_server = grpc::serve(
+        core.clone(),
+        cli.grpc_addr,
+        wait_shutdown(shutdown_rx.clone()),
+    );
+    match cli.rest_addr {
+        Some(rest_addr) => {
+            let listener = std::net::TcpListener::bind(rest_addr)?;
+            let rest_server = rest::serve_with_listener(core, listener, wait_shutdown(shutdown_rx));
+            tokio::try_join!(grpc_server, rest_server)?;
+        }
+        None => grpc_server.await?,
+    }
+
+    Ok(())
+}
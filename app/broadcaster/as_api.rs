@@ -5,39 +5,464 @@
 
 //! AS API for Broadcaster
 
-use anyhow::*;
-use redis::Commands;
+use std::{io, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use log::warn;
+use redis::{AsyncCommands, ConnectionInfo, IntoConnectionInfo, RedisError, RedisResult};
+use thiserror::Error;
+use tokio::time;
 
 /// ASAPI contains interfaces of an `ASAPI` in RVPS.
+#[async_trait]
 pub trait ASAPI {
     /// Publish the message to all the subscribers
-    fn publish(&mut self, message: String) -> Result<()>;
+    async fn publish(&mut self, message: String) -> Result<()>;
+
+    /// Check whether the connection to the subscribers is healthy
+    async fn health_check(&mut self) -> Result<()>;
 }
 
-/// ASProxy implements ASAPI using redis as a publisher. 
-/// It is responsible for communicating with Attestation Service.
-/// * `conn` is the redis connection.
+/// Errors of ASProxy.
+#[derive(Error, Debug)]
+pub enum ASProxyError {
+    #[error("invalid redis address {addr}: {source}")]
+    InvalidAddress { addr: String, source: RedisError },
+
+    #[error("connect to redis failed after {attempts} attempt(s): {source}")]
+    Connect { attempts: u32, source: RedisError },
+
+    #[error("publish to channel {channel} failed: {source}")]
+    Publish { channel: String, source: RedisError },
+
+    #[error("redis health check failed: {0}")]
+    HealthCheck(RedisError),
+}
+
+/// Configuration of ASProxy.
+/// * `addr` is the address of redis server. `redis://` for TCP,
+/// `rediss://` for TLS and `redis+unix://` for unix socket.
 /// * `channel` is the redis channel for publishing.
-pub struct ASProxy {
-    conn: redis::Connection,
-    channel: String,
+/// * `username` and `password` are used for authentication. They
+/// override the ones given in `addr`.
+/// * `connect_timeout` is the timeout of a connection attempt.
+/// * `io_timeout` is the read/write timeout of the connection.
+/// * `max_retries` is the maximum number of reconnection attempts.
+/// * `initial_backoff` and `max_backoff` bound the exponential
+/// backoff between two reconnection attempts.
+#[derive(Clone, Debug)]
+pub struct ASProxyConfig {
+    pub addr: String,
+    pub channel: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub connect_timeout: Duration,
+    pub io_timeout: Duration,
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for ASProxyConfig {
+    fn default() -> Self {
+        Self {
+            addr: "redis://127.0.0.1:6379".into(),
+            channel: "rvps".into(),
+            username: None,
+            password: None,
+            connect_timeout: Duration::from_secs(5),
+            io_timeout: Duration::from_secs(5),
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Connector establishes connections to the subscribers.
+#[async_trait]
+pub trait Connector {
+    type Connection: Connection + Send;
+
+    /// Try to connect once.
+    async fn connect(&self) -> RedisResult<Self::Connection>;
+}
+
+/// Connection to the subscribers.
+#[async_trait]
+pub trait Connection {
+    /// Publish the message to the channel.
+    async fn publish(&mut self, channel: &str, message: &str) -> RedisResult<()>;
+
+    /// Send a `PING`.
+    async fn ping(&mut self) -> RedisResult<()>;
+}
+
+/// Bound the future by the timeout, s.t. it fails with an IO
+/// error of `TimedOut` kind once the timeout elapses.
+async fn with_timeout<T>(
+    timeout: Duration,
+    future: impl std::future::Future<Output = RedisResult<T>>,
+) -> RedisResult<T> {
+    time::timeout(timeout, future).await.map_err(|_| {
+        RedisError::from(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "redis operation timed out",
+        ))
+    })?
+}
+
+/// RedisConnector connects to a redis server.
+/// * `client` is the redis client.
+/// * `connect_timeout` is the timeout of a connection attempt.
+/// * `io_timeout` is the timeout of an operation on the connection.
+pub struct RedisConnector {
+    client: redis::Client,
+    connect_timeout: Duration,
+    io_timeout: Duration,
+}
+
+/// RedisConnection is a connection to a redis server.
+pub struct RedisConnection {
+    conn: redis::aio::Connection,
+    io_timeout: Duration,
+}
+
+#[async_trait]
+impl Connector for RedisConnector {
+    type Connection = RedisConnection;
+
+    async fn connect(&self) -> RedisResult<RedisConnection> {
+        let conn = with_timeout(self.connect_timeout, self.client.get_async_connection()).await?;
+        Ok(RedisConnection {
+            conn,
+            io_timeout: self.io_timeout,
+        })
+    }
+}
+
+#[async_trait]
+impl Connection for RedisConnection {
+    async fn publish(&mut self, channel: &str, message: &str) -> RedisResult<()> {
+        let publish = self.conn.publish::<_, _, i64>(channel, message);
+        with_timeout(self.io_timeout, publish).await.map(|_| ())
+    }
+
+    async fn ping(&mut self) -> RedisResult<()> {
+        let ping = redis::cmd("PING");
+        let ping = ping.query_async::<_, String>(&mut self.conn);
+        with_timeout(self.io_timeout, ping).await.map(|_| ())
+    }
+}
+
+/// ASProxy implements ASAPI using redis as a publisher.
+/// It is responsible for communicating with Attestation Service.
+/// The connection is established lazily when first used, and
+/// re-established with exponential backoff once it drops.
+/// * `connector` establishes the connections.
+/// * `conn` is the current connection, if any.
+/// * `config` is the configuration of the proxy.
+pub struct ASProxy<C: Connector = RedisConnector> {
+    connector: C,
+    conn: Option<C::Connection>,
+    config: ASProxyConfig,
 }
 
 impl ASProxy {
-    /// Create a ASProxy.
-    /// * `channel` is the redis channel for publishing.
-    /// * `addr` is the address of redis server.
-    pub fn new(channel: String, addr: String) -> Result<Self> {
-        let conn = redis::Client::open(addr)?
-            .get_connection()?;
-        
-        Ok(Self { conn, channel })
+    /// Create a ASProxy. No connection is made here.
+    pub fn new(config: ASProxyConfig) -> Result<Self, ASProxyError> {
+        let invalid_address = |source| ASProxyError::InvalidAddress {
+            addr: config.addr.clone(),
+            source,
+        };
+
+        let mut info: ConnectionInfo = config
+            .addr
+            .as_str()
+            .into_connection_info()
+            .map_err(invalid_address)?;
+        if config.username.is_some() {
+            info.redis.username = config.username.clone();
+        }
+        if config.password.is_some() {
+            info.redis.password = config.password.clone();
+        }
+
+        let client = redis::Client::open(info).map_err(invalid_address)?;
+        let connector = RedisConnector {
+            client,
+            connect_timeout: config.connect_timeout,
+            io_timeout: config.io_timeout,
+        };
+
+        Ok(Self::with_connector(connector, config))
+    }
+}
+
+impl<C: Connector + Send + Sync> ASProxy<C> {
+    /// Create a ASProxy connecting by the `connector`. No
+    /// connection is made here.
+    pub fn with_connector(connector: C, config: ASProxyConfig) -> Self {
+        Self {
+            connector,
+            conn: None,
+            config,
+        }
+    }
+
+    /// Connect to the subscribers, retrying with exponential
+    /// backoff for at most `max_retries` times.
+    async fn connect(&mut self) -> Result<&mut C::Connection, ASProxyError> {
+        if self.conn.is_none() {
+            let mut backoff = self.config.initial_backoff;
+            let mut attempts = 0;
+            let conn = loop {
+                attempts += 1;
+                match self.connector.connect().await {
+                    Ok(conn) => break conn,
+                    Err(source) if attempts > self.config.max_retries => {
+                        return Err(ASProxyError::Connect { attempts, source })
+                    }
+                    Err(e) => {
+                        warn!("Connect to redis failed: {}, retry in {:?}", e, backoff);
+                        time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(self.config.max_backoff);
+                    }
+                }
+            };
+            self.conn = Some(conn);
+        }
+
+        Ok(self.conn.as_mut().expect("connection is established"))
+    }
+
+    /// Publish the message to the channel. If the connection
+    /// dropped, reconnect and publish again.
+    pub async fn try_publish(&mut self, message: &str) -> Result<(), ASProxyError> {
+        let channel = self.config.channel.clone();
+        let res = self.connect().await?.publish(&channel, message).await;
+        match res {
+            Ok(()) => Ok(()),
+            Err(e) if e.is_connection_dropped() || e.is_io_error() || e.is_timeout() => {
+                warn!("Redis connection lost: {}, reconnecting", e);
+                self.conn = None;
+                let res = self.connect().await?.publish(&channel, message).await;
+                res.map_err(|source| {
+                    self.conn = None;
+                    ASProxyError::Publish { channel, source }
+                })
+            }
+            Err(source) => Err(ASProxyError::Publish { channel, source }),
+        }
+    }
+
+    /// Check the connection by sending a `PING`. A broken connection
+    /// will be dropped, s.t. it will be re-established next time.
+    pub async fn try_health_check(&mut self) -> Result<(), ASProxyError> {
+        let res = self.connect().await?.ping().await;
+        res.map_err(|e| {
+            self.conn = None;
+            ASProxyError::HealthCheck(e)
+        })
     }
 }
 
-impl ASAPI for ASProxy {
-    fn publish(&mut self, message: String) -> Result<()> {
-        self.conn.publish(self.channel.clone(), message)?;
+#[async_trait]
+impl<C> ASAPI for ASProxy<C>
+where
+    C: Connector + Send + Sync,
+{
+    async fn publish(&mut self, message: String) -> Result<()> {
+        self.try_publish(&message).await?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    async fn health_check(&mut self) -> Result<()> {
+        self.try_health_check().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    use std::{
+        collections::VecDeque,
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use tokio::time::Instant;
+
+    /// What the mock does on a call.
+    #[derive(Clone, Copy, Debug)]
+    enum Outcome {
+        Ok,
+        Dropped,
+        Refused,
+        Rejected,
+    }
+
+    impl Outcome {
+        fn result(self) -> RedisResult<()> {
+            match self {
+                Outcome::Ok => Ok(()),
+                Outcome::Dropped => Err(io::Error::from(io::ErrorKind::BrokenPipe).into()),
+                Outcome::Refused => Err(io::Error::from(io::ErrorKind::ConnectionRefused).into()),
+                Outcome::Rejected => Err((redis::ErrorKind::ResponseError, "rejected").into()),
+            }
+        }
+    }
+
+    /// Calls made on the mock and the outcomes of the next calls,
+    /// `Outcome::Ok` if not given.
+    #[derive(Default)]
+    struct Script {
+        connects: VecDeque<Outcome>,
+        calls: VecDeque<Outcome>,
+        connect_attempts: u32,
+        published: Vec<String>,
+        pings: u32,
+    }
+
+    #[derive(Clone, Default)]
+    struct MockConnector(Arc<Mutex<Script>>);
+
+    struct MockConnection(Arc<Mutex<Script>>);
+
+    #[async_trait]
+    impl Connector for MockConnector {
+        type Connection = MockConnection;
+
+        async fn connect(&self) -> RedisResult<MockConnection> {
+            let mut script = self.0.lock().unwrap();
+            script.connect_attempts += 1;
+            script
+                .connects
+                .pop_front()
+                .unwrap_or(Outcome::Ok)
+                .result()?;
+            Ok(MockConnection(self.0.clone()))
+        }
+    }
+
+    #[async_trait]
+    impl Connection for MockConnection {
+        async fn publish(&mut self, _channel: &str, message: &str) -> RedisResult<()> {
+            let mut script = self.0.lock().unwrap();
+            script.calls.pop_front().unwrap_or(Outcome::Ok).result()?;
+            script.published.push(message.to_string());
+            Ok(())
+        }
+
+        async fn ping(&mut self) -> RedisResult<()> {
+            let mut script = self.0.lock().unwrap();
+            script.pings += 1;
+            script.calls.pop_front().unwrap_or(Outcome::Ok).result()
+        }
+    }
+
+    fn mock_proxy(
+        connects: &[Outcome],
+        calls: &[Outcome],
+    ) -> (ASProxy<MockConnector>, MockConnector) {
+        let connector = MockConnector::default();
+        {
+            let mut script = connector.0.lock().unwrap();
+            script.connects = connects.iter().copied().collect();
+            script.calls = calls.iter().copied().collect();
+        }
+        let config = ASProxyConfig {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(250),
+            ..Default::default()
+        };
+        (
+            ASProxy::with_connector(connector.clone(), config),
+            connector,
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_connect_backoff() {
+        let refused = [Outcome::Refused; 4];
+        let (mut proxy, connector) = mock_proxy(&refused[..3], &[]);
+        let start = Instant::now();
+        proxy.try_publish("rv").await.unwrap();
+        // Backoff of 100ms, 200ms and 250ms, capped by `max_backoff`
+        assert_eq!(start.elapsed(), Duration::from_millis(550));
+        assert_eq!(connector.0.lock().unwrap().connect_attempts, 4);
+
+        let (mut proxy, connector) = mock_proxy(&refused, &[]);
+        let start = Instant::now();
+        let err = proxy.try_publish("rv").await.unwrap_err();
+        assert!(matches!(err, ASProxyError::Connect { attempts: 4, .. }));
+        assert_eq!(start.elapsed(), Duration::from_millis(550));
+        assert_eq!(connector.0.lock().unwrap().connect_attempts, 4);
+    }
+
+    #[tokio::test]
+    async fn test_publish_retries_once() {
+        // A dropped connection is re-established once
+        let (mut proxy, connector) = mock_proxy(&[], &[Outcome::Dropped]);
+        proxy.try_publish("rv").await.unwrap();
+        {
+            let script = connector.0.lock().unwrap();
+            assert_eq!(script.connect_attempts, 2);
+            assert_eq!(script.published, vec!["rv"]);
+        }
+
+        // The connection is reused
+        proxy.try_publish("rv2").await.unwrap();
+        assert_eq!(connector.0.lock().unwrap().connect_attempts, 2);
+
+        // No more retry if the retry fails
+        let (mut proxy, connector) = mock_proxy(&[], &[Outcome::Dropped, Outcome::Dropped]);
+        let err = proxy.try_publish("rv").await.unwrap_err();
+        assert!(matches!(err, ASProxyError::Publish { .. }));
+        assert_eq!(connector.0.lock().unwrap().connect_attempts, 2);
+
+        // The broken connection is dropped, and re-established next time
+        proxy.try_publish("rv").await.unwrap();
+        {
+            let script = connector.0.lock().unwrap();
+            assert_eq!(script.connect_attempts, 3);
+            assert_eq!(script.published, vec!["rv"]);
+        }
+
+        // Other errors are not retried
+        let (mut proxy, connector) = mock_proxy(&[], &[Outcome::Rejected]);
+        let err = proxy.try_publish("rv").await.unwrap_err();
+        assert!(matches!(err, ASProxyError::Publish { .. }));
+        assert_eq!(connector.0.lock().unwrap().connect_attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_health_check() {
+        let (mut proxy, connector) = mock_proxy(&[], &[Outcome::Ok, Outcome::Dropped]);
+        proxy.try_health_check().await.unwrap();
+
+        // A broken connection is dropped, and re-established next time
+        let err = proxy.try_health_check().await.unwrap_err();
+        assert!(matches!(err, ASProxyError::HealthCheck(_)));
+        proxy.try_health_check().await.unwrap();
+        let script = connector.0.lock().unwrap();
+        assert_eq!(script.pings, 3);
+        assert_eq!(script.connect_attempts, 2);
+    }
+
+    #[test]
+    fn test_invalid_address() {
+        let config = ASProxyConfig {
+            addr: "http://127.0.0.1".into(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ASProxy::new(config),
+            Err(ASProxyError::InvalidAddress { .. })
+        ));
+    }
+}
@@ -7,7 +7,7 @@
 
 use std::collections::HashMap;
 
-use crate::data_structs::reference_value::ReferenceValue;
+use reference_value_provider_service::ReferenceValue;
 
 use anyhow::*;
 
@@ -21,10 +21,17 @@ pub trait CacheAPI {
 }
 
 /// An Cache will store reference values.
+#[derive(Default)]
 pub struct Cache {
     inner: HashMap<String, ReferenceValue>,
 }
 
+impl Cache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
 impl CacheAPI for Cache {
     fn put(&mut self, artifact_name: String, reference_value: ReferenceValue) -> Result<()> {
         self.inner.insert(artifact_name, reference_value);
@@ -0,0 +1,10 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Applications of RVPS, s.t. communication componants
+//! around the RVPS Core.
+
+pub mod broadcaster;
+pub mod provenance_api;
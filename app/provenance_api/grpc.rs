@@ -0,0 +1,352 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! gRPC front end of RVPS.
+
+use std::{future::Future, net::SocketAddr};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use log::{info, warn};
+use reference_value_provider_service::{
+    Message, RVPSError, ReferenceValue, VerificationReport, RVPSAPI,
+};
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast, mpsc, watch},
+};
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{transport::Server, Request, Response, Status};
+
+use super::{with_core, Event, SharedCore};
+
+use self::proto::{
+    reference_value_provider_service_server::{
+        ReferenceValueProviderService, ReferenceValueProviderServiceServer,
+    },
+    DeleteReferenceValueRequest, DeleteReferenceValueResponse, EventType, ExtractorCapability,
+    GetCapabilitiesRequest, GetCapabilitiesResponse, GetReferenceValueRequest,
+    GetReferenceValueResponse, HashValuePair, ListReferenceValuesRequest,
+    ListReferenceValuesResponse, VerifyAndExtractRequest, VerifyAndExtractResponse, WatchEvent,
+    WatchRequest,
+};
+
+/// Generated protobuf definitions, see `protos/reference.proto`.
+pub mod proto {
+    tonic::include_proto!("reference");
+}
+
+/// Capacity of the queue of the events streamed to a watcher.
+const WATCH_QUEUE_CAPACITY: usize = 64;
+
+impl From<&ReferenceValue> for proto::ReferenceValue {
+    fn from(rv: &ReferenceValue) -> Self {
+        proto::ReferenceValue {
+            version: rv.version().clone(),
+            name: rv.name().clone(),
+            expired: rv.expired().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            hash_value: rv
+                .hash_values()
+                .iter()
+                .map(|pair| HashValuePair {
+                    alg: pair.alg().clone(),
+                    value: pair.value().clone(),
+                })
+                .collect(),
+            metadata: rv.metadata().clone().into_iter().collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::ReferenceValue> for ReferenceValue {
+    type Error = anyhow::Error;
+
+    fn try_from(rv: proto::ReferenceValue) -> anyhow::Result<Self> {
+        let expired = NaiveDateTime::parse_from_str(&rv.expired, "%Y-%m-%dT%H:%M:%SZ")?;
+        let res = ReferenceValue::new()
+            .set_version(&rv.version)
+            .set_name(&rv.name)
+            .set_expired(DateTime::<Utc>::from_utc(expired, Utc));
+        let res = rv
+            .hash_value
+            .into_iter()
+            .fold(res, |res, pair| res.add_hash_value(pair.alg, pair.value));
+        Ok(rv
+            .metadata
+            .iter()
+            .fold(res, |res, (key, value)| res.add_metadata(key, value)))
+    }
+}
+
+impl From<VerificationReport> for proto::VerificationReport {
+    fn from(report: VerificationReport) -> Self {
+        proto::VerificationReport {
+            provenance_type: report.provenance_type,
+            verified: report.verified,
+            signers: report.signers,
+            steps: report
+                .steps
+                .into_iter()
+                .map(|step| proto::StepReport {
+                    name: step.name,
+                    kind: step.kind,
+                    passed: step.passed,
+                })
+                .collect(),
+            timestamps: report
+                .timestamps
+                .into_iter()
+                .map(|(name, time)| (name, time.format("%Y-%m-%dT%H:%M:%SZ").to_string()))
+                .collect(),
+            warnings: report.warnings,
+            failed_rules: report
+                .failed_rules
+                .into_iter()
+                .map(|rule| proto::FailedRule {
+                    rule: rule.rule,
+                    reason: rule.reason,
+                })
+                .collect(),
+            reference_values: report.reference_values,
+        }
+    }
+}
+
+impl From<proto::Message> for Message {
+    fn from(message: proto::Message) -> Self {
+        let res = Message::new(&message.r#type, &message.payload);
+        match message.version.is_empty() {
+            true => res,
+            false => res.set_version(&message.version),
+        }
+    }
+}
+
+/// Errors of the RVPS Core. Here `{:#}` keeps the whole error chain.
+fn internal_error(e: anyhow::Error) -> Status {
+    Status::internal(format!("{:#}", e))
+}
+
+/// Errors of verifying a Message, mapped due to the carried
+/// `RVPSError` as the REST API does. Failures caused by the Message
+/// itself are invalid arguments.
+fn verification_error(e: anyhow::Error) -> Status {
+    match e.downcast_ref::<RVPSError>() {
+        Some(RVPSError::VersionUnmatched { .. })
+        | Some(RVPSError::UnsupportedProvenanceType(_))
+        | Some(RVPSError::VerificationFailed) => Status::invalid_argument(format!("{:#}", e)),
+        Some(RVPSError::DuplicateExtractor(_))
+        | Some(RVPSError::InvalidExtractorConfig(_))
+        | None => internal_error(e),
+    }
+}
+
+impl From<&Event> for WatchEvent {
+    fn from(event: &Event) -> Self {
+        let (event_type, rv) = match event {
+            Event::Updated(rv) => (EventType::Updated, rv),
+            Event::Deleted(rv) => (EventType::Deleted, rv),
+        };
+        WatchEvent {
+            event_type: event_type as i32,
+            reference_value: Some(rv.into()),
+        }
+    }
+}
+
+/// `RVPSService` implements the gRPC service over a shared RVPS Core.
+/// * `core` is the shared RVPS Core, which also publishes the changes
+/// of reference values to watchers.
+/// * `shutdown` tells the watchers that the server is shutting down.
+pub struct RVPSService {
+    core: SharedCore,
+    shutdown: watch::Receiver<bool>,
+}
+
+impl RVPSService {
+    pub fn new(core: SharedCore, shutdown: watch::Receiver<bool>) -> Self {
+        Self { core, shutdown }
+    }
+}
+
+#[tonic::async_trait]
+impl ReferenceValueProviderService for RVPSService {
+    async fn verify_and_extract(
+        &self,
+        request: Request<VerifyAndExtractRequest>,
+    ) -> Result<Response<VerifyAndExtractResponse>, Status> {
+        let message: Message = request
+            .into_inner()
+            .message
+            .ok_or_else(|| Status::invalid_argument("Message is missing"))?
+            .into();
+
+        let (rvs, report) = with_core(&self.core, |core| core.verify_and_extract(message))
+            .await
+            .map_err(verification_error)?;
+
+        Ok(Response::new(VerifyAndExtractResponse {
+            reference_values: rvs.iter().map(|rv| rv.into()).collect(),
+            report: Some(report.into()),
+        }))
+    }
+
+    async fn get_reference_value(
+        &self,
+        request: Request<GetReferenceValueRequest>,
+    ) -> Result<Response<GetReferenceValueResponse>, Status> {
+        let name = request.into_inner().name;
+        let rv = with_core(&self.core, {
+            let name = name.clone();
+            move |core| core.get_rv(&name)
+        })
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| Status::not_found(format!("Reference value {} not found", name)))?;
+
+        Ok(Response::new(GetReferenceValueResponse {
+            reference_value: Some((&rv).into()),
+        }))
+    }
+
+    async fn list_reference_values(
+        &self,
+        _request: Request<ListReferenceValuesRequest>,
+    ) -> Result<Response<ListReferenceValuesResponse>, Status> {
+        let rvs = with_core(&self.core, |core| core.list_rvs())
+            .await
+            .map_err(internal_error)?;
+
+        Ok(Response::new(ListReferenceValuesResponse {
+            reference_values: rvs.iter().map(|rv| rv.into()).collect(),
+        }))
+    }
+
+    async fn delete_reference_value(
+        &self,
+        request: Request<DeleteReferenceValueRequest>,
+    ) -> Result<Response<DeleteReferenceValueResponse>, Status> {
+        let name = request.into_inner().name;
+        let rv = with_core(&self.core, {
+            let name = name.clone();
+            move |core| core.delete_rv(&name)
+        })
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| Status::not_found(format!("Reference value {} not found", name)))?;
+
+        Ok(Response::new(DeleteReferenceValueResponse {
+            reference_value: Some((&rv).into()),
+        }))
+    }
+
+    async fn get_capabilities(
+        &self,
+        _request: Request<GetCapabilitiesRequest>,
+    ) -> Result<Response<GetCapabilitiesResponse>, Status> {
+        let capabilities = with_core(&self.core, |core| core.capabilities())
+            .await
+            .map_err(internal_error)?;
+
+        Ok(Response::new(GetCapabilitiesResponse {
+            message_versions: capabilities.message_versions,
+            extractors: capabilities
+                .extractors
+                .into_iter()
+                .map(|extractor| ExtractorCapability {
+                    name: extractor.name,
+                    payload_versions: extractor.payload_versions,
+                    available: extractor.available,
+                    error: extractor.error.unwrap_or_default(),
+                })
+                .collect(),
+            cache: capabilities.cache,
+        }))
+    }
+
+    type WatchStream = ReceiverStream<Result<WatchEvent, Status>>;
+
+    async fn watch(
+        &self,
+        request: Request<WatchRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let name = request.into_inner().name;
+        let mut events = self.core.subscribe();
+        let mut shutdown = self.shutdown.clone();
+        let (tx, rx) = mpsc::channel(WATCH_QUEUE_CAPACITY);
+
+        tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    // Also ends the stream if the sender is gone
+                    _ = shutdown.changed() => break,
+                    event = events.recv() => event,
+                };
+
+                let res = match event {
+                    Ok(event) => {
+                        let event = WatchEvent::from(&event);
+                        let matched = name.is_empty()
+                            || event
+                                .reference_value
+                                .as_ref()
+                                .map_or(false, |rv| rv.name == name);
+                        if !matched {
+                            continue;
+                        }
+                        Ok(event)
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!("A watcher lagged behind {} events, disconnect it", n);
+                        Err(Status::data_loss(format!("Lagged behind {} events", n)))
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+
+                let lagged = res.is_err();
+                if tx.send(res).await.is_err() || lagged {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// Serve the gRPC API on the given listener until `signal` completes.
+/// When shutting down, all the watch streams are closed, and then the
+/// in-flight requests are waited to finish.
+pub async fn serve_with_listener<F>(
+    core: SharedCore,
+    listener: TcpListener,
+    signal: F,
+) -> anyhow::Result<()>
+where
+    F: Future<Output = ()>,
+{
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let service = RVPSService::new(core, shutdown_rx);
+
+    info!("RVPS gRPC API listening on {}", listener.local_addr()?);
+    Server::builder()
+        .add_service(ReferenceValueProviderServiceServer::new(service))
+        .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async move {
+            signal.await;
+            info!("RVPS gRPC API shutting down");
+            let _ = shutdown_tx.send(true);
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Serve the gRPC API on `addr` until `signal` completes.
+pub async fn serve<F>(core: SharedCore, addr: SocketAddr, signal: F) -> anyhow::Result<()>
+where
+    F: Future<Output = ()>,
+{
+    let listener = TcpListener::bind(addr).await?;
+    serve_with_listener(core, listener, signal).await
+}
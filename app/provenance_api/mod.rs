@@ -0,0 +1,172 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Provenance API of RVPS, s.t. the network front ends
+//! wrapping the RVPS Core.
+
+pub mod grpc;
+pub mod rest;
+
+use std::{path::Path, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use reference_value_provider_service::{
+    cache::{local_fs::LocalFsCache, simple::SimpleCache, Cache},
+    Config, Core, ReferenceValue,
+};
+use tokio::sync::{broadcast, Mutex};
+
+use crate::broadcaster::Broadcaster;
+
+/// Capacity of the queue of events. A subscriber lagging behind
+/// more than this misses events, e.g. a gRPC watcher is disconnected.
+const EVENT_QUEUE_CAPACITY: usize = 64;
+
+/// A Cache whose concrete type is chosen at runtime.
+pub type DynCache = Box<dyn Cache + Send + Sync>;
+
+/// A change of the stored reference values.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    Updated(ReferenceValue),
+    Deleted(ReferenceValue),
+}
+
+/// A Cache of the RVPS Core publishing every change of the stored
+/// reference values as an `Event`.
+/// * `inner` is the Cache storing the reference values.
+/// * `events` publishes the changes. It is fine that nobody subscribes.
+pub struct EventCache<C> {
+    inner: C,
+    events: broadcast::Sender<Event>,
+}
+
+impl<C: Cache> Cache for EventCache<C> {
+    fn set(&mut self, name: String, rv: ReferenceValue) -> Result<()> {
+        self.inner.set(name, rv.clone())?;
+        let _ = self.events.send(Event::Updated(rv));
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<Option<ReferenceValue>> {
+        self.inner.get(name)
+    }
+
+    fn delete(&mut self, name: &str) -> Result<Option<ReferenceValue>> {
+        let rv = self.inner.delete(name)?;
+        if let Some(rv) = &rv {
+            let _ = self.events.send(Event::Deleted(rv.clone()));
+        }
+        Ok(rv)
+    }
+
+    fn get_all(&self) -> Result<Vec<ReferenceValue>> {
+        self.inner.get_all()
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+}
+
+/// A RVPS Core shared by all the requests of a server, whichever front
+/// end they come from.
+/// * `core` is the RVPS Core.
+/// * `events` publishes the changes of the stored reference values.
+#[derive(Clone)]
+pub struct SharedCore {
+    core: Arc<Mutex<Core<DynCache>>>,
+    events: broadcast::Sender<Event>,
+}
+
+impl SharedCore {
+    /// Subscribe to the changes of the stored reference values made
+    /// after now.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+}
+
+/// Run `f` on the shared RVPS Core in the blocking thread pool. Neither
+/// waiting for the Core nor the work, e.g. verifying signatures or
+/// running WASM plugins, blocks the async runtime.
+pub async fn with_core<F, T>(core: &SharedCore, f: F) -> Result<T>
+where
+    F: FnOnce(&mut Core<DynCache>) -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let mut core = core.core.clone().lock_owned().await;
+    tokio::task::spawn_blocking(move || f(&mut core))
+        .await
+        .map_err(|e| anyhow!("RVPS Core task failed: {}", e))?
+}
+
+/// Cache types that a RVPS server can be backed by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CacheType {
+    /// Stores reference values in memory, see `SimpleCache`.
+    Simple,
+    /// Stores reference values in a local file, see `LocalFsCache`.
+    LocalFs,
+}
+
+impl CacheType {
+    /// Instantiate a Cache of this type. `path` is needed by the
+    /// Caches storing reference values in files.
+    pub fn instantiate(&self, path: Option<&Path>) -> Result<DynCache> {
+        match self {
+            CacheType::Simple => Ok(Box::new(SimpleCache::new())),
+            CacheType::LocalFs => {
+                let path = path.ok_or_else(|| anyhow!("Cache path is needed by local-fs cache"))?;
+                Ok(Box::new(LocalFsCache::new(path)?))
+            }
+        }
+    }
+}
+
+/// Create a new shared RVPS Core backed by the given type of Cache.
+pub fn new_shared_core(cache_type: CacheType, cache_path: Option<&Path>) -> Result<SharedCore> {
+    new_configured_shared_core(cache_type, cache_path, Config::default())
+}
+
+/// Configure the Extractors and the audit log of a RVPS Core. Fails if
+/// any configuration of the Extractors is invalid, or the audit log
+/// cannot be opened.
+pub fn configure_core<T: Cache>(core: &mut Core<T>, config: Config) -> Result<()> {
+    core.configure_extractors(&config)?;
+    if let Some(audit_log) = &config.audit_log {
+        core.set_audit_log(audit_log)
+            .map_err(|e| anyhow!("Open audit log {} failed: {}", audit_log.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Create a new shared RVPS Core backed by the given type of Cache,
+/// configured as `configure_core`. If the Broadcaster is configured,
+/// the stored reference values are broadcasted by it in the background.
+/// The changes of the stored reference values are published to the
+/// subscribers of the returned Core.
+pub fn new_configured_shared_core(
+    cache_type: CacheType,
+    cache_path: Option<&Path>,
+    config: Config,
+) -> Result<SharedCore> {
+    let mut cache = cache_type.instantiate(cache_path)?;
+    if let Some(broadcaster) = &config.broadcaster {
+        cache = Box::new(Broadcaster::from_config(broadcaster)?.spawn(cache));
+    }
+    let (events, _) = broadcast::channel(EVENT_QUEUE_CAPACITY);
+    let cache = EventCache {
+        inner: cache,
+        events: events.clone(),
+    };
+    let mut core = Core::new(Box::new(cache) as DynCache);
+    configure_core(&mut core, config)?;
+    Ok(SharedCore {
+        core: Arc::new(Mutex::new(core)),
+        events,
+    })
+}
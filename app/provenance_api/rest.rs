@@ -0,0 +1,624 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! REST/JSON front end of RVPS.
+//!
+//! * `POST /reference-values` registers a provenance `Message`.
+//! * `GET /reference-values` lists reference values.
+//! * `GET /reference-values/{name}` gets a reference value.
+//! * `DELETE /reference-values/{name}` revokes a reference value.
+//! * `GET /capabilities` gets the capabilities of the RVPS.
+//! * `GET /openapi.json` gets the OpenAPI document of this API.
+//!
+//! All the errors are returned as
+//! `{"error": {"code": "<ERROR-CODE>", "message": "<MESSAGE>"}}`. A
+//! verification returns its `report`, whether succeeded or not.
+
+use std::{future::Future, net::TcpListener};
+
+use axum::{
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        Path, Query,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Extension, Json, Router, Server,
+};
+use log::info;
+use reference_value_provider_service::{
+    Capabilities, Message, RVPSError, ReferenceValue, VerificationReport, RVPSAPI,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{with_core, SharedCore};
+
+/// Default number of reference values in a page.
+const DEFAULT_PAGE_LIMIT: usize = 50;
+
+/// Maximum number of reference values in a page.
+const MAX_PAGE_LIMIT: usize = 1000;
+
+/// An error returned by the REST API. `report` is the report of a
+/// failed verification.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+    report: Option<VerificationReport>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, code: &'static str, message: String) -> Self {
+        Self {
+            status,
+            code,
+            message,
+            report: None,
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            "not_found",
+            format!("Reference value {} not found", name),
+        )
+    }
+}
+
+/// Map the errors of the RVPS Core due to the carried `RVPSError`.
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        let (status, code) = match e.downcast_ref::<RVPSError>() {
+            Some(RVPSError::VersionUnmatched { .. }) => {
+                (StatusCode::BAD_REQUEST, "unsupported_version")
+            }
+            Some(RVPSError::UnsupportedProvenanceType(_)) => {
+                (StatusCode::BAD_REQUEST, "unsupported_type")
+            }
+            Some(RVPSError::VerificationFailed) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, "verification_failed")
+            }
+            Some(RVPSError::DuplicateExtractor(_))
+            | Some(RVPSError::InvalidExtractorConfig(_))
+            | None => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        };
+        let mut err = Self::new(status, code, format!("{:#}", e));
+        err.report = e.downcast_ref::<VerificationReport>().cloned();
+        err
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+            rejection.to_string(),
+        )
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+            rejection.to_string(),
+        )
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut body = json!({
+            "error": {
+                "code": self.code,
+                "message": self.message,
+            }
+        });
+        if let Some(report) = self.report {
+            body["error"]["report"] = json!(report);
+        }
+        (self.status, Json(body)).into_response()
+    }
+}
+
+/// Query parameters of listing reference values.
+/// * `offset`: number of reference values to skip.
+/// * `limit`: maximum number of reference values to return.
+/// * `name_prefix`: only list reference values whose name starts with this.
+/// * `alg`: only list reference values having a hash value of this algorithm.
+#[derive(Deserialize, Debug)]
+pub struct ListParams {
+    #[serde(default)]
+    offset: usize,
+    #[serde(default = "default_limit")]
+    limit: usize,
+    name_prefix: Option<String>,
+    alg: Option<String>,
+}
+
+fn default_limit() -> usize {
+    DEFAULT_PAGE_LIMIT
+}
+
+/// A page of reference values. `total` is the number of reference
+/// values matching the filters.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ListResponse {
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub items: Vec<ReferenceValue>,
+}
+
+/// The registered reference values, one for each artifact in the
+/// provenance, and the report of the verification.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RegisterResponse {
+    pub reference_values: Vec<ReferenceValue>,
+    pub report: VerificationReport,
+}
+
+async fn register(
+    Extension(core): Extension<SharedCore>,
+    message: Result<Json<Message>, JsonRejection>,
+) -> Result<(StatusCode, Json<RegisterResponse>), ApiError> {
+    let Json(message) = message?;
+
+    let (reference_values, report) =
+        with_core(&core, |core| core.verify_and_extract(message)).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(RegisterResponse {
+            reference_values,
+            report,
+        }),
+    ))
+}
+
+async fn list(
+    Extension(core): Extension<SharedCore>,
+    params: Result<Query<ListParams>, QueryRejection>,
+) -> Result<Json<ListResponse>, ApiError> {
+    let Query(params) = params?;
+    if params.limit == 0 || params.limit > MAX_PAGE_LIMIT {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+            format!("limit must be in [1, {}]", MAX_PAGE_LIMIT),
+        ));
+    }
+
+    let mut rvs: Vec<ReferenceValue> = with_core(&core, |core| core.list_rvs())
+        .await?
+        .into_iter()
+        .filter(|rv| {
+            params
+                .name_prefix
+                .as_ref()
+                .map_or(true, |prefix| rv.name().starts_with(prefix))
+        })
+        .filter(|rv| {
+            params
+                .alg
+                .as_ref()
+                .map_or(true, |alg| rv.hash_values().iter().any(|p| p.alg() == alg))
+        })
+        .collect();
+
+    // Sort by name to keep the pages stable
+    rvs.sort_by(|a, b| a.name().cmp(b.name()));
+
+    let total = rvs.len();
+    let items = rvs
+        .into_iter()
+        .skip(params.offset)
+        .take(params.limit)
+        .collect();
+
+    Ok(Json(ListResponse {
+        total,
+        offset: params.offset,
+        limit: params.limit,
+        items,
+    }))
+}
+
+async fn get_rv(
+    Extension(core): Extension<SharedCore>,
+    Path(name): Path<String>,
+) -> Result<Json<ReferenceValue>, ApiError> {
+    let rv = with_core(&core, {
+        let name = name.clone();
+        move |core| core.get_rv(&name)
+    })
+    .await?
+    .ok_or_else(|| ApiError::not_found(&name))?;
+    Ok(Json(rv))
+}
+
+async fn delete_rv(
+    Extension(core): Extension<SharedCore>,
+    Path(name): Path<String>,
+) -> Result<Json<ReferenceValue>, ApiError> {
+    let rv = with_core(&core, {
+        let name = name.clone();
+        move |core| core.delete_rv(&name)
+    })
+    .await?
+    .ok_or_else(|| ApiError::not_found(&name))?;
+    Ok(Json(rv))
+}
+
+async fn capabilities(
+    Extension(core): Extension<SharedCore>,
+) -> Result<Json<Capabilities>, ApiError> {
+    let capabilities = with_core(&core, |core| core.capabilities()).await?;
+    Ok(Json(capabilities))
+}
+
+async fn openapi_document() -> Json<Value> {
+    Json(openapi())
+}
+
+/// Create the router of the REST API over a shared RVPS Core.
+pub fn router(core: SharedCore) -> Router {
+    Router::new()
+        .route("/reference-values", get(list).post(register))
+        .route("/reference-values/:name", get(get_rv).delete(delete_rv))
+        .route("/capabilities", get(capabilities))
+        .route("/openapi.json", get(openapi_document))
+        .layer(Extension(core))
+}
+
+/// Serve the REST API on the given listener until `signal` completes.
+pub async fn serve_with_listener<F>(
+    core: SharedCore,
+    listener: TcpListener,
+    signal: F,
+) -> anyhow::Result<()>
+where
+    F: Future<Output = ()>,
+{
+    info!("RVPS REST API listening on {}", listener.local_addr()?);
+    Server::from_tcp(listener)?
+        .serve(router(core).into_make_service())
+        .with_graceful_shutdown(async move {
+            signal.await;
+            info!("RVPS REST API shutting down");
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// The OpenAPI document of the REST API. A copy is published
+/// as `docs/openapi.json`.
+pub fn openapi() -> Value {
+    let error_response = |description: &str| {
+        json!({
+            "description": description,
+            "content": {
+                "application/json": {
+                    "schema": { "$ref": "#/components/schemas/Error" }
+                }
+            }
+        })
+    };
+    let rv_response = |description: &str| {
+        json!({
+            "description": description,
+            "content": {
+                "application/json": {
+                    "schema": { "$ref": "#/components/schemas/ReferenceValue" }
+                }
+            }
+        })
+    };
+    let name_param = json!({
+        "name": "name",
+        "in": "path",
+        "required": true,
+        "description": "Name of the artifact, percent-encoded",
+        "schema": { "type": "string" }
+    });
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Reference Value Provider Service",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": {
+            "/reference-values": {
+                "post": {
+                    "summary": "Verify a provenance message and register the extracted reference values",
+                    "operationId": "registerProvenance",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": { "$ref": "#/components/schemas/Message" }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "201": {
+                            "description": "The registered reference values and the verification report",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/RegisterResponse" }
+                                }
+                            }
+                        },
+                        "400": error_response("Malformed message, unsupported version or provenance type"),
+                        "422": error_response("Provenance verification failed"),
+                        "500": error_response("Internal error"),
+                    }
+                },
+                "get": {
+                    "summary": "List reference values",
+                    "operationId": "listReferenceValues",
+                    "parameters": [
+                        {
+                            "name": "offset",
+                            "in": "query",
+                            "description": "Number of reference values to skip",
+                            "schema": { "type": "integer", "minimum": 0, "default": 0 }
+                        },
+                        {
+                            "name": "limit",
+                            "in": "query",
+                            "description": "Maximum number of reference values to return",
+                            "schema": {
+                                "type": "integer",
+                                "minimum": 1,
+                                "maximum": MAX_PAGE_LIMIT,
+                                "default": DEFAULT_PAGE_LIMIT,
+                            }
+                        },
+                        {
+                            "name": "name_prefix",
+                            "in": "query",
+                            "description": "Only list reference values whose name starts with this",
+                            "schema": { "type": "string" }
+                        },
+                        {
+                            "name": "alg",
+                            "in": "query",
+                            "description": "Only list reference values having a hash value of this algorithm",
+                            "schema": { "type": "string" }
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "A page of reference values sorted by name",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/ReferenceValueList" }
+                                }
+                            }
+                        },
+                        "400": error_response("Invalid query parameters"),
+                        "500": error_response("Internal error"),
+                    }
+                }
+            },
+            "/reference-values/{name}": {
+                "get": {
+                    "summary": "Get a reference value by the artifact's name",
+                    "operationId": "getReferenceValue",
+                    "parameters": [name_param.clone()],
+                    "responses": {
+                        "200": rv_response("The reference value"),
+                        "404": error_response("Reference value not found"),
+                        "500": error_response("Internal error"),
+                    }
+                },
+                "delete": {
+                    "summary": "Revoke a reference value by the artifact's name",
+                    "operationId": "deleteReferenceValue",
+                    "parameters": [name_param],
+                    "responses": {
+                        "200": rv_response("The revoked reference value"),
+                        "404": error_response("Reference value not found"),
+                        "500": error_response("Internal error"),
+                    }
+                }
+            },
+            "/capabilities": {
+                "get": {
+                    "summary": "Get the accepted message versions, the registered Extractors and the active cache backend",
+                    "operationId": "getCapabilities",
+                    "responses": {
+                        "200": {
+                            "description": "The capabilities of the RVPS",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/Capabilities" }
+                                }
+                            }
+                        },
+                        "500": error_response("Internal error"),
+                    }
+                }
+            }
+        },
+        "components": {
+            "schemas": {
+                "Message": {
+                    "type": "object",
+                    "required": ["type", "payload"],
+                    "properties": {
+                        "version": { "type": "string", "default": "0.1" },
+                        "type": { "type": "string", "description": "Provenance type, e.g. in-toto" },
+                        "payload": { "type": "string", "description": "Content of the provenance" }
+                    }
+                },
+                "HashValuePair": {
+                    "type": "object",
+                    "required": ["alg", "value"],
+                    "properties": {
+                        "alg": { "type": "string" },
+                        "value": { "type": "string" }
+                    }
+                },
+                "ReferenceValue": {
+                    "type": "object",
+                    "required": ["version", "name", "expired", "hash-value"],
+                    "properties": {
+                        "version": { "type": "string" },
+                        "name": { "type": "string" },
+                        "expired": { "type": "string", "format": "date-time" },
+                        "hash-value": {
+                            "type": "array",
+                            "items": { "$ref": "#/components/schemas/HashValuePair" }
+                        },
+                        "metadata": {
+                            "type": "object",
+                            "additionalProperties": { "type": "string" },
+                            "description": "Information about the artifact, e.g. supplier or version"
+                        }
+                    }
+                },
+                "RegisterResponse": {
+                    "type": "object",
+                    "required": ["reference_values", "report"],
+                    "properties": {
+                        "reference_values": {
+                            "type": "array",
+                            "items": { "$ref": "#/components/schemas/ReferenceValue" },
+                            "description": "The registered reference values, one for each artifact in the provenance"
+                        },
+                        "report": { "$ref": "#/components/schemas/VerificationReport" }
+                    }
+                },
+                "ReferenceValueList": {
+                    "type": "object",
+                    "required": ["total", "offset", "limit", "items"],
+                    "properties": {
+                        "total": { "type": "integer" },
+                        "offset": { "type": "integer" },
+                        "limit": { "type": "integer" },
+                        "items": {
+                            "type": "array",
+                            "items": { "$ref": "#/components/schemas/ReferenceValue" }
+                        }
+                    }
+                },
+                "ExtractorCapability": {
+                    "type": "object",
+                    "required": ["name", "payload_versions", "available"],
+                    "properties": {
+                        "name": { "type": "string", "description": "Provenance type, e.g. in-toto" },
+                        "payload_versions": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Supported versions of the provenance format, empty if unversioned"
+                        },
+                        "available": { "type": "boolean", "description": "Whether the Extractor can be instantiated" },
+                        "error": { "type": "string", "description": "Why the Extractor is unavailable" }
+                    }
+                },
+                "Capabilities": {
+                    "type": "object",
+                    "required": ["message_versions", "extractors", "cache"],
+                    "properties": {
+                        "message_versions": {
+                            "type": "array",
+                            "items": { "type": "string" }
+                        },
+                        "extractors": {
+                            "type": "array",
+                            "items": { "$ref": "#/components/schemas/ExtractorCapability" }
+                        },
+                        "cache": { "type": "string", "description": "Name of the cache backend, e.g. simple" }
+                    }
+                },
+                "VerificationReport": {
+                    "type": "object",
+                    "required": ["provenance_type", "verified"],
+                    "properties": {
+                        "provenance_type": { "type": "string" },
+                        "verified": { "type": "boolean" },
+                        "signers": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Ids of the keys or identities which signed the provenance"
+                        },
+                        "steps": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["name", "kind", "passed"],
+                                "properties": {
+                                    "name": { "type": "string" },
+                                    "kind": { "type": "string", "description": "e.g. step or inspection" },
+                                    "passed": { "type": "boolean" }
+                                }
+                            }
+                        },
+                        "timestamps": {
+                            "type": "object",
+                            "additionalProperties": { "type": "string", "format": "date-time" }
+                        },
+                        "warnings": {
+                            "type": "array",
+                            "items": { "type": "string" }
+                        },
+                        "failed_rules": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["rule", "reason"],
+                                "properties": {
+                                    "rule": { "type": "string" },
+                                    "reason": { "type": "string" }
+                                }
+                            }
+                        },
+                        "reference_values": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Names of the extracted reference values"
+                        }
+                    }
+                },
+                "Error": {
+                    "type": "object",
+                    "required": ["error"],
+                    "properties": {
+                        "error": {
+                            "type": "object",
+                            "required": ["code", "message"],
+                            "properties": {
+                                "code": {
+                                    "type": "string",
+                                    "enum": [
+                                        "invalid_request",
+                                        "unsupported_version",
+                                        "unsupported_type",
+                                        "verification_failed",
+                                        "not_found",
+                                        "internal"
+                                    ]
+                                },
+                                "message": { "type": "string" },
+                                "report": { "$ref": "#/components/schemas/VerificationReport" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
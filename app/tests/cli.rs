@@ -0,0 +1,207 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Integration tests of `rvps-cli`.
+
+mod common;
+
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Output, Stdio},
+};
+
+use reference_value_provider_service::ReferenceValue;
+use rvps::provenance_api::{grpc::serve_with_listener, new_shared_core, CacheType};
+use serde_json::json;
+use serial_test::serial;
+use tokio::{net::TcpListener, sync::oneshot};
+
+use common::{generate_in_toto_provenance, IN_TOTO_ARTIFACT};
+
+/// Run `rvps-cli` with the arguments, feeding `stdin` to it.
+fn rvps_cli(args: &[&str], stdin: Option<String>) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rvps-cli"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut child_stdin = child.stdin.take().unwrap();
+    child_stdin
+        .write_all(stdin.unwrap_or_default().as_bytes())
+        .unwrap();
+    drop(child_stdin);
+    child.wait_with_output().unwrap()
+}
+
+fn in_toto_message() -> String {
+    json!({
+        "type": "in-toto",
+        "payload": generate_in_toto_provenance(),
+    })
+    .to_string()
+}
+
+#[test]
+#[serial]
+fn cli_embedded_core() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache_path = dir.path().join("cache.json");
+    let cache_path = cache_path.to_str().unwrap();
+    let export_path = dir.path().join("export.json");
+
+    let output = rvps_cli(
+        &["--cache-path", cache_path, "-o", "json", "register"],
+        Some(in_toto_message()),
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let rvs: Vec<ReferenceValue> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(rvs.len(), 1);
+    let rv = rvs[0].clone();
+    assert_eq!(rv.name(), IN_TOTO_ARTIFACT);
+
+    // The reference value is persisted across invocations
+    let output = rvps_cli(&["--cache-path", cache_path, "get", IN_TOTO_ARTIFACT], None);
+    assert!(output.status.success(), "{:?}", output);
+    let table = String::from_utf8(output.stdout).unwrap();
+    assert!(table.starts_with("NAME"));
+    assert!(table.contains(IN_TOTO_ARTIFACT));
+
+    let output = rvps_cli(
+        &[
+            "--cache-path",
+            cache_path,
+            "-o",
+            "json",
+            "list",
+            "--name-prefix",
+            "foo",
+        ],
+        None,
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let rvs: Vec<ReferenceValue> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(rvs, vec![rv.clone()]);
+
+    let output = rvps_cli(
+        &[
+            "--cache-path",
+            cache_path,
+            "export",
+            "--file",
+            export_path.to_str().unwrap(),
+        ],
+        None,
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let exported: Vec<ReferenceValue> =
+        serde_json::from_slice(&std::fs::read(&export_path).unwrap()).unwrap();
+    assert_eq!(exported, vec![rv]);
+
+    let output = rvps_cli(
+        &["--cache-path", cache_path, "delete", IN_TOTO_ARTIFACT],
+        None,
+    );
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = rvps_cli(&["--cache-path", cache_path, "get", IN_TOTO_ARTIFACT], None);
+    assert!(!output.status.success());
+}
+
+#[test]
+#[serial]
+fn cli_invalid_message() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache_path = dir.path().join("cache.json");
+    let cache_path = cache_path.to_str().unwrap();
+
+    let output = rvps_cli(&["--cache-path", cache_path, "register"], Some("{".into()));
+    assert!(!output.status.success());
+
+    let message = json!({ "type": "unknown", "payload": "" }).to_string();
+    let output = rvps_cli(
+        &["--cache-path", cache_path, "register", "-"],
+        Some(message),
+    );
+    assert!(!output.status.success());
+
+    let output = rvps_cli(&["--cache-path", cache_path, "export"], None);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8(output.stdout).unwrap().trim(), "[]");
+    assert!(!Path::new(cache_path).exists());
+}
+
+#[test]
+#[serial]
+fn cli_embedded_core_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache_path = dir.path().join("cache.json");
+    let cache_path = cache_path.to_str().unwrap();
+    let audit_log = dir.path().join("audit.log");
+    let config_path = dir.path().join("rvps.json");
+    let config_path = config_path.to_str().unwrap();
+
+    std::fs::write(config_path, json!({ "audit_log": audit_log }).to_string()).unwrap();
+    let output = rvps_cli(
+        &[
+            "--cache-path",
+            cache_path,
+            "--config",
+            config_path,
+            "register",
+        ],
+        Some(in_toto_message()),
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let audit = std::fs::read_to_string(&audit_log).unwrap();
+    assert_eq!(audit.lines().count(), 1);
+
+    // An invalid configuration is rejected before the command runs
+    std::fs::write(
+        config_path,
+        json!({ "extractors": { "unknown": {} } }).to_string(),
+    )
+    .unwrap();
+    let output = rvps_cli(
+        &[
+            "--cache-path",
+            cache_path,
+            "--config",
+            config_path,
+            "export",
+        ],
+        None,
+    );
+    assert!(!output.status.success());
+}
+
+#[tokio::test]
+#[serial]
+async fn cli_remote_server() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server = format!("http://{}", listener.local_addr().unwrap());
+    let (shutdown, rx) = oneshot::channel::<()>();
+    let core = new_shared_core(CacheType::Simple, None).unwrap();
+    let handle = tokio::spawn(serve_with_listener(core, listener, async {
+        let _ = rx.await;
+    }));
+
+    let output = tokio::task::spawn_blocking(move || {
+        let output = rvps_cli(&["--server", &server, "register"], Some(in_toto_message()));
+        assert!(output.status.success(), "{:?}", output);
+        rvps_cli(&["--server", &server, "-o", "json", "list"], None)
+    })
+    .await
+    .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let rvs: Vec<ReferenceValue> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(rvs.len(), 1);
+    assert_eq!(rvs[0].name(), IN_TOTO_ARTIFACT);
+
+    shutdown.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
@@ -0,0 +1,21 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Helpers shared by the integration tests.
+
+use reference_value_provider_service::extractors::extractor_modules::in_toto::ProvenanceBuilder;
+
+/// Name of the artifact in the in-toto test provenance.
+pub const IN_TOTO_ARTIFACT: &str = "foo.tar.gz";
+
+/// Directory of the in-toto test provenance.
+pub const IN_TOTO_DIR: &str = "../tests/in-toto";
+
+/// Helps to generate a in-toto provenance encoded
+/// in Base64. All related files are in `<git-repo>/tests/in-toto`
+pub fn generate_in_toto_provenance() -> String {
+    let provenance = ProvenanceBuilder::new(IN_TOTO_DIR).build().unwrap();
+    serde_json::to_string(&provenance).unwrap()
+}
@@ -0,0 +1,284 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Integration tests of the gRPC API against a local server.
+
+mod common;
+
+use std::net::SocketAddr;
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+};
+use rvps::provenance_api::{
+    grpc::{
+        proto::{
+            reference_value_provider_service_client::ReferenceValueProviderServiceClient,
+            DeleteReferenceValueRequest, EventType, GetCapabilitiesRequest,
+            GetReferenceValueRequest, ListReferenceValuesRequest, Message, VerifyAndExtractRequest,
+            WatchRequest,
+        },
+        serve_with_listener,
+    },
+    new_shared_core, rest, CacheType, SharedCore,
+};
+use serde_json::json;
+use serial_test::serial;
+use tokio::{net::TcpListener, sync::oneshot, task::JoinHandle};
+use tonic::{transport::Channel, Code};
+use tower::ServiceExt;
+
+use common::{generate_in_toto_provenance, IN_TOTO_ARTIFACT};
+
+/// A local server. Dropping the `shutdown` sender or sending
+/// on it shuts the server down gracefully.
+struct TestServer {
+    addr: SocketAddr,
+    shutdown: oneshot::Sender<()>,
+    handle: JoinHandle<anyhow::Result<()>>,
+}
+
+async fn start_server() -> TestServer {
+    start_server_with_core(new_shared_core(CacheType::Simple, None).unwrap()).await
+}
+
+async fn start_server_with_core(core: SharedCore) -> TestServer {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (shutdown, rx) = oneshot::channel::<()>();
+    let handle = tokio::spawn(serve_with_listener(core, listener, async {
+        let _ = rx.await;
+    }));
+
+    TestServer {
+        addr,
+        shutdown,
+        handle,
+    }
+}
+
+async fn connect(addr: SocketAddr) -> ReferenceValueProviderServiceClient<Channel> {
+    ReferenceValueProviderServiceClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+}
+
+fn in_toto_request() -> VerifyAndExtractRequest {
+    VerifyAndExtractRequest {
+        message: Some(Message {
+            version: String::new(),
+            r#type: "in-toto".into(),
+            payload: generate_in_toto_provenance(),
+        }),
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn grpc_verify_get_list_delete() {
+    let server = start_server().await;
+    let mut client = connect(server.addr).await;
+
+    let mut watch = client
+        .watch(WatchRequest {
+            name: IN_TOTO_ARTIFACT.into(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    let response = client
+        .verify_and_extract(in_toto_request())
+        .await
+        .unwrap()
+        .into_inner();
+    let rvs = response.reference_values;
+    assert_eq!(rvs.len(), 1);
+    let rv = rvs[0].clone();
+    assert_eq!(rv.name, IN_TOTO_ARTIFACT);
+    assert_eq!(rv.hash_value[0].alg, "sha256");
+    let report = response.report.unwrap();
+    assert!(report.verified);
+    assert_eq!(report.reference_values, vec![IN_TOTO_ARTIFACT]);
+    assert_eq!(report.signers.len(), 1);
+
+    let got = client
+        .get_reference_value(GetReferenceValueRequest {
+            name: IN_TOTO_ARTIFACT.into(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .reference_value;
+    assert_eq!(got, Some(rv.clone()));
+
+    let all = client
+        .list_reference_values(ListReferenceValuesRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .reference_values;
+    assert_eq!(all, vec![rv.clone()]);
+
+    let deleted = client
+        .delete_reference_value(DeleteReferenceValueRequest {
+            name: IN_TOTO_ARTIFACT.into(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .reference_value;
+    assert_eq!(deleted, Some(rv.clone()));
+
+    let status = client
+        .get_reference_value(GetReferenceValueRequest {
+            name: IN_TOTO_ARTIFACT.into(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+
+    let event = watch.message().await.unwrap().unwrap();
+    assert_eq!(event.event_type, EventType::Updated as i32);
+    assert_eq!(event.reference_value, Some(rv.clone()));
+    let event = watch.message().await.unwrap().unwrap();
+    assert_eq!(event.event_type, EventType::Deleted as i32);
+    assert_eq!(event.reference_value, Some(rv));
+
+    server.shutdown.send(()).unwrap();
+    server.handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn grpc_watch_rest_changes() {
+    let core = new_shared_core(CacheType::Simple, None).unwrap();
+    let server = start_server_with_core(core.clone()).await;
+    let app = rest::router(core);
+    let mut client = connect(server.addr).await;
+
+    let mut watch = client
+        .watch(WatchRequest::default())
+        .await
+        .unwrap()
+        .into_inner();
+
+    // Register and delete the reference value by the REST API
+    let message = json!({
+        "type": "in-toto",
+        "payload": generate_in_toto_provenance(),
+    });
+    for (method, uri, body, status) in [
+        (
+            Method::POST,
+            "/reference-values".to_string(),
+            Body::from(message.to_string()),
+            StatusCode::CREATED,
+        ),
+        (
+            Method::DELETE,
+            format!("/reference-values/{}", IN_TOTO_ARTIFACT),
+            Body::empty(),
+            StatusCode::OK,
+        ),
+    ] {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body)
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), status);
+    }
+
+    for event_type in [EventType::Updated, EventType::Deleted] {
+        let event = watch.message().await.unwrap().unwrap();
+        assert_eq!(event.event_type, event_type as i32);
+        assert_eq!(event.reference_value.unwrap().name, IN_TOTO_ARTIFACT);
+    }
+
+    server.shutdown.send(()).unwrap();
+    server.handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn grpc_verify_and_extract_failed() {
+    let server = start_server().await;
+    let mut client = connect(server.addr).await;
+
+    let mut request = in_toto_request();
+    request.message.as_mut().unwrap().r#type = "unknown".into();
+    let status = client.verify_and_extract(request).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    let mut request = in_toto_request();
+    request.message.as_mut().unwrap().version = "0.0".into();
+    let status = client.verify_and_extract(request).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    let status = client
+        .verify_and_extract(VerifyAndExtractRequest { message: None })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    let status = client
+        .delete_reference_value(DeleteReferenceValueRequest {
+            name: IN_TOTO_ARTIFACT.into(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+
+    server.shutdown.send(()).unwrap();
+    server.handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn grpc_get_capabilities() {
+    let server = start_server().await;
+    let mut client = connect(server.addr).await;
+
+    let capabilities = client
+        .get_capabilities(GetCapabilitiesRequest {})
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(capabilities.message_versions, vec!["0.1"]);
+    assert_eq!(capabilities.cache, "simple");
+    let in_toto = capabilities
+        .extractors
+        .iter()
+        .find(|extractor| extractor.name == "in-toto")
+        .unwrap();
+    assert_eq!(in_toto.payload_versions, vec!["0.9"]);
+    assert!(in_toto.available);
+
+    server.shutdown.send(()).unwrap();
+    server.handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn grpc_graceful_shutdown_closes_watchers() {
+    let server = start_server().await;
+    let mut client = connect(server.addr).await;
+
+    let mut watch = client
+        .watch(WatchRequest::default())
+        .await
+        .unwrap()
+        .into_inner();
+
+    server.shutdown.send(()).unwrap();
+
+    // The watch stream ends instead of blocking the shutdown
+    assert!(watch.message().await.unwrap().is_none());
+    server.handle.await.unwrap().unwrap();
+}
@@ -0,0 +1,186 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Integration tests of the REST API.
+
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+    Router,
+};
+use rvps::provenance_api::{
+    new_shared_core,
+    rest::{openapi, router, ListResponse, RegisterResponse},
+    CacheType,
+};
+use serde_json::{json, Value};
+use serial_test::serial;
+use tower::ServiceExt;
+
+use common::{generate_in_toto_provenance, IN_TOTO_ARTIFACT};
+
+/// Send a request to the router, and get the status and the JSON body.
+async fn call(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    body: Option<String>,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(body.map_or_else(Body::empty, Body::from))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap())
+}
+
+fn in_toto_message() -> String {
+    json!({
+        "type": "in-toto",
+        "payload": generate_in_toto_provenance(),
+    })
+    .to_string()
+}
+
+#[tokio::test]
+#[serial]
+async fn rest_register_get_list_delete() {
+    let app = router(new_shared_core(CacheType::Simple, None).unwrap());
+
+    let (status, registered) = call(
+        &app,
+        Method::POST,
+        "/reference-values",
+        Some(in_toto_message()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let rvs = &registered["reference_values"];
+    assert_eq!(rvs.as_array().map(|rvs| rvs.len()), Some(1));
+    let rv = rvs[0].clone();
+    assert_eq!(rv["name"], IN_TOTO_ARTIFACT);
+    let registered: RegisterResponse = serde_json::from_value(registered).unwrap();
+    assert!(registered.report.verified);
+    assert_eq!(registered.report.reference_values, vec![IN_TOTO_ARTIFACT]);
+    assert_eq!(registered.report.signers.len(), 1);
+
+    let uri = format!("/reference-values/{}", IN_TOTO_ARTIFACT);
+    let (status, got) = call(&app, Method::GET, &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(got, rv);
+
+    let (status, page) = call(&app, Method::GET, "/reference-values?alg=sha256", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let page: ListResponse = serde_json::from_value(page).unwrap();
+    assert_eq!(page.total, 1);
+    assert_eq!(page.items[0].name(), IN_TOTO_ARTIFACT);
+
+    let (status, page) = call(&app, Method::GET, "/reference-values?name_prefix=bar", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["total"], 0);
+
+    let (status, page) = call(
+        &app,
+        Method::GET,
+        "/reference-values?offset=1&limit=1",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["items"], json!([]));
+
+    let (status, deleted) = call(&app, Method::DELETE, &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(deleted, rv);
+
+    let (status, err) = call(&app, Method::GET, &uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(err["error"]["code"], "not_found");
+
+    let (status, err) = call(&app, Method::DELETE, &uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(err["error"]["code"], "not_found");
+}
+
+#[tokio::test]
+#[serial]
+async fn rest_register_errors() {
+    let app = router(new_shared_core(CacheType::Simple, None).unwrap());
+
+    let (status, err) = call(&app, Method::POST, "/reference-values", Some("{".into())).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(err["error"]["code"], "invalid_request");
+
+    let message = json!({ "type": "unknown", "payload": "" }).to_string();
+    let (status, err) = call(&app, Method::POST, "/reference-values", Some(message)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(err["error"]["code"], "unsupported_type");
+
+    let message = json!({ "version": "0.0", "type": "in-toto", "payload": "" }).to_string();
+    let (status, err) = call(&app, Method::POST, "/reference-values", Some(message)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(err["error"]["code"], "unsupported_version");
+
+    let message = json!({ "type": "in-toto", "payload": "{}" }).to_string();
+    let (status, err) = call(&app, Method::POST, "/reference-values", Some(message)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(err["error"]["code"], "verification_failed");
+    assert_eq!(err["error"]["report"]["verified"], false);
+    assert_eq!(
+        err["error"]["report"]["failed_rules"][0]["rule"],
+        "verification"
+    );
+
+    let (status, err) = call(&app, Method::GET, "/reference-values?limit=0", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(err["error"]["code"], "invalid_request");
+
+    // Malformed query parameters are told in the JSON error as well
+    let (status, err) = call(&app, Method::GET, "/reference-values?limit=abc", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(err["error"]["code"], "invalid_request");
+
+    let (status, err) = call(&app, Method::GET, "/reference-values?offset=-1", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(err["error"]["code"], "invalid_request");
+}
+
+#[tokio::test]
+#[serial]
+async fn rest_capabilities() {
+    let app = router(new_shared_core(CacheType::Simple, None).unwrap());
+
+    let (status, capabilities) = call(&app, Method::GET, "/capabilities", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(capabilities["message_versions"], json!(["0.1"]));
+    assert_eq!(capabilities["cache"], "simple");
+    let in_toto = capabilities["extractors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|extractor| extractor["name"] == "in-toto")
+        .unwrap();
+    assert_eq!(in_toto["payload_versions"], json!(["0.9"]));
+    assert_eq!(in_toto["available"], true);
+}
+
+#[tokio::test]
+async fn rest_openapi_document_is_published() {
+    let app = router(new_shared_core(CacheType::Simple, None).unwrap());
+    let (status, document) = call(&app, Method::GET, "/openapi.json", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(document, openapi());
+
+    // Regenerate with `cargo run --bin rvps -- --print-openapi > docs/openapi.json`
+    let published: Value = serde_json::from_str(include_str!("../../docs/openapi.json")).unwrap();
+    assert_eq!(published, openapi());
+}
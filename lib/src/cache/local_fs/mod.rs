@@ -0,0 +1,123 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! A cache persisting rv in a JSON file on the local file
+//! system. The stored rv will be loaded again when a new
+//! cache is created on the same file.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use tempfile::NamedTempFile;
+
+use crate::reference_value::ReferenceValue;
+
+use super::Cache;
+
+pub struct LocalFsCache {
+    path: PathBuf,
+    inner: BTreeMap<String, ReferenceValue>,
+}
+
+impl LocalFsCache {
+    /// Create a cache on the file `path`. If the file exists, the
+    /// rv stored in it will be loaded.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let inner = match path.exists() {
+            true => serde_json::from_slice(&fs::read(&path)?)
+                .map_err(|e| anyhow!("Load cache file {:?} failed: {}", path, e))?,
+            false => BTreeMap::new(),
+        };
+
+        Ok(Self { path, inner })
+    }
+
+    /// Write all the rv into the file. A temporary file in the
+    /// same directory is written first and then renamed, s.t. the
+    /// file will never be half-written.
+    fn flush(&self) -> Result<()> {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        fs::create_dir_all(dir)?;
+
+        let mut file = NamedTempFile::new_in(dir)?;
+        file.write_all(&serde_json::to_vec_pretty(&self.inner)?)?;
+        file.as_file().sync_all()?;
+        file.persist(&self.path)?;
+        Ok(())
+    }
+}
+
+impl Cache for LocalFsCache {
+    fn set(&mut self, name: String, rv: ReferenceValue) -> Result<()> {
+        self.inner.insert(name, rv);
+        self.flush()
+    }
+
+    fn get(&self, name: &str) -> Result<Option<ReferenceValue>> {
+        Ok(self.inner.get(name).cloned())
+    }
+
+    fn delete(&mut self, name: &str) -> Result<Option<ReferenceValue>> {
+        let rv = self.inner.remove(name);
+        if rv.is_some() {
+            self.flush()?;
+        }
+        Ok(rv)
+    }
+
+    fn get_all(&self) -> Result<Vec<ReferenceValue>> {
+        Ok(self.inner.values().cloned().collect())
+    }
+
+    fn name(&self) -> &str {
+        "local-fs"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+
+    use crate::{cache::Cache, ReferenceValue};
+
+    use super::LocalFsCache;
+
+    #[test]
+    fn local_fs_cache_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rvps").join("cache.json");
+        let rv = ReferenceValue::new()
+            .set_name("artifact")
+            .set_expired(Utc.ymd(1970, 1, 1).and_hms(0, 0, 0))
+            .add_hash_value("sha256".into(), "123".into());
+
+        let mut cache = LocalFsCache::new(&path).unwrap();
+        cache.set("artifact".into(), rv.clone()).unwrap();
+        cache.set("another".into(), rv.clone()).unwrap();
+        cache.delete("another").unwrap();
+
+        // Reopen the same file
+        let cache = LocalFsCache::new(&path).unwrap();
+        assert_eq!(cache.get("artifact").unwrap(), Some(rv.clone()));
+        assert_eq!(cache.get("another").unwrap(), None);
+        assert_eq!(cache.get_all().unwrap(), vec![rv]);
+    }
+
+    #[test]
+    fn local_fs_cache_corrupted_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "not json").unwrap();
+        assert!(LocalFsCache::new(file.path()).is_err());
+    }
+}
@@ -9,17 +9,54 @@ use crate::reference_value::ReferenceValue;
 
 use anyhow::Result;
 
+pub mod local_fs;
 pub mod simple;
 
 /// Interface of an Cache.
-/// We only provide a simple instance here which implements
-/// Cache. In more scenerios, RV should be stored in persistent
-/// storage, like database, file and so on. All of the mentioned
-/// forms will have the same interface as following.
+/// We provide a simple instance storing RV in memory and a local
+/// fs instance storing RV in a file here. In more scenerios, RV
+/// should be stored in persistent storage, like database and so
+/// on. All of the mentioned forms will have the same interface
+/// as following.
 pub trait Cache {
     /// Store a reference value
     fn set(&mut self, name: String, rv: ReferenceValue) -> Result<()>;
 
     // Retrieve a reference value
     fn get(&self, name: &str) -> Result<Option<ReferenceValue>>;
+
+    /// Delete a reference value, and return the deleted one if any
+    fn delete(&mut self, name: &str) -> Result<Option<ReferenceValue>>;
+
+    /// Retrieve all the stored reference values
+    fn get_all(&self) -> Result<Vec<ReferenceValue>>;
+
+    /// Name of the Cache backend, e.g. `simple`
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// A boxed Cache is also a Cache, s.t. the concrete Cache of a `Core`
+/// can be chosen at runtime, e.g. `Core<Box<dyn Cache + Send + Sync>>`.
+impl<C: Cache + ?Sized> Cache for Box<C> {
+    fn set(&mut self, name: String, rv: ReferenceValue) -> Result<()> {
+        (**self).set(name, rv)
+    }
+
+    fn get(&self, name: &str) -> Result<Option<ReferenceValue>> {
+        (**self).get(name)
+    }
+
+    fn delete(&mut self, name: &str) -> Result<Option<ReferenceValue>> {
+        (**self).delete(name)
+    }
+
+    fn get_all(&self) -> Result<Vec<ReferenceValue>> {
+        (**self).get_all()
+    }
+
+    fn name(&self) -> &str {
+        (**self).name()
+    }
 }
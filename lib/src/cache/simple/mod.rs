@@ -28,6 +28,18 @@ impl Cache for SimpleCache {
     fn get(&self, name: &str) -> Result<Option<ReferenceValue>> {
         Ok(self.inner.get(name).cloned())
     }
+
+    fn delete(&mut self, name: &str) -> Result<Option<ReferenceValue>> {
+        Ok(self.inner.remove(name))
+    }
+
+    fn get_all(&self) -> Result<Vec<ReferenceValue>> {
+        Ok(self.inner.values().cloned().collect())
+    }
+
+    fn name(&self) -> &str {
+        "simple"
+    }
 }
 
 impl SimpleCache {
@@ -0,0 +1,160 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Config file of RVPS.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::extractors::ExtractorsConfig;
+
+#[cfg(feature = "wasm")]
+use crate::extractors::extractor_modules::wasm::Config as WasmConfig;
+
+/// Config of RVPS, read from a JSON file.
+/// * `extractors`: configuration of the Extractors, each in a section
+/// keyed by its provenance type, e.g. `in-toto`. It is the only source
+/// of the policies of the built-in Extractors.
+/// * `wasm`: configuration of the WASM plugins, loaded as the
+/// Extractors of their provenance types. No plugin is loaded if not
+/// set. Only available with feature `wasm`.
+/// * `audit_log`: path to the file storing the verification reports,
+/// one JSON object in a line. No report is stored if not set.
+/// * `broadcaster`: configuration of the Broadcaster of the `rvps`
+/// server. Reference values are not broadcasted if not set.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub extractors: ExtractorsConfig,
+    #[cfg(feature = "wasm")]
+    pub wasm: Option<WasmConfig>,
+    pub audit_log: Option<PathBuf>,
+    pub broadcaster: Option<BroadcasterConfig>,
+}
+
+/// Config of the Broadcaster, which signs the registered reference
+/// values and publishes them to the subscribers, e.g. the Attestation
+/// Service.
+/// * `signing_key`: path to the RVPS signing key, a PKCS#8 Ed25519
+/// private key in PEM format.
+/// * `redis_addr`: address of the redis server to publish on.
+/// * `channel`: redis channel to publish on, `rvps` if not set.
+/// * `username` and `password`: credentials of the redis server. They
+/// override the ones given in `redis_addr`.
+/// * `connect_timeout` and `io_timeout`: timeouts in milliseconds of a
+/// connection attempt and of an operation on the connection.
+/// * `max_retries`: maximum number of reconnection attempts.
+/// * `initial_backoff` and `max_backoff`: bounds in milliseconds of the
+/// exponential backoff between two reconnection attempts.
+///
+/// The Broadcaster's defaults are used for the options not set.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BroadcasterConfig {
+    pub signing_key: PathBuf,
+    pub redis_addr: String,
+    #[serde(default = "default_channel")]
+    pub channel: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub connect_timeout: Option<u64>,
+    pub io_timeout: Option<u64>,
+    pub max_retries: Option<u32>,
+    pub initial_backoff: Option<u64>,
+    pub max_backoff: Option<u64>,
+}
+
+fn default_channel() -> String {
+    "rvps".into()
+}
+
+impl Config {
+    /// Read the config from a JSON file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use serde_json::json;
+
+    use super::Config;
+
+    #[test]
+    fn config_from_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let content = json!({
+            "extractors": {
+                "in-toto": { "layout_path": "/etc/rvps/in-toto/root.layout" },
+                "checksums": { "trusted_keys": [], "threshold": 1 },
+            },
+            "audit_log": "/var/log/rvps/audit.log",
+            "broadcaster": {
+                "signing_key": "/etc/rvps/rvps.key",
+                "redis_addr": "redis://127.0.0.1:6379",
+            },
+        });
+        file.write_all(content.to_string().as_bytes()).unwrap();
+
+        let config = Config::from_file(file.path()).unwrap();
+        assert_eq!(config.extractors.len(), 2);
+        assert_eq!(
+            config.extractors["in-toto"]["layout_path"],
+            "/etc/rvps/in-toto/root.layout"
+        );
+        assert_eq!(
+            config.audit_log.unwrap().to_str(),
+            Some("/var/log/rvps/audit.log")
+        );
+        let broadcaster = config.broadcaster.unwrap();
+        assert_eq!(broadcaster.signing_key.to_str(), Some("/etc/rvps/rvps.key"));
+        assert_eq!(broadcaster.channel, "rvps");
+        assert_eq!(broadcaster.max_retries, None);
+
+        let config: Config = serde_json::from_value(json!({
+            "broadcaster": {
+                "signing_key": "/etc/rvps/rvps.key",
+                "redis_addr": "redis://127.0.0.1:6379",
+                "username": "rvps",
+                "password": "secret",
+                "connect_timeout": 1000,
+                "io_timeout": 2000,
+                "max_retries": 3,
+                "initial_backoff": 50,
+                "max_backoff": 5000,
+            },
+        }))
+        .unwrap();
+        let broadcaster = config.broadcaster.unwrap();
+        assert_eq!(broadcaster.username.as_deref(), Some("rvps"));
+        assert_eq!(broadcaster.io_timeout, Some(2000));
+        assert_eq!(broadcaster.max_retries, Some(3));
+        assert_eq!(broadcaster.max_backoff, Some(5000));
+
+        // Unknown sections are rejected, e.g. typos
+        assert!(serde_json::from_value::<Config>(json!({ "extractor": {} })).is_err());
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn config_wasm() {
+        let config: Config =
+            serde_json::from_value(json!({ "wasm": { "plugin_dir": "/etc/rvps/plugins" } }))
+                .unwrap();
+        let wasm = config.wasm.unwrap();
+        assert_eq!(wasm.plugin_dir.to_str(), Some("/etc/rvps/plugins"));
+        assert_eq!(wasm.fuel, 100_000_000);
+    }
+}
@@ -0,0 +1,603 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! # DSSE
+//!
+//! [Dead Simple Signing Envelope](https://github.com/secure-systems-lab/dsse)
+//! for RVPS. An `Envelope` wraps an arbitrary payload together with
+//! its payload type and signatures. The signatures are calculated over
+//! the PAE (Pre-Authentication Encoding) of the payload type and the
+//! payload, s.t. the payload type is also authenticated.
+//!
+//! Envelopes can be verified with Ed25519, ECDSA P-256/P-384 and
+//! RSA-PSS public keys, either by a single `Verifier`, or by a
+//! `ThresholdVerifier` requiring signatures of several trusted keys.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use ring::{
+    digest,
+    signature::{self, Ed25519KeyPair, KeyPair, UnparsedPublicKey, VerificationAlgorithm},
+};
+use serde::{Deserialize, Serialize};
+use spki::{ObjectIdentifier, SubjectPublicKeyInfo};
+
+/// OID of Ed25519 public key in SubjectPublicKeyInfo
+const ED25519_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+
+/// OID of EC public key in SubjectPublicKeyInfo
+const EC_PUBLIC_KEY_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+
+/// OID of NIST P-256 curve
+const P256_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+
+/// OID of NIST P-384 curve
+const P384_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.34");
+
+/// OID of RSA public key in SubjectPublicKeyInfo
+const RSA_ENCRYPTION_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+
+/// OID of RSASSA-PSS public key in SubjectPublicKeyInfo
+const RSASSA_PSS_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.10");
+
+/// A signature inside an `Envelope`.
+/// * `keyid`: optional hint of the key which made the signature.
+/// * `sig`: the signature, base64 encoded.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Signature {
+    #[serde(default)]
+    pub keyid: String,
+    pub sig: String,
+}
+
+/// The DSSE envelope.
+/// * `payload`: the signed content, base64 encoded.
+/// * `payload_type`: type of the payload, e.g. a media type.
+/// * `signatures`: signatures over PAE(payload_type, payload).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Envelope {
+    pub payload: String,
+    #[serde(rename = "payloadType")]
+    pub payload_type: String,
+    pub signatures: Vec<Signature>,
+}
+
+impl Envelope {
+    /// Get the decoded payload. Note that the payload is NOT
+    /// verified by this function.
+    pub fn payload_bytes(&self) -> Result<Vec<u8>> {
+        Ok(base64::decode(&self.payload)?)
+    }
+}
+
+/// Pre-Authentication Encoding defined in DSSE v1
+/// `"DSSEv1" SP LEN(type) SP type SP LEN(body) SP body`
+pub fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut res = format!(
+        "DSSEv1 {} {} {} ",
+        payload_type.len(),
+        payload_type,
+        payload.len()
+    )
+    .into_bytes();
+    res.extend_from_slice(payload);
+    res
+}
+
+/// Calculate the key id of a public key, s.t. the hex encoded
+/// sha256 digest of its DER encoded SubjectPublicKeyInfo.
+fn key_id(spki_der: &[u8]) -> String {
+    digest::digest(&digest::SHA256, spki_der)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Encode a raw Ed25519 public key into DER SubjectPublicKeyInfo
+pub fn ed25519_spki_der(public_key: &[u8]) -> Vec<u8> {
+    let mut der = vec![
+        0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+    ];
+    der.extend_from_slice(public_key);
+    der
+}
+
+/// Read the contents of a PEM file with the expected tag.
+fn read_pem(pem_str: &str, tag: &str) -> Result<Vec<u8>> {
+    let pem = pem::parse(pem_str)?;
+    if pem.tag != tag {
+        return Err(anyhow!(
+            "Unexpected PEM tag: need {}, given {}.",
+            tag,
+            pem.tag
+        ));
+    }
+    Ok(pem.contents)
+}
+
+/// `Signer` signs payloads and wraps them into `Envelope`s.
+/// Now only Ed25519 private keys are supported.
+pub struct Signer {
+    keyid: String,
+    key_pair: Ed25519KeyPair,
+}
+
+impl Signer {
+    /// Create a `Signer` from a PKCS#8 encoded private key in PEM format.
+    pub fn from_pem(pem_str: &str) -> Result<Self> {
+        let der = read_pem(pem_str, "PRIVATE KEY")?;
+        let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(&der)
+            .map_err(|e| anyhow!("Load Ed25519 private key failed: {}", e))?;
+        let keyid = key_id(&ed25519_spki_der(key_pair.public_key().as_ref()));
+        Ok(Self { keyid, key_pair })
+    }
+
+    /// Create a `Signer` from a PEM file on disk.
+    pub fn from_pem_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let pem_str = fs::read_to_string(path)?;
+        Self::from_pem(&pem_str)
+    }
+
+    /// Key id of the signing key.
+    pub fn keyid(&self) -> &str {
+        &self.keyid
+    }
+
+    /// Public key of the signing key, as a PEM encoded
+    /// SubjectPublicKeyInfo. Subscribers can use this to
+    /// create a `Verifier`.
+    pub fn public_key_pem(&self) -> String {
+        pem::encode(&pem::Pem {
+            tag: "PUBLIC KEY".into(),
+            contents: ed25519_spki_der(self.key_pair.public_key().as_ref()),
+        })
+    }
+
+    /// Sign the payload and generate an `Envelope`.
+    pub fn sign(&self, payload_type: &str, payload: &[u8]) -> Envelope {
+        let sig = self.key_pair.sign(&pae(payload_type, payload));
+        Envelope {
+            payload: base64::encode(payload),
+            payload_type: payload_type.into(),
+            signatures: vec![Signature {
+                keyid: self.keyid.clone(),
+                sig: base64::encode(sig.as_ref()),
+            }],
+        }
+    }
+}
+
+/// Types of the public keys supported by `Verifier`.
+/// * `Ed25519`: Ed25519.
+/// * `EcdsaP256`: ECDSA over P-256 with SHA-256.
+/// * `EcdsaP384`: ECDSA over P-384 with SHA-384.
+/// * `RsaPss`: RSASSA-PSS with SHA-256, 2048 to 8192 bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyType {
+    Ed25519,
+    EcdsaP256,
+    EcdsaP384,
+    RsaPss,
+}
+
+impl KeyType {
+    /// Get the key type from the algorithm of a SubjectPublicKeyInfo.
+    fn from_spki(spki: &SubjectPublicKeyInfo) -> Result<Self> {
+        match spki.algorithm.oid {
+            ED25519_OID => Ok(KeyType::Ed25519),
+            EC_PUBLIC_KEY_OID => {
+                let curve = spki
+                    .algorithm
+                    .parameters_oid()
+                    .map_err(|e| anyhow!("Parse EC curve failed: {}", e))?;
+                match curve {
+                    P256_OID => Ok(KeyType::EcdsaP256),
+                    P384_OID => Ok(KeyType::EcdsaP384),
+                    _ => Err(anyhow!("Unsupported EC curve: {}", curve)),
+                }
+            }
+            RSA_ENCRYPTION_OID | RSASSA_PSS_OID => Ok(KeyType::RsaPss),
+            oid => Err(anyhow!("Unsupported public key algorithm: {}", oid)),
+        }
+    }
+
+    /// Get the verification algorithm of the key type. ECDSA
+    /// signatures are usually ASN.1 DER encoded, but fixed-length
+    /// `r || s` ones are also accepted.
+    fn algorithm(&self, sig: &[u8]) -> &'static dyn VerificationAlgorithm {
+        match self {
+            KeyType::Ed25519 => &signature::ED25519,
+            KeyType::EcdsaP256 if sig.len() == 64 => &signature::ECDSA_P256_SHA256_FIXED,
+            KeyType::EcdsaP256 => &signature::ECDSA_P256_SHA256_ASN1,
+            KeyType::EcdsaP384 if sig.len() == 96 => &signature::ECDSA_P384_SHA384_FIXED,
+            KeyType::EcdsaP384 => &signature::ECDSA_P384_SHA384_ASN1,
+            KeyType::RsaPss => &signature::RSA_PSS_2048_8192_SHA256,
+        }
+    }
+}
+
+/// `Verifier` verifies `Envelope`s with a trusted public key.
+/// See `KeyType` for the supported keys.
+pub struct Verifier {
+    keyid: String,
+    key_type: KeyType,
+    public_key: Vec<u8>,
+}
+
+impl Verifier {
+    /// Create a `Verifier` from a SubjectPublicKeyInfo in PEM format.
+    pub fn from_pem(pem_str: &str) -> Result<Self> {
+        let der = read_pem(pem_str, "PUBLIC KEY")?;
+        Self::from_der(&der)
+    }
+
+    /// Create a `Verifier` from a SubjectPublicKeyInfo in DER format,
+    /// e.g. the public key of a certificate.
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let spki = SubjectPublicKeyInfo::try_from(der)
+            .map_err(|e| anyhow!("Parse public key failed: {}", e))?;
+        let key_type = KeyType::from_spki(&spki)?;
+
+        Ok(Self {
+            keyid: key_id(der),
+            key_type,
+            public_key: spki.subject_public_key.to_vec(),
+        })
+    }
+
+    /// Create a `Verifier` from a PEM file on disk.
+    pub fn from_pem_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let pem_str = fs::read_to_string(path)?;
+        Self::from_pem(&pem_str)
+    }
+
+    /// Key id of the trusted key.
+    pub fn keyid(&self) -> &str {
+        &self.keyid
+    }
+
+    /// Type of the trusted key.
+    pub fn key_type(&self) -> KeyType {
+        self.key_type
+    }
+
+    /// Verify a raw signature over `message` made by the trusted key.
+    /// This helps to verify signatures outside of an envelope, e.g.
+    /// those of a transparency log.
+    pub fn verify_signature(&self, message: &[u8], sig: &[u8]) -> Result<()> {
+        UnparsedPublicKey::new(self.key_type.algorithm(sig), &self.public_key)
+            .verify(message, sig)
+            .map_err(|_| anyhow!("Invalid signature."))
+    }
+
+    /// Whether one of the signatures of the envelope is made by
+    /// the trusted key, over the PAE `message`.
+    fn verify_signatures(&self, envelope: &Envelope, message: &[u8]) -> bool {
+        envelope
+            .signatures
+            .iter()
+            // An empty keyid is only a missing hint, so try the key anyway
+            .filter(|s| s.keyid.is_empty() || s.keyid == self.keyid)
+            .filter_map(|s| base64::decode(&s.sig).ok())
+            .any(|sig| self.verify_signature(message, &sig).is_ok())
+    }
+
+    /// Verify the envelope. The payload type must be the same as
+    /// `payload_type`, and at least one of the signatures must be
+    /// made by the trusted key. If verification succeeds, the
+    /// decoded payload will be returned.
+    pub fn verify(&self, envelope: &Envelope, payload_type: &str) -> Result<Vec<u8>> {
+        check_payload_type(envelope, payload_type)?;
+
+        let payload = envelope.payload_bytes()?;
+        let message = pae(&envelope.payload_type, &payload);
+
+        match self.verify_signatures(envelope, &message) {
+            true => Ok(payload),
+            false => Err(anyhow!("No valid signature found in the envelope.")),
+        }
+    }
+}
+
+/// Check the payload type of the envelope.
+fn check_payload_type(envelope: &Envelope, payload_type: &str) -> Result<()> {
+    if envelope.payload_type != payload_type {
+        return Err(anyhow!(
+            "Payload type unmatched! Need {}, given {}.",
+            payload_type,
+            envelope.payload_type
+        ));
+    }
+    Ok(())
+}
+
+/// `ThresholdVerifier` verifies `Envelope`s with a set of trusted
+/// public keys, and requires valid signatures of at least `threshold`
+/// distinct keys.
+/// * `verifiers`: the trusted keys. Duplicated keys only count once.
+/// * `threshold`: the least number of keys needed.
+pub struct ThresholdVerifier {
+    verifiers: Vec<Verifier>,
+    threshold: usize,
+}
+
+impl ThresholdVerifier {
+    pub fn new(verifiers: Vec<Verifier>, threshold: usize) -> Result<Self> {
+        if threshold == 0 {
+            return Err(anyhow!("Threshold must be at least 1."));
+        }
+        Ok(Self {
+            verifiers,
+            threshold,
+        })
+    }
+
+    /// Create a `ThresholdVerifier` from PEM files on disk.
+    pub fn from_pem_files<P: AsRef<Path>>(paths: &[P], threshold: usize) -> Result<Self> {
+        let verifiers = paths
+            .iter()
+            .map(Verifier::from_pem_file)
+            .collect::<Result<_>>()?;
+        Self::new(verifiers, threshold)
+    }
+
+    /// Verify the envelope. The payload type must be the same as
+    /// `payload_type`, and at least `threshold` distinct trusted keys
+    /// must have signed it. If verification succeeds, the decoded
+    /// payload will be returned.
+    pub fn verify(&self, envelope: &Envelope, payload_type: &str) -> Result<Vec<u8>> {
+        self.verify_with_signers(envelope, payload_type)
+            .map(|(payload, _)| payload)
+    }
+
+    /// Verify the envelope like `verify`, and also return the sorted
+    /// key ids of the trusted keys which signed it.
+    pub fn verify_with_signers(
+        &self,
+        envelope: &Envelope,
+        payload_type: &str,
+    ) -> Result<(Vec<u8>, Vec<String>)> {
+        check_payload_type(envelope, payload_type)?;
+
+        let payload = envelope.payload_bytes()?;
+        let message = pae(&envelope.payload_type, &payload);
+
+        let signed: HashSet<&str> = self
+            .verifiers
+            .iter()
+            .filter(|v| v.verify_signatures(envelope, &message))
+            .map(|v| v.keyid())
+            .collect();
+
+        match signed.len() >= self.threshold {
+            true => {
+                let mut signers: Vec<String> = signed.into_iter().map(String::from).collect();
+                signers.sort();
+                Ok((payload, signers))
+            }
+            false => Err(anyhow!(
+                "Only {} of the {} required trusted keys signed the envelope.",
+                signed.len(),
+                self.threshold
+            )),
+        }
+    }
+}
+
+/// Keys trusted to sign the envelopes, shared by the policies of the
+/// Extractors verifying DSSE envelopes, and flattened into their
+/// sections of the config file.
+/// * `trusted_keys`: paths of the PEM encoded public keys.
+/// * `threshold`: an envelope must be signed by at least this number
+/// of the trusted keys. Defaults to 1.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrustPolicy {
+    pub trusted_keys: Vec<PathBuf>,
+    #[serde(default = "default_threshold")]
+    pub threshold: usize,
+}
+
+/// Use to set default threshold of TrustPolicy
+fn default_threshold() -> usize {
+    1
+}
+
+impl Default for TrustPolicy {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl TrustPolicy {
+    /// Trust the keys, requiring the signature of any one of them.
+    pub fn new(trusted_keys: Vec<PathBuf>) -> Self {
+        Self {
+            trusted_keys,
+            threshold: default_threshold(),
+        }
+    }
+
+    /// Read the trusted keys into a `ThresholdVerifier`. At least one
+    /// key must be trusted.
+    pub fn verifier(&self) -> Result<ThresholdVerifier> {
+        if self.trusted_keys.is_empty() {
+            return Err(anyhow!("No trusted keys given."));
+        }
+        ThresholdVerifier::from_pem_files(&self.trusted_keys, self.threshold)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use ring::{rand::SystemRandom, signature::Ed25519KeyPair};
+
+    use super::{pae, Envelope, KeyType, Signer, ThresholdVerifier, Verifier};
+
+    /// Test vectors generated by `<git-repo>/tests/dsse/generate.sh`
+    const TEST_VECTORS_DIR: &str = "../tests/dsse";
+
+    const TEST_VECTORS_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+    /// Keys of the test vectors
+    const TEST_VECTORS_KEYS: [(&str, KeyType); 4] = [
+        ("ed25519", KeyType::Ed25519),
+        ("ecdsa-p256", KeyType::EcdsaP256),
+        ("ecdsa-p384", KeyType::EcdsaP384),
+        ("rsa-pss", KeyType::RsaPss),
+    ];
+
+    /// A P-521 public key, which is not supported
+    const P521_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MIGbMBAGByqGSM49AgEGBSuBBAAjA4GGAAQBMo3iG1nxKclH+5FXcjUf5aH1UY5B
+7idrysSKfSkwS9BZo5ivzW4QQZfu0NaeNXORjszzUAQasCI5oPpCxbcMGOgANShN
+czBOg3YhEVUdonqb98TLoQ2WBz8phTywGhIpB8HNVmRLhlm6OoAABRGT5+Z9m1jh
+dkMXbIvWBkllKk7Km5I=
+-----END PUBLIC KEY-----";
+
+    fn test_vector_verifier(key: &str) -> Verifier {
+        Verifier::from_pem_file(format!("{}/{}.pub", TEST_VECTORS_DIR, key)).unwrap()
+    }
+
+    fn test_vector_envelope(name: &str) -> Envelope {
+        let content = fs::read_to_string(format!("{}/{}.json", TEST_VECTORS_DIR, name)).unwrap();
+        serde_json::from_str(&content).unwrap()
+    }
+
+    /// Helps to generate a fresh Ed25519 private key in PEM format.
+    pub fn generate_ed25519_private_key_pem() -> String {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        pem::encode(&pem::Pem {
+            tag: "PRIVATE KEY".into(),
+            contents: pkcs8.as_ref().to_vec(),
+        })
+    }
+
+    #[test]
+    fn dsse_pae() {
+        assert_eq!(
+            pae("http://example.com/HelloWorld", b"hello world"),
+            b"DSSEv1 29 http://example.com/HelloWorld 11 hello world".to_vec()
+        );
+    }
+
+    #[test]
+    fn dsse_sign_and_verify() {
+        let signer = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let verifier = Verifier::from_pem(&signer.public_key_pem()).unwrap();
+        assert_eq!(signer.keyid(), verifier.keyid());
+
+        let envelope = signer.sign("text/plain", b"payload");
+        let payload = verifier.verify(&envelope, "text/plain").unwrap();
+        assert_eq!(payload, b"payload".to_vec());
+
+        // Payload type is also authenticated
+        assert!(verifier.verify(&envelope, "application/json").is_err());
+        let mut tampered = envelope.clone();
+        tampered.payload_type = "application/json".into();
+        assert!(verifier.verify(&tampered, "application/json").is_err());
+
+        // Tampered payload
+        let mut tampered = envelope;
+        tampered.payload = base64::encode(b"another payload");
+        assert!(verifier.verify(&tampered, "text/plain").is_err());
+    }
+
+    #[test]
+    fn dsse_verify_with_untrusted_key() {
+        let signer = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let other = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let verifier = Verifier::from_pem(&other.public_key_pem()).unwrap();
+
+        let mut envelope = signer.sign("text/plain", b"payload");
+        assert!(verifier.verify(&envelope, "text/plain").is_err());
+
+        // Even without keyid hints
+        envelope.signatures[0].keyid = String::new();
+        assert!(verifier.verify(&envelope, "text/plain").is_err());
+    }
+
+    #[test]
+    fn dsse_test_vectors() {
+        let payload = fs::read(format!("{}/payload.json", TEST_VECTORS_DIR)).unwrap();
+
+        for (key, key_type) in TEST_VECTORS_KEYS {
+            let verifier = test_vector_verifier(key);
+            assert_eq!(verifier.key_type(), key_type);
+
+            let envelope = test_vector_envelope(key);
+            assert_eq!(envelope.signatures[0].keyid, verifier.keyid());
+            let verified = verifier
+                .verify(&envelope, TEST_VECTORS_PAYLOAD_TYPE)
+                .unwrap();
+            assert_eq!(verified, payload, "{}", key);
+
+            let mut tampered = envelope;
+            tampered.payload = base64::encode(b"another payload");
+            assert!(verifier
+                .verify(&tampered, TEST_VECTORS_PAYLOAD_TYPE)
+                .is_err());
+        }
+
+        // A key does not verify signatures of the others
+        let verifier = test_vector_verifier("ecdsa-p256");
+        let envelope = test_vector_envelope("ecdsa-p384");
+        assert!(verifier
+            .verify(&envelope, TEST_VECTORS_PAYLOAD_TYPE)
+            .is_err());
+
+        assert!(Verifier::from_pem(P521_PUBLIC_KEY).is_err());
+    }
+
+    #[test]
+    fn dsse_threshold() {
+        let verifiers = || -> Vec<Verifier> {
+            TEST_VECTORS_KEYS
+                .iter()
+                .map(|(key, _)| test_vector_verifier(key))
+                .collect()
+        };
+        let multi = test_vector_envelope("multi");
+        let single = test_vector_envelope("rsa-pss");
+
+        let verifier = ThresholdVerifier::new(verifiers(), 4).unwrap();
+        assert!(verifier.verify(&multi, TEST_VECTORS_PAYLOAD_TYPE).is_ok());
+        assert!(verifier.verify(&single, TEST_VECTORS_PAYLOAD_TYPE).is_err());
+
+        let verifier = ThresholdVerifier::new(verifiers(), 5).unwrap();
+        assert!(verifier.verify(&multi, TEST_VECTORS_PAYLOAD_TYPE).is_err());
+
+        let verifier = ThresholdVerifier::new(verifiers(), 1).unwrap();
+        assert!(verifier.verify(&single, TEST_VECTORS_PAYLOAD_TYPE).is_ok());
+        let (_, signers) = verifier
+            .verify_with_signers(&single, TEST_VECTORS_PAYLOAD_TYPE)
+            .unwrap();
+        assert_eq!(signers, vec![test_vector_verifier("rsa-pss").keyid()]);
+        let (_, signers) = verifier
+            .verify_with_signers(&multi, TEST_VECTORS_PAYLOAD_TYPE)
+            .unwrap();
+        assert_eq!(signers.len(), 4);
+
+        // The same key only counts once
+        let verifier = ThresholdVerifier::new(
+            vec![
+                test_vector_verifier("rsa-pss"),
+                test_vector_verifier("rsa-pss"),
+            ],
+            2,
+        )
+        .unwrap();
+        assert!(verifier.verify(&multi, TEST_VECTORS_PAYLOAD_TYPE).is_err());
+
+        assert!(ThresholdVerifier::new(verifiers(), 0).is_err());
+    }
+}
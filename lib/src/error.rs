@@ -0,0 +1,35 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Errors of RVPS
+
+use thiserror::Error;
+
+/// Kinds of failures of the RVPS Core. They are carried inside
+/// the returned `anyhow::Error`s (as the error itself or as a
+/// context), s.t. callers like servers can tell the kind of a
+/// failure by `downcast_ref::<RVPSError>()`.
+#[derive(Error, Debug, PartialEq)]
+pub enum RVPSError {
+    /// The version of the Message is not supported.
+    #[error("Version unmatched! Need {need}, given {given}.")]
+    VersionUnmatched { need: String, given: String },
+
+    /// No Extractor handles the provenance type of the Message.
+    #[error("RVPS Extractors does not support the given extractor: {0}!")]
+    UnsupportedProvenanceType(String),
+
+    /// An Extractor is already registered for the provenance type.
+    #[error("RVPS Extractors already has an extractor registered for: {0}!")]
+    DuplicateExtractor(String),
+
+    /// The configuration of an Extractor is invalid.
+    #[error("Invalid configuration of the extractor: {0}!")]
+    InvalidExtractorConfig(String),
+
+    /// The provenance is malformed or fails the verification.
+    #[error("Provenance verification failed")]
+    VerificationFailed,
+}
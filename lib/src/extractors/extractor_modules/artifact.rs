@@ -0,0 +1,72 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Artifacts referenced by a signed description, e.g. firmware and
+//! kernel of a confidential guest or a disk image, which are checked
+//! against the signed digests.
+
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use ring::digest::{digest, SHA256, SHA384, SHA512};
+use serde::{Deserialize, Serialize};
+
+/// An artifact, either carried by the provenance in base64,
+/// or referenced by a path relative to a trusted directory.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Artifact {
+    Content(String),
+    Path(PathBuf),
+}
+
+impl Artifact {
+    /// Read the artifact, and check it against the digest. Paths
+    /// must be relative and stay inside `dir`, and are not allowed
+    /// if `dir` is not given.
+    pub fn read(&self, dir: Option<&Path>, expected: &str) -> Result<Vec<u8>> {
+        let content = match self {
+            Artifact::Content(content) => {
+                base64::decode(content).map_err(|e| anyhow!("Decode artifact failed: {}", e))?
+            }
+            Artifact::Path(path) => {
+                let dir = dir.ok_or_else(|| anyhow!("Artifacts by path are not allowed."))?;
+                if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+                    return Err(anyhow!("Invalid artifact path: {:?}", path));
+                }
+                fs::read(dir.join(path))?
+            }
+        };
+
+        check_digest(&content, expected)?;
+        Ok(content)
+    }
+}
+
+/// Check that the content matches the digest, e.g. `sha384:<hex>`.
+pub fn check_digest(content: &[u8], expected: &str) -> Result<()> {
+    let (alg, value) = expected
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Malformed digest: {}", expected))?;
+    let algorithm = match alg {
+        "sha256" => &SHA256,
+        "sha384" => &SHA384,
+        "sha512" => &SHA512,
+        _ => return Err(anyhow!("Unsupported digest algorithm: {}", alg)),
+    };
+
+    if hex(digest(algorithm, content).as_ref()) != value.to_lowercase() {
+        return Err(anyhow!("Content does not match the digest {}.", expected));
+    }
+    Ok(())
+}
+
+/// Encode the bytes in lowercase hex.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
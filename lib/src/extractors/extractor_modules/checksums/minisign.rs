@@ -0,0 +1,138 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Verification of minisign signatures, see
+//! <https://jedisct1.github.io/minisign/>.
+
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Result};
+use blake2::{Blake2b512, Digest};
+use ring::signature::{UnparsedPublicKey, ED25519};
+
+use crate::extractors::extractor_modules::artifact::hex;
+
+/// Signature algorithm of the legacy signatures over the content
+const LEGACY_ALGORITHM: &[u8] = b"Ed";
+
+/// Signature algorithm of the signatures over BLAKE2b-512 of the content
+const PREHASHED_ALGORITHM: &[u8] = b"ED";
+
+const KEYID_LEN: usize = 8;
+const PUBLIC_KEY_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+
+const UNTRUSTED_COMMENT_PREFIX: &str = "untrusted comment:";
+const TRUSTED_COMMENT_PREFIX: &str = "trusted comment: ";
+
+/// Whether the content is a minisign signature.
+pub fn is_signature(content: &str) -> bool {
+    content.starts_with(UNTRUSTED_COMMENT_PREFIX)
+}
+
+/// Get the base64 encoded lines of a minisign file, skipping the
+/// untrusted comment.
+fn lines(content: &str) -> impl Iterator<Item = &str> {
+    content
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.is_empty() && !line.starts_with(UNTRUSTED_COMMENT_PREFIX))
+}
+
+fn decode(field: &str, value: &str) -> Result<Vec<u8>> {
+    base64::decode(value).map_err(|e| anyhow!("Decode minisign {} failed: {}", field, e))
+}
+
+/// A trusted minisign public key.
+/// * `keyid`: id of the key, which is also included in signatures.
+/// * `public_key`: the Ed25519 public key.
+pub struct PublicKey {
+    keyid: Vec<u8>,
+    public_key: Vec<u8>,
+}
+
+impl PublicKey {
+    /// Parse a minisign public key file, s.t. an untrusted comment
+    /// and base64(`Ed` || keyid || public key).
+    pub fn parse(content: &str) -> Result<Self> {
+        let key = lines(content)
+            .next()
+            .ok_or_else(|| anyhow!("Minisign public key not found."))?;
+        let key = decode("public key", key)?;
+        if key.len() != 2 + KEYID_LEN + PUBLIC_KEY_LEN || &key[..2] != LEGACY_ALGORITHM {
+            return Err(anyhow!("Malformed minisign public key."));
+        }
+
+        Ok(Self {
+            keyid: key[2..2 + KEYID_LEN].to_vec(),
+            public_key: key[2 + KEYID_LEN..].to_vec(),
+        })
+    }
+
+    /// Read a minisign public key file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    fn verify(&self, message: &[u8], sig: &[u8]) -> Result<()> {
+        UnparsedPublicKey::new(&ED25519, &self.public_key)
+            .verify(message, sig)
+            .map_err(|_| anyhow!("Invalid minisign signature."))
+    }
+}
+
+/// Verify a minisign signature file over the content. The file is
+/// * an untrusted comment.
+/// * base64(algorithm || keyid || signature).
+/// * a trusted comment.
+/// * base64(global signature over signature || trusted comment).
+///
+/// Both signatures must be valid, otherwise the trusted comment may
+/// be replaced.
+pub fn verify(content: &[u8], signature: &str, keys: &[PublicKey]) -> Result<()> {
+    let mut sig_lines = lines(signature);
+    let sig = decode(
+        "signature",
+        sig_lines
+            .next()
+            .ok_or_else(|| anyhow!("Minisign signature not found."))?,
+    )?;
+    let trusted_comment = sig_lines
+        .next()
+        .and_then(|line| line.strip_prefix(TRUSTED_COMMENT_PREFIX))
+        .ok_or_else(|| anyhow!("Minisign trusted comment not found."))?;
+    let global_sig = decode(
+        "global signature",
+        sig_lines
+            .next()
+            .ok_or_else(|| anyhow!("Minisign global signature not found."))?,
+    )?;
+    if sig.len() != 2 + KEYID_LEN + SIGNATURE_LEN {
+        return Err(anyhow!("Malformed minisign signature."));
+    }
+
+    let (algorithm, rest) = sig.split_at(2);
+    let (keyid, sig) = rest.split_at(KEYID_LEN);
+    let key = keys
+        .iter()
+        .find(|k| k.keyid == keyid)
+        .ok_or_else(|| anyhow!("Minisign key {} is not trusted.", keyid_hex(keyid)))?;
+
+    match algorithm {
+        LEGACY_ALGORITHM => key.verify(content, sig)?,
+        PREHASHED_ALGORITHM => key.verify(&Blake2b512::digest(content), sig)?,
+        _ => return Err(anyhow!("Unsupported minisign signature algorithm.")),
+    }
+
+    let mut global_message = sig.to_vec();
+    global_message.extend_from_slice(trusted_comment.as_bytes());
+    key.verify(&global_message, &global_sig)
+}
+
+/// Key ids are shown in little endian and uppercase by minisign.
+fn keyid_hex(keyid: &[u8]) -> String {
+    let keyid: Vec<u8> = keyid.iter().rev().copied().collect();
+    hex(&keyid).to_uppercase()
+}
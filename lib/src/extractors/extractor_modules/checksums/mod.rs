@@ -0,0 +1,299 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! # Checksums Extractor
+//!
+//! This Extractor helps to verify a checksum file, e.g. `SHA256SUMS`,
+//! with its detached OpenPGP or minisign signature, and extract a
+//! reference value for each file listed in it.
+
+pub mod minisign;
+pub mod openpgp;
+pub mod sums;
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::reference_value::{ReferenceValue, REFERENCE_VALUE_VERSION};
+
+use super::{unknown_expiry, ConfigurableExtractor, Extractor};
+
+/// Signed checksum file.
+/// * `checksums`: content of the checksum file.
+/// * `signature`: the detached signature of the checksum file. It
+/// is either an ASCII armored OpenPGP signature, a minisign signature,
+/// or a base64 encoded binary OpenPGP signature.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Provenance {
+    pub checksums: String,
+    pub signature: String,
+}
+
+/// Policy of the Checksums Extractor, given by its section of
+/// the config file.
+/// * `openpgp_keys`: paths of the trusted OpenPGP public key files,
+/// either binary or ASCII armored.
+/// * `minisign_keys`: paths of the trusted minisign public key files.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Policy {
+    #[serde(default)]
+    pub openpgp_keys: Vec<PathBuf>,
+    #[serde(default)]
+    pub minisign_keys: Vec<PathBuf>,
+}
+
+pub struct ChecksumsExtractor {
+    openpgp_keys: Vec<openpgp::PublicKey>,
+    minisign_keys: Vec<minisign::PublicKey>,
+}
+
+impl ChecksumsExtractor {
+    /// Create a Checksums Extractor with the given policy.
+    pub fn with_policy(policy: Policy) -> Result<Self> {
+        let mut openpgp_keys = Vec::new();
+        for path in &policy.openpgp_keys {
+            openpgp_keys.extend(openpgp::PublicKey::from_file(path)?);
+        }
+
+        let minisign_keys = policy
+            .minisign_keys
+            .iter()
+            .map(minisign::PublicKey::from_file)
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            openpgp_keys,
+            minisign_keys,
+        })
+    }
+
+    /// Verify the signature over the checksum file, in the format
+    /// detected from the signature itself. Return when the signature
+    /// expires, if it does.
+    fn verify_signature(&self, checksums: &[u8], signature: &str) -> Result<Option<DateTime<Utc>>> {
+        if minisign::is_signature(signature) {
+            minisign::verify(checksums, signature, &self.minisign_keys)?;
+            return Ok(None);
+        }
+        if openpgp::is_armored_signature(signature) {
+            return openpgp::verify(checksums, signature.as_bytes(), &self.openpgp_keys);
+        }
+
+        let signature = base64::decode(signature.trim())
+            .map_err(|_| anyhow!("Unsupported signature of the checksum file."))?;
+        openpgp::verify(checksums, &signature, &self.openpgp_keys)
+    }
+}
+
+impl ConfigurableExtractor for ChecksumsExtractor {
+    type Config = Policy;
+
+    fn with_config(config: Policy) -> Result<Self> {
+        Self::with_policy(config)
+    }
+}
+
+impl Extractor for ChecksumsExtractor {
+    /// Checksums Extractor.
+    /// The provenance is a checksum file in the format of `sha*sum`,
+    /// together with its detached signature made by a trusted OpenPGP
+    /// or minisign key. A reference value is returned for each file
+    /// listed, with all the digests of it.
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+        let provenance: Provenance = serde_json::from_str(provenance)?;
+        let expires =
+            self.verify_signature(provenance.checksums.as_bytes(), &provenance.signature)?;
+
+        // Group the digests by file, s.t. a file listed with several
+        // algorithms has one reference value
+        let mut files: Vec<(String, Vec<sums::Checksum>)> = Vec::new();
+        for checksum in sums::parse(&provenance.checksums)? {
+            let index = match files.iter().position(|(name, _)| *name == checksum.name) {
+                Some(index) => index,
+                None => {
+                    files.push((checksum.name.clone(), Vec::new()));
+                    files.len() - 1
+                }
+            };
+            let digests = &mut files[index].1;
+
+            match digests.iter().find(|d| d.alg == checksum.alg) {
+                Some(d) if d.value != checksum.value => {
+                    return Err(anyhow!(
+                        "Conflicting {} digests of {}.",
+                        checksum.alg,
+                        checksum.name
+                    ));
+                }
+                Some(_) => {}
+                None => digests.push(checksum),
+            }
+        }
+
+        // The reference values expire with the OpenPGP signature or its
+        // key, and a minisign signature does not expire
+        let expired = expires.unwrap_or_else(unknown_expiry);
+        let rvs = files
+            .into_iter()
+            .map(|(name, digests)| {
+                digests.into_iter().fold(
+                    ReferenceValue::new()
+                        .set_name(&name)
+                        .set_version(REFERENCE_VALUE_VERSION)
+                        .set_expired(expired),
+                    |rv, d| rv.add_hash_value(d.alg, d.value),
+                )
+            })
+            .collect();
+        Ok(rvs)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    use crate::extractors::extractor_modules::Extractor;
+
+    use super::{ChecksumsExtractor, Policy};
+
+    /// Test vectors generated by `<git-repo>/tests/checksums/generate.sh`
+    const TEST_VECTORS_DIR: &str = "../tests/checksums";
+
+    fn read(name: &str) -> String {
+        fs::read_to_string(format!("{}/{}", TEST_VECTORS_DIR, name)).unwrap()
+    }
+
+    fn policy() -> Policy {
+        Policy {
+            openpgp_keys: vec![
+                format!("{}/openpgp-ed25519.asc", TEST_VECTORS_DIR).into(),
+                format!("{}/openpgp-rsa.asc", TEST_VECTORS_DIR).into(),
+            ],
+            minisign_keys: vec![format!("{}/minisign.pub", TEST_VECTORS_DIR).into()],
+        }
+    }
+
+    fn provenance(checksums: &str, signature: &str) -> String {
+        json!({
+            "checksums": checksums,
+            "signature": signature,
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn checksums_extractor() {
+        let e = ChecksumsExtractor::with_policy(policy()).unwrap();
+        let checksums = read("SHA256SUMS");
+
+        for signature in [
+            "SHA256SUMS.ed25519.asc",
+            "SHA256SUMS.rsa.asc",
+            "SHA256SUMS.minisig",
+            "SHA256SUMS.legacy.minisig",
+        ] {
+            let rvs = e
+                .verify_and_extract(&provenance(&checksums, &read(signature)))
+                .unwrap();
+            assert_eq!(rvs.len(), 3);
+            assert_eq!(rvs[1].name(), "rvps-0.1.0-aarch64.tar.gz");
+            assert_eq!(rvs[1].hash_values().len(), 1);
+            assert_eq!(rvs[1].hash_values()[0].alg(), "sha256");
+            assert_eq!(
+                rvs[1].hash_values()[0].value(),
+                "10481f24258117e85e5d4a75d13d23f5615a20fc84f9427244ab452c83949054"
+            );
+        }
+
+        // Binary OpenPGP signature
+        let armored = read("SHA256SUMS.rsa.asc");
+        let binary: String = armored
+            .lines()
+            .skip_while(|l| !l.is_empty())
+            .skip(1)
+            .take_while(|l| !l.starts_with('='))
+            .collect();
+        assert!(e
+            .verify_and_extract(&provenance(&checksums, &binary))
+            .is_ok());
+    }
+
+    #[test]
+    fn checksums_extractor_validity() {
+        let checksums = read("SHA256SUMS");
+        let extractor = |key: &str| {
+            ChecksumsExtractor::with_policy(Policy {
+                openpgp_keys: vec![format!("{}/{}", TEST_VECTORS_DIR, key).into()],
+                minisign_keys: Vec::new(),
+            })
+            .unwrap()
+        };
+
+        // Reference values expire with the signature, before the key
+        let e = extractor("openpgp-expiring.asc");
+        let rvs = e
+            .verify_and_extract(&provenance(&checksums, &read("SHA256SUMS.expiring.asc")))
+            .unwrap();
+        assert_eq!(rvs[0].expired(), &Utc.ymd(2098, 1, 1).and_hms(0, 0, 0));
+
+        // Expired signature
+        let err = e
+            .verify_and_extract(&provenance(&checksums, &read("SHA256SUMS.sig-expired.asc")))
+            .unwrap_err();
+        assert!(err.to_string().contains("signature expired"));
+
+        // Signature by an expired key, made before it expired
+        let err = extractor("openpgp-expired.asc")
+            .verify_and_extract(&provenance(&checksums, &read("SHA256SUMS.expired.asc")))
+            .unwrap_err();
+        assert!(err.to_string().contains("expired at 2020-12-31"));
+
+        // Signature by a revoked key, made before it was revoked
+        let err = extractor("openpgp-revoked.asc")
+            .verify_and_extract(&provenance(&checksums, &read("SHA256SUMS.revoked.asc")))
+            .unwrap_err();
+        assert!(err.to_string().contains("revoked"));
+    }
+
+    #[test]
+    fn checksums_extractor_rejects() {
+        let checksums = read("SHA256SUMS");
+        let tampered = checksums.replacen("90e9", "90e8", 1);
+
+        for signature in ["SHA256SUMS.ed25519.asc", "SHA256SUMS.minisig"] {
+            let signature = read(signature);
+
+            // Tampered checksum file
+            let e = ChecksumsExtractor::with_policy(policy()).unwrap();
+            assert!(e
+                .verify_and_extract(&provenance(&tampered, &signature))
+                .is_err());
+
+            // Untrusted key
+            let e = ChecksumsExtractor::with_policy(Policy {
+                openpgp_keys: vec![format!("{}/openpgp-rsa.asc", TEST_VECTORS_DIR).into()],
+                minisign_keys: Vec::new(),
+            })
+            .unwrap();
+            assert!(e
+                .verify_and_extract(&provenance(&checksums, &signature))
+                .is_err());
+        }
+
+        // Tampered trusted comment of minisign
+        let e = ChecksumsExtractor::with_policy(policy()).unwrap();
+        let signature = read("SHA256SUMS.minisig").replace("timestamp:", "timestamp:1");
+        assert!(e
+            .verify_and_extract(&provenance(&checksums, &signature))
+            .is_err());
+    }
+}
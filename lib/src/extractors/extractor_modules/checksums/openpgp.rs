@@ -0,0 +1,623 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Verification of detached OpenPGP signatures, see RFC 4880.
+//! Only v4 keys and signatures are supported, with RSA or Ed25519
+//! keys, and SHA-256, SHA-384 or SHA-512 digests. Just the packets
+//! needed are parsed, s.t. any other packet in a key file, e.g. user
+//! ids, is skipped. A key file is trusted as a whole, so its self
+//! signatures, binding signatures of subkeys and revocations are not
+//! verified, but their key expiration times and revocations are
+//! honored. A signature is rejected if it is expired, or made by a key
+//! which is expired or revoked.
+
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use ring::{
+    digest::{self, SHA1_FOR_LEGACY_USE_ONLY, SHA256, SHA384, SHA512},
+    signature::{
+        RsaPublicKeyComponents, UnparsedPublicKey, ED25519, RSA_PKCS1_2048_8192_SHA256,
+        RSA_PKCS1_2048_8192_SHA384, RSA_PKCS1_2048_8192_SHA512,
+    },
+};
+
+/// Armor header line of a detached signature
+const SIGNATURE_ARMOR: &str = "-----BEGIN PGP SIGNATURE-----";
+
+/// Armor header line of public keys
+const PUBLIC_KEY_ARMOR: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----";
+
+/// Packet tags
+const SIGNATURE_TAG: u8 = 2;
+const PUBLIC_KEY_TAG: u8 = 6;
+const PUBLIC_SUBKEY_TAG: u8 = 14;
+
+/// Signature types over a document
+const BINARY_SIGNATURE: u8 = 0x00;
+const TEXT_SIGNATURE: u8 = 0x01;
+
+/// Signature types over a key, s.t. certifications of a user id, the
+/// binding of a subkey, a signature directly on a key, and revocations
+const GENERIC_CERTIFICATION: u8 = 0x10;
+const POSITIVE_CERTIFICATION: u8 = 0x13;
+const SUBKEY_BINDING: u8 = 0x18;
+const DIRECT_KEY: u8 = 0x1F;
+const KEY_REVOCATION: u8 = 0x20;
+const SUBKEY_REVOCATION: u8 = 0x28;
+
+/// Public key algorithms
+const RSA: u8 = 1;
+const RSA_SIGN_ONLY: u8 = 3;
+const EDDSA: u8 = 22;
+
+/// OID of Ed25519 for EdDSA
+const ED25519_OID: &[u8] = &[0x2B, 0x06, 0x01, 0x04, 0x01, 0xDA, 0x47, 0x0F, 0x01];
+
+/// Hash algorithms
+const HASH_SHA256: u8 = 8;
+const HASH_SHA384: u8 = 9;
+const HASH_SHA512: u8 = 10;
+
+/// Signature subpackets
+const CREATION_TIME_SUBPACKET: u8 = 2;
+const SIGNATURE_EXPIRATION_SUBPACKET: u8 = 3;
+const KEY_EXPIRATION_SUBPACKET: u8 = 9;
+const ISSUER_SUBPACKET: u8 = 16;
+const ISSUER_FINGERPRINT_SUBPACKET: u8 = 33;
+
+const ED25519_LEN: usize = 32;
+
+/// Whether the content is an ASCII armored signature.
+pub fn is_armored_signature(content: &str) -> bool {
+    content.trim_start().starts_with(SIGNATURE_ARMOR)
+}
+
+/// CRC-24 of the armor checksum, RFC 4880, 6.1.
+fn crc24(data: &[u8]) -> u32 {
+    let mut crc = 0xB704CE_u32;
+    for b in data {
+        crc ^= (*b as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= 0x1864CFB;
+            }
+        }
+    }
+    crc & 0xFFFFFF
+}
+
+/// Decode the ASCII armor beginning with the `header` line.
+fn dearmor(content: &str, header: &str) -> Result<Vec<u8>> {
+    let mut lines = content
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| *line != header)
+        .skip(1);
+
+    // Armor headers end with an empty line
+    for line in lines.by_ref() {
+        if line.is_empty() {
+            break;
+        }
+    }
+
+    let mut body = String::new();
+    let mut checksum = None;
+    for line in lines {
+        if line.starts_with("-----") {
+            break;
+        }
+        match line.strip_prefix('=') {
+            Some(crc) => checksum = Some(base64::decode(crc)?),
+            None => body.push_str(line),
+        }
+    }
+
+    let data = base64::decode(&body).map_err(|e| anyhow!("Decode OpenPGP armor failed: {}", e))?;
+    if let Some(checksum) = checksum {
+        let crc = crc24(&data).to_be_bytes();
+        if checksum != crc[1..] {
+            return Err(anyhow!("Invalid checksum of OpenPGP armor."));
+        }
+    }
+    Ok(data)
+}
+
+/// A reader over the bytes of a packet.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.data.len() {
+            return Err(anyhow!("Truncated OpenPGP packet."));
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<usize> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]) as usize)
+    }
+
+    fn u32(&mut self) -> Result<usize> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+    }
+
+    /// Multiprecision integer, s.t. the length in bits and the
+    /// big-endian number.
+    fn mpi(&mut self) -> Result<&'a [u8]> {
+        let bits = self.u16()?;
+        self.take((bits + 7) / 8)
+    }
+}
+
+/// Split the data into packets of (tag, body), RFC 4880, 4.2.
+fn packets(data: &[u8]) -> Result<Vec<(u8, &[u8])>> {
+    let mut reader = Reader { data };
+    let mut packets = Vec::new();
+    while !reader.data.is_empty() {
+        let header = reader.u8()?;
+        if header & 0x80 == 0 {
+            return Err(anyhow!("Malformed OpenPGP packet header."));
+        }
+
+        let (tag, len) = if header & 0x40 != 0 {
+            // New format
+            let len = match reader.u8()? as usize {
+                len @ 0..=191 => len,
+                len @ 192..=223 => ((len - 192) << 8) + reader.u8()? as usize + 192,
+                255 => reader.u32()?,
+                _ => {
+                    return Err(anyhow!(
+                        "Partial body length of OpenPGP packet is not supported."
+                    ))
+                }
+            };
+            (header & 0x3F, len)
+        } else {
+            // Old format
+            let len = match header & 0x03 {
+                0 => reader.u8()? as usize,
+                1 => reader.u16()?,
+                2 => reader.u32()?,
+                _ => reader.data.len(),
+            };
+            ((header >> 2) & 0x0F, len)
+        };
+        packets.push((tag, reader.take(len)?));
+    }
+    Ok(packets)
+}
+
+#[derive(Clone)]
+enum KeyMaterial {
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    Ed25519(Vec<u8>),
+}
+
+/// A trusted OpenPGP public key or subkey.
+/// * `fingerprint`: v4 fingerprint of the key. The key id is the
+/// low 64 bits of it.
+/// * `created`: creation time of the key, in seconds since epoch.
+/// * `material`: the public key itself, `None` if it is not supported.
+/// * `expires`: when the key expires, if it does.
+/// * `revoked`: whether the key is revoked.
+#[derive(Clone)]
+pub struct PublicKey {
+    fingerprint: Vec<u8>,
+    created: u32,
+    material: Option<KeyMaterial>,
+    expires: Option<DateTime<Utc>>,
+    revoked: bool,
+}
+
+impl PublicKey {
+    /// Parse the body of a public key packet. Keys of unsupported
+    /// versions are skipped, and keys of unsupported algorithms have no
+    /// material.
+    fn parse(body: &[u8]) -> Result<Option<Self>> {
+        let mut reader = Reader { data: body };
+        if reader.u8()? != 4 {
+            return Ok(None);
+        }
+        let created = reader.u32()? as u32;
+
+        let material = match reader.u8()? {
+            RSA | RSA_SIGN_ONLY => {
+                let n = reader.mpi()?.to_vec();
+                let e = reader.mpi()?.to_vec();
+                Some(KeyMaterial::Rsa { n, e })
+            }
+            EDDSA => {
+                let oid_len = reader.u8()? as usize;
+                if reader.take(oid_len)? == ED25519_OID {
+                    // Native point format, prefixed by 0x40
+                    match reader.mpi()? {
+                        [0x40, point @ ..] if point.len() == ED25519_LEN => {
+                            Some(KeyMaterial::Ed25519(point.to_vec()))
+                        }
+                        _ => return Err(anyhow!("Malformed Ed25519 public key.")),
+                    }
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        // fingerprint = SHA-1(0x99 || two-octet length || body)
+        let mut hashed = vec![0x99];
+        hashed.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        hashed.extend_from_slice(body);
+        let fingerprint = digest::digest(&SHA1_FOR_LEGACY_USE_ONLY, &hashed)
+            .as_ref()
+            .to_vec();
+
+        Ok(Some(Self {
+            fingerprint,
+            created,
+            material,
+            expires: None,
+            revoked: false,
+        }))
+    }
+
+    fn keyid(&self) -> &[u8] {
+        &self.fingerprint[self.fingerprint.len() - 8..]
+    }
+
+    /// Apply the signatures following the key which are made by its
+    /// primary key, or by itself if it is the primary key, s.t. the
+    /// revocation and the key expiration time of the latest self
+    /// signature. A subkey is also invalid with its primary key.
+    fn apply(&mut self, signatures: &[Signature], primary: Option<&PublicKey>) {
+        let issuer = primary.map_or_else(|| self.fingerprint.clone(), |p| p.fingerprint.clone());
+        let (is_binding, revocation): (fn(u8) -> bool, u8) = match primary {
+            Some(_) => (|typ| typ == SUBKEY_BINDING, SUBKEY_REVOCATION),
+            None => (
+                |typ| {
+                    (GENERIC_CERTIFICATION..=POSITIVE_CERTIFICATION).contains(&typ)
+                        || typ == DIRECT_KEY
+                },
+                KEY_REVOCATION,
+            ),
+        };
+        let self_signatures: Vec<_> = signatures
+            .iter()
+            .filter(|signature| signature.issued_by(&issuer))
+            .collect();
+
+        self.revoked = self_signatures.iter().any(|s| s.typ == revocation);
+        self.expires = self_signatures
+            .iter()
+            .filter(|s| is_binding(s.typ))
+            .max_by_key(|s| s.created)
+            .and_then(|s| s.key_expiration)
+            .filter(|expiration| *expiration != 0)
+            .map(|expiration| timestamp(self.created, expiration));
+
+        if let Some(primary) = primary {
+            self.revoked |= primary.revoked;
+            self.expires = earliest(self.expires, primary.expires);
+        }
+    }
+
+    /// Check that the key is neither revoked nor expired.
+    fn check_validity(&self) -> Result<()> {
+        let keyid: String = self.keyid().iter().map(|b| format!("{:02X}", b)).collect();
+        if self.revoked {
+            return Err(anyhow!("OpenPGP key {} is revoked.", keyid));
+        }
+        match self.expires {
+            Some(expires) if expires <= Utc::now() => {
+                Err(anyhow!("OpenPGP key {} expired at {}.", keyid, expires))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Read the public keys and subkeys from a key file, either binary
+    /// or ASCII armored.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Vec<Self>> {
+        let content = fs::read(path)?;
+        let data = match std::str::from_utf8(&content) {
+            Ok(text) if text.contains(PUBLIC_KEY_ARMOR) => dearmor(text, PUBLIC_KEY_ARMOR)?,
+            _ => content,
+        };
+
+        // Each key with the signatures following it
+        let mut entries: Vec<(u8, Option<Self>, Vec<Signature>)> = Vec::new();
+        for (tag, body) in packets(&data)? {
+            match tag {
+                PUBLIC_KEY_TAG | PUBLIC_SUBKEY_TAG => {
+                    entries.push((tag, Self::parse(body)?, Vec::new()))
+                }
+                SIGNATURE_TAG => {
+                    if let Some((_, _, signatures)) = entries.last_mut() {
+                        signatures.push(Signature::parse(body)?);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut keys = Vec::new();
+        let mut primary: Option<Self> = None;
+        for (tag, key, signatures) in entries {
+            let mut key = match key {
+                Some(key) => key,
+                None if tag == PUBLIC_KEY_TAG => {
+                    primary = None;
+                    continue;
+                }
+                None => continue,
+            };
+
+            if tag == PUBLIC_KEY_TAG {
+                key.apply(&signatures, None);
+                if key.material.is_some() {
+                    keys.push(key.clone());
+                }
+                primary = Some(key);
+            } else if let Some(primary) = &primary {
+                key.apply(&signatures, Some(primary));
+                if key.material.is_some() {
+                    keys.push(key);
+                }
+            }
+        }
+        if keys.is_empty() {
+            return Err(anyhow!("No supported OpenPGP public key found."));
+        }
+        Ok(keys)
+    }
+}
+
+/// A v4 signature packet.
+struct Signature<'a> {
+    typ: u8,
+    algorithm: u8,
+    hash_algorithm: u8,
+    /// The hashed part of the packet, from the version to the end
+    /// of the hashed subpackets.
+    hashed: &'a [u8],
+    issuers: Vec<&'a [u8]>,
+    /// Creation time, and the expiration times of the signature and of
+    /// the key for a self signature, in seconds after the creation of
+    /// the signature or of the key, 0 for never.
+    created: Option<u32>,
+    expiration: Option<u32>,
+    key_expiration: Option<u32>,
+    left16: &'a [u8],
+    mpis: Vec<&'a [u8]>,
+}
+
+/// The time `offset` seconds after `base`, both from the packets.
+fn timestamp(base: u32, offset: u32) -> DateTime<Utc> {
+    Utc.timestamp(base as i64 + offset as i64, 0)
+}
+
+/// The earlier of two expiration times, `None` if neither expires.
+fn earliest(a: Option<DateTime<Utc>>, b: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Split a subpacket area into subpackets of (type, body), RFC 4880,
+/// 5.2.3.1. The critical bit of the type is cleared.
+fn subpackets(area: &[u8]) -> Result<Vec<(u8, &[u8])>> {
+    let mut reader = Reader { data: area };
+    let mut subpackets = Vec::new();
+    while !reader.data.is_empty() {
+        let len = match reader.u8()? as usize {
+            len @ 0..=191 => len,
+            len @ 192..=254 => ((len - 192) << 8) + reader.u8()? as usize + 192,
+            _ => reader.u32()?,
+        };
+        if let Some((typ, body)) = reader.take(len)?.split_first() {
+            subpackets.push((typ & 0x7F, body));
+        }
+    }
+    Ok(subpackets)
+}
+
+impl<'a> Signature<'a> {
+    fn parse(body: &'a [u8]) -> Result<Self> {
+        let mut reader = Reader { data: body };
+        if reader.u8()? != 4 {
+            return Err(anyhow!("Only v4 OpenPGP signatures are supported."));
+        }
+        let typ = reader.u8()?;
+        let algorithm = reader.u8()?;
+        let hash_algorithm = reader.u8()?;
+        let hashed_area_len = reader.u16()?;
+        let hashed_area = reader.take(hashed_area_len)?;
+        let hashed = &body[..6 + hashed_area_len];
+        let unhashed_area_len = reader.u16()?;
+        let unhashed_area = reader.take(unhashed_area_len)?;
+
+        // Issuers only tell the key to verify with, and may be unhashed
+        let hashed_subpackets = subpackets(hashed_area)?;
+        let issuers = hashed_subpackets
+            .iter()
+            .chain(&subpackets(unhashed_area)?)
+            .filter_map(|subpacket| match *subpacket {
+                (ISSUER_SUBPACKET, issuer) => Some(issuer),
+                // The version of the key comes first
+                (ISSUER_FINGERPRINT_SUBPACKET, [4, fingerprint @ ..]) => Some(fingerprint),
+                _ => None,
+            })
+            .collect();
+
+        // Times are trusted only if hashed
+        let time = |subpacket_type| -> Result<Option<u32>> {
+            hashed_subpackets
+                .iter()
+                .find(|(typ, _)| *typ == subpacket_type)
+                .map(|(_, body)| Reader { data: body }.u32().map(|time| time as u32))
+                .transpose()
+        };
+        let created = time(CREATION_TIME_SUBPACKET)?;
+        let expiration = time(SIGNATURE_EXPIRATION_SUBPACKET)?;
+        let key_expiration = time(KEY_EXPIRATION_SUBPACKET)?;
+        let left16 = reader.take(2)?;
+
+        let mut mpis = Vec::new();
+        while !reader.data.is_empty() {
+            mpis.push(reader.mpi()?);
+        }
+
+        Ok(Self {
+            typ,
+            algorithm,
+            hash_algorithm,
+            hashed,
+            issuers,
+            created,
+            expiration,
+            key_expiration,
+            left16,
+            mpis,
+        })
+    }
+
+    /// Whether the signature is made by the key of the fingerprint,
+    /// told by its key id or fingerprint.
+    fn issued_by(&self, fingerprint: &[u8]) -> bool {
+        let keyid = &fingerprint[fingerprint.len() - 8..];
+        self.issuers
+            .iter()
+            .any(|issuer| *issuer == keyid || *issuer == fingerprint)
+    }
+
+    /// When the signature expires, if it does.
+    fn expires(&self) -> Option<DateTime<Utc>> {
+        match (self.created, self.expiration) {
+            (Some(created), Some(expiration)) if expiration != 0 => {
+                Some(timestamp(created, expiration))
+            }
+            _ => None,
+        }
+    }
+
+    /// Verify the signature over the content with the key, RFC 4880,
+    /// 5.2.4, and check that neither of them is expired, or the key is
+    /// revoked. Return when the signature expires with the key.
+    fn verify(&self, content: &[u8], key: &PublicKey) -> Result<Option<DateTime<Utc>>> {
+        key.check_validity()?;
+        if let Some(expires) = self.expires() {
+            if expires <= Utc::now() {
+                return Err(anyhow!("OpenPGP signature expired at {}.", expires));
+            }
+        }
+
+        // Text signatures are over the content with <CR><LF> line endings
+        let mut message = match self.typ {
+            BINARY_SIGNATURE => content.to_vec(),
+            TEXT_SIGNATURE => canonicalize(content),
+            typ => return Err(anyhow!("Unsupported OpenPGP signature type: {}", typ)),
+        };
+        message.extend_from_slice(self.hashed);
+        message.extend_from_slice(&[0x04, 0xFF]);
+        message.extend_from_slice(&(self.hashed.len() as u32).to_be_bytes());
+
+        let (digest_algorithm, rsa_algorithm) = match self.hash_algorithm {
+            HASH_SHA256 => (&SHA256, &RSA_PKCS1_2048_8192_SHA256),
+            HASH_SHA384 => (&SHA384, &RSA_PKCS1_2048_8192_SHA384),
+            HASH_SHA512 => (&SHA512, &RSA_PKCS1_2048_8192_SHA512),
+            alg => return Err(anyhow!("Unsupported OpenPGP hash algorithm: {}", alg)),
+        };
+        let digest = digest::digest(digest_algorithm, &message);
+        if &digest.as_ref()[..2] != self.left16 {
+            return Err(anyhow!("Invalid OpenPGP signature."));
+        }
+
+        let verified = match (&key.material, self.algorithm, self.mpis.as_slice()) {
+            (Some(KeyMaterial::Rsa { n, e }), RSA | RSA_SIGN_ONLY, [sig]) => {
+                let sig = left_pad(sig, n.len())?;
+                RsaPublicKeyComponents { n, e }.verify(rsa_algorithm, &message, &sig)
+            }
+            (Some(KeyMaterial::Ed25519(public_key)), EDDSA, [r, s]) => {
+                let mut sig = left_pad(r, ED25519_LEN)?;
+                sig.extend(left_pad(s, ED25519_LEN)?);
+                UnparsedPublicKey::new(&ED25519, public_key).verify(digest.as_ref(), &sig)
+            }
+            _ => {
+                return Err(anyhow!(
+                    "OpenPGP signature does not match the key algorithm."
+                ))
+            }
+        };
+        verified.map_err(|_| anyhow!("Invalid OpenPGP signature."))?;
+        Ok(earliest(self.expires(), key.expires))
+    }
+}
+
+/// Leading zeros of MPIs are stripped, but signatures are fixed length.
+fn left_pad(mpi: &[u8], len: usize) -> Result<Vec<u8>> {
+    if mpi.len() > len {
+        return Err(anyhow!("Malformed OpenPGP signature."));
+    }
+    let mut padded = vec![0; len - mpi.len()];
+    padded.extend_from_slice(mpi);
+    Ok(padded)
+}
+
+/// Convert line endings to <CR><LF>.
+fn canonicalize(content: &[u8]) -> Vec<u8> {
+    let mut canonical = Vec::with_capacity(content.len());
+    for (i, b) in content.iter().enumerate() {
+        if *b == b'\n' && (i == 0 || content[i - 1] != b'\r') {
+            canonical.push(b'\r');
+        }
+        canonical.push(*b);
+    }
+    canonical
+}
+
+/// Verify a detached signature over the content, either binary or
+/// ASCII armored. It succeeds if any signature in it is made by one
+/// of the trusted keys, and none of them is expired or revoked. Return
+/// when the signature expires with the key, if it does.
+pub fn verify(
+    content: &[u8],
+    signature: &[u8],
+    keys: &[PublicKey],
+) -> Result<Option<DateTime<Utc>>> {
+    let data = match std::str::from_utf8(signature) {
+        Ok(text) if is_armored_signature(text) => dearmor(text, SIGNATURE_ARMOR)?,
+        _ => signature.to_vec(),
+    };
+
+    let mut result = Err(anyhow!("No OpenPGP signature found."));
+    for (tag, body) in packets(&data)? {
+        if tag != SIGNATURE_TAG {
+            continue;
+        }
+        let signature = Signature::parse(body)?;
+        result = match keys.iter().find(|k| signature.issued_by(&k.fingerprint)) {
+            Some(key) => signature.verify(content, key),
+            None => Err(anyhow!("OpenPGP signature is not made by a trusted key.")),
+        };
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
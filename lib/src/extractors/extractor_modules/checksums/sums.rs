@@ -0,0 +1,178 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Parser of the checksum files generated by the `sha*sum` tools.
+//! Both formats are supported
+//! * GNU: `<hex-digest>  <name>`, or `<hex-digest> *<name>` in binary
+//! mode. A line starting with `\` has `\\` and `\n` escaped in the name.
+//! * BSD (`--tag`): `SHA256 (<name>) = <hex-digest>`.
+
+use anyhow::{anyhow, Result};
+
+/// A file listed in the checksum file.
+/// * `name`: name of the file.
+/// * `alg`: hash algorithm, e.g. `sha256`.
+/// * `value`: hex encoded digest.
+#[derive(Debug, PartialEq)]
+pub struct Checksum {
+    pub name: String,
+    pub alg: String,
+    pub value: String,
+}
+
+/// Get the hash algorithm from the length of a hex encoded digest.
+fn algorithm(hex: &str) -> Option<&'static str> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    match hex.len() {
+        40 => Some("sha1"),
+        56 => Some("sha224"),
+        64 => Some("sha256"),
+        96 => Some("sha384"),
+        128 => Some("sha512"),
+        _ => None,
+    }
+}
+
+/// Unescape the name of a GNU line starting with `\`.
+fn unescape(name: &str) -> Result<String> {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unescaped.push('\\'),
+            Some('n') => unescaped.push('\n'),
+            _ => return Err(anyhow!("Invalid escape in name: {}", name)),
+        }
+    }
+    Ok(unescaped)
+}
+
+/// Parse a line of BSD format, s.t. `SHA256 (<name>) = <hex-digest>`.
+fn parse_bsd(line: &str) -> Option<Checksum> {
+    let (tag, rest) = line.split_once(" (")?;
+    let (name, hex) = rest.rsplit_once(") = ")?;
+    let alg = tag.to_lowercase();
+    if algorithm(hex)? != alg {
+        return None;
+    }
+    Some(Checksum {
+        name: name.to_string(),
+        alg,
+        value: hex.to_lowercase(),
+    })
+}
+
+/// Parse a line of GNU format, s.t. `<hex-digest>  <name>`.
+fn parse_gnu(line: &str) -> Result<Option<Checksum>> {
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+    let (hex, rest) = match line.split_once(' ') {
+        Some(split) => split,
+        None => return Ok(None),
+    };
+    let alg = match algorithm(hex) {
+        Some(alg) => alg,
+        None => return Ok(None),
+    };
+
+    // The mode is ` ` for text, and `*` for binary
+    let name = match rest.strip_prefix(' ').or_else(|| rest.strip_prefix('*')) {
+        Some(name) if !name.is_empty() => name,
+        _ => return Ok(None),
+    };
+    let name = match escaped {
+        true => unescape(name)?,
+        false => name.to_string(),
+    };
+
+    Ok(Some(Checksum {
+        name,
+        alg: alg.to_string(),
+        value: hex.to_lowercase(),
+    }))
+}
+
+/// Parse the checksum file. Empty lines and comments starting with
+/// `#` are skipped, and any other malformed line is an error.
+pub fn parse(content: &str) -> Result<Vec<Checksum>> {
+    let mut checksums = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let checksum = match parse_bsd(line) {
+            Some(checksum) => Some(checksum),
+            None => parse_gnu(line)?,
+        };
+        match checksum {
+            Some(checksum) => checksums.push(checksum),
+            None => return Err(anyhow!("Malformed checksum at line {}.", i + 1)),
+        }
+    }
+
+    if checksums.is_empty() {
+        return Err(anyhow!("No checksum found."));
+    }
+    Ok(checksums)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, Checksum};
+
+    const SHA256: &str = "90e976b08391535aca858cf823347703e949c984b37ee2db6c82200ac35c3aff";
+
+    fn checksum(name: &str, alg: &str, value: &str) -> Checksum {
+        Checksum {
+            name: name.into(),
+            alg: alg.into(),
+            value: value.into(),
+        }
+    }
+
+    #[test]
+    fn checksums_parse() {
+        let sha1 = "a94a8fe5ccb19ba61c4c0873d391e987982fbbd3";
+        let content = format!(
+            "# comment\n\
+             {0}  text mode.tar.gz\n\
+             {0} *binary.tar.gz\r\n\
+             \n\
+             \\{0}  escaped\\\\name\\n\n\
+             SHA256 (bsd (tag).tar.gz) = {0}\n\
+             {1}  legacy.tar.gz\n",
+            SHA256, sha1
+        );
+
+        let checksums = parse(&content).unwrap();
+        assert_eq!(
+            checksums,
+            vec![
+                checksum("text mode.tar.gz", "sha256", SHA256),
+                checksum("binary.tar.gz", "sha256", SHA256),
+                checksum("escaped\\name\n", "sha256", SHA256),
+                checksum("bsd (tag).tar.gz", "sha256", SHA256),
+                checksum("legacy.tar.gz", "sha1", sha1),
+            ]
+        );
+
+        // Malformed lines
+        assert!(parse("").is_err());
+        assert!(parse(&format!("{}\n", SHA256)).is_err());
+        assert!(parse(&format!("{}  short-digest\n", &SHA256[1..])).is_err());
+        assert!(parse(&format!("SHA512 (mismatch) = {}\n", SHA256)).is_err());
+        assert!(parse(&format!("\\{}  bad\\escape\n", SHA256)).is_err());
+    }
+}
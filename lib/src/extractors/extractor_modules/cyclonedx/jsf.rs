@@ -0,0 +1,121 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Verification of the embedded JSON Signature Format (JSF) signature
+//! of a CycloneDX BOM, see <https://cyberphone.github.io/doc/security/jsf.html>.
+//! The signed data is the JSON Canonicalization Scheme (JCS, RFC 8785)
+//! of the BOM, without the `value` of the signature.
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+use crate::dsse::{KeyType, Verifier};
+
+/// Get the key type of a JSF algorithm.
+fn key_type(algorithm: &str) -> Result<KeyType> {
+    match algorithm {
+        "Ed25519" => Ok(KeyType::Ed25519),
+        "ES256" => Ok(KeyType::EcdsaP256),
+        "ES384" => Ok(KeyType::EcdsaP384),
+        "PS256" => Ok(KeyType::RsaPss),
+        _ => Err(anyhow!("Unsupported JSF algorithm: {}", algorithm)),
+    }
+}
+
+/// Serialize the value by JCS. Object properties are sorted by their
+/// UTF-16 code units, and the rest is the same as `serde_json`, as
+/// long as numbers are integers.
+pub fn canonicalize(value: &Value) -> Result<String> {
+    Ok(match value {
+        Value::Array(array) => {
+            let elements = array.iter().map(canonicalize).collect::<Result<Vec<_>>>()?;
+            format!("[{}]", elements.join(","))
+        }
+        Value::Object(object) => {
+            let mut keys: Vec<&String> = object.keys().collect();
+            keys.sort_by(|a, b| a.encode_utf16().cmp(b.encode_utf16()));
+            let members = keys
+                .into_iter()
+                .map(|k| {
+                    Ok(format!(
+                        "{}:{}",
+                        serde_json::to_string(k)?,
+                        canonicalize(&object[k])?
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            format!("{{{}}}", members.join(","))
+        }
+        Value::Number(number) if number.is_f64() => {
+            return Err(anyhow!("Non-integer number is not supported by JCS."))
+        }
+        _ => serde_json::to_string(value)?,
+    })
+}
+
+/// Verify the `signature` of the BOM with one of the trusted keys
+/// of the same algorithm. Only a single signature over the whole BOM
+/// is supported, s.t. `signers`, `chain` and `excludes` are not.
+pub fn verify(bom: &Value, keys: &[Verifier]) -> Result<()> {
+    let mut signed = bom.clone();
+    let object = signed
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("BOM is not a JSON object."))?;
+    let signature = object
+        .get_mut("signature")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| anyhow!("JSF signature not found in the BOM."))?;
+
+    let algorithm = signature
+        .get("algorithm")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("Only a single JSF signature is supported."))?;
+    let key_type = key_type(algorithm)?;
+    let value = signature
+        .remove("value")
+        .and_then(|v| v.as_str().map(String::from))
+        .ok_or_else(|| anyhow!("JSF signature value not found."))?;
+    let sig = base64::decode_config(&value, base64::URL_SAFE_NO_PAD)
+        .map_err(|e| anyhow!("Decode JSF signature failed: {}", e))?;
+
+    // Unsigned properties would be extracted as if they were signed
+    if signature.contains_key("excludes") {
+        return Err(anyhow!("JSF signature with excludes is not supported."));
+    }
+
+    let message = canonicalize(&signed)?;
+    let verified = keys
+        .iter()
+        .filter(|k| k.key_type() == key_type)
+        .any(|k| k.verify_signature(message.as_bytes(), &sig).is_ok());
+    match verified {
+        true => Ok(()),
+        false => Err(anyhow!("No valid JSF signature by the trusted keys.")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::canonicalize;
+
+    #[test]
+    fn cyclonedx_jcs() {
+        let value = json!({
+            "b": [1, "\u{20ac}\n", null, true],
+            "a": { "\u{e9}": 0, "z": -1 },
+            "\u{1f600}": "emoji",
+            "\u{fb33}": "after emoji in UTF-16"
+        });
+        assert_eq!(
+            canonicalize(&value).unwrap(),
+            "{\"a\":{\"z\":-1,\"\u{e9}\":0},\"b\":[1,\"\u{20ac}\\n\",null,true],\
+             \"\u{1f600}\":\"emoji\",\"\u{fb33}\":\"after emoji in UTF-16\"}"
+        );
+
+        assert!(canonicalize(&json!({ "a": 1.5 })).is_err());
+    }
+}
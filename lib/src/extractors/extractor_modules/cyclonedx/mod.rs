@@ -0,0 +1,330 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! # CycloneDX Extractor
+//!
+//! This Extractor helps to verify a signed CycloneDX JSON BOM, either
+//! by an embedded JSF signature or by an external signature, and
+//! extract reference values from the hashes of its components.
+
+pub mod jsf;
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    dsse::Verifier,
+    reference_value::{ReferenceValue, REFERENCE_VALUE_VERSION},
+};
+
+use super::{unknown_expiry, ConfigurableExtractor, Extractor};
+
+/// Supported CycloneDX versions
+const SPEC_VERSIONS: [&str; 2] = ["1.4", "1.5"];
+
+/// Hash algorithms too weak to be used as reference values
+const WEAK_ALGORITHMS: [&str; 1] = ["md5"];
+
+/// Signed CycloneDX BOM.
+/// * `bom`: content of the CycloneDX JSON BOM.
+/// * `signature`: optional base64 encoded signature over `bom`. If
+/// not given, the BOM must have an embedded JSF signature.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Provenance {
+    pub bom: String,
+    pub signature: Option<String>,
+}
+
+/// Hash of a component.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Hash {
+    pub alg: String,
+    pub content: String,
+}
+
+/// Organizational entity, e.g. the supplier of a component.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OrganizationalEntity {
+    pub name: Option<String>,
+}
+
+/// A component in the BOM, which may have nested components. Only
+/// the fields needed are included.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Component {
+    pub name: String,
+    pub group: Option<String>,
+    pub version: Option<String>,
+    pub purl: Option<String>,
+    pub supplier: Option<OrganizationalEntity>,
+    #[serde(default)]
+    pub hashes: Vec<Hash>,
+    #[serde(default)]
+    pub components: Vec<Component>,
+}
+
+impl Component {
+    /// Name of the reference value, s.t. the package URL, or
+    /// `[<group>/]<name>[@<version>]` if it is not given.
+    fn reference_name(&self) -> String {
+        if let Some(purl) = &self.purl {
+            return purl.clone();
+        }
+
+        let mut name = match &self.group {
+            Some(group) => format!("{}/{}", group, self.name),
+            None => self.name.clone(),
+        };
+        if let Some(version) = &self.version {
+            name = format!("{}@{}", name, version);
+        }
+        name
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Metadata {
+    pub component: Option<Component>,
+}
+
+/// CycloneDX JSON BOM. Only the fields needed are included.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Bom {
+    pub bom_format: String,
+    pub spec_version: String,
+    pub metadata: Option<Metadata>,
+    #[serde(default)]
+    pub components: Vec<Component>,
+}
+
+/// Policy of the CycloneDX Extractor, given by its section of
+/// the config file.
+/// * `trusted_keys`: paths of the PEM encoded public keys.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Policy {
+    pub trusted_keys: Vec<PathBuf>,
+}
+
+/// Get the CycloneDX algorithm name in the form of reference values,
+/// e.g. `SHA-256` to `sha256`, `SHA3-256` to `sha3-256` and
+/// `BLAKE2b-256` to `blake2b-256`.
+fn normalize_algorithm(algorithm: &str) -> String {
+    let algorithm = algorithm.to_lowercase();
+    match algorithm.strip_prefix("sha-") {
+        Some(bits) => format!("sha{}", bits),
+        None => algorithm,
+    }
+}
+
+/// Generate reference values of the component and its nested
+/// components, depth first. Components without hashes are skipped.
+fn reference_values(component: &Component, rvs: &mut Vec<ReferenceValue>) {
+    let mut rv = ReferenceValue::new()
+        .set_name(&component.reference_name())
+        .set_version(REFERENCE_VALUE_VERSION)
+        .set_expired(unknown_expiry());
+    for hash in &component.hashes {
+        let alg = normalize_algorithm(&hash.alg);
+        if !WEAK_ALGORITHMS.contains(&alg.as_str()) {
+            rv = rv.add_hash_value(alg, hash.content.to_lowercase());
+        }
+    }
+
+    if !rv.hash_values().is_empty() {
+        if let Some(supplier) = component.supplier.as_ref().and_then(|s| s.name.as_ref()) {
+            rv = rv.add_metadata("supplier", supplier);
+        }
+        if let Some(version) = &component.version {
+            rv = rv.add_metadata("version", version);
+        }
+        rvs.push(rv);
+    }
+
+    for nested in &component.components {
+        reference_values(nested, rvs);
+    }
+}
+
+pub struct CycloneDxExtractor {
+    trusted_keys: Vec<Verifier>,
+}
+
+impl CycloneDxExtractor {
+    /// Create a CycloneDX Extractor with the given policy. At least
+    /// one key must be trusted.
+    pub fn with_policy(policy: Policy) -> Result<Self> {
+        if policy.trusted_keys.is_empty() {
+            return Err(anyhow!("No trusted keys given."));
+        }
+        let trusted_keys = policy
+            .trusted_keys
+            .iter()
+            .map(Verifier::from_pem_file)
+            .collect::<Result<_>>()?;
+        Ok(Self { trusted_keys })
+    }
+
+    /// Verify the external signature over the BOM, or the embedded
+    /// JSF signature if no external one is given.
+    fn verify_signature(&self, provenance: &Provenance, bom: &Value) -> Result<()> {
+        let signature = match &provenance.signature {
+            Some(signature) => signature,
+            None => return jsf::verify(bom, &self.trusted_keys),
+        };
+
+        let sig = base64::decode(signature.trim())
+            .map_err(|e| anyhow!("Decode signature of the BOM failed: {}", e))?;
+        let verified = self
+            .trusted_keys
+            .iter()
+            .any(|k| k.verify_signature(provenance.bom.as_bytes(), &sig).is_ok());
+        match verified {
+            true => Ok(()),
+            false => Err(anyhow!(
+                "No valid signature of the BOM by the trusted keys."
+            )),
+        }
+    }
+}
+
+impl ConfigurableExtractor for CycloneDxExtractor {
+    type Config = Policy;
+
+    fn with_config(config: Policy) -> Result<Self> {
+        Self::with_policy(config)
+    }
+}
+
+impl Extractor for CycloneDxExtractor {
+    /// CycloneDX's Extractor.
+    /// The provenance is a CycloneDX 1.4 or 1.5 JSON BOM, together
+    /// with an optional external signature. A reference value is
+    /// generated for each component with hashes, including the nested
+    /// ones and the one described by the metadata.
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+        let provenance: Provenance = serde_json::from_str(provenance)?;
+        let value: Value = serde_json::from_str(&provenance.bom)?;
+        self.verify_signature(&provenance, &value)?;
+
+        let bom: Bom = serde_json::from_value(value)?;
+        if bom.bom_format != "CycloneDX" || !SPEC_VERSIONS.contains(&bom.spec_version.as_str()) {
+            return Err(anyhow!(
+                "Unsupported BOM: {} {}",
+                bom.bom_format,
+                bom.spec_version
+            ));
+        }
+
+        let mut rvs = Vec::new();
+        if let Some(component) = bom.metadata.and_then(|m| m.component) {
+            reference_values(&component, &mut rvs);
+        }
+        for component in &bom.components {
+            reference_values(component, &mut rvs);
+        }
+
+        if rvs.is_empty() {
+            return Err(anyhow!("No component hash found in the BOM."));
+        }
+        Ok(rvs)
+    }
+
+    /// The CycloneDX specification versions of the BOM.
+    fn payload_versions(&self) -> Vec<String> {
+        SPEC_VERSIONS.iter().map(|v| v.to_string()).collect()
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use serde_json::{json, Value};
+
+    use crate::extractors::extractor_modules::Extractor;
+
+    use super::{CycloneDxExtractor, Policy};
+
+    /// Test vectors generated by `<git-repo>/tests/cyclonedx/generate.py`
+    const TEST_VECTORS_DIR: &str = "../tests/cyclonedx";
+
+    fn read(name: &str) -> String {
+        fs::read_to_string(format!("{}/{}", TEST_VECTORS_DIR, name)).unwrap()
+    }
+
+    fn extractor() -> CycloneDxExtractor {
+        CycloneDxExtractor::with_policy(Policy {
+            trusted_keys: vec![format!("{}/es256.pub", TEST_VECTORS_DIR).into()],
+        })
+        .unwrap()
+    }
+
+    fn provenance(bom: &str, signature: Option<String>) -> String {
+        json!({ "bom": bom, "signature": signature }).to_string()
+    }
+
+    #[test]
+    fn cyclonedx_extractor() {
+        let e = extractor();
+        let external = provenance(&read("bom.json"), Some(read("bom.json.sig")));
+        let embedded = provenance(&read("bom.signed.json"), None);
+
+        for provenance in [external, embedded] {
+            let rvs = e.verify_and_extract(&provenance).unwrap();
+            assert_eq!(rvs.len(), 3);
+
+            assert_eq!(rvs[0].name(), "rvps@0.1.0");
+            assert_eq!(rvs[0].metadata()["supplier"], "Alibaba Cloud");
+            assert_eq!(rvs[0].metadata()["version"], "0.1.0");
+
+            assert_eq!(rvs[1].name(), "pkg:cargo/ring@0.16.20");
+            let algs: Vec<_> = rvs[1].hash_values().iter().map(|h| h.alg()).collect();
+            assert_eq!(algs, ["sha256", "sha512"]);
+
+            // Nested component
+            assert_eq!(rvs[2].name(), "/bin/sh");
+            assert_eq!(rvs[2].hash_values()[0].alg(), "sha3-256");
+        }
+    }
+
+    #[test]
+    fn cyclonedx_extractor_rejects() {
+        // Without any trusted key, the policy is rejected
+        assert!(CycloneDxExtractor::with_policy(Policy::default()).is_err());
+
+        let e = extractor();
+
+        // Tampered BOM with the external signature
+        let bom = read("bom.json").replace("0.16.20", "0.16.19");
+        assert!(e
+            .verify_and_extract(&provenance(&bom, Some(read("bom.json.sig"))))
+            .is_err());
+
+        // Tampered BOM with the embedded signature
+        let mut bom: Value = serde_json::from_str(&read("bom.signed.json")).unwrap();
+        bom["components"][0]["hashes"][0]["content"] = "00".into();
+        assert!(e
+            .verify_and_extract(&provenance(&bom.to_string(), None))
+            .is_err());
+
+        // No signature
+        assert!(e
+            .verify_and_extract(&provenance(&read("bom.json"), None))
+            .is_err());
+
+        // Untrusted key
+        let e = CycloneDxExtractor::with_policy(Policy {
+            trusted_keys: vec!["../tests/dsse/ecdsa-p256.pub".into()],
+        })
+        .unwrap();
+        assert!(e
+            .verify_and_extract(&provenance(&read("bom.signed.json"), None))
+            .is_err());
+    }
+}
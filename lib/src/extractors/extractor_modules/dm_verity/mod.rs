@@ -0,0 +1,358 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! # dm-verity Extractor
+//!
+//! This Extractor helps to verify a signed description of a disk
+//! image protected by dm-verity, e.g. a guest rootfs, and calculate
+//! the expected root hash, either from the image or from its hash
+//! tree.
+
+pub mod tree;
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dsse::{Envelope, ThresholdVerifier, TrustPolicy},
+    extractors::extractor_modules::artifact::{hex, Artifact},
+    reference_value::{ReferenceValue, REFERENCE_VALUE_VERSION},
+};
+
+use self::tree::Verity;
+
+use super::{unknown_expiry, ConfigurableExtractor, Extractor};
+
+/// Payload type of the image description inside a DSSE envelope
+pub const DM_VERITY_PAYLOAD_TYPE: &str = "application/vnd.rvps.dm-verity+json";
+
+/// Signed description of a dm-verity protected image.
+/// * `name`: name of the reference value.
+/// * `image`, `hash_tree`: digests of the image and its hash tree,
+/// e.g. `sha256:<hex>`. At least one of them is given.
+/// * `data_blocks`: number of data blocks. Defaults to the whole
+/// image, and must be given if the image is not.
+/// * `hash_algorithm`, `data_block_size`, `hash_block_size`, `salt`,
+/// `format`: the verity parameters, with the same defaults as
+/// `veritysetup format`, except that the salt defaults to empty. The
+/// salt is hex encoded.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Description {
+    pub name: String,
+    pub image: Option<String>,
+    pub hash_tree: Option<String>,
+    pub data_blocks: Option<u64>,
+    #[serde(default = "default_hash_algorithm")]
+    pub hash_algorithm: String,
+    #[serde(default = "default_block_size")]
+    pub data_block_size: usize,
+    #[serde(default = "default_block_size")]
+    pub hash_block_size: usize,
+    #[serde(default)]
+    pub salt: String,
+    #[serde(default = "default_format")]
+    pub format: u32,
+}
+
+/// Use to set default hash algorithm of Description
+fn default_hash_algorithm() -> String {
+    "sha256".to_string()
+}
+
+/// Use to set default block sizes of Description
+fn default_block_size() -> usize {
+    4096
+}
+
+/// Use to set default format of Description
+fn default_format() -> u32 {
+    1
+}
+
+/// Provenance of a dm-verity protected image.
+/// * `envelope`: DSSE envelope of the `Description`.
+/// * `image`: the image, to build the hash tree locally.
+/// * `hash_tree`: the precomputed hash tree, e.g. the hash device made
+/// by `veritysetup format`, used if the image is not given.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Provenance {
+    pub envelope: Envelope,
+    pub image: Option<Artifact>,
+    pub hash_tree: Option<Artifact>,
+}
+
+/// Policy of the dm-verity Extractor, given by its section of
+/// the config file.
+/// * `trust`: keys trusted to sign the description, see `TrustPolicy`.
+/// * `artifact_dir`: directory of the images and hash trees referenced
+/// by path. If not set, they must be carried by the provenance.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Policy {
+    #[serde(flatten)]
+    pub trust: TrustPolicy,
+    pub artifact_dir: Option<PathBuf>,
+}
+
+pub struct DmVerityExtractor {
+    verifier: ThresholdVerifier,
+    artifact_dir: Option<PathBuf>,
+}
+
+impl DmVerityExtractor {
+    /// Create a dm-verity Extractor with the given policy.
+    pub fn with_policy(policy: Policy) -> Result<Self> {
+        let verifier = policy.trust.verifier()?;
+        Ok(Self {
+            verifier,
+            artifact_dir: policy.artifact_dir,
+        })
+    }
+}
+
+/// Decode the hex encoded salt.
+fn decode_salt(salt: &str) -> Result<Vec<u8>> {
+    if salt.len() % 2 != 0 {
+        return Err(anyhow!("Malformed dm-verity salt."));
+    }
+    (0..salt.len())
+        .step_by(2)
+        .map(|i| {
+            salt.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+                .ok_or_else(|| anyhow!("Malformed dm-verity salt."))
+        })
+        .collect()
+}
+
+/// Get the digest of the artifact given in the provenance.
+fn expected_digest<'a>(name: &str, digest: &'a Option<String>) -> Result<&'a str> {
+    digest
+        .as_deref()
+        .ok_or_else(|| anyhow!("{} is not in the description.", name))
+}
+
+impl ConfigurableExtractor for DmVerityExtractor {
+    type Config = Policy;
+
+    fn with_config(config: Policy) -> Result<Self> {
+        Self::with_policy(config)
+    }
+}
+
+impl Extractor for DmVerityExtractor {
+    /// dm-verity Extractor.
+    /// The provenance is a signed description of the image and the
+    /// verity parameters, together with the image or its hash tree.
+    /// The reference value is the root hash, named by the `name` of
+    /// the description, with the parameters in the metadata.
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+        let provenance: Provenance = serde_json::from_str(provenance)?;
+        let payload = self
+            .verifier
+            .verify(&provenance.envelope, DM_VERITY_PAYLOAD_TYPE)?;
+        let description: Description = serde_json::from_slice(&payload)?;
+
+        let verity = Verity::new(
+            &description.hash_algorithm,
+            description.data_block_size,
+            description.hash_block_size,
+            decode_salt(&description.salt)?,
+            description.format,
+        )?;
+
+        let dir = self.artifact_dir.as_deref();
+        let (root_hash, data_blocks) = match (&provenance.image, &provenance.hash_tree) {
+            (Some(image), _) => {
+                let image = image.read(dir, expected_digest("Image", &description.image)?)?;
+                let data_blocks = verity.data_blocks(image.len(), description.data_blocks)?;
+                (verity.root_hash_of_image(&image, data_blocks)?, data_blocks)
+            }
+            (None, Some(tree)) => {
+                let tree = tree.read(dir, expected_digest("Hash tree", &description.hash_tree)?)?;
+                let data_blocks = description
+                    .data_blocks
+                    .ok_or_else(|| anyhow!("Number of data blocks is not in the description."))?;
+                let data_blocks = usize::try_from(data_blocks)?;
+                (verity.root_hash_of_tree(&tree, data_blocks)?, data_blocks)
+            }
+            (None, None) => return Err(anyhow!("Neither image nor hash tree is given.")),
+        };
+
+        let rv = ReferenceValue::new()
+            .set_name(&description.name)
+            .set_version(REFERENCE_VALUE_VERSION)
+            .set_expired(unknown_expiry())
+            .add_hash_value(description.hash_algorithm.clone(), hex(&root_hash))
+            .add_metadata("data_blocks", &data_blocks.to_string())
+            .add_metadata("data_block_size", &description.data_block_size.to_string())
+            .add_metadata("hash_block_size", &description.hash_block_size.to_string())
+            .add_metadata("salt", &description.salt.to_lowercase())
+            .add_metadata("format", &description.format.to_string());
+        Ok(vec![rv])
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use ring::digest::{digest, SHA256};
+    use serde_json::{json, Value};
+
+    use crate::{
+        dsse::{test::generate_ed25519_private_key_pem, Signer, TrustPolicy},
+        extractors::extractor_modules::{artifact::hex, Extractor},
+    };
+
+    use super::{DmVerityExtractor, Policy, DM_VERITY_PAYLOAD_TYPE};
+
+    /// Test vectors generated by `<git-repo>/tests/dm-verity/generate.py`
+    const TEST_VECTORS_DIR: &str = "../tests/dm-verity";
+
+    fn read(name: &str) -> Vec<u8> {
+        fs::read(format!("{}/{}", TEST_VECTORS_DIR, name)).unwrap()
+    }
+
+    fn sha256(name: &str) -> String {
+        format!("sha256:{}", hex(digest(&SHA256, &read(name)).as_ref()))
+    }
+
+    /// Sign the description with a new key, and get the envelope and
+    /// an Extractor trusting the key.
+    fn sign(description: &Value) -> (Value, DmVerityExtractor) {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let key_path = dir.path().join("key.pub");
+        fs::write(&key_path, signer.public_key_pem()).unwrap();
+
+        let envelope = signer.sign(DM_VERITY_PAYLOAD_TYPE, description.to_string().as_bytes());
+        let extractor = DmVerityExtractor::with_policy(Policy {
+            trust: TrustPolicy::new(vec![key_path]),
+            artifact_dir: Some(TEST_VECTORS_DIR.into()),
+        })
+        .unwrap();
+        (serde_json::to_value(envelope).unwrap(), extractor)
+    }
+
+    #[test]
+    fn dm_verity_extractor() {
+        let m: Value = serde_json::from_slice(&read("measurements.json")).unwrap();
+        let salt = m["salt"].as_str().unwrap();
+
+        for (params, alg, data_block_size, hash_block_size, salt, format) in [
+            ("sha256-1024-512", "sha256", 1024, 512, salt, 1),
+            ("sha1-1024-1024-v0", "sha1", 1024, 1024, "", 0),
+            ("sha512-4096-4096", "sha512", 4096, 4096, salt, 1),
+        ] {
+            let (envelope, e) = sign(&json!({
+                "name": "rootfs",
+                "image": sha256("rootfs.img"),
+                "hash_algorithm": alg,
+                "data_block_size": data_block_size,
+                "hash_block_size": hash_block_size,
+                "salt": salt,
+                "format": format,
+            }));
+            let provenance = json!({
+                "envelope": envelope,
+                "image": { "path": "rootfs.img" },
+            });
+            let rvs = e.verify_and_extract(&provenance.to_string()).unwrap();
+            assert_eq!(rvs.len(), 1);
+            assert_eq!(rvs[0].name(), "rootfs");
+            assert_eq!(rvs[0].hash_values()[0].alg(), alg);
+            assert_eq!(rvs[0].hash_values()[0].value(), &m[params]);
+            assert_eq!(rvs[0].metadata()["salt"], salt);
+        }
+
+        // Precomputed hash tree
+        let (envelope, e) = sign(&json!({
+            "name": "rootfs",
+            "hash_tree": sha256("hash_tree.img"),
+            "data_blocks": 100,
+            "data_block_size": 1024,
+            "hash_block_size": 512,
+            "salt": salt,
+        }));
+        let provenance = json!({
+            "envelope": envelope,
+            "hash_tree": { "path": "hash_tree.img" },
+        });
+        let rvs = e.verify_and_extract(&provenance.to_string()).unwrap();
+        assert_eq!(rvs[0].hash_values()[0].value(), &m["sha256-1024-512"]);
+        assert_eq!(rvs[0].metadata()["data_blocks"], "100");
+    }
+
+    #[test]
+    fn dm_verity_extractor_rejects() {
+        let m: Value = serde_json::from_slice(&read("measurements.json")).unwrap();
+        let description = json!({
+            "name": "rootfs",
+            "image": sha256("rootfs.img"),
+            "hash_tree": sha256("hash_tree.img"),
+            "data_blocks": 100,
+            "data_block_size": 1024,
+            "hash_block_size": 512,
+            "salt": m["salt"],
+        });
+        let (envelope, e) = sign(&description);
+
+        let cases = [
+            // Image not matching the description
+            json!({ "image": { "path": "hash_tree.img" } }),
+            // Neither image nor hash tree
+            json!({}),
+            // Path out of artifact_dir
+            json!({ "image": { "path": "../dm-verity/rootfs.img" } }),
+        ];
+        for case in cases {
+            let mut provenance = case;
+            provenance["envelope"] = envelope.clone();
+            assert!(e.verify_and_extract(&provenance.to_string()).is_err());
+        }
+
+        // Inconsistent hash tree
+        let mut tree = read("hash_tree.img");
+        let last = tree.len() - 1;
+        tree[last] ^= 1;
+        let mut d = description.clone();
+        d["hash_tree"] = json!(format!("sha256:{}", hex(digest(&SHA256, &tree).as_ref())));
+        let (envelope, e) = sign(&d);
+        let provenance = json!({
+            "envelope": envelope,
+            "hash_tree": { "content": base64::encode(&tree) },
+        });
+        assert!(e.verify_and_extract(&provenance.to_string()).is_err());
+
+        // Invalid parameters
+        for (field, value) in [
+            ("hash_algorithm", json!("md5")),
+            ("data_block_size", json!(1000)),
+            ("salt", json!("xyz")),
+            ("format", json!(2)),
+            ("data_blocks", json!(101)),
+        ] {
+            let mut d = description.clone();
+            d[field] = value;
+            let (envelope, e) = sign(&d);
+            let provenance = json!({
+                "envelope": envelope,
+                "image": { "path": "rootfs.img" },
+            });
+            assert!(e.verify_and_extract(&provenance.to_string()).is_err());
+        }
+
+        // Untrusted key
+        let (envelope, _) = sign(&description);
+        let (_, e) = sign(&description);
+        let provenance = json!({
+            "envelope": envelope,
+            "image": { "path": "rootfs.img" },
+        });
+        assert!(e.verify_and_extract(&provenance.to_string()).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! The dm-verity hash tree, built in the same way as `veritysetup
+//! format` of cryptsetup, see `Documentation/admin-guide/device-mapper/
+//! verity.rst` of the Linux kernel.
+
+use anyhow::{anyhow, Result};
+use ring::digest::{Algorithm, Context, SHA1_FOR_LEGACY_USE_ONLY, SHA256, SHA384, SHA512};
+
+/// Signature of the superblock written by veritysetup before the tree
+const SUPERBLOCK_SIGNATURE: &[u8] = b"verity\0\0";
+
+/// Size of the superblock
+const SUPERBLOCK_SIZE: usize = 512;
+
+/// Min and max size of data and hash blocks
+const MIN_BLOCK_SIZE: usize = 512;
+const MAX_BLOCK_SIZE: usize = 64 * 1024;
+
+/// Parameters of a dm-verity device.
+/// * `format`: the hash format, s.t. 1 for the normal format, where
+/// the salt is hashed before each block and the hashes are padded to
+/// a power of two, or 0 for the original Chrome OS format, where the
+/// salt is hashed after each block and the hashes are not padded.
+pub struct Verity {
+    algorithm: &'static Algorithm,
+    data_block_size: usize,
+    hash_block_size: usize,
+    salt: Vec<u8>,
+    format: u32,
+}
+
+impl Verity {
+    pub fn new(
+        hash_algorithm: &str,
+        data_block_size: usize,
+        hash_block_size: usize,
+        salt: Vec<u8>,
+        format: u32,
+    ) -> Result<Self> {
+        let algorithm = match hash_algorithm {
+            "sha1" => &SHA1_FOR_LEGACY_USE_ONLY,
+            "sha256" => &SHA256,
+            "sha384" => &SHA384,
+            "sha512" => &SHA512,
+            _ => {
+                return Err(anyhow!(
+                    "Unsupported dm-verity hash algorithm: {}",
+                    hash_algorithm
+                ))
+            }
+        };
+        for size in [data_block_size, hash_block_size] {
+            if !size.is_power_of_two() || !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&size) {
+                return Err(anyhow!("Invalid dm-verity block size: {}", size));
+            }
+        }
+        if format > 1 {
+            return Err(anyhow!("Unsupported dm-verity format: {}", format));
+        }
+
+        Ok(Self {
+            algorithm,
+            data_block_size,
+            hash_block_size,
+            salt,
+            format,
+        })
+    }
+
+    fn hash(&self, block: &[u8]) -> Vec<u8> {
+        let mut context = Context::new(self.algorithm);
+        if self.format == 1 {
+            context.update(&self.salt);
+        }
+        context.update(block);
+        if self.format == 0 {
+            context.update(&self.salt);
+        }
+        context.finish().as_ref().to_vec()
+    }
+
+    /// Space of a hash in a hash block.
+    fn hash_stride(&self) -> usize {
+        match self.format {
+            0 => self.algorithm.output_len,
+            _ => self.algorithm.output_len.next_power_of_two(),
+        }
+    }
+
+    /// Number of hashes in a hash block, s.t. the power of two which
+    /// fits, with the hashes padded to a power of two.
+    fn hashes_per_block(&self) -> usize {
+        let max = self.hash_block_size / self.algorithm.output_len.next_power_of_two();
+        1 << (usize::BITS - 1 - max.leading_zeros())
+    }
+
+    /// Number of the blocks in each level of the tree, from the bottom.
+    fn level_sizes(&self, data_blocks: usize) -> Vec<usize> {
+        let mut sizes = Vec::new();
+        let mut blocks = data_blocks;
+        while blocks > 1 {
+            blocks = (blocks + self.hashes_per_block() - 1) / self.hashes_per_block();
+            sizes.push(blocks);
+        }
+        sizes
+    }
+
+    /// Build the hash blocks of the blocks in a lower level.
+    fn hash_level<'a>(&self, blocks: impl Iterator<Item = &'a [u8]>) -> Vec<u8> {
+        let mut level = Vec::new();
+        for (i, block) in blocks.enumerate() {
+            if i % self.hashes_per_block() == 0 {
+                level.resize(level.len() + self.hash_block_size, 0);
+            }
+            let offset = level.len() - self.hash_block_size
+                + (i % self.hashes_per_block()) * self.hash_stride();
+            let hash = self.hash(block);
+            level[offset..offset + hash.len()].copy_from_slice(&hash);
+        }
+        level
+    }
+
+    /// Get the number of data blocks, s.t. `data_blocks` if given, or
+    /// the whole of `data_size`.
+    pub fn data_blocks(&self, data_size: usize, data_blocks: Option<u64>) -> Result<usize> {
+        let blocks = match data_blocks {
+            Some(blocks) => usize::try_from(blocks)?,
+            None if data_size % self.data_block_size == 0 => data_size / self.data_block_size,
+            None => {
+                return Err(anyhow!(
+                    "Image size is not a multiple of the data block size."
+                ))
+            }
+        };
+        if blocks == 0 || blocks > data_size / self.data_block_size {
+            return Err(anyhow!(
+                "Invalid number of dm-verity data blocks: {}",
+                blocks
+            ));
+        }
+        Ok(blocks)
+    }
+
+    /// Calculate the root hash by building the hash tree of the first
+    /// `data_blocks` blocks of the image.
+    pub fn root_hash_of_image(&self, image: &[u8], data_blocks: usize) -> Result<Vec<u8>> {
+        let data = image
+            .get(..data_blocks * self.data_block_size)
+            .ok_or_else(|| anyhow!("Image is smaller than the data blocks."))?;
+
+        if data_blocks == 1 {
+            return Ok(self.hash(data));
+        }
+        let mut level = self.hash_level(data.chunks(self.data_block_size));
+        while level.len() > self.hash_block_size {
+            level = self.hash_level(level.chunks(self.hash_block_size));
+        }
+        Ok(self.hash(&level))
+    }
+
+    /// Calculate the root hash from a hash tree made by veritysetup,
+    /// s.t. the levels from the top, optionally after the superblock.
+    /// Each level is checked against the level above, while the bottom
+    /// level cannot be checked without the data.
+    pub fn root_hash_of_tree(&self, tree: &[u8], data_blocks: usize) -> Result<Vec<u8>> {
+        let tree = match tree.starts_with(SUPERBLOCK_SIGNATURE) {
+            true => {
+                let blocks = (SUPERBLOCK_SIZE + self.hash_block_size - 1) / self.hash_block_size;
+                tree.get(blocks * self.hash_block_size..)
+                    .unwrap_or_default()
+            }
+            false => tree,
+        };
+
+        let sizes = self.level_sizes(data_blocks);
+        if sizes.is_empty() {
+            return Err(anyhow!("No hash tree for a single data block."));
+        }
+        let tree_size = sizes
+            .iter()
+            .sum::<usize>()
+            .checked_mul(self.hash_block_size)
+            .filter(|size| *size <= tree.len())
+            .ok_or_else(|| anyhow!("Hash tree is truncated."))?;
+
+        // Levels are stored from the top, so the offset of a level is
+        // the size of the levels above it
+        let mut levels = Vec::new();
+        let mut end = tree_size;
+        for size in &sizes {
+            let start = end - size * self.hash_block_size;
+            levels.push(&tree[start..end]);
+            end = start;
+        }
+
+        for pair in levels.windows(2) {
+            let (lower, upper) = (pair[0], pair[1]);
+            if self.hash_level(lower.chunks(self.hash_block_size)) != upper {
+                return Err(anyhow!("Hash tree is inconsistent."));
+            }
+        }
+        Ok(self.hash(levels[levels.len() - 1]))
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Helpers to parse OVMF based firmware, e.g. OVMF of SEV and TDVF,
+//! whose GUIDed footer table tells how the firmware is launched.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+/// Size of the header of a footer table entry, s.t. a u16 size and
+/// a GUID, which is after the data of the entry.
+const ENTRY_HEADER_SIZE: usize = 18;
+
+const OVMF_TABLE_FOOTER_GUID: &str = "96b582de-1fb2-45f7-baea-a366c55a082d";
+
+/// Get the mixed-endian binary form of a GUID, as it is in firmware.
+pub fn guid_bytes(guid: &str) -> Result<[u8; 16]> {
+    let hex: String = guid.chars().filter(|c| *c != '-').collect();
+    if hex.len() != 32 {
+        return Err(anyhow!("Malformed GUID: {}", guid));
+    }
+
+    let mut bytes = [0u8; 16];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| anyhow!("Malformed GUID: {}", guid))?;
+    }
+    bytes[0..4].reverse();
+    bytes[4..6].reverse();
+    bytes[6..8].reverse();
+    Ok(bytes)
+}
+
+fn read<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    let slice = offset
+        .checked_add(N)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| anyhow!("Firmware is truncated at {:#x}.", offset))?;
+    bytes.copy_from_slice(slice);
+    Ok(bytes)
+}
+
+pub fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    read(data, offset).map(u16::from_le_bytes)
+}
+
+pub fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    read(data, offset).map(u32::from_le_bytes)
+}
+
+pub fn read_u64(data: &[u8], offset: usize) -> Result<u64> {
+    read(data, offset).map(u64::from_le_bytes)
+}
+
+/// Parse the footer table into the data of the entries by GUID. The
+/// table ends 32 bytes before the end of the firmware, and the
+/// entries are parsed backwards from there.
+pub fn parse_footer_table(data: &[u8]) -> Result<HashMap<[u8; 16], Vec<u8>>> {
+    let footer_start = data
+        .len()
+        .checked_sub(32 + ENTRY_HEADER_SIZE)
+        .ok_or_else(|| anyhow!("Firmware is too small."))?;
+    let footer = &data[footer_start..footer_start + ENTRY_HEADER_SIZE];
+    if footer[2..] != guid_bytes(OVMF_TABLE_FOOTER_GUID)? {
+        return Err(anyhow!("OVMF footer table not found."));
+    }
+
+    let table_size = (read_u16(footer, 0)? as usize)
+        .checked_sub(ENTRY_HEADER_SIZE)
+        .ok_or_else(|| anyhow!("Invalid OVMF footer table size."))?;
+    let table_start = footer_start
+        .checked_sub(table_size)
+        .ok_or_else(|| anyhow!("Invalid OVMF footer table size."))?;
+
+    let mut entries = HashMap::new();
+    let mut table = &data[table_start..footer_start];
+    while table.len() >= ENTRY_HEADER_SIZE {
+        let header = &table[table.len() - ENTRY_HEADER_SIZE..];
+        let entry_size = read_u16(header, 0)? as usize;
+        if entry_size < ENTRY_HEADER_SIZE || entry_size > table.len() {
+            return Err(anyhow!("Invalid OVMF footer table entry size."));
+        }
+
+        let mut guid = [0u8; 16];
+        guid.copy_from_slice(&header[2..]);
+        let entry_data = &table[table.len() - entry_size..table.len() - ENTRY_HEADER_SIZE];
+        entries.insert(guid, entry_data.to_vec());
+        table = &table[..table.len() - entry_size];
+    }
+    Ok(entries)
+}
@@ -0,0 +1,183 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Parser of the IMA runtime measurement list, in the ASCII and binary
+//! formats exported by securityfs, s.t.
+//! `/sys/kernel/security/ima/ascii_runtime_measurements` and
+//! `/sys/kernel/security/ima/binary_runtime_measurements`.
+//!
+//! Only entries of the `ima-ng` and `ima-sig` templates are returned,
+//! others, e.g. `ima-buf`, do not measure files and are skipped.
+
+use anyhow::{anyhow, Result};
+
+/// Templates with the file digest and path
+pub const SUPPORTED_TEMPLATES: &[&str] = &["ima-ng", "ima-sig"];
+
+/// Size of the template digest in the binary list, s.t. SHA-1
+const TEMPLATE_DIGEST_SIZE: usize = 20;
+
+/// Type and version of an IMA signature, s.t. EVM_IMA_XATTR_DIGSIG v2,
+/// which starts the hex encoded signature of `ima-sig`.
+const IMA_SIGNATURE_PREFIX: &str = "0302";
+
+/// An entry of the measurement list.
+/// * `alg`, `digest`: the file digest, with the algorithm as named by
+/// IMA, e.g. `sha256`, and the hex encoded value.
+/// * `path`: the file name hint, s.t. the path when it is measured.
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    pub pcr: u32,
+    pub template: String,
+    pub alg: String,
+    pub digest: String,
+    pub path: String,
+}
+
+impl Entry {
+    /// Whether the entry is a violation, s.t. the file was opened for
+    /// write while measured, whose digest is all zero.
+    pub fn is_violation(&self) -> bool {
+        self.digest.chars().all(|c| c == '0')
+    }
+}
+
+fn is_hex(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Parse the ASCII measurement list. Each line is
+/// `<pcr> <template-digest> <template> <alg>:<digest> <path>`, and
+/// `ima-sig` entries may end with the hex encoded signature.
+pub fn parse_ascii(list: &str) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for (i, line) in list.lines().enumerate().filter(|(_, l)| !l.is_empty()) {
+        let malformed = || anyhow!("Malformed IMA measurement at line {}.", i + 1);
+
+        let fields: Vec<&str> = line.splitn(5, ' ').collect();
+        if fields.len() != 5 {
+            return Err(malformed());
+        }
+        let pcr = fields[0].parse().map_err(|_| malformed())?;
+        let template = fields[2];
+        if !SUPPORTED_TEMPLATES.contains(&template) {
+            continue;
+        }
+
+        let (alg, digest) = fields[3].split_once(':').ok_or_else(malformed)?;
+        if alg.is_empty() || !is_hex(digest) {
+            return Err(malformed());
+        }
+
+        // The path may contain spaces, so only a trailing signature is
+        // taken off
+        let path = match fields[4].rsplit_once(' ') {
+            Some((path, sig))
+                if template == "ima-sig"
+                    && sig.starts_with(IMA_SIGNATURE_PREFIX)
+                    && is_hex(sig) =>
+            {
+                path
+            }
+            _ => fields[4],
+        };
+
+        entries.push(Entry {
+            pcr,
+            template: template.to_string(),
+            alg: alg.to_string(),
+            digest: digest.to_lowercase(),
+            path: path.to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Cursor over the binary measurement list.
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.offset..end))
+            .ok_or_else(|| anyhow!("IMA measurement list is truncated at {:#x}.", self.offset))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    /// Read a field prefixed by its u32 length.
+    fn field(&mut self) -> Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.bytes(len)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+}
+
+/// Parse the binary measurement list. Each entry is the PCR, the SHA-1
+/// template digest, the template name and the template data, where
+/// the data of `ima-ng` and `ima-sig` starts with the `d-ng` field,
+/// s.t. `<alg>:\0<digest>`, and the `n-ng` field, s.t. the path with
+/// a NUL terminator.
+pub fn parse_binary(list: &[u8]) -> Result<Vec<Entry>> {
+    let mut reader = Reader {
+        data: list,
+        offset: 0,
+    };
+
+    let mut entries = Vec::new();
+    while !reader.is_empty() {
+        let pcr = reader.u32()?;
+        reader.bytes(TEMPLATE_DIGEST_SIZE)?;
+        let template = String::from_utf8(reader.field()?.to_vec())
+            .map_err(|_| anyhow!("Invalid IMA template name."))?;
+        let mut data = Reader {
+            data: reader.field()?,
+            offset: 0,
+        };
+        if !SUPPORTED_TEMPLATES.contains(&template.as_str()) {
+            continue;
+        }
+
+        let digest = data.field()?;
+        let separator = digest
+            .windows(2)
+            .position(|w| w == b":\0")
+            .ok_or_else(|| anyhow!("Malformed IMA file digest."))?;
+        let (alg, digest) = (&digest[..separator], &digest[separator + 2..]);
+        if alg.is_empty() || digest.is_empty() {
+            return Err(anyhow!("Malformed IMA file digest."));
+        }
+        let alg = String::from_utf8(alg.to_vec())
+            .map_err(|_| anyhow!("Invalid IMA digest algorithm."))?;
+
+        let path = data.field()?;
+        let path = path.strip_suffix(b"\0").unwrap_or(path);
+        let path =
+            String::from_utf8(path.to_vec()).map_err(|_| anyhow!("Invalid IMA file path."))?;
+
+        entries.push(Entry {
+            pcr,
+            template,
+            alg,
+            digest: digest.iter().map(|b| format!("{:02x}", b)).collect(),
+            path,
+        });
+    }
+    Ok(entries)
+}
@@ -0,0 +1,295 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! # IMA Extractor
+//!
+//! This Extractor helps to verify a signed IMA runtime measurement
+//! list of a reference system, and extract a reference value for each
+//! file measured in it.
+
+pub mod list;
+
+use anyhow::{anyhow, Result};
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dsse::{Envelope, ThresholdVerifier, TrustPolicy},
+    reference_value::{ReferenceValue, REFERENCE_VALUE_VERSION},
+};
+
+use super::{unknown_expiry, ConfigurableExtractor, Extractor};
+
+/// Payload type of the ASCII measurement list inside a DSSE envelope
+pub const IMA_ASCII_PAYLOAD_TYPE: &str = "application/vnd.rvps.ima-ascii-measurements";
+
+/// Payload type of the binary measurement list inside a DSSE envelope
+pub const IMA_BINARY_PAYLOAD_TYPE: &str = "application/vnd.rvps.ima-binary-measurements";
+
+/// The first entry of the list, which is the aggregate of the TPM
+/// PCRs rather than a file
+const BOOT_AGGREGATE: &str = "boot_aggregate";
+
+/// Provenance of an IMA measurement list.
+/// * `envelope`: DSSE envelope of the measurement list, whose payload
+/// type tells the format.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Provenance {
+    pub envelope: Envelope,
+}
+
+/// Policy of the IMA Extractor, given by its section of
+/// the config file.
+/// * `trust`: keys trusted to sign the measurement list, see
+/// `TrustPolicy`.
+/// * `includes`: only files matching one of the patterns are
+/// extracted. All files are extracted if no pattern is given.
+/// * `excludes`: files matching one of the patterns are not extracted.
+///
+/// Patterns are globs (e.g. `/usr/bin/*`) matched against the path.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Policy {
+    #[serde(flatten)]
+    pub trust: TrustPolicy,
+    #[serde(default)]
+    pub includes: Vec<String>,
+    #[serde(default)]
+    pub excludes: Vec<String>,
+}
+
+pub struct ImaExtractor {
+    verifier: ThresholdVerifier,
+    includes: Vec<Pattern>,
+    excludes: Vec<Pattern>,
+}
+
+impl ImaExtractor {
+    /// Create an IMA Extractor with the given policy.
+    pub fn with_policy(policy: Policy) -> Result<Self> {
+        let verifier = policy.trust.verifier()?;
+        let compile = |patterns: &[String]| -> Result<Vec<Pattern>> {
+            patterns
+                .iter()
+                .map(|p| Pattern::new(p).map_err(|e| anyhow!("Invalid pattern {}: {}", p, e)))
+                .collect()
+        };
+        Ok(Self {
+            verifier,
+            includes: compile(&policy.includes)?,
+            excludes: compile(&policy.excludes)?,
+        })
+    }
+
+    /// Whether the file is chosen by the patterns of the policy.
+    fn is_included(&self, path: &str) -> bool {
+        let included = self.includes.is_empty() || self.includes.iter().any(|p| p.matches(path));
+        let excluded = self.excludes.iter().any(|p| p.matches(path));
+        included && !excluded
+    }
+}
+
+impl ConfigurableExtractor for ImaExtractor {
+    type Config = Policy;
+
+    fn with_config(config: Policy) -> Result<Self> {
+        Self::with_policy(config)
+    }
+}
+
+impl Extractor for ImaExtractor {
+    /// IMA Extractor.
+    /// The provenance is a signed IMA measurement list in the ASCII or
+    /// binary format. A reference value is returned for each file
+    /// chosen by the policy, named by its path. A file measured more
+    /// than once, e.g. after an update, has all its distinct digests.
+    /// The boot aggregate and violations are skipped.
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+        let provenance: Provenance = serde_json::from_str(provenance)?;
+        let payload_type = provenance.envelope.payload_type.as_str();
+        if payload_type != IMA_ASCII_PAYLOAD_TYPE && payload_type != IMA_BINARY_PAYLOAD_TYPE {
+            return Err(anyhow!(
+                "Unsupported IMA measurement list type: {}",
+                payload_type
+            ));
+        }
+        let payload = self.verifier.verify(&provenance.envelope, payload_type)?;
+        let entries = match payload_type {
+            IMA_ASCII_PAYLOAD_TYPE => list::parse_ascii(
+                std::str::from_utf8(&payload)
+                    .map_err(|_| anyhow!("IMA ASCII measurement list is not UTF-8."))?,
+            )?,
+            _ => list::parse_binary(&payload)?,
+        };
+
+        // Group the digests by path, in the order of the list
+        let mut files: Vec<(String, Vec<(String, String)>)> = Vec::new();
+        for entry in entries {
+            if entry.path == BOOT_AGGREGATE
+                || entry.is_violation()
+                || !self.is_included(&entry.path)
+            {
+                continue;
+            }
+
+            let index = match files.iter().position(|(path, _)| *path == entry.path) {
+                Some(index) => index,
+                None => {
+                    files.push((entry.path.clone(), Vec::new()));
+                    files.len() - 1
+                }
+            };
+            let digest = (entry.alg, entry.digest);
+            if !files[index].1.contains(&digest) {
+                files[index].1.push(digest);
+            }
+        }
+
+        let rvs = files
+            .into_iter()
+            .map(|(path, digests)| {
+                digests.into_iter().fold(
+                    ReferenceValue::new()
+                        .set_name(&path)
+                        .set_version(REFERENCE_VALUE_VERSION)
+                        .set_expired(unknown_expiry()),
+                    |rv, (alg, digest)| rv.add_hash_value(alg, digest),
+                )
+            })
+            .collect();
+        Ok(rvs)
+    }
+
+    /// Templates of the measurement list entries returned.
+    fn payload_versions(&self) -> Vec<String> {
+        list::SUPPORTED_TEMPLATES
+            .iter()
+            .map(|t| t.to_string())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use serde_json::json;
+
+    use crate::{
+        dsse::{test::generate_ed25519_private_key_pem, Signer, TrustPolicy},
+        extractors::extractor_modules::Extractor,
+    };
+
+    use super::{ImaExtractor, Policy, IMA_ASCII_PAYLOAD_TYPE, IMA_BINARY_PAYLOAD_TYPE};
+
+    /// Test vectors generated by `<git-repo>/tests/ima/generate.py`
+    const TEST_VECTORS_DIR: &str = "../tests/ima";
+
+    fn read(name: &str) -> Vec<u8> {
+        fs::read(format!("{}/{}", TEST_VECTORS_DIR, name)).unwrap()
+    }
+
+    /// Sign the measurement list with a new key, and get the provenance
+    /// and an Extractor trusting the key with the given filters.
+    fn sign(
+        payload_type: &str,
+        list: &[u8],
+        includes: &[&str],
+        excludes: &[&str],
+    ) -> (String, ImaExtractor) {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let key_path = dir.path().join("key.pub");
+        fs::write(&key_path, signer.public_key_pem()).unwrap();
+
+        let envelope = signer.sign(payload_type, list);
+        let extractor = ImaExtractor::with_policy(Policy {
+            trust: TrustPolicy::new(vec![key_path]),
+            includes: includes.iter().map(|p| p.to_string()).collect(),
+            excludes: excludes.iter().map(|p| p.to_string()).collect(),
+        })
+        .unwrap();
+        (json!({ "envelope": envelope }).to_string(), extractor)
+    }
+
+    #[test]
+    fn ima_extractor() {
+        for (payload_type, name) in [
+            (IMA_ASCII_PAYLOAD_TYPE, "ascii_runtime_measurements"),
+            (IMA_BINARY_PAYLOAD_TYPE, "binary_runtime_measurements"),
+        ] {
+            let (provenance, e) = sign(payload_type, &read(name), &[], &[]);
+            let rvs = e.verify_and_extract(&provenance).unwrap();
+            let names: Vec<_> = rvs.iter().map(|rv| rv.name().as_str()).collect();
+            assert_eq!(
+                names,
+                [
+                    "/usr/bin/bash",
+                    "/usr/lib64/libc.so.6",
+                    "/etc/app/my config.conf",
+                    "/usr/bin/ls",
+                    "/tmp/run.sh"
+                ]
+            );
+
+            let bash = rvs[0].hash_values();
+            assert_eq!(bash.len(), 3);
+            assert_eq!(bash[0].alg(), "sha256");
+            assert_eq!(
+                bash[0].value(),
+                "37d2b12d5d9abc2a364ef9448767ee03938e383c0284193477dc7618f4b7c6c2"
+            );
+            assert_eq!(bash[2].alg(), "sha512");
+            assert_eq!(rvs[3].hash_values()[0].alg(), "sha1");
+
+            // Filters
+            let (provenance, e) = sign(payload_type, &read(name), &["/usr/*"], &["/usr/bin/ls"]);
+            let rvs = e.verify_and_extract(&provenance).unwrap();
+            let names: Vec<_> = rvs.iter().map(|rv| rv.name().as_str()).collect();
+            assert_eq!(names, ["/usr/bin/bash", "/usr/lib64/libc.so.6"]);
+        }
+    }
+
+    #[test]
+    fn ima_extractor_rejects() {
+        let ascii = read("ascii_runtime_measurements");
+        let binary = read("binary_runtime_measurements");
+
+        // Unsupported payload type
+        let (provenance, e) = sign("text/plain", &ascii, &[], &[]);
+        assert!(e.verify_and_extract(&provenance).is_err());
+
+        // Binary list signed as ASCII
+        let (provenance, e) = sign(IMA_ASCII_PAYLOAD_TYPE, &binary, &[], &[]);
+        assert!(e.verify_and_extract(&provenance).is_err());
+
+        // Truncated binary list
+        let (provenance, e) = sign(
+            IMA_BINARY_PAYLOAD_TYPE,
+            &binary[..binary.len() - 1],
+            &[],
+            &[],
+        );
+        assert!(e.verify_and_extract(&provenance).is_err());
+
+        // Malformed file digest
+        let tampered = String::from_utf8(ascii.clone())
+            .unwrap()
+            .replacen("sha256:", "sha256:x", 1);
+        let (provenance, e) = sign(IMA_ASCII_PAYLOAD_TYPE, tampered.as_bytes(), &[], &[]);
+        assert!(e.verify_and_extract(&provenance).is_err());
+
+        // Untrusted key
+        let (provenance, _) = sign(IMA_ASCII_PAYLOAD_TYPE, &ascii, &[], &[]);
+        let (_, e) = sign(IMA_ASCII_PAYLOAD_TYPE, &ascii, &[], &[]);
+        assert!(e.verify_and_extract(&provenance).is_err());
+
+        // Invalid pattern
+        let e = ImaExtractor::with_policy(Policy {
+            includes: vec!["/usr/[".into()],
+            ..Default::default()
+        });
+        assert!(e.is_err());
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Builder of in-toto provenance.
+//!
+//! Helps to package a directory of in-toto metadata (`.layout`,
+//! `.pub`, `.link` files, and artifacts to be inspected if any)
+//! into a `Provenance`, or a ready-to-submit `Message`.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use glob::Pattern;
+use walkdir::WalkDir;
+
+use crate::Message;
+
+use super::{Provenance, INTOTO_VERSION};
+
+/// Provenance type of in-toto in a `Message`
+pub const INTOTO_PROVENANCE_TYPE: &str = "in-toto";
+
+/// `ProvenanceBuilder` walks a directory and packages the files
+/// into a `Provenance`.
+/// * `root`: the directory to walk. Paths of the files in the
+/// provenance are relative to it.
+/// * `line_normalization`: see `Provenance`. Defaults to `true`.
+/// * `layout_required`: whether a `.layout` file must be packaged.
+/// Defaults to `true`. Not required if the layout is trusted by the
+/// policy of the in-toto Extractor.
+/// * `includes`: only files matching one of the patterns are
+/// packaged. All files are packaged if no pattern is given.
+/// * `excludes`: files matching one of the patterns are not packaged.
+///
+/// Patterns are globs (e.g. `*.link`, `keys/*.pub`) matched against
+/// the relative path, using `/` as separator.
+pub struct ProvenanceBuilder {
+    root: PathBuf,
+    line_normalization: bool,
+    layout_required: bool,
+    includes: Vec<Pattern>,
+    excludes: Vec<Pattern>,
+}
+
+impl ProvenanceBuilder {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            line_normalization: true,
+            layout_required: true,
+            includes: Vec::new(),
+            excludes: Vec::new(),
+        }
+    }
+
+    /// Set whether line normalization is enabled.
+    pub fn line_normalization(mut self, line_normalization: bool) -> Self {
+        self.line_normalization = line_normalization;
+        self
+    }
+
+    /// Set whether a layout file must be packaged.
+    pub fn layout_required(mut self, layout_required: bool) -> Self {
+        self.layout_required = layout_required;
+        self
+    }
+
+    /// Add an include pattern.
+    pub fn include(mut self, pattern: &str) -> Result<Self> {
+        self.includes.push(Pattern::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Add an exclude pattern.
+    pub fn exclude(mut self, pattern: &str) -> Result<Self> {
+        self.excludes.push(Pattern::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Whether the file of the relative path should be packaged.
+    fn is_selected(&self, relative_path: &str) -> bool {
+        let included =
+            self.includes.is_empty() || self.includes.iter().any(|p| p.matches(relative_path));
+        let excluded = self.excludes.iter().any(|p| p.matches(relative_path));
+        included && !excluded
+    }
+
+    /// Walk the directory and package the selected files.
+    pub fn build(&self) -> Result<Provenance> {
+        let mut files = HashMap::new();
+
+        for entry in WalkDir::new(&self.root).follow_links(true) {
+            let entry = entry?;
+            if entry.file_type().is_dir() {
+                continue;
+            }
+
+            let relative_path = entry
+                .path()
+                .strip_prefix(&self.root)?
+                .components()
+                .map(|c| {
+                    c.as_os_str()
+                        .to_str()
+                        .ok_or_else(|| anyhow!("Non UTF-8 file path: {:?}", entry.path()))
+                })
+                .collect::<Result<Vec<_>>>()?
+                .join("/");
+
+            if !self.is_selected(&relative_path) {
+                continue;
+            }
+
+            let content = fs::read(entry.path())?;
+            files.insert(relative_path, base64::encode(content));
+        }
+
+        if self.layout_required && !files.keys().any(|k| k.ends_with(".layout")) {
+            return Err(anyhow!("Layout file not found in {:?}.", self.root));
+        }
+
+        Ok(Provenance {
+            version: INTOTO_VERSION.into(),
+            line_normalization: self.line_normalization,
+            files,
+        })
+    }
+
+    /// Package the selected files into a `Message` of type in-toto.
+    pub fn build_message(&self) -> Result<Message> {
+        let provenance = self.build()?;
+        let payload = serde_json::to_string(&provenance)?;
+        Ok(Message::new(INTOTO_PROVENANCE_TYPE, &payload))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::ProvenanceBuilder;
+
+    #[test]
+    fn in_toto_builder_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("keys")).unwrap();
+        fs::write(dir.path().join("demo.layout"), "layout").unwrap();
+        fs::write(dir.path().join("keys/alice.pub"), "key").unwrap();
+        fs::write(dir.path().join("step.link"), "link").unwrap();
+        fs::write(dir.path().join("notes.txt"), "notes").unwrap();
+
+        let provenance = ProvenanceBuilder::new(dir.path())
+            .line_normalization(false)
+            .exclude("*.txt")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(!provenance.line_normalization);
+        let mut names: Vec<_> = provenance.files.keys().cloned().collect();
+        names.sort();
+        assert_eq!(names, vec!["demo.layout", "keys/alice.pub", "step.link"]);
+        assert_eq!(provenance.files["keys/alice.pub"], base64::encode("key"));
+
+        let provenance = ProvenanceBuilder::new(dir.path())
+            .include("*.layout")
+            .unwrap()
+            .include("keys/*")
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut names: Vec<_> = provenance.files.keys().cloned().collect();
+        names.sort();
+        assert_eq!(names, vec!["demo.layout", "keys/alice.pub"]);
+
+        // A layout is a must, unless told otherwise
+        assert!(ProvenanceBuilder::new(dir.path())
+            .exclude("*.layout")
+            .unwrap()
+            .build()
+            .is_err());
+        let provenance = ProvenanceBuilder::new(dir.path())
+            .layout_required(false)
+            .exclude("*.layout")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(!provenance.files.contains_key("demo.layout"));
+    }
+}
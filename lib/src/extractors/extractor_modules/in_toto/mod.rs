@@ -8,22 +8,32 @@
 //! This Extractor helps to verify in-toto metadata and extract
 //! related reference value from link file.
 
+pub mod builder;
+
 use std::{
     collections::{BTreeMap, HashMap},
     env,
-    fs::{create_dir_all, File},
+    fs::{self, create_dir_all, File},
     io::Write,
+    path::{Path, PathBuf},
 };
 
 use anyhow::{anyhow, Result};
-use chrono::{TimeZone, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 use in_toto::models::{TargetDescription, VirtualTargetPath};
 use in_totolib_rs::intoto::verify;
+use ring::digest;
 use serde::{Deserialize, Serialize};
 
-use crate::reference_value::{ReferenceValue, REFERENCE_VALUE_VERSION};
+use crate::{
+    dsse::{KeyType, Verifier},
+    reference_value::{ReferenceValue, REFERENCE_VALUE_VERSION},
+    report::VerificationReport,
+};
+
+use super::{ConfigurableExtractor, Extractor};
 
-use super::Extractor;
+pub use builder::{ProvenanceBuilder, INTOTO_PROVENANCE_TYPE};
 
 static INTOTO_VERSION: &str = "0.9";
 
@@ -46,38 +56,188 @@ pub struct Payload {
     artifacts: BTreeMap<VirtualTargetPath, TargetDescription>,
 }
 
-pub struct InTotoExtractor;
+/// Policy of the in-toto Extractor, given by its configuration.
+/// * `layout_path`: path to the trusted layout file. If not set, the
+/// layout file in the provenance is used.
+/// * `pub_key_paths`: paths to the trusted public keys of the layout.
+/// If empty, the public keys in the provenance are used.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Policy {
+    pub layout_path: Option<PathBuf>,
+    #[serde(default)]
+    pub pub_key_paths: Vec<PathBuf>,
+}
+
+/// Part of an in-toto layout file told in the verification report.
+#[derive(Deserialize)]
+struct Layout {
+    signed: LayoutSigned,
+    #[serde(default)]
+    signatures: Vec<LayoutSignature>,
+}
+
+#[derive(Deserialize)]
+struct LayoutSigned {
+    expires: Option<DateTime<Utc>>,
+    #[serde(default)]
+    steps: Vec<LayoutStep>,
+    #[serde(default)]
+    inspect: Vec<LayoutStep>,
+}
+
+#[derive(Deserialize)]
+struct LayoutStep {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct LayoutSignature {
+    keyid: String,
+}
+
+impl Layout {
+    fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Record the steps, inspections and signers of the verified layout.
+    /// Only the signatures of the `trusted_keyids` are verified by
+    /// in-toto, so the others are not told as signers.
+    fn report(&self, report: &mut VerificationReport, trusted_keyids: &[String]) {
+        for signature in &self.signatures {
+            if trusted_keyids.contains(&signature.keyid) {
+                report.add_signer(&signature.keyid);
+            }
+        }
+        for step in &self.signed.steps {
+            report.add_step(&step.name, "step", true);
+        }
+        for inspection in &self.signed.inspect {
+            report.add_step(&inspection.name, "inspection", true);
+        }
+        if let Some(expires) = self.signed.expires {
+            report.add_timestamp("layout-expires", expires);
+        }
+    }
+}
+
+/// Calculate the in-toto key id of a public key file in PEM format,
+/// s.t. the hex encoded sha256 digest of the canonical JSON of the key
+/// as loaded by in-toto. The PEM contains neither `"` nor `\`, which
+/// are the only characters escaped by canonical JSON.
+fn in_toto_keyid(path: &str) -> Result<String> {
+    let pem_str = fs::read_to_string(path)?;
+    let pem_str = pem_str.trim();
+    let (keytype, scheme, public) = match Verifier::from_pem(pem_str)?.key_type() {
+        KeyType::RsaPss => ("rsa", "rsassa-pss-sha256", pem_str.to_string()),
+        KeyType::EcdsaP256 => ("ecdsa", "ecdsa-sha2-nistp256", pem_str.to_string()),
+        KeyType::EcdsaP384 => ("ecdsa", "ecdsa-sha2-nistp384", pem_str.to_string()),
+        KeyType::Ed25519 => {
+            // The raw key follows the 12 bytes header of the SPKI
+            let der = pem::parse(pem_str)?.contents;
+            let public = der[12..].iter().map(|b| format!("{:02x}", b)).collect();
+            ("ed25519", "ed25519", public)
+        }
+    };
+    let key = format!(
+        r#"{{"keyid_hash_algorithms":["sha256","sha512"],"keytype":"{}","keyval":{{"public":"{}"}},"scheme":"{}"}}"#,
+        keytype, public, scheme
+    );
+    Ok(digest::digest(&digest::SHA256, key.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+pub struct InTotoExtractor {
+    layout_path: Option<String>,
+    pub_key_paths: Vec<String>,
+}
+
+/// Get the absolute path of an existing file as a string, as the
+/// verification runs in another working directory.
+fn absolute_path(path: &Path) -> Result<String> {
+    let path = fs::canonicalize(path).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+    path.to_str()
+        .map(|p| p.to_string())
+        .ok_or_else(|| anyhow!("Invalid path: {}", path.display()))
+}
 
 impl InTotoExtractor {
+    /// Create an in-toto Extractor trusting the layout and public keys
+    /// in the provenance.
     pub fn new() -> Self {
-        InTotoExtractor
+        InTotoExtractor {
+            layout_path: None,
+            pub_key_paths: Vec::new(),
+        }
+    }
+
+    /// Create an in-toto Extractor with the given policy.
+    pub fn with_policy(policy: Policy) -> Result<Self> {
+        let layout_path = policy
+            .layout_path
+            .as_deref()
+            .map(absolute_path)
+            .transpose()?;
+        let pub_key_paths = policy
+            .pub_key_paths
+            .iter()
+            .map(|p| absolute_path(p))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(InTotoExtractor {
+            layout_path,
+            pub_key_paths,
+        })
+    }
+}
+
+impl ConfigurableExtractor for InTotoExtractor {
+    type Config = Policy;
+
+    fn with_config(config: Policy) -> Result<Self> {
+        Self::with_policy(config)
     }
 }
 
 impl Extractor for InTotoExtractor {
     /// In-toto's Extractor.
-    /// It needs the following parameters in the HashMap:
-    /// * `layout_path`: path to the layout file.
-    /// * `pub_key_paths`: serialized json string of a Vec, including
-    /// paths of public keys.
-    /// * `intermediate_paths`: serialized json string of a Vec, including
-    /// paths of intermediate.
-    /// * `link_dir`: path to the directory of link files.
-    /// * `line_normalization`: whether line normalization is enabled (true
-    /// or false), which means whether Windows-style line separators
-    /// (CRLF) are normalized to Unix-style line separators (LF) for
-    /// cross-platform consistency.
-    fn verify_and_extract(&self, provenance: &str) -> Result<ReferenceValue> {
+    /// The links in the provenance are verified against the layout and
+    /// public keys of the policy, or those in the provenance if the
+    /// policy does not set them. `line_normalization` of the provenance
+    /// tells whether Windows-style line separators (CRLF) are
+    /// normalized to Unix-style line separators (LF) for cross-platform
+    /// consistency.
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+        let mut report = VerificationReport::new(INTOTO_PROVENANCE_TYPE);
+        self.verify_and_extract_with_report(provenance, &mut report)
+    }
+
+    /// The signers are the key ids of the layout signatures made by the
+    /// trusted public keys, and all the steps and inspections of the
+    /// layout pass once verified, as the in-toto library only tells the
+    /// first failure.
+    fn verify_and_extract_with_report(
+        &self,
+        provenance: &str,
+        report: &mut VerificationReport,
+    ) -> Result<Vec<ReferenceValue>> {
         // Deserialize Provenance
         let payload: Provenance = serde_json::from_str(provenance)?;
 
         // Judge version
         if payload.version != INTOTO_VERSION {
-            return Err(anyhow!(
-                "Version unmatched! Need {}, given {}.",
-                INTOTO_VERSION,
-                payload.version
-            ));
+            return report.check(
+                "version",
+                Err(anyhow!(
+                    "Version unmatched! Need {}, given {}.",
+                    INTOTO_VERSION,
+                    payload.version
+                )),
+            );
         }
 
         // Create tempdir and put the files
@@ -107,22 +267,31 @@ impl Extractor for InTotoExtractor {
             .to_string();
 
         // get layout file
-        let layout_name = payload
-            .files
-            .keys()
-            .find(|&k| k.ends_with(".layout"))
-            .ok_or_else(|| anyhow!("Layout file not found."))?
-            .to_owned();
-
-        let mut layout_path_buf = tempdir_path.clone();
-        layout_path_buf.push(layout_name);
-        let layout_path = layout_path_buf
-            .to_str()
-            .ok_or_else(|| anyhow!("Get layout file path failed."))?
-            .to_string();
+        let layout_path = match &self.layout_path {
+            Some(layout_path) => layout_path.clone(),
+            None => {
+                report.add_warning("The layout in the provenance is trusted.");
+                let layout_name = payload
+                    .files
+                    .keys()
+                    .find(|&k| k.ends_with(".layout"))
+                    .ok_or_else(|| anyhow!("Layout file not found."))?
+                    .to_owned();
+
+                let mut layout_path_buf = tempdir_path.clone();
+                layout_path_buf.push(layout_name);
+                layout_path_buf
+                    .to_str()
+                    .ok_or_else(|| anyhow!("Get layout file path failed."))?
+                    .to_string()
+            }
+        };
 
         // get pub keys
-        let pub_key_paths: Vec<String> = {
+        let pub_key_paths: Vec<String> = if !self.pub_key_paths.is_empty() {
+            self.pub_key_paths.clone()
+        } else {
+            report.add_warning("The public keys in the provenance are trusted.");
             let file_names: Vec<String> = payload
                 .files
                 .keys()
@@ -150,24 +319,49 @@ impl Extractor for InTotoExtractor {
 
         let line_normalization = payload.line_normalization;
 
+        // The layout is read again to tell its details in the report
+        let layout = Layout::from_file(&layout_path);
+        let trusted_keyids: Vec<String> = pub_key_paths
+            .iter()
+            .filter_map(|path| match in_toto_keyid(path) {
+                Ok(keyid) => Some(keyid),
+                Err(e) => {
+                    report.add_warning(&format!("Read the public key {} failed: {}", path, e));
+                    None
+                }
+            })
+            .collect();
+
         // Store and change current dir to the tmp dir
         let cwd = env::current_dir()?;
         env::set_current_dir(tempdir_path)?;
 
         // Here the returned value is "" when verification successeds
-        let summary_link = verify(
+        let verified = verify(
             layout_path,
             pub_key_paths,
             intermediate_paths,
             link_dir,
             line_normalization,
-        )?;
+        );
 
-        // Change back working dir
+        // Change back working dir, also if the verification fails
         env::set_current_dir(cwd)?;
+        let summary_link = report.check("in-toto-verification", verified)?;
+
+        match layout {
+            Ok(layout) => layout.report(report, &trusted_keyids),
+            Err(e) => report.add_warning(&format!("Read the layout failed: {}", e)),
+        }
 
         // Only the first artifact? TODO
         let names: Vec<_> = summary_link.products().keys().collect();
+        if names.len() > 1 {
+            report.add_warning(&format!(
+                "Only the first of the {} products is extracted.",
+                names.len()
+            ));
+        }
         let name = names
             .first()
             .ok_or_else(|| anyhow!("No products found in the in-toto metadata"))?;
@@ -197,22 +391,33 @@ impl Extractor for InTotoExtractor {
             rv = rv.add_hash_value(alg.to_string(), value.to_string());
         }
 
-        Ok(rv)
+        Ok(vec![rv])
+    }
+
+    /// The in-toto version of the provenance.
+    fn payload_versions(&self) -> Vec<String> {
+        vec![INTOTO_VERSION.to_string()]
     }
 }
 
 #[cfg(test)]
 pub mod test {
-    use std::{collections::HashMap, fs};
+    use std::fs;
 
     use chrono::{TimeZone, Utc};
     use serial_test::serial;
     use sha2::{Digest, Sha256};
-    use walkdir::WalkDir;
 
-    use crate::{extractors::extractor_modules::Extractor, ReferenceValue};
+    use crate::{
+        extractors::extractor_modules::{ConfigurableExtractor, Extractor},
+        report::VerificationReport,
+        ReferenceValue,
+    };
 
-    use super::{InTotoExtractor, Provenance, INTOTO_VERSION};
+    use super::{
+        in_toto_keyid, InTotoExtractor, Layout, LayoutSignature, Policy, ProvenanceBuilder,
+        INTOTO_PROVENANCE_TYPE,
+    };
 
     /// Helps to generate a reference value.
     pub fn generate_in_toto_reference_value() -> String {
@@ -232,29 +437,7 @@ pub mod test {
     /// Helps to generate a in-toto provenance encoded
     /// in Base64. All related files are in `<git-repo>/tests/in-toto`
     pub fn generate_in_toto_provenance() -> String {
-        let mut files = HashMap::new();
-
-        for path in WalkDir::new("../tests/in-toto") {
-            let path = path.unwrap();
-            if path.file_type().is_dir() {
-                continue;
-            }
-
-            let ent = path.path();
-            let content = fs::read(&ent).unwrap();
-            let mut file_name = ent.to_str().unwrap().to_string();
-            let content_base64 = base64::encode(content);
-
-            // split_off will delete the prefix "../tests/in-toto" for every
-            // file
-            files.insert(file_name.split_off(17), content_base64);
-        }
-
-        let p = Provenance {
-            version: INTOTO_VERSION.into(),
-            line_normalization: true,
-            files,
-        };
+        let p = ProvenanceBuilder::new("../tests/in-toto").build().unwrap();
 
         let provenance = serde_json::to_string(&p).unwrap();
         provenance
@@ -272,6 +455,105 @@ pub mod test {
         let provenance = generate_in_toto_provenance();
         let res = e.verify_and_extract(&provenance).unwrap();
 
-        assert_eq!(res, rv);
+        assert_eq!(res, vec![rv]);
+    }
+
+    #[test]
+    #[serial]
+    fn in_toto_extractor_report() {
+        let e = InTotoExtractor::new();
+        let provenance = generate_in_toto_provenance();
+        let mut report = VerificationReport::new(INTOTO_PROVENANCE_TYPE);
+        e.verify_and_extract_with_report(&provenance, &mut report)
+            .unwrap();
+
+        assert_eq!(
+            report.signers,
+            vec!["70ca5750c2eda80b18f41f4ec5f92146789b5d68dd09577be422a0159bd13680"]
+        );
+        let steps: Vec<(&str, &str, bool)> = report
+            .steps
+            .iter()
+            .map(|step| (step.name.as_str(), step.kind.as_str(), step.passed))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("write-code", "step", true),
+                ("package", "step", true),
+                ("untar", "inspection", true)
+            ]
+        );
+        assert_eq!(
+            report.timestamps["layout-expires"],
+            Utc.ymd(2030, 11, 18).and_hms(16, 6, 36)
+        );
+        // Nothing is configured, so the provenance is trusted
+        assert_eq!(report.warnings.len(), 2);
+        assert!(report.failed_rules.is_empty());
+
+        // A tampered link fails the verification
+        let mut p = ProvenanceBuilder::new("../tests/in-toto").build().unwrap();
+        p.files
+            .insert("package.d3ffd108.link".into(), base64::encode("{}"));
+        let mut report = VerificationReport::new(INTOTO_PROVENANCE_TYPE);
+        assert!(e
+            .verify_and_extract_with_report(&serde_json::to_string(&p).unwrap(), &mut report)
+            .is_err());
+        assert_eq!(report.failed_rules[0].rule, "in-toto-verification");
+        assert!(report.steps.is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn in_toto_extractor_with_config() {
+        let config = Policy {
+            layout_path: Some("../tests/in-toto/demo.layout".into()),
+            pub_key_paths: vec!["../tests/in-toto/alice.pub".into()],
+        };
+        let e = InTotoExtractor::with_config(config).unwrap();
+
+        // The layout and public key are trusted by the configuration,
+        // not carried by the provenance
+        let p = ProvenanceBuilder::new("../tests/in-toto")
+            .layout_required(false)
+            .exclude("*.layout")
+            .unwrap()
+            .exclude("*.pub")
+            .unwrap()
+            .build()
+            .unwrap();
+        let res = e
+            .verify_and_extract(&serde_json::to_string(&p).unwrap())
+            .unwrap();
+        assert_eq!(res[0].name(), "foo.tar.gz");
+
+        let config = Policy {
+            layout_path: Some("../tests/in-toto/nonexistent.layout".into()),
+            pub_key_paths: Vec::new(),
+        };
+        assert!(InTotoExtractor::with_config(config).is_err());
+    }
+
+    #[test]
+    fn in_toto_layout_signers() {
+        let alice = in_toto_keyid("../tests/in-toto/alice.pub").unwrap();
+        assert_eq!(
+            alice,
+            "70ca5750c2eda80b18f41f4ec5f92146789b5d68dd09577be422a0159bd13680"
+        );
+
+        // Only the signatures of the trusted keys are told
+        let mut layout = Layout::from_file("../tests/in-toto/demo.layout").unwrap();
+        layout.signatures.push(LayoutSignature {
+            keyid: "untrusted".into(),
+        });
+        let mut report = VerificationReport::new(INTOTO_PROVENANCE_TYPE);
+        layout.report(&mut report, &[alice.clone()]);
+        assert_eq!(report.signers, vec![alice]);
+
+        let mut report = VerificationReport::new(INTOTO_PROVENANCE_TYPE);
+        layout.report(&mut report, &[]);
+        assert!(report.signers.is_empty());
     }
 }
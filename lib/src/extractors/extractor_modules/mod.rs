@@ -8,20 +8,153 @@
 #[cfg(feature = "in-toto")]
 pub mod in_toto;
 
+#[cfg(feature = "slsa")]
+pub mod slsa;
+
+#[cfg(feature = "sigstore")]
+pub mod sigstore;
+
+#[cfg(feature = "checksums")]
+pub mod checksums;
+
+#[cfg(feature = "spdx")]
+pub mod spdx;
+
+#[cfg(feature = "cyclonedx")]
+pub mod cyclonedx;
+
+#[cfg(feature = "oci")]
+pub mod oci;
+
+#[cfg(feature = "sev-snp")]
+pub mod sev_snp;
+
+#[cfg(feature = "tdx")]
+pub mod tdx;
+
+#[cfg(feature = "tcg-event-log")]
+pub mod tcg_event_log;
+
+#[cfg(feature = "ima")]
+pub mod ima;
+
+#[cfg(feature = "dm-verity")]
+pub mod dm_verity;
+
+#[cfg(feature = "tuf")]
+pub mod tuf;
+
+#[cfg(feature = "x509-manifest")]
+pub mod x509_manifest;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(any(
+    feature = "sigstore",
+    feature = "checksums",
+    feature = "sev-snp",
+    feature = "tcg-event-log",
+    feature = "tdx",
+    feature = "dm-verity",
+    feature = "tuf",
+    feature = "x509-manifest"
+))]
+pub mod artifact;
+
+#[cfg(any(feature = "sev-snp", feature = "tdx"))]
+pub mod firmware;
+
 use anyhow::*;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::de::DeserializeOwned;
 use std::collections::HashMap;
 
-use crate::reference_value::ReferenceValue;
+use crate::{reference_value::ReferenceValue, report::VerificationReport, RVPSError};
 
 /// Extractor is a standard interface that all provenance extractors
 /// need to implement. Here reference_value can be modified in the
-/// handler, added any field if needed.
+/// handler, added any field if needed. A provenance may describe
+/// more than one artifact, so a reference value will be returned
+/// for each of them.
 pub trait Extractor {
-    fn verify_and_extract(&self, provenance: &str) -> Result<ReferenceValue>;
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>>;
+
+    /// Verify and extract like `verify_and_extract`, and record the
+    /// details of the verification in the report, e.g. the signers and
+    /// the failed rules. By default nothing is recorded.
+    fn verify_and_extract_with_report(
+        &self,
+        provenance: &str,
+        _report: &mut VerificationReport,
+    ) -> Result<Vec<ReferenceValue>> {
+        self.verify_and_extract(provenance)
+    }
+
+    /// Versions of the provenance format that the Extractor supports,
+    /// e.g. the in-toto version. Empty if the format is unversioned.
+    fn payload_versions(&self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
+/// An Extractor with a typed configuration, deserialized from the
+/// section of its provenance type in the config file.
+pub trait ConfigurableExtractor: Extractor + Sized {
+    type Config: DeserializeOwned;
+
+    /// Create the Extractor with the configuration, or fail if the
+    /// configuration is invalid.
+    fn with_config(config: Self::Config) -> Result<Self>;
+}
+
+/// Expiry of the reference values extracted from provenance which does
+/// not tell one, s.t. the Unix epoch. Most provenance formats only sign
+/// what the artifacts are, not until when they may be trusted.
+pub fn unknown_expiry() -> DateTime<Utc> {
+    Utc.ymd(1970, 1, 1).and_hms(0, 0, 0)
+}
+
+/// Configuration of an Extractor, s.t. the section of its provenance
+/// type in the config file, before it is deserialized to the typed
+/// configuration of the Extractor.
+pub type ExtractorConfig = serde_json::Value;
+
 pub type ExtractorInstance = Box<dyn Extractor + Sync + Send>;
-type ExtractorInstantiateFunc = Box<dyn Fn() -> ExtractorInstance + Send + Sync>;
+
+/// Instantiate an Extractor with its configuration, or `None` if it
+/// is not configured.
+pub type ExtractorInstantiateFunc =
+    Box<dyn Fn(Option<&ExtractorConfig>) -> Result<ExtractorInstance> + Send + Sync>;
+
+/// Get the instantiate function of a configurable Extractor, which is
+/// created by `new` if it is not configured.
+pub fn configurable<E>(new: fn() -> E) -> ExtractorInstantiateFunc
+where
+    E: ConfigurableExtractor + Send + Sync + 'static,
+{
+    Box::new(move |config| -> Result<ExtractorInstance> {
+        let extractor = match config {
+            Some(config) => E::with_config(serde_json::from_value(config.clone())?)?,
+            None => new(),
+        };
+        Ok(Box::new(extractor))
+    })
+}
+
+/// Get the instantiate function of an Extractor which must be
+/// configured, e.g. with the keys it trusts. It fails to be instantiated
+/// if it is not configured.
+pub fn configured<E>() -> ExtractorInstantiateFunc
+where
+    E: ConfigurableExtractor + Send + Sync + 'static,
+{
+    Box::new(|config| -> Result<ExtractorInstance> {
+        let config = config.ok_or_else(|| anyhow!("The Extractor is not configured."))?;
+        let extractor = E::with_config(serde_json::from_value(config.clone())?)?;
+        Ok(Box::new(extractor))
+    })
+}
 
 pub struct ExtractorModuleList {
     mod_list: HashMap<String, ExtractorInstantiateFunc>,
@@ -34,21 +167,134 @@ impl ExtractorModuleList {
         #[cfg(feature = "in-toto")]
         {
             let instantiate_func: ExtractorInstantiateFunc =
-                Box::new(|| -> ExtractorInstance { Box::new(in_toto::InTotoExtractor::new()) });
+                configurable(in_toto::InTotoExtractor::new);
             mod_list.insert("in-toto".to_string(), instantiate_func);
         }
 
+        #[cfg(feature = "slsa")]
+        {
+            let instantiate_func: ExtractorInstantiateFunc = configured::<slsa::SlsaExtractor>();
+            mod_list.insert("slsa".to_string(), instantiate_func);
+        }
+
+        #[cfg(feature = "sigstore")]
+        {
+            let instantiate_func: ExtractorInstantiateFunc =
+                configured::<sigstore::SigstoreExtractor>();
+            mod_list.insert("sigstore".to_string(), instantiate_func);
+        }
+
+        #[cfg(feature = "checksums")]
+        {
+            let instantiate_func: ExtractorInstantiateFunc =
+                configured::<checksums::ChecksumsExtractor>();
+            mod_list.insert("checksums".to_string(), instantiate_func);
+        }
+
+        #[cfg(feature = "spdx")]
+        {
+            let instantiate_func: ExtractorInstantiateFunc = configured::<spdx::SpdxExtractor>();
+            mod_list.insert("spdx".to_string(), instantiate_func);
+        }
+
+        #[cfg(feature = "cyclonedx")]
+        {
+            let instantiate_func: ExtractorInstantiateFunc =
+                configured::<cyclonedx::CycloneDxExtractor>();
+            mod_list.insert("cyclonedx".to_string(), instantiate_func);
+        }
+
+        #[cfg(feature = "oci")]
+        {
+            let instantiate_func: ExtractorInstantiateFunc = configured::<oci::OciExtractor>();
+            mod_list.insert("oci".to_string(), instantiate_func);
+        }
+
+        #[cfg(feature = "sev-snp")]
+        {
+            let instantiate_func: ExtractorInstantiateFunc =
+                configured::<sev_snp::SevSnpExtractor>();
+            mod_list.insert("sev-snp".to_string(), instantiate_func);
+        }
+
+        #[cfg(feature = "tdx")]
+        {
+            let instantiate_func: ExtractorInstantiateFunc = configured::<tdx::TdxExtractor>();
+            mod_list.insert("tdx".to_string(), instantiate_func);
+        }
+
+        #[cfg(feature = "tcg-event-log")]
+        {
+            let instantiate_func: ExtractorInstantiateFunc =
+                configured::<tcg_event_log::TcgEventLogExtractor>();
+            mod_list.insert("tcg-event-log".to_string(), instantiate_func);
+        }
+
+        #[cfg(feature = "ima")]
+        {
+            let instantiate_func: ExtractorInstantiateFunc = configured::<ima::ImaExtractor>();
+            mod_list.insert("ima".to_string(), instantiate_func);
+        }
+
+        #[cfg(feature = "dm-verity")]
+        {
+            let instantiate_func: ExtractorInstantiateFunc =
+                configured::<dm_verity::DmVerityExtractor>();
+            mod_list.insert("dm-verity".to_string(), instantiate_func);
+        }
+
+        #[cfg(feature = "tuf")]
+        {
+            let instantiate_func: ExtractorInstantiateFunc = configured::<tuf::TufExtractor>();
+            mod_list.insert("tuf".to_string(), instantiate_func);
+        }
+
+        #[cfg(feature = "x509-manifest")]
+        {
+            let instantiate_func: ExtractorInstantiateFunc =
+                configured::<x509_manifest::X509ManifestExtractor>();
+            mod_list.insert("x509-manifest".to_string(), instantiate_func);
+        }
+
         ExtractorModuleList { mod_list }
     }
 
     pub fn get_func(&self, extractor_name: &str) -> Result<&ExtractorInstantiateFunc> {
-        let instantiate_func: &ExtractorInstantiateFunc =
-            self.mod_list.get(extractor_name).ok_or_else(|| {
-                anyhow!(
-                    "RVPS Extractors does not support the given extractor: {}!",
-                    extractor_name
-                )
-            })?;
+        let instantiate_func: &ExtractorInstantiateFunc = self
+            .mod_list
+            .get(extractor_name)
+            .ok_or_else(|| RVPSError::UnsupportedProvenanceType(extractor_name.into()))?;
         Ok(instantiate_func)
     }
+
+    /// Names of the Extractors with registered instantiate functions.
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.mod_list.keys()
+    }
+
+    /// Whether an instantiate function is registered for `extractor_name`.
+    pub fn contains(&self, extractor_name: &str) -> bool {
+        self.mod_list.contains_key(extractor_name)
+    }
+
+    /// Register the instantiate function of an Extractor for the
+    /// provenance type `extractor_name`, which must not be registered.
+    pub fn register(
+        &mut self,
+        extractor_name: &str,
+        instantiate_func: ExtractorInstantiateFunc,
+    ) -> Result<()> {
+        if self.contains(extractor_name) {
+            return Err(RVPSError::DuplicateExtractor(extractor_name.into()).into());
+        }
+        self.mod_list
+            .insert(extractor_name.to_string(), instantiate_func);
+        Ok(())
+    }
+
+    /// Unregister the instantiate function for `extractor_name`, and
+    /// return whether it was registered.
+    pub fn unregister(&mut self, extractor_name: &str) -> bool {
+        self.mod_list.remove(extractor_name).is_some()
+    }
 }
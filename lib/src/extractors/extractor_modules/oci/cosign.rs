@@ -0,0 +1,81 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Offline verification of cosign signatures, s.t. a signature over
+//! a simple signing payload which binds the repository to the digest
+//! of the manifest.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::dsse::Verifier;
+
+use super::image::check_digest;
+
+/// Type of the simple signing payload of cosign
+const COSIGN_PAYLOAD_TYPE: &str = "cosign container image signature";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct Identity {
+    pub docker_reference: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct Image {
+    pub docker_manifest_digest: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Critical {
+    pub identity: Identity,
+    pub image: Image,
+    #[serde(rename = "type")]
+    pub _type: String,
+}
+
+/// Simple signing payload, only the critical part is needed.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SimpleSigning {
+    pub critical: Critical,
+}
+
+/// Verify the base64 encoded `signature` over the `payload` with one
+/// of the trusted keys, and check that the payload refers to the
+/// `manifest` in the `repository`.
+pub fn verify(
+    payload: &str,
+    signature: &str,
+    keys: &[Verifier],
+    repository: &str,
+    manifest: &[u8],
+) -> Result<()> {
+    let sig = base64::decode(signature.trim())
+        .map_err(|e| anyhow!("Decode cosign signature failed: {}", e))?;
+    if !keys
+        .iter()
+        .any(|k| k.verify_signature(payload.as_bytes(), &sig).is_ok())
+    {
+        return Err(anyhow!("No valid cosign signature by the trusted keys."));
+    }
+
+    let payload: SimpleSigning = serde_json::from_str(payload)?;
+    let critical = payload.critical;
+    if critical._type != COSIGN_PAYLOAD_TYPE {
+        return Err(anyhow!(
+            "Unexpected cosign payload type: {}",
+            critical._type
+        ));
+    }
+    if critical.identity.docker_reference != repository {
+        return Err(anyhow!(
+            "Cosign signature is for {}, not {}.",
+            critical.identity.docker_reference,
+            repository
+        ));
+    }
+    check_digest(manifest, &critical.image.docker_manifest_digest)
+}
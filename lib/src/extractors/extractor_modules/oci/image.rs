@@ -0,0 +1,134 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! OCI image manifests and indexes, see the OCI image spec. Docker
+//! manifests (schema 2) and manifest lists are also accepted, as
+//! they are in the same form.
+
+use anyhow::{anyhow, Result};
+use ring::digest::{digest, SHA256, SHA512};
+use serde::{Deserialize, Serialize};
+
+/// Platform of a manifest in an index.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Platform {
+    pub architecture: String,
+    pub os: String,
+    pub variant: Option<String>,
+}
+
+impl Platform {
+    /// e.g. `linux/arm64/v8`.
+    pub fn name(&self) -> String {
+        match &self.variant {
+            Some(variant) => format!("{}/{}/{}", self.os, self.architecture, variant),
+            None => format!("{}/{}", self.os, self.architecture),
+        }
+    }
+}
+
+/// Content descriptor.
+/// * `digest`: e.g. `sha256:<hex>`.
+/// * `platform`: only for manifests in an index.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Descriptor {
+    pub media_type: Option<String>,
+    pub digest: String,
+    pub size: u64,
+    pub platform: Option<Platform>,
+}
+
+/// Image manifest.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub media_type: Option<String>,
+    pub config: Descriptor,
+    pub layers: Vec<Descriptor>,
+}
+
+/// Image index, s.t. a multi-arch image.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Index {
+    pub media_type: Option<String>,
+    pub manifests: Vec<Descriptor>,
+}
+
+/// Either an image manifest or an image index. The `mediaType` is
+/// optional, so they are told apart by the fields.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum ImageManifest {
+    Index(Index),
+    Manifest(Manifest),
+}
+
+/// Split a digest into the algorithm and the hex encoded value.
+pub fn split_digest(digest: &str) -> Result<(&str, &str)> {
+    digest
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Malformed digest: {}", digest))
+}
+
+/// Calculate the hex encoded digest of the content.
+pub fn calculate_digest(alg: &str, content: &[u8]) -> Result<String> {
+    let algorithm = match alg {
+        "sha256" => &SHA256,
+        "sha512" => &SHA512,
+        _ => return Err(anyhow!("Unsupported digest algorithm: {}", alg)),
+    };
+
+    Ok(digest(algorithm, content)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Check that the content matches the digest, e.g. `sha256:<hex>`.
+pub fn check_digest(content: &[u8], expected: &str) -> Result<()> {
+    let (alg, value) = split_digest(expected)?;
+    if calculate_digest(alg, content)? != value {
+        return Err(anyhow!("Content does not match the digest {}.", expected));
+    }
+    Ok(())
+}
+
+/// Get the repository of an image reference, and the digest if the
+/// reference is by digest, e.g. `registry.example.com/app:v1` to
+/// `registry.example.com/app`.
+pub fn repository(reference: &str) -> (&str, Option<&str>) {
+    let (name, digest) = match reference.split_once('@') {
+        Some((name, digest)) => (name, Some(digest)),
+        None => (reference, None),
+    };
+
+    // A colon after the last slash is the tag, otherwise the port
+    let name_start = name.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match name[name_start..].rfind(':') {
+        Some(i) => (&name[..name_start + i], digest),
+        None => (name, digest),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::repository;
+
+    #[test]
+    fn oci_repository() {
+        assert_eq!(
+            repository("registry.example.com:5000/app:v1"),
+            ("registry.example.com:5000/app", None)
+        );
+        assert_eq!(
+            repository("registry.example.com:5000/app"),
+            ("registry.example.com:5000/app", None)
+        );
+        assert_eq!(repository("app:v1@sha256:aa"), ("app", Some("sha256:aa")));
+    }
+}
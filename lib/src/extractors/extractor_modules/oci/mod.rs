@@ -0,0 +1,353 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! # OCI Extractor
+//!
+//! This Extractor helps to verify a signed OCI image manifest or
+//! index, by a cosign or notation signature, and extract reference
+//! values from the digests of the manifests, configs and layers.
+
+pub mod cosign;
+pub mod image;
+pub mod notation;
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dsse::Verifier,
+    reference_value::{ReferenceValue, REFERENCE_VALUE_VERSION},
+};
+
+use self::image::{
+    calculate_digest, check_digest, repository, split_digest, Descriptor, ImageManifest,
+};
+
+use super::{unknown_expiry, ConfigurableExtractor, Extractor};
+
+/// Signature of the manifest or index.
+/// * `Cosign`: the simple signing `payload` and the base64 encoded
+/// `signature` over it.
+/// * `Notation`: the JWS `envelope`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "format", rename_all = "lowercase")]
+pub enum Signature {
+    Cosign { payload: String, signature: String },
+    Notation { envelope: String },
+}
+
+/// Signed OCI image.
+/// * `reference`: image reference, e.g. `registry.example.com/app:v1`.
+/// * `manifest`: content of the signed image manifest or index.
+/// * `manifests`: contents of the platform manifests, if `manifest`
+/// is an index. They are optional, and checked against the index.
+/// * `signature`: signature of `manifest`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Provenance {
+    pub reference: String,
+    pub manifest: String,
+    #[serde(default)]
+    pub manifests: Vec<String>,
+    pub signature: Signature,
+}
+
+/// Policy of the OCI Extractor, given by its section of
+/// the config file.
+/// * `cosign_keys`: paths of the PEM encoded cosign public keys.
+/// * `notation_certificates`: paths of the PEM encoded trusted
+/// certificates for notation signatures.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Policy {
+    #[serde(default)]
+    pub cosign_keys: Vec<PathBuf>,
+    #[serde(default)]
+    pub notation_certificates: Vec<PathBuf>,
+}
+
+/// Generate a reference value of the digest.
+fn reference_value(name: &str, digest: &str) -> Result<ReferenceValue> {
+    let (alg, value) = split_digest(digest)?;
+    Ok(ReferenceValue::new()
+        .set_name(name)
+        .set_version(REFERENCE_VALUE_VERSION)
+        .add_hash_value(alg.to_string(), value.to_string()))
+}
+
+/// Generate reference values of the config and layers of an image
+/// manifest, named `<prefix>/config` and `<prefix>/layers/<index>`.
+fn manifest_reference_values(
+    prefix: &str,
+    config: &Descriptor,
+    layers: &[Descriptor],
+    rvs: &mut Vec<ReferenceValue>,
+) -> Result<()> {
+    rvs.push(reference_value(
+        &format!("{}/config", prefix),
+        &config.digest,
+    )?);
+    for (i, layer) in layers.iter().enumerate() {
+        rvs.push(reference_value(
+            &format!("{}/layers/{}", prefix, i),
+            &layer.digest,
+        )?);
+    }
+    Ok(())
+}
+
+pub struct OciExtractor {
+    cosign_keys: Vec<Verifier>,
+    notation_certificates: Vec<Vec<u8>>,
+}
+
+impl OciExtractor {
+    /// Create an OCI Extractor with the given policy.
+    pub fn with_policy(policy: Policy) -> Result<Self> {
+        let cosign_keys = policy
+            .cosign_keys
+            .iter()
+            .map(Verifier::from_pem_file)
+            .collect::<Result<_>>()?;
+
+        let mut notation_certificates = Vec::new();
+        for path in &policy.notation_certificates {
+            let pems = pem::parse_many(fs::read(path)?)?;
+            for pem in pems.into_iter().filter(|p| p.tag == "CERTIFICATE") {
+                notation_certificates.push(pem.contents);
+            }
+        }
+
+        Ok(Self {
+            cosign_keys,
+            notation_certificates,
+        })
+    }
+}
+
+impl ConfigurableExtractor for OciExtractor {
+    type Config = Policy;
+
+    fn with_config(config: Policy) -> Result<Self> {
+        Self::with_policy(config)
+    }
+}
+
+impl Extractor for OciExtractor {
+    /// OCI's Extractor.
+    /// The provenance is a signed image manifest or index. Reference
+    /// values are named by the image reference:
+    /// * `<reference>`: digest of the manifest or index.
+    /// * `<reference>/config`, `<reference>/layers/<index>`: digests
+    /// of the config and layers of a manifest.
+    /// * `<reference>/<os>/<arch>[/<variant>]`: digest of the manifest
+    /// of each platform in an index, and the config and layers under
+    /// it if the platform manifest is given.
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+        let provenance: Provenance = serde_json::from_str(provenance)?;
+        let reference = provenance.reference.as_str();
+        let manifest = provenance.manifest.as_bytes();
+
+        let (repository, digest) = repository(reference);
+        if let Some(digest) = digest {
+            check_digest(manifest, digest)?;
+        }
+
+        let expiry = match &provenance.signature {
+            Signature::Cosign { payload, signature } => {
+                cosign::verify(payload, signature, &self.cosign_keys, repository, manifest)?;
+                None
+            }
+            Signature::Notation { envelope } => {
+                notation::verify(envelope, &self.notation_certificates, manifest)?
+            }
+        };
+
+        let digest = format!("sha256:{}", calculate_digest("sha256", manifest)?);
+        let mut rvs = vec![reference_value(reference, &digest)?];
+
+        match serde_json::from_str(&provenance.manifest)? {
+            ImageManifest::Manifest(m) => {
+                if !provenance.manifests.is_empty() {
+                    return Err(anyhow!("Platform manifests are given for a manifest."));
+                }
+                manifest_reference_values(reference, &m.config, &m.layers, &mut rvs)?;
+            }
+            ImageManifest::Index(index) => {
+                let platforms: Vec<_> = index
+                    .manifests
+                    .iter()
+                    .filter_map(|d| d.platform.as_ref().map(|p| (d, p.name())))
+                    .collect();
+                for (descriptor, platform) in &platforms {
+                    let name = format!("{}/{}", reference, platform);
+                    rvs.push(
+                        reference_value(&name, &descriptor.digest)?
+                            .add_metadata("platform", platform),
+                    );
+                }
+
+                for content in &provenance.manifests {
+                    let (descriptor, platform) = platforms
+                        .iter()
+                        .find(|(d, _)| check_digest(content.as_bytes(), &d.digest).is_ok())
+                        .ok_or_else(|| anyhow!("Platform manifest is not in the index."))?;
+                    let m = match serde_json::from_str(content)? {
+                        ImageManifest::Manifest(m) => m,
+                        ImageManifest::Index(_) => {
+                            return Err(anyhow!(
+                                "Nested index {} is not supported.",
+                                descriptor.digest
+                            ))
+                        }
+                    };
+                    let prefix = format!("{}/{}", reference, platform);
+                    manifest_reference_values(&prefix, &m.config, &m.layers, &mut rvs)?;
+                }
+            }
+        }
+
+        // The reference values expire with the signature, and a cosign
+        // signature does not tell its expiry
+        let expired = expiry.unwrap_or_else(unknown_expiry);
+        Ok(rvs.into_iter().map(|rv| rv.set_expired(expired)).collect())
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    use crate::extractors::extractor_modules::Extractor;
+
+    use super::{OciExtractor, Policy};
+
+    /// Test vectors generated by `<git-repo>/tests/oci/generate.py`
+    const TEST_VECTORS_DIR: &str = "../tests/oci";
+
+    const REFERENCE: &str = "registry.example.com/rvps/app:v1";
+
+    fn read(name: &str) -> String {
+        fs::read_to_string(format!("{}/{}", TEST_VECTORS_DIR, name)).unwrap()
+    }
+
+    fn extractor() -> OciExtractor {
+        OciExtractor::with_policy(Policy {
+            cosign_keys: vec![format!("{}/cosign.pub", TEST_VECTORS_DIR).into()],
+            notation_certificates: vec![format!("{}/notation_root.pem", TEST_VECTORS_DIR).into()],
+        })
+        .unwrap()
+    }
+
+    fn cosign(reference: &str, index: &str, manifests: &[String]) -> String {
+        json!({
+            "reference": reference,
+            "manifest": index,
+            "manifests": manifests,
+            "signature": {
+                "format": "cosign",
+                "payload": read("index.cosign.payload"),
+                "signature": read("index.cosign.sig"),
+            },
+        })
+        .to_string()
+    }
+
+    fn notation(manifest: &str) -> String {
+        json!({
+            "reference": REFERENCE,
+            "manifest": manifest,
+            "signature": {
+                "format": "notation",
+                "envelope": read("manifest-amd64.jws"),
+            },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn oci_extractor() {
+        let e = extractor();
+
+        // Cosign signed index, with the amd64 manifest
+        let manifests = [read("manifest-amd64.json")];
+        let rvs = e
+            .verify_and_extract(&cosign(REFERENCE, &read("index.json"), &manifests))
+            .unwrap();
+        let names: Vec<_> = rvs.iter().map(|rv| rv.name().clone()).collect();
+        assert_eq!(
+            names,
+            [
+                REFERENCE.to_string(),
+                format!("{}/linux/amd64", REFERENCE),
+                format!("{}/linux/arm64", REFERENCE),
+                format!("{}/linux/amd64/config", REFERENCE),
+                format!("{}/linux/amd64/layers/0", REFERENCE),
+                format!("{}/linux/amd64/layers/1", REFERENCE),
+            ]
+        );
+        assert_eq!(rvs[1].metadata()["platform"], "linux/amd64");
+        assert_eq!(rvs[0].hash_values()[0].alg(), "sha256");
+        assert_eq!(rvs[0].expired(), &Utc.ymd(1970, 1, 1).and_hms(0, 0, 0));
+
+        // Notation signed manifest
+        let rvs = e
+            .verify_and_extract(&notation(&read("manifest-amd64.json")))
+            .unwrap();
+        let names: Vec<_> = rvs.iter().map(|rv| rv.name().clone()).collect();
+        assert_eq!(
+            names,
+            [
+                REFERENCE.to_string(),
+                format!("{}/config", REFERENCE),
+                format!("{}/layers/0", REFERENCE),
+                format!("{}/layers/1", REFERENCE),
+            ]
+        );
+        assert!(rvs
+            .iter()
+            .all(|rv| rv.expired() == &Utc.ymd(2032, 9, 1).and_hms(0, 0, 0)));
+    }
+
+    #[test]
+    fn oci_extractor_rejects() {
+        let e = extractor();
+        let index = read("index.json");
+
+        // Tampered index
+        let tampered = index.replace("arm64", "s390x");
+        assert!(e
+            .verify_and_extract(&cosign(REFERENCE, &tampered, &[]))
+            .is_err());
+
+        // Wrong repository
+        assert!(e
+            .verify_and_extract(&cosign("registry.example.com/other:v1", &index, &[]))
+            .is_err());
+
+        // Platform manifest not in the index
+        let manifests = [read("manifest-amd64.json").replacen("{", "{ ", 1)];
+        assert!(e
+            .verify_and_extract(&cosign(REFERENCE, &index, &manifests))
+            .is_err());
+
+        // Notation envelope for another manifest
+        assert!(e
+            .verify_and_extract(&notation(&read("manifest-arm64.json")))
+            .is_err());
+
+        // Untrusted keys
+        let e = OciExtractor::with_policy(Policy::default()).unwrap();
+        assert!(e
+            .verify_and_extract(&cosign(REFERENCE, &index, &[]))
+            .is_err());
+        assert!(e
+            .verify_and_extract(&notation(&read("manifest-amd64.json")))
+            .is_err());
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Offline verification of notation signatures in the JWS envelope,
+//! see the Notary Project signature specification. Only the
+//! `notary.x509` signing scheme is supported.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use x509_parser::{certificate::X509Certificate, prelude::FromDer, time::ASN1Time};
+
+use crate::dsse::{KeyType, Verifier};
+
+use super::image::{check_digest, Descriptor};
+
+/// Content type of the notation payload
+const NOTATION_PAYLOAD_TYPE: &str = "application/vnd.cncf.notary.payload.v1+json";
+
+/// The supported signing scheme
+const SIGNING_SCHEME: &str = "notary.x509";
+
+const SIGNING_SCHEME_HEADER: &str = "io.cncf.notary.signingScheme";
+
+const EXPIRY_HEADER: &str = "io.cncf.notary.expiry";
+
+/// Max length of a certificate chain
+const MAX_CHAIN_LENGTH: usize = 8;
+
+/// JWS in JSON serialization.
+/// * `x5c`: base64 encoded DER certificates, starting with the
+/// signing certificate.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Envelope {
+    pub payload: String,
+    pub protected: String,
+    pub header: UnprotectedHeader,
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UnprotectedHeader {
+    pub x5c: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProtectedHeader {
+    pub alg: String,
+    pub cty: String,
+    #[serde(default)]
+    pub crit: Vec<String>,
+    #[serde(rename = "io.cncf.notary.signingScheme")]
+    pub signing_scheme: String,
+    #[serde(rename = "io.cncf.notary.expiry")]
+    pub expiry: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Payload {
+    pub target_artifact: Descriptor,
+}
+
+/// Get the key type of a JWS algorithm.
+fn key_type(algorithm: &str) -> Result<KeyType> {
+    match algorithm {
+        "ES256" => Ok(KeyType::EcdsaP256),
+        "ES384" => Ok(KeyType::EcdsaP384),
+        "PS256" => Ok(KeyType::RsaPss),
+        _ => Err(anyhow!("Unsupported notation algorithm: {}", algorithm)),
+    }
+}
+
+fn decode(value: &str) -> Result<Vec<u8>> {
+    base64::decode_config(value, base64::URL_SAFE_NO_PAD)
+        .map_err(|e| anyhow!("Decode notation envelope failed: {}", e))
+}
+
+fn parse(der: &[u8]) -> Result<X509Certificate<'_>> {
+    let (_, certificate) =
+        X509Certificate::from_der(der).map_err(|e| anyhow!("Parse certificate failed: {}", e))?;
+    Ok(certificate)
+}
+
+/// Whether `issuer` issued `certificate`, and both are valid at `time`.
+fn issued_by(certificate: &X509Certificate, issuer: &X509Certificate, time: ASN1Time) -> bool {
+    certificate.issuer().as_raw() == issuer.subject().as_raw()
+        && issuer.validity().is_valid_at(time)
+        && certificate
+            .verify_signature(Some(issuer.public_key()))
+            .is_ok()
+}
+
+/// Verify the certificate chain up to one of the trusted certificates,
+/// which may be a root CA or the signing certificate itself, and get
+/// the public key of the signing certificate.
+fn verify_chain(chain: &[Vec<u8>], trusted: &[Vec<u8>]) -> Result<Verifier> {
+    let time = ASN1Time::now();
+    let chain = chain
+        .iter()
+        .map(|der| parse(der))
+        .collect::<Result<Vec<_>>>()?;
+    let trusted = trusted
+        .iter()
+        .map(|der| parse(der))
+        .collect::<Result<Vec<_>>>()?;
+
+    let leaf = chain
+        .first()
+        .ok_or_else(|| anyhow!("Signing certificate not found."))?;
+    if !leaf.validity().is_valid_at(time) {
+        return Err(anyhow!("Signing certificate is not valid now."));
+    }
+    let code_signing = leaf
+        .extended_key_usage()?
+        .map(|eku| eku.value.code_signing)
+        .unwrap_or(false);
+    if !code_signing {
+        return Err(anyhow!("Signing certificate is not for code signing."));
+    }
+
+    for (i, current) in chain.iter().take(MAX_CHAIN_LENGTH).enumerate() {
+        if trusted
+            .iter()
+            .any(|t| t.as_ref() == current.as_ref() || issued_by(current, t, time))
+        {
+            return Verifier::from_der(leaf.public_key().raw);
+        }
+
+        match chain.get(i + 1) {
+            Some(ca) if ca.is_ca() && issued_by(current, ca, time) => {}
+            _ => {
+                return Err(anyhow!(
+                    "Certificate chain is not issued by a trusted certificate."
+                ))
+            }
+        }
+    }
+
+    Err(anyhow!("Certificate chain is too long."))
+}
+
+/// Verify the JWS `envelope` with the certificate chain in it, up to
+/// one of the DER encoded `trusted_certs`, and check that the target
+/// artifact is the `manifest`. Return the expiry of the signature if
+/// it has one.
+pub fn verify(
+    envelope: &str,
+    trusted_certs: &[Vec<u8>],
+    manifest: &[u8],
+) -> Result<Option<DateTime<Utc>>> {
+    let envelope: Envelope = serde_json::from_str(envelope)?;
+    let header: ProtectedHeader = serde_json::from_slice(&decode(&envelope.protected)?)?;
+    if header.cty != NOTATION_PAYLOAD_TYPE {
+        return Err(anyhow!("Unexpected notation payload type: {}", header.cty));
+    }
+    if header.signing_scheme != SIGNING_SCHEME {
+        return Err(anyhow!(
+            "Unsupported signing scheme: {}",
+            header.signing_scheme
+        ));
+    }
+    if let Some(unknown) = header
+        .crit
+        .iter()
+        .find(|c| ![SIGNING_SCHEME_HEADER, EXPIRY_HEADER].contains(&c.as_str()))
+    {
+        return Err(anyhow!("Unsupported critical header: {}", unknown));
+    }
+    let expiry = match &header.expiry {
+        Some(expiry) => Some(DateTime::parse_from_rfc3339(expiry)?.with_timezone(&Utc)),
+        None => None,
+    };
+    if let Some(expiry) = expiry {
+        if expiry < Utc::now() {
+            return Err(anyhow!("Notation signature expired at {}.", expiry));
+        }
+    }
+
+    let chain = envelope
+        .header
+        .x5c
+        .iter()
+        .map(|c| base64::decode(c).map_err(|e| anyhow!("Decode certificate failed: {}", e)))
+        .collect::<Result<Vec<_>>>()?;
+    let verifier = verify_chain(&chain, trusted_certs)?;
+    if verifier.key_type() != key_type(&header.alg)? {
+        return Err(anyhow!(
+            "Algorithm {} does not match the signing certificate.",
+            header.alg
+        ));
+    }
+
+    let signing_input = format!("{}.{}", envelope.protected, envelope.payload);
+    verifier.verify_signature(signing_input.as_bytes(), &decode(&envelope.signature)?)?;
+
+    let payload: Payload = serde_json::from_slice(&decode(&envelope.payload)?)?;
+    let target = payload.target_artifact;
+    if target.size != manifest.len() as u64 {
+        return Err(anyhow!("Size of the target artifact does not match."));
+    }
+    check_digest(manifest, &target.digest)?;
+    Ok(expiry)
+}
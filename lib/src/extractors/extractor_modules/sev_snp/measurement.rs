@@ -0,0 +1,194 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Calculation of the SEV-SNP launch measurement, s.t. the launch
+//! digest extended by SNP_LAUNCH_UPDATE of each page, see the SEV
+//! Secure Nested Paging Firmware ABI Specification.
+
+use anyhow::{anyhow, Result};
+use ring::digest::{digest, SHA256, SHA384};
+
+use crate::extractors::extractor_modules::firmware::guid_bytes;
+
+use super::{
+    ovmf::{Ovmf, SectionType},
+    vmsa,
+};
+
+pub const PAGE_SIZE: usize = 4096;
+
+/// Size of the launch digest, s.t. SHA-384
+const LD_SIZE: usize = 48;
+
+/// Size of the PAGE_INFO structure
+const PAGE_INFO_SIZE: u16 = 0x70;
+
+/// Guest physical address of the VMSA pages
+const VMSA_GPA: u64 = 0xffff_ffff_f000;
+
+const SEV_HASH_TABLE_HEADER_GUID: &str = "9438d606-4f22-4cc9-b479-a793d411fd21";
+const SEV_KERNEL_ENTRY_GUID: &str = "4de79437-abd2-427f-b835-d5b172d2045b";
+const SEV_INITRD_ENTRY_GUID: &str = "44baf731-3a2f-4bd7-9af1-41e29169781d";
+const SEV_CMDLINE_ENTRY_GUID: &str = "97d02dd8-bd20-4c94-aa78-e7714d36ab2a";
+
+/// Size of an entry of the SEV hashes table, s.t. GUID, length and
+/// SHA-256 hash.
+const SEV_HASH_TABLE_ENTRY_SIZE: u16 = 50;
+
+/// Page types of SNP_LAUNCH_UPDATE.
+#[derive(Clone, Copy)]
+enum PageType {
+    Normal = 1,
+    Vmsa = 2,
+    Zero = 3,
+    Secrets = 5,
+    Cpuid = 6,
+}
+
+/// Guest context, which keeps the launch digest.
+struct Gctx {
+    ld: [u8; LD_SIZE],
+}
+
+impl Gctx {
+    fn new() -> Self {
+        Self { ld: [0; LD_SIZE] }
+    }
+
+    /// Extend the launch digest with the PAGE_INFO of a page.
+    fn update(&mut self, page_type: PageType, gpa: u64, contents: &[u8]) {
+        let mut page_info = Vec::with_capacity(PAGE_INFO_SIZE as usize);
+        page_info.extend_from_slice(&self.ld);
+        page_info.extend_from_slice(contents);
+        page_info.extend_from_slice(&PAGE_INFO_SIZE.to_le_bytes());
+        page_info.push(page_type as u8);
+        // IMI page, VMPL3/2/1 permissions and reserved
+        page_info.extend_from_slice(&[0; 5]);
+        page_info.extend_from_slice(&gpa.to_le_bytes());
+
+        self.ld
+            .copy_from_slice(digest(&SHA384, &page_info).as_ref());
+    }
+
+    fn update_normal_pages(&mut self, gpa: u64, data: &[u8]) {
+        for (i, page) in data.chunks(PAGE_SIZE).enumerate() {
+            let contents = digest(&SHA384, page);
+            let page_gpa = gpa + (i * PAGE_SIZE) as u64;
+            self.update(PageType::Normal, page_gpa, contents.as_ref());
+        }
+    }
+
+    fn update_zero_pages(&mut self, gpa: u64, size: u64) {
+        for offset in (0..size).step_by(PAGE_SIZE) {
+            self.update(PageType::Zero, gpa + offset, &[0; LD_SIZE]);
+        }
+    }
+}
+
+/// Hashes of the kernel, initrd and cmdline, which are checked by
+/// OVMF on direct boot.
+pub struct SevHashes {
+    kernel: Vec<u8>,
+    initrd: Vec<u8>,
+    cmdline: Vec<u8>,
+}
+
+impl SevHashes {
+    /// The cmdline is hashed with the terminating NUL, and a missing
+    /// initrd is hashed as empty.
+    pub fn new(kernel: &[u8], initrd: Option<&[u8]>, cmdline: Option<&str>) -> Self {
+        let mut cmdline = cmdline.unwrap_or_default().as_bytes().to_vec();
+        cmdline.push(0);
+        Self {
+            kernel: digest(&SHA256, kernel).as_ref().to_vec(),
+            initrd: digest(&SHA256, initrd.unwrap_or_default())
+                .as_ref()
+                .to_vec(),
+            cmdline: digest(&SHA256, &cmdline).as_ref().to_vec(),
+        }
+    }
+
+    /// Build the page of the SEV hashes table, with the table at
+    /// `offset` of the page. The table is padded to 16 bytes.
+    fn page(&self, offset: usize) -> Result<Vec<u8>> {
+        let mut table = guid_bytes(SEV_HASH_TABLE_HEADER_GUID)?.to_vec();
+        table.extend_from_slice(&(18 + 3 * SEV_HASH_TABLE_ENTRY_SIZE).to_le_bytes());
+        for (guid, hash) in [
+            (SEV_CMDLINE_ENTRY_GUID, &self.cmdline),
+            (SEV_INITRD_ENTRY_GUID, &self.initrd),
+            (SEV_KERNEL_ENTRY_GUID, &self.kernel),
+        ] {
+            table.extend_from_slice(&guid_bytes(guid)?);
+            table.extend_from_slice(&SEV_HASH_TABLE_ENTRY_SIZE.to_le_bytes());
+            table.extend_from_slice(hash);
+        }
+        table.resize((table.len() + 15) & !15, 0);
+
+        if offset + table.len() > PAGE_SIZE {
+            return Err(anyhow!("SEV hashes table crosses the page."));
+        }
+        let mut page = vec![0u8; PAGE_SIZE];
+        page[offset..offset + table.len()].copy_from_slice(&table);
+        Ok(page)
+    }
+}
+
+/// Calculate the launch measurement of a guest launched by QEMU with
+/// the OVMF firmware, the optional kernel hashes and `vcpus` vCPUs.
+pub fn launch_digest(
+    ovmf: &Ovmf,
+    hashes: Option<&SevHashes>,
+    vcpus: u32,
+    vcpu_sig: u32,
+    sev_features: u64,
+) -> Result<Vec<u8>> {
+    if ovmf.metadata_sections().is_empty() {
+        return Err(anyhow!("OVMF does not support SEV-SNP."));
+    }
+
+    let mut gctx = Gctx::new();
+    gctx.update_normal_pages(ovmf.gpa(), ovmf.data());
+
+    let mut hashes_measured = false;
+    for section in ovmf.metadata_sections() {
+        let gpa = section.gpa as u64;
+        match section.section_type {
+            SectionType::SnpSecMemory | SectionType::SvsmCaa => {
+                gctx.update_zero_pages(gpa, section.size as u64)
+            }
+            SectionType::SnpSecrets => gctx.update(PageType::Secrets, gpa, &[0; LD_SIZE]),
+            SectionType::Cpuid => gctx.update(PageType::Cpuid, gpa, &[0; LD_SIZE]),
+            SectionType::SnpKernelHashes => match hashes {
+                Some(hashes) => {
+                    let offset = ovmf.sev_hashes_table_gpa()? as usize & (PAGE_SIZE - 1);
+                    gctx.update_normal_pages(gpa, &hashes.page(offset)?);
+                    hashes_measured = true;
+                }
+                None => gctx.update_zero_pages(gpa, section.size as u64),
+            },
+        }
+    }
+    if hashes.is_some() && !hashes_measured {
+        return Err(anyhow!("OVMF does not support measured direct boot."));
+    }
+
+    let bsp = digest(
+        &SHA384,
+        &vmsa::save_area(vmsa::BSP_EIP, sev_features, vcpu_sig),
+    );
+    let ap = digest(
+        &SHA384,
+        &vmsa::save_area(ovmf.sev_es_reset_eip()?, sev_features, vcpu_sig),
+    );
+    for i in 0..vcpus {
+        let page = match i {
+            0 => &bsp,
+            _ => &ap,
+        };
+        gctx.update(PageType::Vmsa, VMSA_GPA, page.as_ref());
+    }
+
+    Ok(gctx.ld.to_vec())
+}
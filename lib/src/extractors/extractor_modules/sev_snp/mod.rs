@@ -0,0 +1,314 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! # SEV-SNP Extractor
+//!
+//! This Extractor helps to verify the signed launch components of an
+//! AMD SEV-SNP guest, s.t. OVMF firmware, kernel, initrd and cmdline,
+//! and calculate the expected launch measurement of the guest.
+
+pub mod measurement;
+pub mod ovmf;
+pub mod vmsa;
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dsse::{Envelope, ThresholdVerifier, TrustPolicy},
+    extractors::extractor_modules::artifact::{hex, Artifact},
+    reference_value::{ReferenceValue, REFERENCE_VALUE_VERSION},
+};
+
+use self::{
+    measurement::{launch_digest, SevHashes},
+    ovmf::Ovmf,
+};
+
+use super::{unknown_expiry, ConfigurableExtractor, Extractor};
+
+/// Payload type of the launch description inside a DSSE envelope
+pub const SNP_LAUNCH_PAYLOAD_TYPE: &str = "application/vnd.rvps.sev-snp-launch+json";
+
+/// Max number of vCPUs of a guest
+const MAX_VCPUS: u32 = 512;
+
+/// Signed launch description.
+/// * `name`: name of the reference value.
+/// * `ovmf`, `kernel`, `initrd`: digests of the components, e.g.
+/// `sha384:<hex>`.
+/// * `cmdline`: the kernel cmdline.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Launch {
+    pub name: String,
+    pub ovmf: String,
+    pub kernel: Option<String>,
+    pub initrd: Option<String>,
+    pub cmdline: Option<String>,
+}
+
+/// Provenance of a SEV-SNP guest.
+/// * `envelope`: DSSE envelope of the `Launch`.
+/// * `ovmf`, `kernel`, `initrd`: the components in the `Launch`,
+/// carried by content or referenced by path.
+/// * `vcpus`: number of vCPUs.
+/// * `vcpu_type`: QEMU vCPU type, e.g. `EPYC-Milan`.
+/// * `guest_features`: SEV features of the guest. Defaults to 1,
+/// s.t. only SNPActive.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Provenance {
+    pub envelope: Envelope,
+    pub ovmf: Artifact,
+    pub kernel: Option<Artifact>,
+    pub initrd: Option<Artifact>,
+    pub vcpus: u32,
+    pub vcpu_type: String,
+    #[serde(default = "default_guest_features")]
+    pub guest_features: u64,
+}
+
+/// Use to set default guest features of Provenance
+fn default_guest_features() -> u64 {
+    1
+}
+
+/// Policy of the SEV-SNP Extractor, given by its section of
+/// the config file.
+/// * `trust`: keys trusted to sign the launch description, see
+/// `TrustPolicy`.
+/// * `artifact_dir`: directory of the components referenced by path.
+/// If not set, components must be carried by the provenance.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Policy {
+    #[serde(flatten)]
+    pub trust: TrustPolicy,
+    pub artifact_dir: Option<PathBuf>,
+}
+
+pub struct SevSnpExtractor {
+    verifier: ThresholdVerifier,
+    artifact_dir: Option<PathBuf>,
+}
+
+impl SevSnpExtractor {
+    /// Create a SEV-SNP Extractor with the given policy.
+    pub fn with_policy(policy: Policy) -> Result<Self> {
+        let verifier = policy.trust.verifier()?;
+        Ok(Self {
+            verifier,
+            artifact_dir: policy.artifact_dir,
+        })
+    }
+}
+
+impl ConfigurableExtractor for SevSnpExtractor {
+    type Config = Policy;
+
+    fn with_config(config: Policy) -> Result<Self> {
+        Self::with_policy(config)
+    }
+}
+
+impl Extractor for SevSnpExtractor {
+    /// SEV-SNP's Extractor.
+    /// The provenance is a signed launch description, the components
+    /// in it, and the vCPU configuration. The reference value is the
+    /// SHA-384 launch measurement of the guest launched by QEMU with
+    /// the components.
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+        let provenance: Provenance = serde_json::from_str(provenance)?;
+        let payload = self
+            .verifier
+            .verify(&provenance.envelope, SNP_LAUNCH_PAYLOAD_TYPE)?;
+        let launch: Launch = serde_json::from_slice(&payload)?;
+
+        if provenance.vcpus == 0 || provenance.vcpus > MAX_VCPUS {
+            return Err(anyhow!("Invalid number of vCPUs: {}", provenance.vcpus));
+        }
+        let vcpu_sig = vmsa::vcpu_signature(&provenance.vcpu_type)?;
+
+        let dir = self.artifact_dir.as_deref();
+        let ovmf = Ovmf::new(provenance.ovmf.read(dir, &launch.ovmf)?)?;
+        let hashes = match (&launch.kernel, &provenance.kernel) {
+            (Some(digest), Some(kernel)) => {
+                let kernel = kernel.read(dir, digest)?;
+                let initrd = match (&launch.initrd, &provenance.initrd) {
+                    (Some(digest), Some(initrd)) => Some(initrd.read(dir, digest)?),
+                    (None, None) => None,
+                    _ => return Err(anyhow!("Initrd does not match the launch description.")),
+                };
+                Some(SevHashes::new(
+                    &kernel,
+                    initrd.as_deref(),
+                    launch.cmdline.as_deref(),
+                ))
+            }
+            (None, None) if launch.initrd.is_none() && launch.cmdline.is_none() => None,
+            _ => return Err(anyhow!("Kernel does not match the launch description.")),
+        };
+
+        let measurement = launch_digest(
+            &ovmf,
+            hashes.as_ref(),
+            provenance.vcpus,
+            vcpu_sig,
+            provenance.guest_features,
+        )?;
+
+        let rv = ReferenceValue::new()
+            .set_name(&launch.name)
+            .set_version(REFERENCE_VALUE_VERSION)
+            .set_expired(unknown_expiry())
+            .add_hash_value("sha384".to_string(), hex(&measurement))
+            .add_metadata("vcpus", &provenance.vcpus.to_string())
+            .add_metadata("vcpu_type", &provenance.vcpu_type);
+        Ok(vec![rv])
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use serde_json::{json, Value};
+
+    use crate::{
+        dsse::{test::generate_ed25519_private_key_pem, Signer, TrustPolicy},
+        extractors::extractor_modules::Extractor,
+    };
+
+    use super::{Policy, SevSnpExtractor, SNP_LAUNCH_PAYLOAD_TYPE};
+
+    /// Test vectors generated by `<git-repo>/tests/sev-snp/generate.py`
+    const TEST_VECTORS_DIR: &str = "../tests/sev-snp";
+
+    fn read(name: &str) -> Vec<u8> {
+        fs::read(format!("{}/{}", TEST_VECTORS_DIR, name)).unwrap()
+    }
+
+    fn measurements() -> Value {
+        serde_json::from_slice(&read("measurements.json")).unwrap()
+    }
+
+    /// Sign the launch description with a new key, and get an
+    /// Extractor trusting the key.
+    fn sign(launch: &Value, artifact_dir: bool) -> (Value, SevSnpExtractor) {
+        let dir = tempfile::tempdir().unwrap();
+        let pem = generate_ed25519_private_key_pem();
+        let signer = Signer::from_pem(&pem).unwrap();
+        let key_path = dir.path().join("key.pub");
+        fs::write(&key_path, signer.public_key_pem()).unwrap();
+
+        let envelope = signer.sign(SNP_LAUNCH_PAYLOAD_TYPE, launch.to_string().as_bytes());
+        let extractor = SevSnpExtractor::with_policy(Policy {
+            trust: TrustPolicy::new(vec![key_path]),
+            artifact_dir: artifact_dir.then(|| TEST_VECTORS_DIR.into()),
+        })
+        .unwrap();
+        (serde_json::to_value(envelope).unwrap(), extractor)
+    }
+
+    fn content(name: &str) -> Value {
+        json!({ "content": base64::encode(read(name)) })
+    }
+
+    #[test]
+    fn sev_snp_extractor() {
+        let m = measurements();
+        let launch = json!({
+            "name": "guest",
+            "ovmf": format!("sha384:{}", m["ovmf"]["sha384"].as_str().unwrap()),
+            "kernel": format!("sha256:{}", m["kernel"]["sha256"].as_str().unwrap()),
+            "initrd": format!("sha256:{}", m["kernel"]["initrd-sha256"].as_str().unwrap()),
+            "cmdline": m["kernel"]["cmdline"],
+        });
+        let (envelope, e) = sign(&launch, true);
+
+        for (vcpus, vcpu_type, expected) in [
+            (4, "EPYC-Milan", "EPYC-Milan-4"),
+            (2, "EPYC-v4", "EPYC-v4-2"),
+        ] {
+            let provenance = json!({
+                "envelope": envelope,
+                "ovmf": { "path": "ovmf.fd" },
+                "kernel": content("vmlinuz"),
+                "initrd": { "path": "initrd" },
+                "vcpus": vcpus,
+                "vcpu_type": vcpu_type,
+            });
+            let rvs = e.verify_and_extract(&provenance.to_string()).unwrap();
+            assert_eq!(rvs.len(), 1);
+            assert_eq!(rvs[0].name(), "guest");
+            assert_eq!(rvs[0].hash_values()[0].alg(), "sha384");
+            assert_eq!(rvs[0].hash_values()[0].value(), &m["kernel"][expected]);
+            assert_eq!(rvs[0].metadata()["vcpus"], vcpus.to_string());
+        }
+
+        // Without kernel
+        let launch = json!({
+            "name": "guest",
+            "ovmf": format!("sha384:{}", m["ovmf"]["sha384"].as_str().unwrap()),
+        });
+        let (envelope, e) = sign(&launch, false);
+        let provenance = json!({
+            "envelope": envelope,
+            "ovmf": content("ovmf.fd"),
+            "vcpus": 1,
+            "vcpu_type": "EPYC-Milan",
+        });
+        let rvs = e.verify_and_extract(&provenance.to_string()).unwrap();
+        assert_eq!(rvs[0].hash_values()[0].value(), &m["ovmf"]["EPYC-Milan-1"]);
+    }
+
+    #[test]
+    fn sev_snp_extractor_rejects() {
+        let m = measurements();
+        let launch = json!({
+            "name": "guest",
+            "ovmf": format!("sha384:{}", m["ovmf"]["sha384"].as_str().unwrap()),
+            "kernel": format!("sha256:{}", m["kernel"]["sha256"].as_str().unwrap()),
+        });
+        let (envelope, e) = sign(&launch, false);
+        let provenance = json!({
+            "envelope": envelope,
+            "ovmf": content("ovmf.fd"),
+            "kernel": content("vmlinuz"),
+            "vcpus": 1,
+            "vcpu_type": "EPYC-Milan",
+        });
+        assert!(e.verify_and_extract(&provenance.to_string()).is_ok());
+
+        let cases = [
+            // Component not in the launch description
+            ("initrd", content("initrd")),
+            // Component not matching the launch description
+            ("kernel", content("initrd")),
+            // Paths are not allowed without artifact_dir
+            ("ovmf", json!({ "path": "ovmf.fd" })),
+            ("vcpus", json!(0)),
+            ("vcpu_type", json!("Skylake")),
+        ];
+        for (field, value) in cases {
+            let mut p = provenance.clone();
+            p[field] = value;
+            assert!(e.verify_and_extract(&p.to_string()).is_err());
+        }
+
+        // Path out of artifact_dir
+        let (envelope, e) = sign(&launch, true);
+        let mut p = provenance.clone();
+        p["envelope"] = envelope;
+        p["ovmf"] = json!({ "path": "../sev-snp/ovmf.fd" });
+        assert!(e.verify_and_extract(&p.to_string()).is_err());
+
+        // Untrusted key
+        let mut p = provenance;
+        let (envelope, _) = sign(&launch, false);
+        p["envelope"] = envelope;
+        assert!(e.verify_and_extract(&p.to_string()).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Parser of the OVMF firmware, for the GUIDed footer table and the
+//! SEV metadata which tell how the firmware is launched.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::extractors::extractor_modules::firmware::{guid_bytes, parse_footer_table, read_u32};
+
+use super::measurement::PAGE_SIZE;
+
+/// The firmware is mapped right below 4 GiB
+const FOUR_GB: u64 = 0x1_0000_0000;
+
+const SEV_HASH_TABLE_RV_GUID: &str = "7255371f-3a3b-4b04-927b-1da6efa8d454";
+const SEV_ES_RESET_BLOCK_GUID: &str = "00f771de-1a7e-4fcb-890e-68c77e2fb44e";
+const OVMF_SEV_METADATA_GUID: &str = "dc886566-984a-4798-a75e-5585a7bf67cc";
+
+/// Signature of the SEV metadata
+const SEV_METADATA_SIGNATURE: &[u8] = b"ASEV";
+
+/// Size of the SEV metadata header, s.t. the signature, size, version
+/// and number of sections.
+const SEV_METADATA_HEADER_SIZE: usize = 16;
+
+/// Size of a SEV metadata section, s.t. GPA, size and type.
+const SEV_METADATA_SECTION_SIZE: usize = 12;
+
+/// Type of a section in the SEV metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionType {
+    SnpSecMemory,
+    SnpSecrets,
+    Cpuid,
+    SvsmCaa,
+    SnpKernelHashes,
+}
+
+impl SectionType {
+    fn from_u32(value: u32) -> Result<Self> {
+        match value {
+            1 => Ok(SectionType::SnpSecMemory),
+            2 => Ok(SectionType::SnpSecrets),
+            3 => Ok(SectionType::Cpuid),
+            4 => Ok(SectionType::SvsmCaa),
+            0x10 => Ok(SectionType::SnpKernelHashes),
+            _ => Err(anyhow!("Unknown SEV metadata section type: {:#x}", value)),
+        }
+    }
+}
+
+/// A section in the SEV metadata, s.t. memory to be prepared by the
+/// VMM before launch.
+#[derive(Debug)]
+pub struct MetadataSection {
+    pub gpa: u32,
+    pub size: u32,
+    pub section_type: SectionType,
+}
+
+/// OVMF firmware.
+pub struct Ovmf {
+    data: Vec<u8>,
+    table: HashMap<[u8; 16], Vec<u8>>,
+    metadata_sections: Vec<MetadataSection>,
+}
+
+impl Ovmf {
+    /// Parse the firmware.
+    pub fn new(data: Vec<u8>) -> Result<Self> {
+        if data.is_empty() || data.len() % PAGE_SIZE != 0 || data.len() as u64 > FOUR_GB {
+            return Err(anyhow!("Invalid OVMF size: {:#x}", data.len()));
+        }
+
+        let mut ovmf = Self {
+            table: parse_footer_table(&data)?,
+            data,
+            metadata_sections: Vec::new(),
+        };
+        ovmf.parse_sev_metadata()?;
+        Ok(ovmf)
+    }
+
+    fn parse_sev_metadata(&mut self) -> Result<()> {
+        let entry = match self.table.get(&guid_bytes(OVMF_SEV_METADATA_GUID)?) {
+            Some(entry) => entry,
+            None => return Ok(()),
+        };
+        let offset_from_end = read_u32(entry, 0)? as usize;
+        let start = self
+            .data
+            .len()
+            .checked_sub(offset_from_end)
+            .ok_or_else(|| anyhow!("Invalid SEV metadata offset."))?;
+
+        let header = self
+            .data
+            .get(start..start + SEV_METADATA_HEADER_SIZE)
+            .ok_or_else(|| anyhow!("SEV metadata is truncated."))?;
+        if &header[..4] != SEV_METADATA_SIGNATURE {
+            return Err(anyhow!("Invalid SEV metadata signature."));
+        }
+        let size = read_u32(header, 4)? as usize;
+        let num_sections = read_u32(header, 12)? as usize;
+        if size < SEV_METADATA_HEADER_SIZE + num_sections * SEV_METADATA_SECTION_SIZE {
+            return Err(anyhow!("Invalid SEV metadata size."));
+        }
+
+        for i in 0..num_sections {
+            let offset = start + SEV_METADATA_HEADER_SIZE + i * SEV_METADATA_SECTION_SIZE;
+            self.metadata_sections.push(MetadataSection {
+                gpa: read_u32(&self.data, offset)?,
+                size: read_u32(&self.data, offset + 4)?,
+                section_type: SectionType::from_u32(read_u32(&self.data, offset + 8)?)?,
+            });
+        }
+        Ok(())
+    }
+
+    fn table_u32(&self, guid: &str) -> Result<u32> {
+        let entry = self
+            .table
+            .get(&guid_bytes(guid)?)
+            .ok_or_else(|| anyhow!("OVMF footer table entry {} not found.", guid))?;
+        read_u32(entry, 0)
+    }
+
+    /// Content of the firmware.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Guest physical address of the firmware.
+    pub fn gpa(&self) -> u64 {
+        FOUR_GB - self.data.len() as u64
+    }
+
+    /// Reset EIP of the application processors.
+    pub fn sev_es_reset_eip(&self) -> Result<u32> {
+        self.table_u32(SEV_ES_RESET_BLOCK_GUID)
+    }
+
+    /// Guest physical address of the SEV hashes table, where the
+    /// hashes of the kernel, initrd and cmdline are put.
+    pub fn sev_hashes_table_gpa(&self) -> Result<u32> {
+        self.table_u32(SEV_HASH_TABLE_RV_GUID)
+    }
+
+    /// Sections in the SEV metadata.
+    pub fn metadata_sections(&self) -> &[MetadataSection] {
+        &self.metadata_sections
+    }
+}
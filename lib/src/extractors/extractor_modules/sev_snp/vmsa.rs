@@ -0,0 +1,105 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Initial VM Save Area (VMSA) of the vCPUs, as set by QEMU and KVM.
+//! Offsets are of the SEV-ES save area in the AMD64 APM Vol. 2.
+
+use anyhow::{anyhow, Result};
+
+use super::measurement::PAGE_SIZE;
+
+/// Reset EIP of the bootstrap processor
+pub const BSP_EIP: u32 = 0xffff_fff0;
+
+/// CPU signatures of the QEMU vCPU types, by family, model and
+/// stepping.
+const VCPU_TYPES: [(&str, u32, u32, u32); 9] = [
+    ("EPYC", 23, 1, 2),
+    ("EPYC-v1", 23, 1, 2),
+    ("EPYC-v2", 23, 1, 2),
+    ("EPYC-IBPB", 23, 1, 2),
+    ("EPYC-v3", 23, 1, 2),
+    ("EPYC-v4", 23, 1, 2),
+    ("EPYC-Rome", 23, 49, 0),
+    ("EPYC-Milan", 25, 1, 1),
+    ("EPYC-Genoa", 25, 17, 0),
+];
+
+/// Get the CPU signature, s.t. EAX of CPUID leaf 1, of a vCPU type.
+pub fn vcpu_signature(vcpu_type: &str) -> Result<u32> {
+    let (_, family, model, stepping) = VCPU_TYPES
+        .iter()
+        .find(|(name, ..)| *name == vcpu_type)
+        .ok_or_else(|| anyhow!("Unsupported vCPU type: {}", vcpu_type))?;
+
+    let (family_low, family_high) = match *family > 0xf {
+        true => (0xf, (family - 0xf) & 0xff),
+        false => (*family, 0),
+    };
+    Ok((family_high << 20)
+        | (((model >> 4) & 0xf) << 16)
+        | (family_low << 8)
+        | ((model & 0xf) << 4)
+        | (stepping & 0xf))
+}
+
+fn put(page: &mut [u8], offset: usize, value: &[u8]) {
+    page[offset..offset + value.len()].copy_from_slice(value);
+}
+
+/// Put a segment register, s.t. selector, attributes, limit and base.
+fn put_segment(page: &mut [u8], offset: usize, selector: u16, attrib: u16, base: u64) {
+    put(page, offset, &selector.to_le_bytes());
+    put(page, offset + 2, &attrib.to_le_bytes());
+    put(page, offset + 4, &0xffffu32.to_le_bytes());
+    put(page, offset + 8, &base.to_le_bytes());
+}
+
+/// Build the VMSA page of a vCPU starting at `eip`.
+pub fn save_area(eip: u32, sev_features: u64, vcpu_sig: u32) -> Vec<u8> {
+    let mut page = vec![0u8; PAGE_SIZE];
+
+    put_segment(&mut page, 0x00, 0, 0x93, 0); // es
+    put_segment(&mut page, 0x10, 0xf000, 0x9b, (eip & 0xffff_0000) as u64); // cs
+    put_segment(&mut page, 0x20, 0, 0x93, 0); // ss
+    put_segment(&mut page, 0x30, 0, 0x93, 0); // ds
+    put_segment(&mut page, 0x40, 0, 0x93, 0); // fs
+    put_segment(&mut page, 0x50, 0, 0x93, 0); // gs
+    put_segment(&mut page, 0x60, 0, 0, 0); // gdtr
+    put_segment(&mut page, 0x70, 0, 0x82, 0); // ldtr
+    put_segment(&mut page, 0x80, 0, 0, 0); // idtr
+    put_segment(&mut page, 0x90, 0, 0x8b, 0); // tr
+
+    // KVM enables EFER.SVME and CR4.MCE
+    put(&mut page, 0xd0, &0x1000u64.to_le_bytes()); // efer
+    put(&mut page, 0x148, &0x40u64.to_le_bytes()); // cr4
+    put(&mut page, 0x158, &0x10u64.to_le_bytes()); // cr0
+    put(&mut page, 0x160, &0x400u64.to_le_bytes()); // dr7
+    put(&mut page, 0x168, &0xffff_0ff0u64.to_le_bytes()); // dr6
+    put(&mut page, 0x170, &0x2u64.to_le_bytes()); // rflags
+    put(&mut page, 0x178, &((eip & 0xffff) as u64).to_le_bytes()); // rip
+    put(&mut page, 0x268, &0x0007_0406_0007_0406u64.to_le_bytes()); // g_pat
+    put(&mut page, 0x310, &(vcpu_sig as u64).to_le_bytes()); // rdx
+    put(&mut page, 0x3b0, &sev_features.to_le_bytes()); // sev_features
+    put(&mut page, 0x3e8, &0x1u64.to_le_bytes()); // xcr0
+    put(&mut page, 0x408, &0x1f80u32.to_le_bytes()); // mxcsr
+    put(&mut page, 0x410, &0x37fu16.to_le_bytes()); // x87_fcw
+
+    page
+}
+
+#[cfg(test)]
+mod test {
+    use super::vcpu_signature;
+
+    #[test]
+    fn sev_snp_vcpu_signature() {
+        assert_eq!(vcpu_signature("EPYC-v4").unwrap(), 0x800f12);
+        assert_eq!(vcpu_signature("EPYC-Rome").unwrap(), 0x830f10);
+        assert_eq!(vcpu_signature("EPYC-Milan").unwrap(), 0xa00f11);
+        assert_eq!(vcpu_signature("EPYC-Genoa").unwrap(), 0xa10f10);
+        assert!(vcpu_signature("Skylake").is_err());
+    }
+}
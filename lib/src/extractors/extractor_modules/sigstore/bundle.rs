@@ -0,0 +1,145 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Sigstore bundle, in the JSON encoding of the `Bundle` protobuf
+//! message of sigstore/protobuf-specs. Only the fields needed by
+//! offline verification are included.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Media type of the supported Sigstore bundle
+pub const BUNDLE_MEDIA_TYPE: &str = "application/vnd.dev.sigstore.bundle+json;version=0.1";
+
+/// 64-bit integers are encoded as strings in the JSON encoding of
+/// protobuf, but plain numbers are also accepted.
+fn int64<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<i64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Int64 {
+        String(String),
+        Number(i64),
+    }
+
+    match Int64::deserialize(deserializer)? {
+        Int64::String(s) => s.parse().map_err(serde::de::Error::custom),
+        Int64::Number(n) => Ok(n),
+    }
+}
+
+/// Decode a base64 encoded field of the bundle.
+pub fn decode(field: &str, value: &str) -> Result<Vec<u8>> {
+    base64::decode(value).map_err(|e| anyhow!("Decode {} failed: {}", field, e))
+}
+
+/// Sigstore bundle.
+/// * `media_type`: media type of the bundle.
+/// * `verification_material`: certificates and transparency log entries.
+/// * `message_signature`: signature over the artifact.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Bundle {
+    pub media_type: String,
+    pub verification_material: VerificationMaterial,
+    pub message_signature: Option<MessageSignature>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationMaterial {
+    pub x509_certificate_chain: Option<X509CertificateChain>,
+    #[serde(default)]
+    pub tlog_entries: Vec<TransparencyLogEntry>,
+}
+
+/// Certificate chain, with the signing certificate first.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct X509CertificateChain {
+    pub certificates: Vec<X509Certificate>,
+}
+
+/// A DER encoded certificate, base64 encoded.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct X509Certificate {
+    pub raw_bytes: String,
+}
+
+/// Entry of the artifact in Rekor.
+/// * `log_index`: index of the entry in the log.
+/// * `log_id`: sha256 of the Rekor public key.
+/// * `integrated_time`: when the entry is added to the log, in
+/// seconds since Unix epoch.
+/// * `inclusion_promise`: the Signed Entry Timestamp (SET).
+/// * `inclusion_proof`: Merkle inclusion proof of the entry.
+/// * `canonicalized_body`: the entry itself.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TransparencyLogEntry {
+    #[serde(deserialize_with = "int64")]
+    pub log_index: i64,
+    pub log_id: LogId,
+    pub kind_version: KindVersion,
+    #[serde(deserialize_with = "int64")]
+    pub integrated_time: i64,
+    pub inclusion_promise: Option<InclusionPromise>,
+    pub inclusion_proof: Option<InclusionProof>,
+    pub canonicalized_body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LogId {
+    pub key_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct KindVersion {
+    pub kind: String,
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InclusionPromise {
+    pub signed_entry_timestamp: String,
+}
+
+/// Merkle inclusion proof, see RFC 6962.
+/// * `checkpoint`: signed tree head, which authenticates `root_hash`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InclusionProof {
+    #[serde(deserialize_with = "int64")]
+    pub log_index: i64,
+    pub root_hash: String,
+    #[serde(deserialize_with = "int64")]
+    pub tree_size: i64,
+    pub hashes: Vec<String>,
+    pub checkpoint: Option<Checkpoint>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Checkpoint {
+    pub envelope: String,
+}
+
+/// Signature over the artifact.
+/// * `message_digest`: digest of the artifact.
+/// * `signature`: signature over the artifact, base64 encoded.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSignature {
+    pub message_digest: MessageDigest,
+    pub signature: String,
+}
+
+/// * `algorithm`: e.g. `SHA2_256`.
+/// * `digest`: base64 encoded digest.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MessageDigest {
+    pub algorithm: String,
+    pub digest: String,
+}
@@ -0,0 +1,132 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Offline verification of Fulcio signing certificates.
+
+use anyhow::{anyhow, Result};
+use x509_parser::{
+    certificate::X509Certificate, der_parser::der::parse_der_utf8string, extensions::GeneralName,
+    prelude::FromDer, time::ASN1Time,
+};
+
+/// OIDC issuer extension of Fulcio, raw string
+const OIDC_ISSUER_V1_OID: &str = "1.3.6.1.4.1.57264.1.1";
+
+/// OIDC issuer extension of Fulcio, DER encoded UTF8String
+const OIDC_ISSUER_V2_OID: &str = "1.3.6.1.4.1.57264.1.8";
+
+/// Max length of a certificate chain
+const MAX_CHAIN_LENGTH: usize = 8;
+
+/// The verified signing certificate.
+/// * `identity`: the email or URI in the Subject Alternative Name.
+/// * `issuer`: the OIDC issuer which authenticated the identity.
+/// * `public_key`: SEC1 encoded public key of the certificate.
+pub struct SigningCertificate {
+    pub identity: String,
+    pub issuer: String,
+    pub public_key: Vec<u8>,
+}
+
+/// Parse a DER encoded certificate.
+pub fn parse(der: &[u8]) -> Result<X509Certificate<'_>> {
+    let (_, certificate) =
+        X509Certificate::from_der(der).map_err(|e| anyhow!("Parse certificate failed: {}", e))?;
+    Ok(certificate)
+}
+
+/// Whether `issuer` issued `certificate`, and both are valid at `time`.
+fn issued_by(certificate: &X509Certificate, issuer: &X509Certificate, time: ASN1Time) -> bool {
+    certificate.issuer().as_raw() == issuer.subject().as_raw()
+        && issuer.validity().is_valid_at(time)
+        && certificate
+            .verify_signature(Some(issuer.public_key()))
+            .is_ok()
+}
+
+/// Get the OIDC issuer from the Fulcio extensions.
+fn oidc_issuer(certificate: &X509Certificate) -> Result<String> {
+    for extension in certificate.extensions() {
+        match extension.oid.to_id_string().as_str() {
+            OIDC_ISSUER_V2_OID => {
+                let (_, value) = parse_der_utf8string(extension.value)
+                    .map_err(|e| anyhow!("Parse OIDC issuer failed: {}", e))?;
+                return Ok(value.as_str()?.to_string());
+            }
+            OIDC_ISSUER_V1_OID => {
+                return Ok(String::from_utf8(extension.value.to_vec())?);
+            }
+            _ => {}
+        }
+    }
+    Err(anyhow!("OIDC issuer not found in the signing certificate."))
+}
+
+/// Get the identity from the Subject Alternative Name.
+fn identity(certificate: &X509Certificate) -> Result<String> {
+    let san = certificate
+        .subject_alternative_name()?
+        .ok_or_else(|| anyhow!("SAN not found in the signing certificate."))?;
+    san.value
+        .general_names
+        .iter()
+        .find_map(|name| match name {
+            GeneralName::RFC822Name(email) => Some(email.to_string()),
+            GeneralName::URI(uri) => Some(uri.to_string()),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("No email or URI found in the SAN."))
+}
+
+/// Verify the certificate chain, whose first certificate is the
+/// signing certificate, up to one of the trusted Fulcio roots at
+/// the given `time` (seconds since Unix epoch). Fulcio certificates
+/// are short-lived, so `time` should be the trusted integrated time
+/// of the transparency log entry, rather than now.
+pub fn verify_chain(chain: &[Vec<u8>], roots: &[Vec<u8>], time: i64) -> Result<SigningCertificate> {
+    let time = ASN1Time::from_timestamp(time)?;
+    let chain = chain
+        .iter()
+        .map(|der| parse(der))
+        .collect::<Result<Vec<_>>>()?;
+    let roots = roots
+        .iter()
+        .map(|der| parse(der))
+        .collect::<Result<Vec<_>>>()?;
+
+    let leaf = chain
+        .first()
+        .ok_or_else(|| anyhow!("Signing certificate not found."))?;
+    if !leaf.validity().is_valid_at(time) {
+        return Err(anyhow!(
+            "Signing certificate is not valid at the integrated time."
+        ));
+    }
+    let code_signing = leaf
+        .extended_key_usage()?
+        .map(|eku| eku.value.code_signing)
+        .unwrap_or(false);
+    if !code_signing {
+        return Err(anyhow!("Signing certificate is not for code signing."));
+    }
+
+    let mut current = leaf;
+    for _ in 0..MAX_CHAIN_LENGTH {
+        if roots.iter().any(|root| issued_by(current, root, time)) {
+            return Ok(SigningCertificate {
+                identity: identity(leaf)?,
+                issuer: oidc_issuer(leaf)?,
+                public_key: leaf.public_key().subject_public_key.data.to_vec(),
+            });
+        }
+
+        current = chain[1..]
+            .iter()
+            .find(|ca| ca.is_ca() && issued_by(current, ca, time))
+            .ok_or_else(|| anyhow!("Certificate chain is not issued by a trusted Fulcio root."))?;
+    }
+
+    Err(anyhow!("Certificate chain is too long."))
+}
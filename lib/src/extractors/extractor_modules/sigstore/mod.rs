@@ -0,0 +1,322 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! # Sigstore Extractor
+//!
+//! This Extractor helps to verify a Sigstore bundle of an artifact
+//! signed by cosign fully offline, s.t. against a configured Fulcio
+//! root and Rekor public key, and extract the signed artifact digest
+//! as reference value.
+
+pub mod bundle;
+pub mod certificate;
+pub mod rekor;
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use p256::ecdsa::{signature::hazmat::PrehashVerifier, Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dsse::Verifier,
+    extractors::extractor_modules::artifact::hex,
+    reference_value::{ReferenceValue, REFERENCE_VALUE_VERSION},
+};
+
+use self::bundle::{decode, Bundle, BUNDLE_MEDIA_TYPE};
+
+use super::{unknown_expiry, ConfigurableExtractor, Extractor};
+
+/// Digest algorithm of the message signature
+const SHA2_256: &str = "SHA2_256";
+
+/// Sigstore provenance. A bundle does not include the name of
+/// the artifact, so it is given together.
+/// * `artifact`: name of the artifact.
+/// * `bundle`: the Sigstore bundle of the artifact.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Provenance {
+    pub artifact: String,
+    pub bundle: Bundle,
+}
+
+/// An identity allowed to sign artifacts.
+/// * `subject`: the email or URI in the signing certificate.
+/// * `issuer`: the OIDC issuer which authenticated the subject.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Identity {
+    pub subject: String,
+    pub issuer: String,
+}
+
+/// Policy of the Sigstore Extractor, given by its section of
+/// the config file.
+/// * `fulcio_roots`: paths of the PEM encoded trusted Fulcio
+/// certificates. A file may include several certificates.
+/// * `rekor_keys`: paths of the PEM encoded trusted Rekor public keys.
+/// * `identities`: identities allowed to sign artifacts.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Policy {
+    pub fulcio_roots: Vec<PathBuf>,
+    pub rekor_keys: Vec<PathBuf>,
+    pub identities: Vec<Identity>,
+}
+
+pub struct SigstoreExtractor {
+    fulcio_roots: Vec<Vec<u8>>,
+    rekor_keys: Vec<Verifier>,
+    identities: Vec<Identity>,
+}
+
+impl SigstoreExtractor {
+    /// Create a Sigstore Extractor with the given policy.
+    pub fn with_policy(policy: Policy) -> Result<Self> {
+        let mut fulcio_roots = Vec::new();
+        for path in &policy.fulcio_roots {
+            let pems = pem::parse_many(fs::read(path)?)?;
+            for pem in pems.into_iter().filter(|p| p.tag == "CERTIFICATE") {
+                certificate::parse(&pem.contents)?;
+                fulcio_roots.push(pem.contents);
+            }
+        }
+
+        let rekor_keys = policy
+            .rekor_keys
+            .iter()
+            .map(Verifier::from_pem_file)
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            fulcio_roots,
+            rekor_keys,
+            identities: policy.identities,
+        })
+    }
+}
+
+impl ConfigurableExtractor for SigstoreExtractor {
+    type Config = Policy;
+
+    fn with_config(config: Policy) -> Result<Self> {
+        Self::with_policy(config)
+    }
+}
+
+impl Extractor for SigstoreExtractor {
+    /// Sigstore's Extractor.
+    /// The provenance is a Sigstore bundle together with the name of
+    /// the artifact. The verification includes
+    /// * the Signed Entry Timestamp and the inclusion proof of the
+    /// Rekor entry, which also authenticate the integrated time.
+    /// * the Rekor entry records the signature and the certificate.
+    /// * the certificate chain up to a Fulcio root at the integrated time.
+    /// * the identity and the issuer in the certificate.
+    /// * the signature over the artifact digest.
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+        let provenance: Provenance = serde_json::from_str(provenance)?;
+        let bundle = provenance.bundle;
+        if bundle.media_type != BUNDLE_MEDIA_TYPE {
+            return Err(anyhow!("Unsupported bundle: {}", bundle.media_type));
+        }
+
+        let message_signature = bundle
+            .message_signature
+            .ok_or_else(|| anyhow!("Message signature not found in the bundle."))?;
+        if message_signature.message_digest.algorithm != SHA2_256 {
+            return Err(anyhow!(
+                "Unsupported digest algorithm: {}",
+                message_signature.message_digest.algorithm
+            ));
+        }
+        let digest = decode("digest", &message_signature.message_digest.digest)?;
+        let signature = decode("signature", &message_signature.signature)?;
+
+        let chain = bundle
+            .verification_material
+            .x509_certificate_chain
+            .ok_or_else(|| anyhow!("Certificate chain not found in the bundle."))?
+            .certificates
+            .iter()
+            .map(|c| decode("certificate", &c.raw_bytes))
+            .collect::<Result<Vec<_>>>()?;
+        let leaf = chain
+            .first()
+            .ok_or_else(|| anyhow!("Signing certificate not found."))?;
+
+        // Transparency log
+        let entry = bundle
+            .verification_material
+            .tlog_entries
+            .first()
+            .ok_or_else(|| anyhow!("Transparency log entry not found in the bundle."))?;
+        rekor::verify_entry(entry, &self.rekor_keys)?;
+        rekor::check_body(entry, &digest, &signature, leaf)?;
+
+        // Signing certificate and identity
+        let signing_certificate =
+            certificate::verify_chain(&chain, &self.fulcio_roots, entry.integrated_time)?;
+        let identity = Identity {
+            subject: signing_certificate.identity,
+            issuer: signing_certificate.issuer,
+        };
+        if !self.identities.contains(&identity) {
+            return Err(anyhow!(
+                "Identity {} from {} is not trusted.",
+                identity.subject,
+                identity.issuer
+            ));
+        }
+
+        // Signature over the artifact
+        let public_key = VerifyingKey::from_sec1_bytes(&signing_certificate.public_key)
+            .map_err(|_| anyhow!("Only ECDSA P-256 signing keys are supported."))?;
+        let signature = Signature::from_der(&signature)
+            .map_err(|_| anyhow!("Malformed signature of the artifact."))?;
+        public_key
+            .verify_prehash(&digest, &signature)
+            .map_err(|_| anyhow!("Invalid signature of the artifact."))?;
+
+        // The short-lived signing certificate expires minutes after the
+        // signing, which is not an expiry of the artifact, and the bundle
+        // tells no other
+        let rv = ReferenceValue::new()
+            .set_name(&provenance.artifact)
+            .set_version(REFERENCE_VALUE_VERSION)
+            .set_expired(unknown_expiry())
+            .add_hash_value("sha256".into(), hex(&digest));
+        Ok(vec![rv])
+    }
+
+    /// The media type of the bundle, carrying its version.
+    fn payload_versions(&self) -> Vec<String> {
+        vec![BUNDLE_MEDIA_TYPE.to_string()]
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use ring::digest::{digest, SHA256};
+    use serde_json::Value;
+
+    use crate::extractors::extractor_modules::Extractor;
+
+    use super::{Identity, Policy, SigstoreExtractor};
+
+    /// Test vectors generated by `<git-repo>/tests/sigstore/generate.py`
+    const TEST_VECTORS_DIR: &str = "../tests/sigstore";
+
+    fn policy() -> Policy {
+        Policy {
+            fulcio_roots: vec![format!("{}/fulcio_root.pem", TEST_VECTORS_DIR).into()],
+            rekor_keys: vec![format!("{}/rekor.pub", TEST_VECTORS_DIR).into()],
+            identities: vec![Identity {
+                subject: "alice@example.com".into(),
+                issuer: "https://accounts.example.com".into(),
+            }],
+        }
+    }
+
+    fn provenance() -> Value {
+        let content = fs::read_to_string(format!("{}/provenance.json", TEST_VECTORS_DIR)).unwrap();
+        serde_json::from_str(&content).unwrap()
+    }
+
+    #[test]
+    fn sigstore_extractor() {
+        let e = SigstoreExtractor::with_policy(policy()).unwrap();
+        let rvs = e.verify_and_extract(&provenance().to_string()).unwrap();
+
+        let artifact = fs::read(format!("{}/artifact.txt", TEST_VECTORS_DIR)).unwrap();
+        let sha256: String = digest(&SHA256, &artifact)
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        assert_eq!(rvs.len(), 1);
+        assert_eq!(rvs[0].name(), "artifact.txt");
+        assert_eq!(rvs[0].hash_values()[0].alg(), "sha256");
+        assert_eq!(rvs[0].hash_values()[0].value(), &sha256);
+    }
+
+    #[test]
+    fn sigstore_extractor_rejects_policy() {
+        let provenance = provenance().to_string();
+
+        // Untrusted identity
+        let mut p = policy();
+        p.identities[0].subject = "bob@example.com".into();
+        let e = SigstoreExtractor::with_policy(p).unwrap();
+        assert!(e.verify_and_extract(&provenance).is_err());
+
+        // Untrusted issuer
+        let mut p = policy();
+        p.identities[0].issuer = "https://accounts.google.com".into();
+        let e = SigstoreExtractor::with_policy(p).unwrap();
+        assert!(e.verify_and_extract(&provenance).is_err());
+
+        // No trusted Fulcio root
+        let mut p = policy();
+        p.fulcio_roots.clear();
+        let e = SigstoreExtractor::with_policy(p).unwrap();
+        assert!(e.verify_and_extract(&provenance).is_err());
+
+        // Untrusted Rekor
+        let mut p = policy();
+        p.rekor_keys = vec!["../tests/dsse/ecdsa-p256.pub".into()];
+        let e = SigstoreExtractor::with_policy(p).unwrap();
+        assert!(e.verify_and_extract(&provenance).is_err());
+    }
+
+    #[test]
+    fn sigstore_extractor_rejects_tampered_bundle() {
+        let e = SigstoreExtractor::with_policy(policy()).unwrap();
+        let tampered = |f: &dyn Fn(&mut Value)| {
+            let mut p = provenance();
+            f(&mut p["bundle"]);
+            e.verify_and_extract(&p.to_string())
+        };
+
+        // Another artifact
+        assert!(tampered(&|b| {
+            b["messageSignature"]["messageDigest"]["digest"] =
+                base64::encode(digest(&SHA256, b"another artifact")).into()
+        })
+        .is_err());
+
+        // Integrated time out of validity of the certificate
+        assert!(tampered(&|b| {
+            b["verificationMaterial"]["tlogEntries"][0]["integratedTime"] = "1700000000".into()
+        })
+        .is_err());
+
+        // SET is a must
+        assert!(tampered(&|b| {
+            b["verificationMaterial"]["tlogEntries"][0]
+                .as_object_mut()
+                .unwrap()
+                .remove("inclusionPromise");
+        })
+        .is_err());
+
+        // Inclusion proof
+        assert!(tampered(&|b| {
+            b["verificationMaterial"]["tlogEntries"][0]["inclusionProof"]["hashes"][0] =
+                base64::encode([0u8; 32]).into()
+        })
+        .is_err());
+
+        // Only the signing certificate, without the intermediate
+        assert!(tampered(&|b| {
+            b["verificationMaterial"]["x509CertificateChain"]["certificates"]
+                .as_array_mut()
+                .unwrap()
+                .truncate(1);
+        })
+        .is_err());
+    }
+}
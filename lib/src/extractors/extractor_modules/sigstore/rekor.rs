@@ -0,0 +1,302 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Offline verification of Rekor transparency log entries, using
+//! the Signed Entry Timestamp (SET) and the Merkle inclusion proof.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use ring::digest::{digest, SHA256};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{dsse::Verifier, extractors::extractor_modules::artifact::hex};
+
+use super::bundle::{decode, InclusionProof, TransparencyLogEntry};
+
+/// Entry kind of an artifact signature
+const HASHED_REKORD_KIND: &str = "hashedrekord";
+
+/// `hashedrekord` entry in the log.
+#[derive(Deserialize)]
+struct HashedRekord {
+    kind: String,
+    spec: HashedRekordSpec,
+}
+
+#[derive(Deserialize)]
+struct HashedRekordSpec {
+    data: HashedRekordData,
+    signature: HashedRekordSignature,
+}
+
+#[derive(Deserialize)]
+struct HashedRekordData {
+    hash: HashedRekordHash,
+}
+
+#[derive(Deserialize)]
+struct HashedRekordHash {
+    algorithm: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct HashedRekordSignature {
+    content: String,
+    #[serde(rename = "publicKey")]
+    public_key: HashedRekordPublicKey,
+}
+
+#[derive(Deserialize)]
+struct HashedRekordPublicKey {
+    content: String,
+}
+
+/// Verify the entry against the trusted Rekor keys. The Signed Entry
+/// Timestamp is needed, as only it authenticates the integrated time.
+/// The inclusion proof is also verified if present. If verification
+/// succeeds, the integrated time of the entry is trusted.
+pub fn verify_entry(entry: &TransparencyLogEntry, rekor_keys: &[Verifier]) -> Result<()> {
+    let log_id = hex(&decode("log id", &entry.log_id.key_id)?);
+    let rekor_key = rekor_keys
+        .iter()
+        .find(|k| k.keyid() == log_id)
+        .ok_or_else(|| anyhow!("Rekor log {} is not trusted.", log_id))?;
+
+    let promise = entry
+        .inclusion_promise
+        .as_ref()
+        .ok_or_else(|| anyhow!("SET not found in the log entry."))?;
+    verify_signed_entry_timestamp(entry, &log_id, &promise.signed_entry_timestamp, rekor_key)?;
+
+    if let Some(proof) = &entry.inclusion_proof {
+        if proof.log_index != entry.log_index {
+            return Err(anyhow!("Inclusion proof does not match the log index."));
+        }
+        let body = decode("canonicalized body", &entry.canonicalized_body)?;
+        verify_inclusion_proof(&body, proof, rekor_key)?;
+    }
+
+    Ok(())
+}
+
+/// The SET is a signature over the canonical JSON of the entry.
+fn verify_signed_entry_timestamp(
+    entry: &TransparencyLogEntry,
+    log_id: &str,
+    signed_entry_timestamp: &str,
+    rekor_key: &Verifier,
+) -> Result<()> {
+    // Keys of a BTreeMap are serialized in order, as canonical JSON needs
+    let mut payload = BTreeMap::new();
+    payload.insert("body", json!(entry.canonicalized_body));
+    payload.insert("integratedTime", json!(entry.integrated_time));
+    payload.insert("logID", json!(log_id));
+    payload.insert("logIndex", json!(entry.log_index));
+    let payload = serde_json::to_vec(&payload)?;
+
+    let sig = decode("SET", signed_entry_timestamp)?;
+    rekor_key
+        .verify_signature(&payload, &sig)
+        .map_err(|_| anyhow!("Invalid SET of the log entry."))
+}
+
+fn leaf_hash(data: &[u8]) -> Vec<u8> {
+    let mut input = vec![0x00];
+    input.extend_from_slice(data);
+    digest(&SHA256, &input).as_ref().to_vec()
+}
+
+fn node_hash(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut input = vec![0x01];
+    input.extend_from_slice(left);
+    input.extend_from_slice(right);
+    digest(&SHA256, &input).as_ref().to_vec()
+}
+
+/// Calculate the root hash from the inclusion proof, as
+/// RFC 9162, 2.1.3.2 describes.
+fn root_from_inclusion_proof(
+    index: u64,
+    tree_size: u64,
+    leaf: Vec<u8>,
+    hashes: &[Vec<u8>],
+) -> Result<Vec<u8>> {
+    if index >= tree_size {
+        return Err(anyhow!(
+            "Log index {} out of tree size {}.",
+            index,
+            tree_size
+        ));
+    }
+
+    let (mut fn_, mut sn) = (index, tree_size - 1);
+    let mut r = leaf;
+    for p in hashes {
+        if sn == 0 {
+            return Err(anyhow!("Inclusion proof is too long."));
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            r = node_hash(p, &r);
+            while fn_ & 1 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            r = node_hash(&r, p);
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+
+    if sn != 0 {
+        return Err(anyhow!("Inclusion proof is too short."));
+    }
+    Ok(r)
+}
+
+/// Verify the inclusion proof of the entry, and the checkpoint
+/// which authenticates the root hash.
+fn verify_inclusion_proof(body: &[u8], proof: &InclusionProof, rekor_key: &Verifier) -> Result<()> {
+    let hashes = proof
+        .hashes
+        .iter()
+        .map(|h| decode("inclusion proof hash", h))
+        .collect::<Result<Vec<_>>>()?;
+    let root_hash = decode("root hash", &proof.root_hash)?;
+
+    let calculated = root_from_inclusion_proof(
+        proof.log_index.try_into()?,
+        proof.tree_size.try_into()?,
+        leaf_hash(body),
+        &hashes,
+    )?;
+    if calculated != root_hash {
+        return Err(anyhow!("Inclusion proof does not match the root hash."));
+    }
+
+    let checkpoint = proof
+        .checkpoint
+        .as_ref()
+        .ok_or_else(|| anyhow!("Checkpoint not found in the inclusion proof."))?;
+    verify_checkpoint(
+        &checkpoint.envelope,
+        proof.tree_size,
+        &proof.root_hash,
+        rekor_key,
+    )
+}
+
+/// A checkpoint is a signed note, s.t. the note text, an empty line,
+/// and signature lines `— <name> <base64(key hint || signature)>`.
+/// The first lines of the text are the origin, the tree size and the
+/// root hash.
+fn verify_checkpoint(
+    envelope: &str,
+    tree_size: i64,
+    root_hash: &str,
+    rekor_key: &Verifier,
+) -> Result<()> {
+    let split = envelope
+        .find("\n\n")
+        .ok_or_else(|| anyhow!("Malformed checkpoint."))?;
+    let (text, signatures) = envelope.split_at(split + 1);
+
+    let verified = signatures
+        .lines()
+        .filter_map(|line| line.strip_prefix("\u{2014} "))
+        .filter_map(|line| line.split(' ').nth(1))
+        .filter_map(|sig| base64::decode(sig).ok())
+        .filter(|sig| sig.len() > 4)
+        .any(|sig| {
+            rekor_key
+                .verify_signature(text.as_bytes(), &sig[4..])
+                .is_ok()
+        });
+    if !verified {
+        return Err(anyhow!("Invalid signature of the checkpoint."));
+    }
+
+    let mut lines = text.lines().skip(1);
+    if lines.next() != Some(tree_size.to_string().as_str()) || lines.next() != Some(root_hash) {
+        return Err(anyhow!("Checkpoint does not match the inclusion proof."));
+    }
+
+    Ok(())
+}
+
+/// Check that the `hashedrekord` entry records the given artifact
+/// digest, signature and signing certificate.
+pub fn check_body(
+    entry: &TransparencyLogEntry,
+    digest: &[u8],
+    signature: &[u8],
+    certificate_der: &[u8],
+) -> Result<()> {
+    if entry.kind_version.kind != HASHED_REKORD_KIND {
+        return Err(anyhow!(
+            "Unsupported log entry kind: {}",
+            entry.kind_version.kind
+        ));
+    }
+
+    let body = decode("canonicalized body", &entry.canonicalized_body)?;
+    let body: HashedRekord = serde_json::from_slice(&body)?;
+    if body.kind != HASHED_REKORD_KIND {
+        return Err(anyhow!("Unsupported log entry kind: {}", body.kind));
+    }
+
+    let hash = &body.spec.data.hash;
+    if hash.algorithm != "sha256" || hash.value != hex(digest) {
+        return Err(anyhow!("Log entry does not match the artifact digest."));
+    }
+
+    if decode("log entry signature", &body.spec.signature.content)? != signature {
+        return Err(anyhow!("Log entry does not match the signature."));
+    }
+
+    let public_key = decode(
+        "log entry public key",
+        &body.spec.signature.public_key.content,
+    )?;
+    let certificate = pem::parse(public_key)?;
+    if certificate.contents != certificate_der {
+        return Err(anyhow!("Log entry does not match the signing certificate."));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{leaf_hash, node_hash, root_from_inclusion_proof};
+
+    #[test]
+    fn sigstore_inclusion_proof() {
+        // A tree of 3 leaves: root = H(H(l0, l1), l2)
+        let leaves: Vec<_> = (0..3u8).map(|i| leaf_hash(&[i])).collect();
+        let left = node_hash(&leaves[0], &leaves[1]);
+        let root = node_hash(&left, &leaves[2]);
+
+        let proof = vec![leaves[0].clone(), leaves[2].clone()];
+        let calculated = root_from_inclusion_proof(1, 3, leaves[1].clone(), &proof).unwrap();
+        assert_eq!(calculated, root);
+
+        let proof = vec![left];
+        let calculated = root_from_inclusion_proof(2, 3, leaves[2].clone(), &proof).unwrap();
+        assert_eq!(calculated, root);
+
+        // Wrong index, wrong length of proof
+        let proof = vec![leaves[0].clone(), leaves[2].clone()];
+        assert_ne!(
+            root_from_inclusion_proof(0, 3, leaves[1].clone(), &proof).unwrap(),
+            root
+        );
+        assert!(root_from_inclusion_proof(1, 3, leaves[1].clone(), &proof[..1]).is_err());
+        assert!(root_from_inclusion_proof(3, 3, leaves[1].clone(), &proof).is_err());
+    }
+}
@@ -0,0 +1,400 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! # SLSA Extractor
+//!
+//! This Extractor helps to verify SLSA provenance, s.t. an in-toto
+//! attestation Statement wrapped in a DSSE envelope, and extract
+//! reference values from the subjects of the Statement.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    dsse::{Envelope, ThresholdVerifier, TrustPolicy},
+    reference_value::{ReferenceValue, REFERENCE_VALUE_VERSION},
+    report::VerificationReport,
+};
+
+use super::{unknown_expiry, ConfigurableExtractor, Extractor};
+
+/// Provenance type of SLSA provenance
+pub const SLSA_PROVENANCE_TYPE: &str = "slsa";
+
+/// Payload type of an in-toto attestation inside a DSSE envelope
+pub const INTOTO_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+/// Supported in-toto Statement types
+const STATEMENT_TYPES: [&str; 2] = [
+    "https://in-toto.io/Statement/v0.1",
+    "https://in-toto.io/Statement/v1",
+];
+
+/// SLSA provenance predicate of v0.2
+pub const SLSA_PROVENANCE_V02: &str = "https://slsa.dev/provenance/v0.2";
+
+/// SLSA provenance predicate of v1.0
+pub const SLSA_PROVENANCE_V1: &str = "https://slsa.dev/provenance/v1";
+
+/// Subject of an in-toto Statement.
+/// * `name`: name of the artifact.
+/// * `digest`: maps hash algorithm to the hex encoded digest.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Subject {
+    pub name: String,
+    pub digest: BTreeMap<String, String>,
+}
+
+/// in-toto attestation Statement.
+/// * `typ`: type of the Statement.
+/// * `subject`: artifacts this Statement is about.
+/// * `predicate_type`: type of the predicate, e.g. SLSA provenance.
+/// * `predicate`: the predicate itself.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Statement {
+    #[serde(rename = "_type")]
+    pub typ: String,
+    pub subject: Vec<Subject>,
+    #[serde(rename = "predicateType")]
+    pub predicate_type: String,
+    #[serde(default)]
+    pub predicate: Value,
+}
+
+impl Statement {
+    /// Get builder id of the SLSA provenance predicate.
+    /// * v0.2: `predicate.builder.id`
+    /// * v1.0: `predicate.runDetails.builder.id`
+    fn builder_id(&self) -> Option<&str> {
+        let builder = match self.predicate_type.as_str() {
+            SLSA_PROVENANCE_V02 => &self.predicate["builder"],
+            SLSA_PROVENANCE_V1 => &self.predicate["runDetails"]["builder"],
+            _ => return None,
+        };
+        builder["id"].as_str()
+    }
+}
+
+/// Policy of the SLSA Extractor, given by its section of
+/// the config file.
+/// * `trust`: keys trusted to sign the provenance, see `TrustPolicy`.
+/// * `predicate_types`: accepted predicate types. Both SLSA
+/// provenance v0.2 and v1.0 are accepted by default.
+/// * `builder_ids`: accepted builder ids. A provenance from any
+/// other builder is rejected.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Policy {
+    #[serde(flatten)]
+    pub trust: TrustPolicy,
+    #[serde(default = "default_predicate_types")]
+    pub predicate_types: Vec<String>,
+    pub builder_ids: Vec<String>,
+}
+
+/// Use to set default predicate types of Policy
+fn default_predicate_types() -> Vec<String> {
+    vec![SLSA_PROVENANCE_V02.into(), SLSA_PROVENANCE_V1.into()]
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            trust: TrustPolicy::default(),
+            predicate_types: default_predicate_types(),
+            builder_ids: Vec::new(),
+        }
+    }
+}
+
+pub struct SlsaExtractor {
+    verifier: ThresholdVerifier,
+    predicate_types: Vec<String>,
+    builder_ids: Vec<String>,
+}
+
+impl SlsaExtractor {
+    /// Create a SLSA Extractor with the given policy.
+    pub fn with_policy(policy: Policy) -> Result<Self> {
+        Ok(Self {
+            verifier: policy.trust.verifier()?,
+            predicate_types: policy.predicate_types,
+            builder_ids: policy.builder_ids,
+        })
+    }
+
+    /// Verify the envelope with the trusted keys, and get the Statement
+    /// and the key ids of the signers.
+    fn verify_envelope(&self, envelope: &Envelope) -> Result<(Statement, Vec<String>)> {
+        let (payload, signers) = self
+            .verifier
+            .verify_with_signers(envelope, INTOTO_PAYLOAD_TYPE)?;
+        Ok((serde_json::from_slice(&payload)?, signers))
+    }
+
+    /// Check the Statement against the policy, and record the failed
+    /// rule in the report.
+    fn check_policy(&self, statement: &Statement, report: &mut VerificationReport) -> Result<()> {
+        let statement_type = match STATEMENT_TYPES.contains(&statement.typ.as_str()) {
+            true => Ok(()),
+            false => Err(anyhow!("Unsupported Statement type: {}", statement.typ)),
+        };
+        report.check("statement-type", statement_type)?;
+
+        let predicate_type = match self.predicate_types.contains(&statement.predicate_type) {
+            true => Ok(()),
+            false => Err(anyhow!(
+                "Predicate type {} is not allowed.",
+                statement.predicate_type
+            )),
+        };
+        report.check("predicate-type", predicate_type)?;
+
+        let builder_id = statement
+            .builder_id()
+            .ok_or_else(|| anyhow!("Builder id not found in the SLSA provenance."))
+            .and_then(
+                |builder_id| match self.builder_ids.iter().any(|id| id == builder_id) {
+                    true => Ok(()),
+                    false => Err(anyhow!("Builder {} is not trusted.", builder_id)),
+                },
+            );
+        report.check("builder-id", builder_id)
+    }
+}
+
+impl ConfigurableExtractor for SlsaExtractor {
+    type Config = Policy;
+
+    fn with_config(config: Policy) -> Result<Self> {
+        Self::with_policy(config)
+    }
+}
+
+impl Extractor for SlsaExtractor {
+    /// SLSA's Extractor.
+    /// The provenance is a DSSE envelope in JSON, whose payload
+    /// is an in-toto Statement. A reference value is generated
+    /// for each subject of the Statement.
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+        let mut report = VerificationReport::new(SLSA_PROVENANCE_TYPE);
+        self.verify_and_extract_with_report(provenance, &mut report)
+    }
+
+    /// The signers are the trusted keys which signed the envelope.
+    fn verify_and_extract_with_report(
+        &self,
+        provenance: &str,
+        report: &mut VerificationReport,
+    ) -> Result<Vec<ReferenceValue>> {
+        let envelope: Envelope = serde_json::from_str(provenance)?;
+        let (statement, signers) = report.check("signature", self.verify_envelope(&envelope))?;
+        for signer in &signers {
+            report.add_signer(signer);
+        }
+        self.check_policy(&statement, report)?;
+
+        let subject = match statement.subject.is_empty() {
+            true => Err(anyhow!("No subject found in the SLSA provenance.")),
+            false => Ok(()),
+        };
+        report.check("subject", subject)?;
+
+        statement
+            .subject
+            .into_iter()
+            .map(|subject| {
+                if subject.digest.is_empty() {
+                    return report.check(
+                        "subject",
+                        Err(anyhow!("No digest found for subject {}", subject.name)),
+                    );
+                }
+
+                let mut rv = ReferenceValue::new()
+                    .set_name(&subject.name)
+                    .set_version(REFERENCE_VALUE_VERSION)
+                    .set_expired(unknown_expiry());
+                for (alg, value) in subject.digest {
+                    rv = rv.add_hash_value(alg, value);
+                }
+                Ok(rv)
+            })
+            .collect()
+    }
+
+    /// The accepted predicate types, s.t. the SLSA provenance versions.
+    fn payload_versions(&self) -> Vec<String> {
+        self.predicate_types.clone()
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use serde_json::{json, Value};
+
+    use crate::{
+        dsse::{test::generate_ed25519_private_key_pem, Signer, TrustPolicy},
+        extractors::extractor_modules::Extractor,
+        report::VerificationReport,
+    };
+
+    use super::{
+        Policy, SlsaExtractor, INTOTO_PAYLOAD_TYPE, SLSA_PROVENANCE_TYPE, SLSA_PROVENANCE_V02,
+    };
+
+    const BUILDER_ID: &str = "https://github.com/slsa-framework/slsa-github-generator/.github/workflows/generator_generic_slsa3.yml@refs/tags/v1.2.0";
+
+    /// Helps to generate a SLSA v0.2 Statement.
+    pub fn generate_slsa_v02_statement(builder_id: &str) -> Value {
+        json!({
+            "_type": "https://in-toto.io/Statement/v0.1",
+            "subject": [
+                { "name": "foo.tar.gz", "digest": { "sha256": "aa", "sha512": "bb" } },
+                { "name": "bar.tar.gz", "digest": { "sha256": "cc" } }
+            ],
+            "predicateType": SLSA_PROVENANCE_V02,
+            "predicate": { "builder": { "id": builder_id }, "buildType": "test" }
+        })
+    }
+
+    /// Helps to generate a SLSA v1.0 Statement.
+    pub fn generate_slsa_v1_statement(builder_id: &str) -> Value {
+        json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "subject": [{ "name": "foo.tar.gz", "digest": { "sha256": "aa" } }],
+            "predicateType": "https://slsa.dev/provenance/v1",
+            "predicate": {
+                "buildDefinition": { "buildType": "test" },
+                "runDetails": { "builder": { "id": builder_id } }
+            }
+        })
+    }
+
+    /// Helps to sign the Statement into a DSSE envelope in JSON.
+    pub fn sign_statement(signer: &Signer, statement: &Value) -> String {
+        let envelope = signer.sign(INTOTO_PAYLOAD_TYPE, statement.to_string().as_bytes());
+        serde_json::to_string(&envelope).unwrap()
+    }
+
+    /// Helps to create a SLSA Extractor trusting the signer and the builder.
+    fn extractor(dir: &tempfile::TempDir, signer: &Signer) -> SlsaExtractor {
+        let key_path = dir.path().join("builder.pub");
+        fs::write(&key_path, signer.public_key_pem()).unwrap();
+        SlsaExtractor::with_policy(Policy {
+            trust: TrustPolicy::new(vec![key_path]),
+            builder_ids: vec![BUILDER_ID.into()],
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn slsa_extractor() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let e = extractor(&dir, &signer);
+
+        let provenance = sign_statement(&signer, &generate_slsa_v02_statement(BUILDER_ID));
+        let rvs = e.verify_and_extract(&provenance).unwrap();
+        assert_eq!(rvs.len(), 2);
+        assert_eq!(rvs[0].name(), "foo.tar.gz");
+        assert_eq!(rvs[0].hash_values().len(), 2);
+        assert_eq!(rvs[0].hash_values()[0].alg(), "sha256");
+        assert_eq!(rvs[0].hash_values()[0].value(), "aa");
+        assert_eq!(rvs[1].name(), "bar.tar.gz");
+
+        let provenance = sign_statement(&signer, &generate_slsa_v1_statement(BUILDER_ID));
+        let rvs = e.verify_and_extract(&provenance).unwrap();
+        assert_eq!(rvs.len(), 1);
+        assert_eq!(rvs[0].name(), "foo.tar.gz");
+    }
+
+    #[test]
+    fn slsa_extractor_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let e = extractor(&dir, &signer);
+
+        let provenance = sign_statement(&signer, &generate_slsa_v02_statement(BUILDER_ID));
+        let mut report = VerificationReport::new(SLSA_PROVENANCE_TYPE);
+        e.verify_and_extract_with_report(&provenance, &mut report)
+            .unwrap();
+        assert_eq!(report.signers, vec![signer.keyid()]);
+        assert!(report.failed_rules.is_empty());
+
+        let statement = generate_slsa_v02_statement("https://example.com/builder");
+        let mut report = VerificationReport::new(SLSA_PROVENANCE_TYPE);
+        assert!(e
+            .verify_and_extract_with_report(&sign_statement(&signer, &statement), &mut report)
+            .is_err());
+        assert_eq!(report.failed_rules.len(), 1);
+        assert_eq!(report.failed_rules[0].rule, "builder-id");
+        assert_eq!(
+            report.failed_rules[0].reason,
+            "Builder https://example.com/builder is not trusted."
+        );
+
+        let other = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let provenance = sign_statement(&other, &generate_slsa_v02_statement(BUILDER_ID));
+        let mut report = VerificationReport::new(SLSA_PROVENANCE_TYPE);
+        assert!(e
+            .verify_and_extract_with_report(&provenance, &mut report)
+            .is_err());
+        assert!(report.signers.is_empty());
+        assert_eq!(report.failed_rules[0].rule, "signature");
+    }
+
+    #[test]
+    fn slsa_extractor_rejects() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let e = extractor(&dir, &signer);
+
+        // Untrusted signer
+        let other = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let provenance = sign_statement(&other, &generate_slsa_v02_statement(BUILDER_ID));
+        assert!(e.verify_and_extract(&provenance).is_err());
+
+        // Untrusted builder
+        let statement = generate_slsa_v02_statement("https://example.com/builder");
+        assert!(e
+            .verify_and_extract(&sign_statement(&signer, &statement))
+            .is_err());
+
+        // Predicate type not allowed
+        let mut statement = generate_slsa_v02_statement(BUILDER_ID);
+        statement["predicateType"] = "https://example.com/predicate".into();
+        assert!(e
+            .verify_and_extract(&sign_statement(&signer, &statement))
+            .is_err());
+
+        // Subject without digest
+        let mut statement = generate_slsa_v1_statement(BUILDER_ID);
+        statement["subject"][0]["digest"] = json!({});
+        assert!(e
+            .verify_and_extract(&sign_statement(&signer, &statement))
+            .is_err());
+
+        // Without any trusted key, the policy is rejected
+        assert!(SlsaExtractor::with_policy(Policy::default()).is_err());
+    }
+
+    #[test]
+    fn slsa_policy_from_config() {
+        let policy: Policy = serde_json::from_value(json!({
+            "trusted_keys": ["/etc/rvps/slsa/builder.pub"],
+            "builder_ids": [BUILDER_ID],
+        }))
+        .unwrap();
+        assert_eq!(policy.trust.trusted_keys.len(), 1);
+        assert_eq!(policy.trust.threshold, 1);
+        assert_eq!(policy.predicate_types.len(), 2);
+    }
+}
@@ -0,0 +1,421 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! # SPDX Extractor
+//!
+//! This Extractor helps to verify a signed SPDX 2.x JSON SBOM, s.t.
+//! a DSSE envelope of the SPDX document, and extract reference values
+//! from the checksums of the packages and files listed in it.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dsse::{Envelope, ThresholdVerifier, TrustPolicy},
+    reference_value::{ReferenceValue, REFERENCE_VALUE_VERSION},
+};
+
+use super::{unknown_expiry, ConfigurableExtractor, Extractor};
+
+/// Payload type of an SPDX JSON document inside a DSSE envelope
+pub const SPDX_PAYLOAD_TYPE: &str = "application/spdx+json";
+
+/// Payload type of an in-toto attestation inside a DSSE envelope
+pub const INTOTO_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+/// Predicate type of an in-toto Statement carrying an SPDX document
+pub const SPDX_PREDICATE_TYPE: &str = "https://spdx.dev/Document";
+
+/// Supported SPDX versions
+const SPDX_VERSIONS: [&str; 2] = ["SPDX-2.2", "SPDX-2.3"];
+
+/// Value of SPDX fields which are not asserted
+const NOASSERTION: &str = "NOASSERTION";
+
+/// Checksum algorithms too weak to be used as reference values
+const WEAK_ALGORITHMS: [&str; 5] = ["adler32", "md2", "md4", "md5", "md6"];
+
+/// Placeholders of the naming template.
+/// * `{spdx_id}`: SPDX identifier of the package or file.
+/// * `{name}`: name of the package, or file name of the file.
+/// * `{version}`: version of the package, empty for files.
+/// * `{namespace}`: namespace of the SPDX document.
+const PLACEHOLDERS: [&str; 4] = ["{spdx_id}", "{name}", "{version}", "{namespace}"];
+
+/// Default naming template of the reference values
+const DEFAULT_NAMING: &str = "{spdx_id}";
+
+/// Checksum of a package or file.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Checksum {
+    pub algorithm: String,
+    pub checksum_value: String,
+}
+
+/// A package in the SPDX document. Only the fields needed are
+/// included.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Package {
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    pub name: String,
+    pub version_info: Option<String>,
+    pub supplier: Option<String>,
+    #[serde(default)]
+    pub checksums: Vec<Checksum>,
+}
+
+/// A file in the SPDX document. Only the fields needed are included.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct File {
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    pub file_name: String,
+    #[serde(default)]
+    pub checksums: Vec<Checksum>,
+}
+
+/// SPDX 2.x document in JSON. Only the fields needed are included.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Document {
+    pub spdx_version: String,
+    pub document_namespace: String,
+    #[serde(default)]
+    pub packages: Vec<Package>,
+    #[serde(default)]
+    pub files: Vec<File>,
+}
+
+/// in-toto Statement with an SPDX document as the predicate.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Statement {
+    predicate_type: String,
+    predicate: Document,
+}
+
+/// Policy of the SPDX Extractor, given by its section of
+/// the config file.
+/// * `trust`: keys trusted to sign the SBOM, see `TrustPolicy`.
+/// * `naming`: template of the reference value names, see
+/// `PLACEHOLDERS`. Defaults to `{spdx_id}`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Policy {
+    #[serde(flatten)]
+    pub trust: TrustPolicy,
+    #[serde(default = "default_naming")]
+    pub naming: String,
+}
+
+/// Use to set default naming of Policy
+fn default_naming() -> String {
+    DEFAULT_NAMING.into()
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            trust: TrustPolicy::default(),
+            naming: default_naming(),
+        }
+    }
+}
+
+/// Fill the placeholders of the naming template.
+fn render(naming: &str, values: [&str; 4]) -> String {
+    PLACEHOLDERS
+        .iter()
+        .zip(values)
+        .fold(naming.to_string(), |name, (placeholder, value)| {
+            name.replace(placeholder, value)
+        })
+}
+
+/// Get the SPDX algorithm name in the form of reference values,
+/// e.g. `SHA256` to `sha256`, and `SHA3-256` to `sha3-256`.
+fn normalize_algorithm(algorithm: &str) -> String {
+    algorithm.to_lowercase()
+}
+
+pub struct SpdxExtractor {
+    verifier: ThresholdVerifier,
+    naming: String,
+}
+
+impl SpdxExtractor {
+    /// Create an SPDX Extractor with the given policy.
+    pub fn with_policy(policy: Policy) -> Result<Self> {
+        let rest = render(&policy.naming, ["", "", "", ""]);
+        if rest.contains('{') || rest.contains('}') {
+            return Err(anyhow!("Unknown placeholder in naming {}", policy.naming));
+        }
+
+        let verifier = policy.trust.verifier()?;
+        Ok(Self {
+            verifier,
+            naming: policy.naming,
+        })
+    }
+
+    /// Verify the envelope with the trusted keys, and get the SPDX
+    /// document. The payload is either the SPDX document itself, or
+    /// an in-toto Statement with the SPDX document as the predicate.
+    fn verify_envelope(&self, envelope: &Envelope) -> Result<Document> {
+        match envelope.payload_type.as_str() {
+            SPDX_PAYLOAD_TYPE => {
+                let payload = self.verifier.verify(envelope, SPDX_PAYLOAD_TYPE)?;
+                Ok(serde_json::from_slice(&payload)?)
+            }
+            INTOTO_PAYLOAD_TYPE => {
+                let payload = self.verifier.verify(envelope, INTOTO_PAYLOAD_TYPE)?;
+                let statement: Statement = serde_json::from_slice(&payload)?;
+                if statement.predicate_type != SPDX_PREDICATE_TYPE {
+                    return Err(anyhow!(
+                        "Unsupported predicate type: {}",
+                        statement.predicate_type
+                    ));
+                }
+                Ok(statement.predicate)
+            }
+            typ => Err(anyhow!("Unsupported payload type: {}", typ)),
+        }
+    }
+
+    /// Generate a reference value from the checksums. Weak checksums
+    /// are skipped, and `None` is returned if nothing is left.
+    fn reference_value(&self, values: [&str; 4], checksums: &[Checksum]) -> Option<ReferenceValue> {
+        let mut rv = ReferenceValue::new()
+            .set_name(&render(&self.naming, values))
+            .set_version(REFERENCE_VALUE_VERSION)
+            .set_expired(unknown_expiry());
+        for checksum in checksums {
+            let alg = normalize_algorithm(&checksum.algorithm);
+            if !WEAK_ALGORITHMS.contains(&alg.as_str()) {
+                rv = rv.add_hash_value(alg, checksum.checksum_value.to_lowercase());
+            }
+        }
+
+        match rv.hash_values().is_empty() {
+            true => None,
+            false => Some(rv),
+        }
+    }
+}
+
+impl ConfigurableExtractor for SpdxExtractor {
+    type Config = Policy;
+
+    fn with_config(config: Policy) -> Result<Self> {
+        Self::with_policy(config)
+    }
+}
+
+impl Extractor for SpdxExtractor {
+    /// SPDX's Extractor.
+    /// The provenance is a DSSE envelope in JSON of an SPDX 2.x JSON
+    /// document. A reference value is generated for each package and
+    /// file with checksums, and the supplier and version of packages
+    /// are kept as metadata.
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+        let envelope: Envelope = serde_json::from_str(provenance)?;
+        let document = self.verify_envelope(&envelope)?;
+        if !SPDX_VERSIONS.contains(&document.spdx_version.as_str()) {
+            return Err(anyhow!(
+                "Unsupported SPDX version: {}",
+                document.spdx_version
+            ));
+        }
+
+        let namespace = &document.document_namespace;
+        let mut rvs = Vec::new();
+        for package in &document.packages {
+            let version = package.version_info.as_deref().unwrap_or_default();
+            let values = [package.spdx_id.as_str(), &package.name, version, namespace];
+            let mut rv = match self.reference_value(values, &package.checksums) {
+                Some(rv) => rv,
+                None => continue,
+            };
+
+            if let Some(supplier) = package.supplier.as_deref() {
+                if supplier != NOASSERTION {
+                    rv = rv.add_metadata("supplier", supplier);
+                }
+            }
+            if !version.is_empty() && version != NOASSERTION {
+                rv = rv.add_metadata("version", version);
+            }
+            rvs.push(rv);
+        }
+
+        for file in &document.files {
+            let values = [file.spdx_id.as_str(), &file.file_name, "", namespace];
+            rvs.extend(self.reference_value(values, &file.checksums));
+        }
+
+        if rvs.is_empty() {
+            return Err(anyhow!("No checksum found in the SPDX document."));
+        }
+        Ok(rvs)
+    }
+
+    /// The SPDX versions of the document.
+    fn payload_versions(&self) -> Vec<String> {
+        SPDX_VERSIONS.iter().map(|v| v.to_string()).collect()
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use serde_json::{json, Value};
+
+    use crate::{
+        dsse::{test::generate_ed25519_private_key_pem, Signer, TrustPolicy},
+        extractors::extractor_modules::Extractor,
+    };
+
+    use super::{Policy, SpdxExtractor, INTOTO_PAYLOAD_TYPE, SPDX_PAYLOAD_TYPE};
+
+    /// Helps to generate an SPDX 2.3 document.
+    pub fn generate_spdx_document() -> Value {
+        json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": "rvps-image",
+            "documentNamespace": "https://example.com/spdx/rvps-image",
+            "creationInfo": {
+                "created": "2022-09-01T00:00:00Z",
+                "creators": ["Tool: syft"]
+            },
+            "packages": [
+                {
+                    "SPDXID": "SPDXRef-Package-openssl",
+                    "name": "openssl",
+                    "versionInfo": "3.0.5",
+                    "supplier": "Organization: OpenSSL",
+                    "downloadLocation": "NOASSERTION",
+                    "checksums": [
+                        { "algorithm": "SHA256", "checksumValue": "AA" },
+                        { "algorithm": "MD5", "checksumValue": "bb" }
+                    ]
+                },
+                {
+                    "SPDXID": "SPDXRef-Package-busybox",
+                    "name": "busybox",
+                    "supplier": "NOASSERTION",
+                    "downloadLocation": "NOASSERTION",
+                    "checksums": [{ "algorithm": "SHA3-256", "checksumValue": "cc" }]
+                },
+                {
+                    "SPDXID": "SPDXRef-Package-no-checksum",
+                    "name": "no-checksum",
+                    "downloadLocation": "NOASSERTION"
+                }
+            ],
+            "files": [
+                {
+                    "SPDXID": "SPDXRef-File-bin-sh",
+                    "fileName": "/bin/sh",
+                    "checksums": [
+                        { "algorithm": "SHA1", "checksumValue": "dd" },
+                        { "algorithm": "SHA256", "checksumValue": "ee" }
+                    ]
+                }
+            ]
+        })
+    }
+
+    /// Helps to create an SPDX Extractor trusting the signer.
+    fn extractor(dir: &tempfile::TempDir, signer: &Signer, naming: &str) -> SpdxExtractor {
+        let key_path = dir.path().join("sbom.pub");
+        fs::write(&key_path, signer.public_key_pem()).unwrap();
+        SpdxExtractor::with_policy(Policy {
+            trust: TrustPolicy::new(vec![key_path]),
+            naming: naming.into(),
+        })
+        .unwrap()
+    }
+
+    fn sign(signer: &Signer, payload_type: &str, payload: &Value) -> String {
+        let envelope = signer.sign(payload_type, payload.to_string().as_bytes());
+        serde_json::to_string(&envelope).unwrap()
+    }
+
+    #[test]
+    fn spdx_extractor() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let e = extractor(&dir, &signer, "{spdx_id}");
+
+        let provenance = sign(&signer, SPDX_PAYLOAD_TYPE, &generate_spdx_document());
+        let rvs = e.verify_and_extract(&provenance).unwrap();
+        assert_eq!(rvs.len(), 3);
+
+        assert_eq!(rvs[0].name(), "SPDXRef-Package-openssl");
+        assert_eq!(rvs[0].hash_values().len(), 1);
+        assert_eq!(rvs[0].hash_values()[0].alg(), "sha256");
+        assert_eq!(rvs[0].hash_values()[0].value(), "aa");
+        assert_eq!(rvs[0].metadata()["supplier"], "Organization: OpenSSL");
+        assert_eq!(rvs[0].metadata()["version"], "3.0.5");
+
+        assert_eq!(rvs[1].name(), "SPDXRef-Package-busybox");
+        assert_eq!(rvs[1].hash_values()[0].alg(), "sha3-256");
+        assert!(rvs[1].metadata().is_empty());
+
+        assert_eq!(rvs[2].name(), "SPDXRef-File-bin-sh");
+        assert_eq!(rvs[2].hash_values().len(), 2);
+
+        // An in-toto Statement with a custom naming
+        let e = extractor(&dir, &signer, "{name}@{version}");
+        let statement = json!({
+            "_type": "https://in-toto.io/Statement/v0.1",
+            "subject": [],
+            "predicateType": "https://spdx.dev/Document",
+            "predicate": generate_spdx_document()
+        });
+        let provenance = sign(&signer, INTOTO_PAYLOAD_TYPE, &statement);
+        let rvs = e.verify_and_extract(&provenance).unwrap();
+        assert_eq!(rvs[0].name(), "openssl@3.0.5");
+        assert_eq!(rvs[2].name(), "/bin/sh@");
+    }
+
+    #[test]
+    fn spdx_extractor_rejects() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let e = extractor(&dir, &signer, "{spdx_id}");
+
+        // Untrusted signer
+        let other = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let provenance = sign(&other, SPDX_PAYLOAD_TYPE, &generate_spdx_document());
+        assert!(e.verify_and_extract(&provenance).is_err());
+
+        // Unsupported SPDX version
+        let mut document = generate_spdx_document();
+        document["spdxVersion"] = "SPDX-3.0".into();
+        let provenance = sign(&signer, SPDX_PAYLOAD_TYPE, &document);
+        assert!(e.verify_and_extract(&provenance).is_err());
+
+        // No checksum
+        let mut document = generate_spdx_document();
+        document["packages"] = json!([]);
+        document["files"] = json!([]);
+        let provenance = sign(&signer, SPDX_PAYLOAD_TYPE, &document);
+        assert!(e.verify_and_extract(&provenance).is_err());
+
+        // Unknown placeholder
+        assert!(SpdxExtractor::with_policy(Policy {
+            naming: "{unknown}".into(),
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
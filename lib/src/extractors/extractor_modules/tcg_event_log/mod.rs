@@ -0,0 +1,252 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! # TCG Event Log Extractor
+//!
+//! This Extractor helps to verify a signed TCG2 event log of a
+//! reference boot, and replay it into the expected PCR values of a
+//! vTPM, together with the digests of the chosen events.
+
+pub mod parser;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dsse::{Envelope, ThresholdVerifier, TrustPolicy},
+    extractors::extractor_modules::artifact::hex,
+    reference_value::{ReferenceValue, REFERENCE_VALUE_VERSION},
+};
+
+use self::parser::{event_type_name, Digests, EventLog};
+
+use super::{unknown_expiry, ConfigurableExtractor, Extractor};
+
+/// Payload type of the event log description inside a DSSE envelope
+pub const EVENT_LOG_PAYLOAD_TYPE: &str = "application/vnd.rvps.tcg-event-log+json";
+
+/// An event to get a reference value for, s.t. the `occurrence`-th
+/// event in PCR `pcr`, counting only the events of `event_type` if
+/// given, e.g. `EV_EFI_BOOT_SERVICES_APPLICATION`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EventSelector {
+    pub name: String,
+    pub pcr: u32,
+    #[serde(rename = "type")]
+    pub event_type: Option<String>,
+    #[serde(default)]
+    pub occurrence: usize,
+}
+
+/// Signed description of the event log.
+/// * `name`: prefix of the reference value names.
+/// * `event_log`: base64 encoded binary event log.
+/// * `events`: events to get reference values for.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Description {
+    pub name: String,
+    pub event_log: String,
+    #[serde(default)]
+    pub events: Vec<EventSelector>,
+}
+
+/// Provenance of an event log.
+/// * `envelope`: DSSE envelope of the `Description`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Provenance {
+    pub envelope: Envelope,
+}
+
+/// Policy of the TCG Event Log Extractor, given by its section of
+/// the config file, s.t. the keys trusted to sign the event log.
+pub type Policy = TrustPolicy;
+
+pub struct TcgEventLogExtractor {
+    verifier: ThresholdVerifier,
+}
+
+impl TcgEventLogExtractor {
+    /// Create a TCG Event Log Extractor with the given policy.
+    pub fn with_policy(policy: Policy) -> Result<Self> {
+        let verifier = policy.verifier()?;
+        Ok(Self { verifier })
+    }
+}
+
+/// Build a reference value of the digests by algorithm.
+fn reference_value(name: &str, digests: Digests) -> ReferenceValue {
+    digests.into_iter().fold(
+        ReferenceValue::new()
+            .set_name(name)
+            .set_version(REFERENCE_VALUE_VERSION)
+            .set_expired(unknown_expiry()),
+        |rv, (alg, digest)| rv.add_hash_value(alg, hex(&digest)),
+    )
+}
+
+impl ConfigurableExtractor for TcgEventLogExtractor {
+    type Config = Policy;
+
+    fn with_config(config: Policy) -> Result<Self> {
+        Self::with_policy(config)
+    }
+}
+
+impl Extractor for TcgEventLogExtractor {
+    /// TCG Event Log Extractor.
+    /// The provenance is a signed description of a TCG2 crypto-agile
+    /// event log. Reference values are named by the `name` of the
+    /// description:
+    /// * `<name>/pcr<index>`: each PCR with events, with the replayed
+    /// values of all the supported banks.
+    /// * `<name>/<event>`: each selected event, with its digests.
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+        let provenance: Provenance = serde_json::from_str(provenance)?;
+        let payload = self
+            .verifier
+            .verify(&provenance.envelope, EVENT_LOG_PAYLOAD_TYPE)?;
+        let description: Description = serde_json::from_slice(&payload)?;
+        let event_log = base64::decode(&description.event_log)
+            .map_err(|e| anyhow!("Decode event log failed: {}", e))?;
+        let log = EventLog::parse(&event_log)?;
+
+        let mut rvs: Vec<_> = log
+            .replay()
+            .into_iter()
+            .map(|(pcr, values)| {
+                reference_value(&format!("{}/pcr{}", description.name, pcr), values)
+            })
+            .collect();
+
+        for selector in &description.events {
+            let event = log
+                .events
+                .iter()
+                .filter(|e| e.pcr == selector.pcr)
+                .filter(|e| match &selector.event_type {
+                    Some(event_type) => event_type_name(e.event_type) == *event_type,
+                    None => true,
+                })
+                .nth(selector.occurrence)
+                .ok_or_else(|| anyhow!("Event {} not found in the event log.", selector.name))?;
+
+            let rv = reference_value(
+                &format!("{}/{}", description.name, selector.name),
+                event.supported_digests(),
+            )
+            .add_metadata("pcr", &event.pcr.to_string())
+            .add_metadata("event_type", &event_type_name(event.event_type));
+            rvs.push(rv);
+        }
+
+        Ok(rvs)
+    }
+
+    /// Signature of the Spec ID event, telling the crypto-agile format
+    /// of the event log.
+    fn payload_versions(&self) -> Vec<String> {
+        let signature = String::from_utf8_lossy(parser::SPEC_ID_EVENT_SIGNATURE);
+        vec![signature.trim_end_matches('\0').into()]
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use serde_json::{json, Value};
+
+    use crate::{
+        dsse::{test::generate_ed25519_private_key_pem, Signer},
+        extractors::extractor_modules::Extractor,
+    };
+
+    use super::{Policy, TcgEventLogExtractor, EVENT_LOG_PAYLOAD_TYPE};
+
+    /// Test vectors generated by `<git-repo>/tests/tcg-event-log/generate.py`
+    const TEST_VECTORS_DIR: &str = "../tests/tcg-event-log";
+
+    fn read(name: &str) -> Vec<u8> {
+        fs::read(format!("{}/{}", TEST_VECTORS_DIR, name)).unwrap()
+    }
+
+    fn description(event_log: &[u8]) -> Value {
+        let application = "EV_EFI_BOOT_SERVICES_APPLICATION";
+        json!({
+            "name": "reference-boot",
+            "event_log": base64::encode(event_log),
+            "events": [
+                { "name": "shim", "pcr": 4, "type": application },
+                { "name": "grub", "pcr": 4, "type": application, "occurrence": 1 },
+                { "name": "kernel", "pcr": 4, "type": application, "occurrence": 2 },
+            ],
+        })
+    }
+
+    /// Sign the description with a new key, and get the provenance and
+    /// an Extractor trusting the key.
+    fn sign(description: &Value) -> (String, TcgEventLogExtractor) {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let key_path = dir.path().join("key.pub");
+        fs::write(&key_path, signer.public_key_pem()).unwrap();
+
+        let envelope = signer.sign(EVENT_LOG_PAYLOAD_TYPE, description.to_string().as_bytes());
+        let extractor = TcgEventLogExtractor::with_policy(Policy::new(vec![key_path])).unwrap();
+        (json!({ "envelope": envelope }).to_string(), extractor)
+    }
+
+    #[test]
+    fn tcg_event_log_extractor() {
+        let m: Value = serde_json::from_slice(&read("measurements.json")).unwrap();
+        let (provenance, e) = sign(&description(&read("eventlog.bin")));
+        let rvs = e.verify_and_extract(&provenance).unwrap();
+
+        let pcrs = m["pcrs"].as_object().unwrap();
+        assert_eq!(rvs.len(), pcrs.len() + 3);
+        for (rv, (pcr, values)) in rvs.iter().zip(pcrs) {
+            assert_eq!(rv.name(), &format!("reference-boot/pcr{}", pcr));
+            // The sm3_256 bank is not replayed
+            assert_eq!(rv.hash_values().len(), 3);
+            for hash in rv.hash_values() {
+                assert_eq!(hash.value(), &values[hash.alg()]);
+            }
+        }
+
+        for (rv, name) in rvs[pcrs.len()..].iter().zip(["shim", "grub", "kernel"]) {
+            assert_eq!(rv.name(), &format!("reference-boot/{}", name));
+            assert_eq!(rv.metadata()["pcr"], "4");
+            for hash in rv.hash_values() {
+                assert_eq!(hash.value(), &m["events"][name][hash.alg()]);
+            }
+        }
+    }
+
+    #[test]
+    fn tcg_event_log_extractor_rejects() {
+        let event_log = read("eventlog.bin");
+
+        // Truncated event log
+        let (provenance, e) = sign(&description(&event_log[..event_log.len() - 1]));
+        assert!(e.verify_and_extract(&provenance).is_err());
+
+        // Not a crypto-agile event log
+        let mut tampered = event_log.clone();
+        tampered[32] = b'X';
+        let (provenance, e) = sign(&description(&tampered));
+        assert!(e.verify_and_extract(&provenance).is_err());
+
+        // Selected event not found
+        let mut d = description(&event_log);
+        d["events"][2]["occurrence"] = json!(3);
+        let (provenance, e) = sign(&d);
+        assert!(e.verify_and_extract(&provenance).is_err());
+
+        // Untrusted key
+        let (provenance, _) = sign(&description(&event_log));
+        let (_, e) = sign(&description(&event_log));
+        assert!(e.verify_and_extract(&provenance).is_err());
+    }
+}
@@ -0,0 +1,302 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Parser of the TCG2 crypto-agile event log, and replay of it into
+//! PCR values, see the TCG PC Client Platform Firmware Profile
+//! Specification.
+
+use anyhow::{anyhow, Result};
+use ring::digest::{Algorithm, Context, SHA1_FOR_LEGACY_USE_ONLY, SHA256, SHA384, SHA512};
+
+/// Number of PCRs of a PC Client TPM
+pub const PCR_COUNT: u32 = 24;
+
+const EV_NO_ACTION: u32 = 0x3;
+
+/// Signature of the Spec ID event, which is the first event in the
+/// log and gives the digest sizes of the banks.
+pub const SPEC_ID_EVENT_SIGNATURE: &[u8] = b"Spec ID Event03\0";
+
+/// Signature of the EV_NO_ACTION event of the startup locality, which
+/// is the initial value of PCR 0.
+const STARTUP_LOCALITY_SIGNATURE: &[u8] = b"StartupLocality\0";
+
+/// Size of the digest in the Spec ID event, which is in SHA-1 format
+const SHA1_DIGEST_SIZE: usize = 20;
+
+/// Names of the event types, by value.
+const EVENT_TYPES: &[(u32, &str)] = &[
+    (0x0, "EV_PREBOOT_CERT"),
+    (0x1, "EV_POST_CODE"),
+    (0x3, "EV_NO_ACTION"),
+    (0x4, "EV_SEPARATOR"),
+    (0x5, "EV_ACTION"),
+    (0x6, "EV_EVENT_TAG"),
+    (0x7, "EV_S_CRTM_CONTENTS"),
+    (0x8, "EV_S_CRTM_VERSION"),
+    (0x9, "EV_CPU_MICROCODE"),
+    (0xA, "EV_PLATFORM_CONFIG_FLAGS"),
+    (0xB, "EV_TABLE_OF_DEVICES"),
+    (0xC, "EV_COMPACT_HASH"),
+    (0xD, "EV_IPL"),
+    (0xE, "EV_IPL_PARTITION_DATA"),
+    (0xF, "EV_NONHOST_CODE"),
+    (0x10, "EV_NONHOST_CONFIG"),
+    (0x11, "EV_NONHOST_INFO"),
+    (0x12, "EV_OMIT_BOOT_DEVICE_EVENTS"),
+    (0x80000001, "EV_EFI_VARIABLE_DRIVER_CONFIG_LEGACY"),
+    (0x80000002, "EV_EFI_VARIABLE_BOOT"),
+    (0x80000003, "EV_EFI_BOOT_SERVICES_APPLICATION"),
+    (0x80000004, "EV_EFI_BOOT_SERVICES_DRIVER"),
+    (0x80000005, "EV_EFI_RUNTIME_SERVICES_DRIVER"),
+    (0x80000006, "EV_EFI_GPT_EVENT"),
+    (0x80000007, "EV_EFI_ACTION"),
+    (0x80000008, "EV_EFI_PLATFORM_FIRMWARE_BLOB"),
+    (0x80000009, "EV_EFI_HANDOFF_TABLES"),
+    (0x8000000A, "EV_EFI_PLATFORM_FIRMWARE_BLOB2"),
+    (0x8000000B, "EV_EFI_HANDOFF_TABLES2"),
+    (0x8000000C, "EV_EFI_VARIABLE_BOOT2"),
+    (0x80000010, "EV_EFI_HCRTM_EVENT"),
+    (0x800000E0, "EV_EFI_VARIABLE_DRIVER_CONFIG"),
+    (0x800000E1, "EV_EFI_VARIABLE_AUTHORITY"),
+    (0x800000E2, "EV_EFI_SPDM_FIRMWARE_BLOB"),
+    (0x800000E3, "EV_EFI_SPDM_FIRMWARE_CONFIG"),
+];
+
+/// Get the name of an event type, or its hex value if unknown.
+pub fn event_type_name(event_type: u32) -> String {
+    EVENT_TYPES
+        .iter()
+        .find(|(value, _)| *value == event_type)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| format!("{:#x}", event_type))
+}
+
+/// Get the name and hash algorithm of a TPM algorithm ID. Banks of
+/// other algorithms, e.g. SM3, are parsed but not replayed.
+fn bank_algorithm(alg_id: u16) -> Option<(&'static str, &'static Algorithm)> {
+    match alg_id {
+        0x0004 => Some(("sha1", &SHA1_FOR_LEGACY_USE_ONLY)),
+        0x000B => Some(("sha256", &SHA256)),
+        0x000C => Some(("sha384", &SHA384)),
+        0x000D => Some(("sha512", &SHA512)),
+        _ => None,
+    }
+}
+
+/// Digests by the name of the algorithm, e.g. `sha256`.
+pub type Digests = Vec<(String, Vec<u8>)>;
+
+/// A bank of the event log, s.t. a hash algorithm.
+#[derive(Debug)]
+pub struct Bank {
+    pub alg_id: u16,
+    pub digest_size: usize,
+}
+
+/// An event which is extended to a PCR.
+/// * `digests`: the digests of the event by TPM algorithm ID.
+#[derive(Debug)]
+pub struct Event {
+    pub pcr: u32,
+    pub event_type: u32,
+    pub digests: Vec<(u16, Vec<u8>)>,
+    pub data: Vec<u8>,
+}
+
+/// A TCG2 crypto-agile event log.
+#[derive(Debug)]
+pub struct EventLog {
+    pub banks: Vec<Bank>,
+    pub startup_locality: u8,
+    pub events: Vec<Event>,
+}
+
+/// Cursor over the binary event log.
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.offset..end))
+            .ok_or_else(|| anyhow!("Event log is truncated at {:#x}.", self.offset))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let mut bytes = [0u8; 2];
+        bytes.copy_from_slice(self.bytes(2)?);
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+}
+
+impl EventLog {
+    /// Parse the binary event log, which must start with the Spec ID
+    /// event in SHA-1 format.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader { data, offset: 0 };
+
+        let (pcr, event_type) = (reader.u32()?, reader.u32()?);
+        reader.bytes(SHA1_DIGEST_SIZE)?;
+        let size = reader.u32()? as usize;
+        let mut spec_id = Reader {
+            data: reader.bytes(size)?,
+            offset: 0,
+        };
+        if pcr != 0
+            || event_type != EV_NO_ACTION
+            || spec_id.bytes(SPEC_ID_EVENT_SIGNATURE.len())? != SPEC_ID_EVENT_SIGNATURE
+        {
+            return Err(anyhow!("Event log is not in crypto-agile format."));
+        }
+
+        // Platform class, spec version and uintn size
+        spec_id.bytes(8)?;
+        let mut banks = Vec::new();
+        for _ in 0..spec_id.u32()? {
+            banks.push(Bank {
+                alg_id: spec_id.u16()?,
+                digest_size: spec_id.u16()? as usize,
+            });
+        }
+
+        // At least one bank must be replayed, with the right digest size
+        let mut supported = false;
+        for bank in &banks {
+            if let Some((name, algorithm)) = bank_algorithm(bank.alg_id) {
+                if algorithm.output_len != bank.digest_size {
+                    return Err(anyhow!("Invalid digest size of {} bank.", name));
+                }
+                supported = true;
+            }
+        }
+        if !supported {
+            return Err(anyhow!("No supported bank in the event log."));
+        }
+
+        let mut log = EventLog {
+            banks,
+            startup_locality: 0,
+            events: Vec::new(),
+        };
+        while !reader.is_empty() {
+            let pcr = reader.u32()?;
+            let event_type = reader.u32()?;
+            if pcr >= PCR_COUNT {
+                return Err(anyhow!("Invalid PCR index: {}", pcr));
+            }
+
+            let mut digests = Vec::new();
+            for _ in 0..reader.u32()? {
+                let alg_id = reader.u16()?;
+                let bank = log
+                    .banks
+                    .iter()
+                    .find(|b| b.alg_id == alg_id)
+                    .ok_or_else(|| anyhow!("Unknown bank in the event log: {:#x}", alg_id))?;
+                digests.push((alg_id, reader.bytes(bank.digest_size)?.to_vec()));
+            }
+            let size = reader.u32()? as usize;
+            let data = reader.bytes(size)?.to_vec();
+
+            if event_type == EV_NO_ACTION {
+                if pcr == 0 && data.starts_with(STARTUP_LOCALITY_SIGNATURE) {
+                    log.startup_locality = *data
+                        .get(STARTUP_LOCALITY_SIGNATURE.len())
+                        .ok_or_else(|| anyhow!("Malformed startup locality event."))?;
+                }
+                continue;
+            }
+
+            if log
+                .banks
+                .iter()
+                .any(|b| digests.iter().all(|d| d.0 != b.alg_id))
+            {
+                return Err(anyhow!("Event does not have digests of all banks."));
+            }
+            log.events.push(Event {
+                pcr,
+                event_type,
+                digests,
+                data,
+            });
+        }
+
+        Ok(log)
+    }
+
+    /// Replay the events into PCR values of each supported bank, by
+    /// the name of the algorithm. Only the PCRs with events are
+    /// returned, in the order of the index.
+    pub fn replay(&self) -> Vec<(u32, Digests)> {
+        let mut pcrs = Vec::new();
+        for pcr in 0..PCR_COUNT {
+            if !self.events.iter().any(|e| e.pcr == pcr) {
+                continue;
+            }
+
+            let mut values = Vec::new();
+            for bank in &self.banks {
+                let (name, algorithm) = match bank_algorithm(bank.alg_id) {
+                    Some(alg) => alg,
+                    None => continue,
+                };
+
+                let mut value = vec![0u8; bank.digest_size];
+                if pcr == 0 {
+                    value[bank.digest_size - 1] = self.startup_locality;
+                }
+                for event in self.events.iter().filter(|e| e.pcr == pcr) {
+                    let mut context = Context::new(algorithm);
+                    context.update(&value);
+                    context.update(event.digest(bank.alg_id));
+                    value = context.finish().as_ref().to_vec();
+                }
+                values.push((name.to_string(), value));
+            }
+            pcrs.push((pcr, values));
+        }
+        pcrs
+    }
+}
+
+impl Event {
+    /// Digest of the event in the bank.
+    fn digest(&self, alg_id: u16) -> &[u8] {
+        self.digests
+            .iter()
+            .find(|d| d.0 == alg_id)
+            .map(|d| d.1.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Digests of the event in the supported banks, by the name of the
+    /// algorithm.
+    pub fn supported_digests(&self) -> Digests {
+        self.digests
+            .iter()
+            .filter_map(|(alg_id, digest)| {
+                bank_algorithm(*alg_id).map(|(name, _)| (name.to_string(), digest.clone()))
+            })
+            .collect()
+    }
+}
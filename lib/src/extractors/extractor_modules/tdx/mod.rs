@@ -0,0 +1,336 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! # TDX Extractor
+//!
+//! This Extractor helps to verify the signed launch components of an
+//! Intel TDX guest, s.t. TDVF firmware, kernel, initrd and cmdline,
+//! and calculate the expected MRTD and RTMRs of the guest.
+
+pub mod pe;
+pub mod tdvf;
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use ring::digest::{digest, SHA384};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dsse::{Envelope, ThresholdVerifier, TrustPolicy},
+    extractors::extractor_modules::artifact::{hex, Artifact},
+    reference_value::{ReferenceValue, REFERENCE_VALUE_VERSION},
+};
+
+use self::tdvf::Tdvf;
+
+use super::{unknown_expiry, ConfigurableExtractor, Extractor};
+
+/// Payload type of the launch description inside a DSSE envelope
+pub const TDX_LAUNCH_PAYLOAD_TYPE: &str = "application/vnd.rvps.tdx-launch+json";
+
+/// Number of RTMRs
+const RTMR_COUNT: usize = 4;
+
+/// An event extended to an RTMR. The digest is SHA-384 of
+/// * `Digest`: nothing, the `value` is the hex encoded digest.
+/// * `Action`: the `value`, s.t. EV_EFI_ACTION.
+/// * `Separator`: four zero bytes, s.t. EV_SEPARATOR.
+/// * `Kernel`: the Authenticode hash of the kernel.
+/// * `Initrd`: the initrd.
+/// * `Cmdline`: the cmdline in UTF-16LE with the terminating NUL, as
+/// the EFI stub measures the load options.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Event {
+    Digest { value: String },
+    Action { value: String },
+    Separator,
+    Kernel,
+    Initrd,
+    Cmdline,
+}
+
+/// Signed launch description.
+/// * `name`: prefix of the reference value names.
+/// * `tdvf`, `kernel`, `initrd`: digests of the components, e.g.
+/// `sha384:<hex>`.
+/// * `cmdline`: the kernel cmdline.
+/// * `rtmrs`: the expected events of each RTMR, by index.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Launch {
+    pub name: String,
+    pub tdvf: String,
+    pub kernel: Option<String>,
+    pub initrd: Option<String>,
+    pub cmdline: Option<String>,
+    #[serde(default)]
+    pub rtmrs: BTreeMap<usize, Vec<Event>>,
+}
+
+/// Provenance of a TDX guest.
+/// * `envelope`: DSSE envelope of the `Launch`.
+/// * `tdvf`, `kernel`, `initrd`: the components in the `Launch`,
+/// carried by content or referenced by path.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Provenance {
+    pub envelope: Envelope,
+    pub tdvf: Artifact,
+    pub kernel: Option<Artifact>,
+    pub initrd: Option<Artifact>,
+}
+
+/// Policy of the TDX Extractor, given by its section of
+/// the config file.
+/// * `trust`: keys trusted to sign the launch description, see
+/// `TrustPolicy`.
+/// * `artifact_dir`: directory of the components referenced by path.
+/// If not set, components must be carried by the provenance.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Policy {
+    #[serde(flatten)]
+    pub trust: TrustPolicy,
+    pub artifact_dir: Option<PathBuf>,
+}
+
+/// Read an optional component in the launch description.
+fn read_optional(
+    name: &str,
+    digest: &Option<String>,
+    artifact: &Option<Artifact>,
+    dir: Option<&Path>,
+) -> Result<Option<Vec<u8>>> {
+    match (digest, artifact) {
+        (Some(digest), Some(artifact)) => Ok(Some(artifact.read(dir, digest)?)),
+        (None, None) => Ok(None),
+        _ => Err(anyhow!("{} does not match the launch description.", name)),
+    }
+}
+
+pub struct TdxExtractor {
+    verifier: ThresholdVerifier,
+    artifact_dir: Option<PathBuf>,
+}
+
+impl TdxExtractor {
+    /// Create a TDX Extractor with the given policy.
+    pub fn with_policy(policy: Policy) -> Result<Self> {
+        let verifier = policy.trust.verifier()?;
+        Ok(Self {
+            verifier,
+            artifact_dir: policy.artifact_dir,
+        })
+    }
+}
+
+/// Replay the events, s.t. RTMR = SHA-384(RTMR || digest) from zero.
+fn replay(
+    events: &[Event],
+    kernel: Option<&[u8]>,
+    initrd: Option<&[u8]>,
+    cmdline: Option<&str>,
+) -> Result<Vec<u8>> {
+    let mut rtmr = vec![0u8; SHA384.output_len];
+    for event in events {
+        let event_digest = match event {
+            Event::Digest { value } => {
+                let value = (0..value.len())
+                    .step_by(2)
+                    .map(|i| {
+                        value
+                            .get(i..i + 2)
+                            .and_then(|b| u8::from_str_radix(b, 16).ok())
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|d| d.len() == SHA384.output_len)
+                    .ok_or_else(|| anyhow!("Invalid SHA-384 digest: {}", value))?;
+                value
+            }
+            Event::Action { value } => digest(&SHA384, value.as_bytes()).as_ref().to_vec(),
+            Event::Separator => digest(&SHA384, &[0; 4]).as_ref().to_vec(),
+            Event::Kernel => {
+                let kernel = kernel.ok_or_else(|| anyhow!("Kernel event without kernel."))?;
+                pe::authenticode(&SHA384, kernel)?
+            }
+            Event::Initrd => {
+                let initrd = initrd.ok_or_else(|| anyhow!("Initrd event without initrd."))?;
+                digest(&SHA384, initrd).as_ref().to_vec()
+            }
+            Event::Cmdline => {
+                let cmdline = cmdline.ok_or_else(|| anyhow!("Cmdline event without cmdline."))?;
+                let utf16: Vec<u8> = cmdline
+                    .encode_utf16()
+                    .chain([0])
+                    .flat_map(u16::to_le_bytes)
+                    .collect();
+                digest(&SHA384, &utf16).as_ref().to_vec()
+            }
+        };
+
+        rtmr.extend_from_slice(&event_digest);
+        rtmr = digest(&SHA384, &rtmr).as_ref().to_vec();
+    }
+    Ok(rtmr)
+}
+
+impl ConfigurableExtractor for TdxExtractor {
+    type Config = Policy;
+
+    fn with_config(config: Policy) -> Result<Self> {
+        Self::with_policy(config)
+    }
+}
+
+impl Extractor for TdxExtractor {
+    /// TDX's Extractor.
+    /// The provenance is a signed launch description, and the
+    /// components in it. Reference values are named by the `name` of
+    /// the launch description:
+    /// * `<name>/mrtd`: the MRTD calculated from the TDVF.
+    /// * `<name>/rtmr<index>`: the RTMRs with expected events.
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+        let provenance: Provenance = serde_json::from_str(provenance)?;
+        let payload = self
+            .verifier
+            .verify(&provenance.envelope, TDX_LAUNCH_PAYLOAD_TYPE)?;
+        let launch: Launch = serde_json::from_slice(&payload)?;
+        if let Some(index) = launch.rtmrs.keys().find(|i| **i >= RTMR_COUNT) {
+            return Err(anyhow!("Invalid RTMR index: {}", index));
+        }
+
+        let dir = self.artifact_dir.as_deref();
+        let tdvf = Tdvf::new(provenance.tdvf.read(dir, &launch.tdvf)?)?;
+        let kernel = read_optional("Kernel", &launch.kernel, &provenance.kernel, dir)?;
+        let initrd = read_optional("Initrd", &launch.initrd, &provenance.initrd, dir)?;
+
+        let mut measurements = vec![("mrtd".to_string(), tdvf.mrtd()?)];
+        for (index, events) in &launch.rtmrs {
+            let rtmr = replay(
+                events,
+                kernel.as_deref(),
+                initrd.as_deref(),
+                launch.cmdline.as_deref(),
+            )?;
+            measurements.push((format!("rtmr{}", index), rtmr));
+        }
+
+        let rvs = measurements
+            .into_iter()
+            .map(|(register, value)| {
+                ReferenceValue::new()
+                    .set_name(&format!("{}/{}", launch.name, register))
+                    .set_version(REFERENCE_VALUE_VERSION)
+                    .set_expired(unknown_expiry())
+                    .add_hash_value("sha384".to_string(), hex(&value))
+            })
+            .collect();
+        Ok(rvs)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use serde_json::{json, Value};
+
+    use crate::{
+        dsse::{test::generate_ed25519_private_key_pem, Signer, TrustPolicy},
+        extractors::extractor_modules::Extractor,
+    };
+
+    use super::{Policy, TdxExtractor, TDX_LAUNCH_PAYLOAD_TYPE};
+
+    /// Test vectors generated by `<git-repo>/tests/tdx/generate.py`
+    const TEST_VECTORS_DIR: &str = "../tests/tdx";
+
+    fn read_json(name: &str) -> Value {
+        serde_json::from_slice(&fs::read(format!("{}/{}", TEST_VECTORS_DIR, name)).unwrap())
+            .unwrap()
+    }
+
+    /// Sign the launch description with a new key, and get the
+    /// provenance and an Extractor trusting the key.
+    fn sign(launch: &Value) -> (Value, TdxExtractor) {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let key_path = dir.path().join("key.pub");
+        fs::write(&key_path, signer.public_key_pem()).unwrap();
+
+        let envelope = signer.sign(TDX_LAUNCH_PAYLOAD_TYPE, launch.to_string().as_bytes());
+        let extractor = TdxExtractor::with_policy(Policy {
+            trust: TrustPolicy::new(vec![key_path]),
+            artifact_dir: Some(TEST_VECTORS_DIR.into()),
+        })
+        .unwrap();
+        let provenance = json!({
+            "envelope": envelope,
+            "tdvf": { "path": "tdvf.fd" },
+            "kernel": { "path": "vmlinuz" },
+            "initrd": { "path": "initrd" },
+        });
+        (provenance, extractor)
+    }
+
+    #[test]
+    fn tdx_extractor() {
+        let m = read_json("measurements.json");
+        let (provenance, e) = sign(&read_json("launch.json"));
+        let rvs = e.verify_and_extract(&provenance.to_string()).unwrap();
+
+        let names: Vec<_> = rvs.iter().map(|rv| rv.name().as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "td-guest/mrtd",
+                "td-guest/rtmr0",
+                "td-guest/rtmr1",
+                "td-guest/rtmr2"
+            ]
+        );
+        for rv in &rvs {
+            let register = rv.name().trim_start_matches("td-guest/");
+            assert_eq!(rv.hash_values()[0].alg(), "sha384");
+            assert_eq!(rv.hash_values()[0].value(), &m[register]);
+        }
+    }
+
+    #[test]
+    fn tdx_extractor_rejects() {
+        let launch = read_json("launch.json");
+
+        // Component not matching the launch description
+        let (mut provenance, e) = sign(&launch);
+        provenance["kernel"] = json!({ "path": "initrd" });
+        assert!(e.verify_and_extract(&provenance.to_string()).is_err());
+
+        // Component not in the launch description
+        let mut l = launch.clone();
+        l.as_object_mut().unwrap().remove("initrd");
+        let (provenance, e) = sign(&l);
+        assert!(e.verify_and_extract(&provenance.to_string()).is_err());
+
+        // Invalid RTMR index
+        let mut l = launch.clone();
+        l["rtmrs"]["4"] = json!([{ "type": "separator" }]);
+        let (provenance, e) = sign(&l);
+        assert!(e.verify_and_extract(&provenance.to_string()).is_err());
+
+        // Kernel is not a PE image
+        let mut l = launch.clone();
+        l["kernel"] = l["initrd"].clone();
+        let (mut provenance, e) = sign(&l);
+        provenance["kernel"] = json!({ "path": "initrd" });
+        assert!(e.verify_and_extract(&provenance.to_string()).is_err());
+
+        // Untrusted key
+        let (provenance, _) = sign(&launch);
+        let (_, e) = sign(&launch);
+        assert!(e.verify_and_extract(&provenance.to_string()).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Authenticode hash of a PE/COFF image, e.g. an EFI stub kernel,
+//! which is how the firmware measures an EFI application, see the
+//! Windows Authenticode Portable Executable Signature Format.
+
+use anyhow::{anyhow, Result};
+use ring::digest::{Algorithm, Context};
+
+use crate::extractors::extractor_modules::firmware::{read_u16, read_u32};
+
+const PE32_MAGIC: u16 = 0x10b;
+const PE32_PLUS_MAGIC: u16 = 0x20b;
+
+/// Index of the certificate table in the data directories
+const CERTIFICATE_TABLE_INDEX: usize = 4;
+
+/// Size of a section header
+const SECTION_HEADER_SIZE: usize = 40;
+
+/// Calculate the Authenticode hash of the PE image, s.t. the hash of
+/// the headers without the checksum and the certificate table entry,
+/// the sections in the order of the file, and the rest of the file
+/// without the certificate table.
+pub fn authenticode(algorithm: &'static Algorithm, image: &[u8]) -> Result<Vec<u8>> {
+    if image.get(..2) != Some(b"MZ") {
+        return Err(anyhow!("Kernel is not a PE image."));
+    }
+    let pe_offset = read_u32(image, 0x3c)? as usize;
+    if image.get(pe_offset..pe_offset + 4) != Some(b"PE\0\0") {
+        return Err(anyhow!("PE signature not found."));
+    }
+
+    let coff = pe_offset + 4;
+    let num_sections = read_u16(image, coff + 2)? as usize;
+    let optional_header_size = read_u16(image, coff + 16)? as usize;
+    let optional = coff + 20;
+    let directories = match read_u16(image, optional)? {
+        PE32_MAGIC => optional + 92,
+        PE32_PLUS_MAGIC => optional + 108,
+        magic => return Err(anyhow!("Unknown PE optional header magic: {:#x}", magic)),
+    };
+    let checksum = optional + 64;
+    let headers_size = read_u32(image, optional + 60)? as usize;
+
+    let num_directories = read_u32(image, directories)? as usize;
+    let certificate_entry = directories + 4 + CERTIFICATE_TABLE_INDEX * 8;
+    let certificate_size = match num_directories > CERTIFICATE_TABLE_INDEX {
+        true => read_u32(image, certificate_entry + 4)? as usize,
+        false => 0,
+    };
+    if headers_size > image.len() || certificate_entry + 8 > headers_size {
+        return Err(anyhow!("Invalid PE headers size."));
+    }
+
+    let mut hash = Context::new(algorithm);
+    hash.update(&image[..checksum]);
+    match num_directories > CERTIFICATE_TABLE_INDEX {
+        true => {
+            hash.update(&image[checksum + 4..certificate_entry]);
+            hash.update(&image[certificate_entry + 8..headers_size]);
+        }
+        false => hash.update(&image[checksum + 4..headers_size]),
+    }
+
+    let section_table = optional + optional_header_size;
+    let mut sections = (0..num_sections)
+        .map(|i| {
+            let header = section_table + i * SECTION_HEADER_SIZE;
+            let size = read_u32(image, header + 16)? as usize;
+            let pointer = read_u32(image, header + 20)? as usize;
+            Ok((pointer, size))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    sections.sort_unstable();
+
+    let mut hashed = headers_size;
+    for (pointer, size) in sections.into_iter().filter(|(_, size)| *size != 0) {
+        let content = pointer
+            .checked_add(size)
+            .and_then(|end| image.get(pointer..end))
+            .ok_or_else(|| anyhow!("PE section is out of the image."))?;
+        hash.update(content);
+        hashed += size;
+    }
+
+    let end = image
+        .len()
+        .checked_sub(certificate_size)
+        .ok_or_else(|| anyhow!("Invalid PE certificate table size."))?;
+    if end > hashed {
+        hash.update(&image[hashed..end]);
+    }
+
+    Ok(hash.finish().as_ref().to_vec())
+}
@@ -0,0 +1,200 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Parser of the TDVF metadata, and calculation of the MRTD, s.t. the
+//! hash of TDH.MEM.PAGE.ADD and TDH.MR.EXTEND of the firmware pages,
+//! see the Intel TDX Virtual Firmware Design Guide and the Intel TDX
+//! Module Specification.
+
+use anyhow::{anyhow, Result};
+use ring::digest::{Context, SHA384};
+
+use crate::extractors::extractor_modules::firmware::{
+    guid_bytes, parse_footer_table, read_u32, read_u64,
+};
+
+const PAGE_SIZE: u64 = 4096;
+
+/// TDH.MR.EXTEND measures 256 bytes at a time
+const MR_EXTEND_GRANULARITY: usize = 256;
+
+/// Size of the buffer measured by TDH.MEM.PAGE.ADD and TDH.MR.EXTEND
+const MEASUREMENT_BUFFER_SIZE: usize = 128;
+
+/// Footer table entry of the offset from the end to the metadata
+const TDX_METADATA_OFFSET_GUID: &str = "e47a6535-984a-4798-865e-4685a7bf8ec2";
+
+/// GUID right before the metadata
+const TDX_METADATA_GUID: &str = "e9eaf9f3-168e-44d5-a8eb-7f4d8738f6ae";
+
+/// Signature of the metadata
+const TDX_METADATA_SIGNATURE: &[u8] = b"TDVF";
+
+/// Size of the metadata header, s.t. the signature, length, version
+/// and number of sections.
+const TDX_METADATA_HEADER_SIZE: usize = 16;
+
+/// Size of a metadata section
+const TDX_METADATA_SECTION_SIZE: usize = 32;
+
+/// The section is measured by TDH.MR.EXTEND
+const ATTRIBUTE_MR_EXTEND: u32 = 0x1;
+
+/// The section is added by TDH.MEM.PAGE.AUG after launch
+const ATTRIBUTE_PAGE_AUG: u32 = 0x2;
+
+/// Type of a section in the TDVF metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionType {
+    Bfv,
+    Cfv,
+    TdHob,
+    TempMem,
+    PermMem,
+    Payload,
+    PayloadParam,
+}
+
+impl SectionType {
+    fn from_u32(value: u32) -> Result<Self> {
+        match value {
+            0 => Ok(SectionType::Bfv),
+            1 => Ok(SectionType::Cfv),
+            2 => Ok(SectionType::TdHob),
+            3 => Ok(SectionType::TempMem),
+            4 => Ok(SectionType::PermMem),
+            5 => Ok(SectionType::Payload),
+            6 => Ok(SectionType::PayloadParam),
+            _ => Err(anyhow!("Unknown TDVF section type: {}", value)),
+        }
+    }
+}
+
+/// A section in the TDVF metadata.
+/// * `data_offset`, `raw_data_size`: the content in the image, if any.
+/// * `memory_address`, `memory_data_size`: where it is in the guest.
+#[derive(Debug)]
+pub struct Section {
+    pub data_offset: u32,
+    pub raw_data_size: u32,
+    pub memory_address: u64,
+    pub memory_data_size: u64,
+    pub section_type: SectionType,
+    pub attributes: u32,
+}
+
+/// TDVF firmware.
+pub struct Tdvf {
+    data: Vec<u8>,
+    sections: Vec<Section>,
+}
+
+impl Tdvf {
+    /// Parse the firmware. The metadata is found by the footer table.
+    pub fn new(data: Vec<u8>) -> Result<Self> {
+        let table = parse_footer_table(&data)?;
+        let entry = table
+            .get(&guid_bytes(TDX_METADATA_OFFSET_GUID)?)
+            .ok_or_else(|| anyhow!("TDX metadata not found in TDVF."))?;
+        let start = data
+            .len()
+            .checked_sub(read_u32(entry, 0)? as usize)
+            .filter(|start| *start >= 16)
+            .ok_or_else(|| anyhow!("Invalid TDX metadata offset."))?;
+        if data[start - 16..start] != guid_bytes(TDX_METADATA_GUID)? {
+            return Err(anyhow!("TDX metadata GUID not found."));
+        }
+
+        let header = data
+            .get(start..start + TDX_METADATA_HEADER_SIZE)
+            .ok_or_else(|| anyhow!("TDX metadata is truncated."))?;
+        if &header[..4] != TDX_METADATA_SIGNATURE {
+            return Err(anyhow!("Invalid TDX metadata signature."));
+        }
+        let length = read_u32(header, 4)? as usize;
+        let num_sections = read_u32(header, 12)? as usize;
+        if length < TDX_METADATA_HEADER_SIZE + num_sections * TDX_METADATA_SECTION_SIZE {
+            return Err(anyhow!("Invalid TDX metadata length."));
+        }
+
+        let mut sections = Vec::new();
+        for i in 0..num_sections {
+            let offset = start + TDX_METADATA_HEADER_SIZE + i * TDX_METADATA_SECTION_SIZE;
+            let section = Section {
+                data_offset: read_u32(&data, offset)?,
+                raw_data_size: read_u32(&data, offset + 4)?,
+                memory_address: read_u64(&data, offset + 8)?,
+                memory_data_size: read_u64(&data, offset + 16)?,
+                section_type: SectionType::from_u32(read_u32(&data, offset + 24)?)?,
+                attributes: read_u32(&data, offset + 28)?,
+            };
+
+            if section.memory_address % PAGE_SIZE != 0
+                || section.memory_data_size % PAGE_SIZE != 0
+                || section.raw_data_size as u64 > section.memory_data_size
+                || section.data_offset as usize + section.raw_data_size as usize > data.len()
+            {
+                return Err(anyhow!("Invalid TDVF section: {:?}", section));
+            }
+            sections.push(section);
+        }
+
+        Ok(Self { data, sections })
+    }
+
+    /// Sections in the TDVF metadata.
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    /// Calculate the MRTD of the TD launched with the firmware. Each
+    /// page is added and then extended if needed, in the order of the
+    /// sections. Permanent memory and pages added after launch are not
+    /// measured.
+    pub fn mrtd(&self) -> Result<Vec<u8>> {
+        let mut mrtd = Context::new(&SHA384);
+        for section in &self.sections {
+            if section.section_type == SectionType::PermMem
+                || section.attributes & ATTRIBUTE_PAGE_AUG != 0
+            {
+                continue;
+            }
+
+            // Only the content in the image can be extended, e.g. not
+            // the TD HOB built by the VMM
+            let extend = section.attributes & ATTRIBUTE_MR_EXTEND != 0;
+            if extend && section.raw_data_size as u64 != section.memory_data_size {
+                return Err(anyhow!("Unsupported measured TDVF section: {:?}", section));
+            }
+            let start = section.data_offset as usize;
+            let content = &self.data[start..start + section.raw_data_size as usize];
+
+            for offset in (0..section.memory_data_size).step_by(PAGE_SIZE as usize) {
+                let gpa = section.memory_address + offset;
+                mrtd.update(&measurement_buffer(b"MEM.PAGE.ADD", gpa));
+                if !extend {
+                    continue;
+                }
+
+                for chunk in (0..PAGE_SIZE).step_by(MR_EXTEND_GRANULARITY) {
+                    let chunk_offset = (offset + chunk) as usize;
+                    mrtd.update(&measurement_buffer(b"MR.EXTEND", gpa + chunk));
+                    mrtd.update(&content[chunk_offset..chunk_offset + MR_EXTEND_GRANULARITY]);
+                }
+            }
+        }
+
+        Ok(mrtd.finish().as_ref().to_vec())
+    }
+}
+
+/// Build the buffer measured by a TDX module function, s.t. the
+/// function name padded to 16 bytes, then the GPA.
+fn measurement_buffer(function: &[u8], gpa: u64) -> [u8; MEASUREMENT_BUFFER_SIZE] {
+    let mut buffer = [0u8; MEASUREMENT_BUFFER_SIZE];
+    buffer[..function.len()].copy_from_slice(function);
+    buffer[16..24].copy_from_slice(&gpa.to_le_bytes());
+    buffer
+}
@@ -0,0 +1,356 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! TUF metadata and its verification, see The Update Framework
+//! Specification v1.0 <https://theupdateframework.github.io/specification/latest/>.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use ring::digest::{digest, SHA256, SHA512};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::dsse::{ed25519_spki_der, KeyType, Verifier};
+
+/// Major version of the TUF specification supported
+pub const SPEC_MAJOR_VERSION: &str = "1";
+
+/// Serialize the value by the canonical JSON of OLPC, which TUF signs,
+/// s.t. object keys are sorted, no whitespace, and only `"` and `\`
+/// are escaped in strings.
+pub fn canonical_json(value: &Value) -> Result<String> {
+    Ok(match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) if n.is_f64() => {
+            return Err(anyhow!("Non-integer number is not canonical JSON."))
+        }
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => canonical_string(s),
+        Value::Array(array) => {
+            let elements = array
+                .iter()
+                .map(canonical_json)
+                .collect::<Result<Vec<_>>>()?;
+            format!("[{}]", elements.join(","))
+        }
+        Value::Object(object) => {
+            let mut members = object.iter().collect::<Vec<_>>();
+            members.sort_by(|a, b| a.0.cmp(b.0));
+            let members = members
+                .into_iter()
+                .map(|(k, v)| Ok(format!("{}:{}", canonical_string(k), canonical_json(v)?)))
+                .collect::<Result<Vec<_>>>()?;
+            format!("{{{}}}", members.join(","))
+        }
+    })
+}
+
+fn canonical_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Decode a hex string.
+pub fn unhex(s: &str) -> Result<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return Err(anyhow!("Malformed hex string: {}", s));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            s.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+                .ok_or_else(|| anyhow!("Malformed hex string: {}", s))
+        })
+        .collect()
+}
+
+/// A public key in TUF metadata.
+/// * `keytype`, `scheme`: one of `ed25519`/`ed25519`,
+/// `ecdsa`/`ecdsa-sha2-nistp256`, `ecdsa`/`ecdsa-sha2-nistp384`, and
+/// `rsa`/`rsassa-pss-sha256`. `ecdsa-sha2-nistp256` and
+/// `ecdsa-sha2-nistp384` are also accepted as key types.
+/// * `keyval`: the public key, hex encoded for Ed25519, and PEM
+/// encoded SubjectPublicKeyInfo for others.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Key {
+    pub keytype: String,
+    pub scheme: String,
+    pub keyval: KeyVal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KeyVal {
+    pub public: String,
+}
+
+impl Key {
+    /// Get a `Verifier` of the key.
+    pub fn verifier(&self) -> Result<Verifier> {
+        let (verifier, key_type) = match (self.keytype.as_str(), self.scheme.as_str()) {
+            ("ed25519", "ed25519") => {
+                let public_key = unhex(&self.keyval.public)?;
+                let verifier = Verifier::from_der(&ed25519_spki_der(&public_key))?;
+                (verifier, KeyType::Ed25519)
+            }
+            ("ecdsa" | "ecdsa-sha2-nistp256", "ecdsa-sha2-nistp256") => {
+                (Verifier::from_pem(&self.keyval.public)?, KeyType::EcdsaP256)
+            }
+            ("ecdsa" | "ecdsa-sha2-nistp384", "ecdsa-sha2-nistp384") => {
+                (Verifier::from_pem(&self.keyval.public)?, KeyType::EcdsaP384)
+            }
+            ("rsa", "rsassa-pss-sha256") => {
+                (Verifier::from_pem(&self.keyval.public)?, KeyType::RsaPss)
+            }
+            (keytype, scheme) => {
+                return Err(anyhow!(
+                    "Unsupported TUF key type {} with scheme {}.",
+                    keytype,
+                    scheme
+                ))
+            }
+        };
+
+        if verifier.key_type() != key_type {
+            return Err(anyhow!(
+                "TUF key does not match its scheme {}.",
+                self.scheme
+            ));
+        }
+        Ok(verifier)
+    }
+}
+
+/// Keys and threshold of a role.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Role {
+    pub keyids: Vec<String>,
+    pub threshold: usize,
+}
+
+/// Root metadata.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Root {
+    pub version: u64,
+    pub expires: DateTime<Utc>,
+    pub keys: HashMap<String, Key>,
+    pub roles: HashMap<String, Role>,
+}
+
+impl Root {
+    /// Get a top-level role.
+    pub fn role(&self, name: &str) -> Result<&Role> {
+        self.roles
+            .get(name)
+            .ok_or_else(|| anyhow!("Role {} not found in TUF root.", name))
+    }
+}
+
+/// Information of a metadata file in timestamp or snapshot metadata.
+/// `length` and `hashes` are optional.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MetaFile {
+    pub version: u64,
+    pub length: Option<usize>,
+    pub hashes: Option<BTreeMap<String, String>>,
+}
+
+impl MetaFile {
+    /// Check the length and hashes of the metadata file if given.
+    pub fn check(&self, name: &str, content: &[u8]) -> Result<()> {
+        if let Some(length) = self.length {
+            if content.len() != length {
+                return Err(anyhow!("Length of {} does not match.", name));
+            }
+        }
+        if let Some(hashes) = &self.hashes {
+            check_hashes(name, content, hashes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check the content against the hashes, of which at least one must
+/// be supported.
+fn check_hashes(name: &str, content: &[u8], hashes: &BTreeMap<String, String>) -> Result<()> {
+    let mut checked = false;
+    for (alg, value) in hashes {
+        let algorithm = match alg.as_str() {
+            "sha256" => &SHA256,
+            "sha512" => &SHA512,
+            _ => continue,
+        };
+        if unhex(value)? != digest(algorithm, content).as_ref() {
+            return Err(anyhow!("{} hash of {} does not match.", alg, name));
+        }
+        checked = true;
+    }
+    match checked {
+        true => Ok(()),
+        false => Err(anyhow!("No supported hash of {}.", name)),
+    }
+}
+
+/// Timestamp or snapshot metadata.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Meta {
+    pub version: u64,
+    pub expires: DateTime<Utc>,
+    pub meta: HashMap<String, MetaFile>,
+}
+
+impl Meta {
+    /// Get the information of a metadata file, e.g. `snapshot.json`.
+    pub fn file(&self, name: &str) -> Result<&MetaFile> {
+        self.meta
+            .get(name)
+            .ok_or_else(|| anyhow!("{} is not listed in TUF metadata.", name))
+    }
+}
+
+/// A target in targets metadata.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TargetFile {
+    pub length: u64,
+    pub hashes: BTreeMap<String, String>,
+}
+
+/// A delegated targets role.
+/// * `terminating`: if the role matches a target path, roles after
+/// it are not searched for the target.
+/// * `paths`, `path_hash_prefixes`: the target paths the role is
+/// trusted for, by glob patterns, or by prefixes of the hex encoded
+/// SHA-256 of the path.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DelegatedRole {
+    pub name: String,
+    pub keyids: Vec<String>,
+    pub threshold: usize,
+    #[serde(default)]
+    pub terminating: bool,
+    pub paths: Option<Vec<String>>,
+    pub path_hash_prefixes: Option<Vec<String>>,
+}
+
+impl DelegatedRole {
+    /// The keys and threshold of the role.
+    pub fn role(&self) -> Role {
+        Role {
+            keyids: self.keyids.clone(),
+            threshold: self.threshold,
+        }
+    }
+
+    /// Whether the role is trusted for the target path. A `*` in the
+    /// patterns does not match `/`.
+    pub fn matches(&self, path: &str) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        if let Some(paths) = &self.paths {
+            return paths.iter().any(|p| {
+                glob::Pattern::new(p)
+                    .map(|p| p.matches_with(path, options))
+                    .unwrap_or(false)
+            });
+        }
+        if let Some(prefixes) = &self.path_hash_prefixes {
+            let hash: String = digest(&SHA256, path.as_bytes())
+                .as_ref()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            return prefixes.iter().any(|p| hash.starts_with(p.as_str()));
+        }
+        false
+    }
+}
+
+/// Delegations of targets metadata.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Delegations {
+    pub keys: HashMap<String, Key>,
+    pub roles: Vec<DelegatedRole>,
+}
+
+/// Targets metadata.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Targets {
+    pub version: u64,
+    pub expires: DateTime<Utc>,
+    pub targets: BTreeMap<String, TargetFile>,
+    pub delegations: Option<Delegations>,
+}
+
+/// A signature of metadata, hex encoded.
+#[derive(Serialize, Deserialize, Debug)]
+struct Signature {
+    keyid: String,
+    sig: String,
+}
+
+/// A metadata file, s.t. the signed part and its signatures.
+#[derive(Serialize, Deserialize, Debug)]
+struct Metadata {
+    signed: Value,
+    signatures: Vec<Signature>,
+}
+
+/// Verify the metadata of type `role_type`, e.g. `timestamp`, which
+/// must be signed by at least `threshold` distinct keys of the role.
+/// If verification succeeds, the signed part is returned.
+pub fn verify<T: DeserializeOwned>(
+    content: &[u8],
+    role_type: &str,
+    keys: &HashMap<String, Key>,
+    role: &Role,
+) -> Result<T> {
+    let metadata: Metadata = serde_json::from_slice(content)?;
+    if metadata.signed["_type"] != role_type {
+        return Err(anyhow!("TUF metadata is not of type {}.", role_type));
+    }
+    let spec_version = metadata.signed["spec_version"].as_str().unwrap_or_default();
+    if spec_version.split('.').next() != Some(SPEC_MAJOR_VERSION) {
+        return Err(anyhow!("Unsupported TUF spec version: {}", spec_version));
+    }
+    if role.threshold == 0 {
+        return Err(anyhow!(
+            "Threshold of TUF role {} must be at least 1.",
+            role_type
+        ));
+    }
+
+    let message = canonical_json(&metadata.signed)?;
+    let mut signed = HashSet::new();
+    for keyid in &role.keyids {
+        let verifier = match keys.get(keyid).map(Key::verifier) {
+            Some(Ok(verifier)) => verifier,
+            _ => continue,
+        };
+        let valid = metadata
+            .signatures
+            .iter()
+            .filter(|s| s.keyid == *keyid)
+            .filter_map(|s| unhex(&s.sig).ok())
+            .any(|sig| verifier.verify_signature(message.as_bytes(), &sig).is_ok());
+        // The same key listed by several keyids only counts once
+        if valid {
+            signed.insert(verifier.keyid().to_string());
+        }
+    }
+
+    if signed.len() < role.threshold {
+        return Err(anyhow!(
+            "Only {} of the {} required keys signed the TUF {} metadata.",
+            signed.len(),
+            role.threshold,
+            role_type
+        ));
+    }
+    Ok(serde_json::from_value(metadata.signed)?)
+}
@@ -0,0 +1,648 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! # TUF Extractor
+//!
+//! This Extractor helps to verify the metadata of a repository of
+//! The Update Framework (TUF), s.t. root, timestamp, snapshot, targets
+//! and delegated targets, against a locally pinned root, and extract
+//! the hashes of the targets.
+
+pub mod metadata;
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::reference_value::{ReferenceValue, REFERENCE_VALUE_VERSION};
+
+use self::metadata::{Meta, Root, TargetFile, Targets};
+
+use super::{ConfigurableExtractor, Extractor};
+
+/// Max depth of delegations to follow from the top-level targets
+const MAX_DELEGATION_DEPTH: usize = 32;
+
+/// Provenance of TUF targets, s.t. the metadata files of the
+/// repository, each carried as the content of the file.
+/// * `roots`: the versions of root metadata after the pinned root, in
+/// order, if the root has been rotated.
+/// * `timestamp`, `snapshot`, `targets`: the top-level metadata.
+/// * `delegations`: metadata of the delegated targets roles, keyed by
+/// the role name. All the roles delegated to must be given.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Provenance {
+    #[serde(default)]
+    pub roots: Vec<String>,
+    pub timestamp: String,
+    pub snapshot: String,
+    pub targets: String,
+    #[serde(default)]
+    pub delegations: HashMap<String, String>,
+}
+
+/// Policy of the TUF Extractor, given by its section of
+/// the config file.
+/// * `root`: path of the pinned root metadata of the repository. If
+/// not set, all metadata is rejected.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Policy {
+    pub root: Option<PathBuf>,
+}
+
+/// Verified metadata of the delegated targets roles, keyed by the
+/// delegating role and the delegated role.
+type DelegatedTargets = HashMap<(String, String), Targets>;
+
+/// A target found in the metadata, with the name of the role listing
+/// it, and the expiry of that metadata.
+type Found<'a> = (&'a str, &'a TargetFile, DateTime<Utc>);
+
+pub struct TufExtractor {
+    root: Option<Root>,
+}
+
+impl TufExtractor {
+    /// Create a TUF Extractor with the given policy. The pinned root
+    /// must be signed by itself.
+    pub fn with_policy(policy: Policy) -> Result<Self> {
+        let root = match policy.root {
+            Some(path) => {
+                let content = fs::read(path)?;
+                let root: Root = serde_json::from_slice::<serde_json::Value>(&content)
+                    .ok()
+                    .and_then(|m| serde_json::from_value(m["signed"].clone()).ok())
+                    .ok_or_else(|| anyhow!("Malformed TUF root metadata."))?;
+                metadata::verify::<Root>(&content, "root", &root.keys, root.role("root")?)?;
+                Some(root)
+            }
+            None => None,
+        };
+        Ok(Self { root })
+    }
+}
+
+/// Reject the metadata if it has expired.
+fn check_expires(role: &str, expires: &DateTime<Utc>, now: &DateTime<Utc>) -> Result<()> {
+    match expires < now {
+        true => Err(anyhow!("TUF {} metadata expired at {}.", role, expires)),
+        false => Ok(()),
+    }
+}
+
+/// Check the version of the metadata against the one listed in the
+/// timestamp or snapshot metadata.
+fn check_version(role: &str, version: u64, expected: u64) -> Result<()> {
+    match version == expected {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "Version {} of TUF {} metadata does not match the listed version {}.",
+            version,
+            role,
+            expected
+        )),
+    }
+}
+
+/// Verify the metadata of the roles delegated to by `delegator`, and
+/// those delegated to by them in turn.
+fn load_delegations(
+    provenance: &Provenance,
+    snapshot: &Meta,
+    delegator: &str,
+    targets: &Targets,
+    depth: usize,
+    now: &DateTime<Utc>,
+    loaded: &mut DelegatedTargets,
+) -> Result<()> {
+    let delegations = match &targets.delegations {
+        Some(delegations) => delegations,
+        None => return Ok(()),
+    };
+    if depth >= MAX_DELEGATION_DEPTH {
+        return Err(anyhow!("TUF delegations are too deep."));
+    }
+
+    for role in &delegations.roles {
+        let key = (delegator.to_string(), role.name.clone());
+        if loaded.contains_key(&key) {
+            continue;
+        }
+        let content = provenance
+            .delegations
+            .get(&role.name)
+            .ok_or_else(|| anyhow!("Metadata of TUF delegated role {} is missing.", role.name))?;
+        let meta = snapshot.file(&format!("{}.json", role.name))?;
+        meta.check(&role.name, content.as_bytes())?;
+        let delegated: Targets = metadata::verify(
+            content.as_bytes(),
+            "targets",
+            &delegations.keys,
+            &role.role(),
+        )?;
+        check_version(&role.name, delegated.version, meta.version)?;
+        check_expires(&role.name, &delegated.expires, now)?;
+
+        load_delegations(
+            provenance,
+            snapshot,
+            &role.name,
+            &delegated,
+            depth + 1,
+            now,
+            loaded,
+        )?;
+        loaded.insert(key, delegated);
+    }
+    Ok(())
+}
+
+/// Find the target in the way a TUF client does, s.t. a pre-order
+/// depth-first search of the roles trusted for the path, from the
+/// top-level targets, which stops at a terminating role.
+fn find<'a>(
+    loaded: &'a DelegatedTargets,
+    role: &'a str,
+    targets: &'a Targets,
+    path: &str,
+    depth: usize,
+) -> Option<Found<'a>> {
+    if let Some(target) = targets.targets.get(path) {
+        return Some((role, target, targets.expires));
+    }
+    if depth >= MAX_DELEGATION_DEPTH {
+        return None;
+    }
+
+    for delegated in &targets.delegations.as_ref()?.roles {
+        if !delegated.matches(path) {
+            continue;
+        }
+        let key = (role.to_string(), delegated.name.clone());
+        if let Some(found) = loaded
+            .get(&key)
+            .and_then(|t| find(loaded, &delegated.name, t, path, depth + 1))
+        {
+            return Some(found);
+        }
+        if delegated.terminating {
+            return None;
+        }
+    }
+    None
+}
+
+impl ConfigurableExtractor for TufExtractor {
+    type Config = Policy;
+
+    fn with_config(config: Policy) -> Result<Self> {
+        Self::with_policy(config)
+    }
+}
+
+impl Extractor for TufExtractor {
+    /// TUF Extractor.
+    /// The provenance is the metadata of a TUF repository, which is
+    /// verified from the pinned root as a TUF client does. A reference
+    /// value is generated for each target, named by its path, expiring
+    /// with the targets metadata listing it.
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+        let provenance: Provenance = serde_json::from_str(provenance)?;
+        let mut root = self
+            .root
+            .clone()
+            .ok_or_else(|| anyhow!("No pinned TUF root."))?;
+        let now = Utc::now();
+
+        // Each new root must be signed by both the old and new root keys
+        for content in &provenance.roots {
+            let new_root: Root =
+                metadata::verify(content.as_bytes(), "root", &root.keys, root.role("root")?)?;
+            metadata::verify::<Root>(
+                content.as_bytes(),
+                "root",
+                &new_root.keys,
+                new_root.role("root")?,
+            )?;
+            check_version("root", new_root.version, root.version + 1)?;
+            root = new_root;
+        }
+        check_expires("root", &root.expires, &now)?;
+
+        let timestamp: Meta = metadata::verify(
+            provenance.timestamp.as_bytes(),
+            "timestamp",
+            &root.keys,
+            root.role("timestamp")?,
+        )?;
+        check_expires("timestamp", &timestamp.expires, &now)?;
+
+        let meta = timestamp.file("snapshot.json")?;
+        meta.check("snapshot", provenance.snapshot.as_bytes())?;
+        let snapshot: Meta = metadata::verify(
+            provenance.snapshot.as_bytes(),
+            "snapshot",
+            &root.keys,
+            root.role("snapshot")?,
+        )?;
+        check_version("snapshot", snapshot.version, meta.version)?;
+        check_expires("snapshot", &snapshot.expires, &now)?;
+
+        let meta = snapshot.file("targets.json")?;
+        meta.check("targets", provenance.targets.as_bytes())?;
+        let targets: Targets = metadata::verify(
+            provenance.targets.as_bytes(),
+            "targets",
+            &root.keys,
+            root.role("targets")?,
+        )?;
+        check_version("targets", targets.version, meta.version)?;
+        check_expires("targets", &targets.expires, &now)?;
+
+        let mut loaded = DelegatedTargets::new();
+        load_delegations(
+            &provenance,
+            &snapshot,
+            "targets",
+            &targets,
+            0,
+            &now,
+            &mut loaded,
+        )?;
+
+        let paths: BTreeSet<&String> = targets
+            .targets
+            .keys()
+            .chain(loaded.values().flat_map(|t| t.targets.keys()))
+            .collect();
+
+        let mut rvs = Vec::new();
+        for path in paths {
+            let (role, target, expires) = match find(&loaded, "targets", &targets, path, 0) {
+                Some(found) => found,
+                // Listed by a role not trusted for the path
+                None => continue,
+            };
+            let mut rv = ReferenceValue::new()
+                .set_name(path)
+                .set_version(REFERENCE_VALUE_VERSION)
+                .set_expired(expires)
+                .add_metadata("length", &target.length.to_string())
+                .add_metadata("role", role);
+            for (alg, value) in &target.hashes {
+                rv = rv.add_hash_value(alg.to_string(), value.to_lowercase());
+            }
+            rvs.push(rv);
+        }
+        Ok(rvs)
+    }
+
+    /// Major version of the TUF specification, s.t. any `1.x` of the
+    /// `spec_version` of the metadata.
+    fn payload_versions(&self) -> Vec<String> {
+        vec![metadata::SPEC_MAJOR_VERSION.into()]
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use chrono::{DateTime, Utc};
+    use ring::{
+        digest::{digest, SHA256},
+        rand::SystemRandom,
+        signature::{Ed25519KeyPair, KeyPair},
+    };
+    use serde_json::{json, Value};
+
+    use crate::extractors::extractor_modules::{artifact::hex, Extractor};
+
+    use super::{
+        metadata::{canonical_json, unhex},
+        Policy, TufExtractor,
+    };
+
+    struct Key {
+        key_pair: Ed25519KeyPair,
+        keyid: String,
+        key: Value,
+    }
+
+    impl Key {
+        fn generate() -> Self {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+            let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+            let key = json!({
+                "keytype": "ed25519",
+                "scheme": "ed25519",
+                "keyval": { "public": hex(key_pair.public_key().as_ref()) },
+            });
+            let keyid = hex(digest(&SHA256, canonical_json(&key).unwrap().as_bytes()).as_ref());
+            Self {
+                key_pair,
+                keyid,
+                key,
+            }
+        }
+    }
+
+    /// Sign the metadata, and get the content of the metadata file.
+    fn sign(signed: &Value, keys: &[&Key]) -> String {
+        let message = canonical_json(signed).unwrap();
+        let signatures: Vec<Value> = keys
+            .iter()
+            .map(|k| {
+                json!({
+                    "keyid": k.keyid,
+                    "sig": hex(k.key_pair.sign(message.as_bytes()).as_ref()),
+                })
+            })
+            .collect();
+        json!({ "signed": signed, "signatures": signatures }).to_string()
+    }
+
+    fn meta_file(content: &str, version: u64) -> Value {
+        json!({
+            "version": version,
+            "length": content.len(),
+            "hashes": { "sha256": hex(digest(&SHA256, content.as_bytes()).as_ref()) },
+        })
+    }
+
+    fn target(content: &str) -> Value {
+        meta_file(content, 0)
+            .as_object()
+            .map(|o| json!({ "length": o["length"], "hashes": o["hashes"] }))
+            .unwrap()
+    }
+
+    fn root(version: u64, root_key: &Key, online: &Key) -> Value {
+        json!({
+            "_type": "root",
+            "spec_version": "1.0.31",
+            "version": version,
+            "expires": "2100-01-01T00:00:00Z",
+            "consistent_snapshot": false,
+            "keys": {
+                root_key.keyid.clone(): root_key.key,
+                online.keyid.clone(): online.key,
+            },
+            "roles": {
+                "root": { "keyids": [root_key.keyid], "threshold": 1 },
+                "timestamp": { "keyids": [online.keyid], "threshold": 1 },
+                "snapshot": { "keyids": [online.keyid], "threshold": 1 },
+                "targets": { "keyids": [online.keyid], "threshold": 1 },
+            },
+        })
+    }
+
+    /// Build a repository whose root is rotated once, with a
+    /// terminating delegation `firmware` trusted for `firmware/*`, and
+    /// a delegation `others` trusted for all paths. Each signed part is
+    /// modified by `modify` with the role name before it is signed.
+    /// Get the provenance and an Extractor pinning the first root.
+    fn repository(modify: impl Fn(&str, &mut Value)) -> (Value, TufExtractor) {
+        let (root1, root2, online, delegated) = (
+            Key::generate(),
+            Key::generate(),
+            Key::generate(),
+            Key::generate(),
+        );
+        let signed = |role: &str, mut value: Value, keys: &[&Key]| {
+            modify(role, &mut value);
+            sign(&value, keys)
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let root_path = dir.path().join("root.json");
+        fs::write(&root_path, sign(&root(1, &root1, &online), &[&root1])).unwrap();
+        let root_v2 = signed("root", root(2, &root2, &online), &[&root1, &root2]);
+
+        let delegated_targets = |expires: &str, targets: Value| {
+            json!({
+                "_type": "targets",
+                "spec_version": "1.0.31",
+                "version": 1,
+                "expires": expires,
+                "targets": targets,
+            })
+        };
+        let firmware = signed(
+            "firmware",
+            delegated_targets(
+                "2099-01-01T00:00:00Z",
+                json!({
+                    "firmware/ovmf.fd": target("ovmf"),
+                    "firmware/sub/shim.efi": target("shim"),
+                    "kernel": target("bad kernel"),
+                }),
+            ),
+            &[&delegated],
+        );
+        let others = signed(
+            "others",
+            delegated_targets(
+                "2098-01-01T00:00:00Z",
+                json!({
+                    "firmware/ovmf.fd": target("bad ovmf"),
+                    "initrd": target("initrd"),
+                }),
+            ),
+            &[&delegated],
+        );
+        let targets = signed(
+            "targets",
+            json!({
+                "_type": "targets",
+                "spec_version": "1.0.31",
+                "version": 3,
+                "expires": "2100-01-01T00:00:00Z",
+                "targets": {
+                    "kernel": target("kernel"),
+                    "rootfs.img": target("rootfs"),
+                },
+                "delegations": {
+                    "keys": { delegated.keyid.clone(): delegated.key },
+                    "roles": [
+                        {
+                            "name": "firmware",
+                            "keyids": [delegated.keyid],
+                            "threshold": 1,
+                            "terminating": true,
+                            "paths": ["firmware/*"],
+                        },
+                        {
+                            "name": "others",
+                            "keyids": [delegated.keyid],
+                            "threshold": 1,
+                            "terminating": false,
+                            "paths": ["*", "firmware/*"],
+                        },
+                    ],
+                },
+            }),
+            &[&online],
+        );
+        let snapshot = signed(
+            "snapshot",
+            json!({
+                "_type": "snapshot",
+                "spec_version": "1.0.31",
+                "version": 5,
+                "expires": "2100-01-01T00:00:00Z",
+                "meta": {
+                    "targets.json": { "version": 3 },
+                    "firmware.json": meta_file(&firmware, 1),
+                    "others.json": { "version": 1 },
+                },
+            }),
+            &[&online],
+        );
+        let timestamp = signed(
+            "timestamp",
+            json!({
+                "_type": "timestamp",
+                "spec_version": "1.0.31",
+                "version": 7,
+                "expires": "2100-01-01T00:00:00Z",
+                "meta": { "snapshot.json": meta_file(&snapshot, 5) },
+            }),
+            &[&online],
+        );
+
+        let extractor = TufExtractor::with_policy(Policy {
+            root: Some(root_path),
+        })
+        .unwrap();
+        let provenance = json!({
+            "roots": [root_v2],
+            "timestamp": timestamp,
+            "snapshot": snapshot,
+            "targets": targets,
+            "delegations": { "firmware": firmware, "others": others },
+        });
+        (provenance, extractor)
+    }
+
+    #[test]
+    fn canonical_json_of_olpc() {
+        let value = json!({ "b": "a\"\\\n", "a": [1, true, null], "": {} });
+        assert_eq!(
+            canonical_json(&value).unwrap(),
+            "{\"\":{},\"a\":[1,true,null],\"b\":\"a\\\"\\\\\n\"}"
+        );
+        assert!(canonical_json(&json!(1.5)).is_err());
+        assert_eq!(unhex("00ff").unwrap(), vec![0, 255]);
+        assert!(unhex("0g").is_err());
+    }
+
+    #[test]
+    fn tuf_extractor() {
+        let (provenance, e) = repository(|_, _| {});
+        let rvs = e.verify_and_extract(&provenance.to_string()).unwrap();
+
+        let expected = [
+            (
+                "firmware/ovmf.fd",
+                "ovmf",
+                "firmware",
+                "2099-01-01T00:00:00Z",
+            ),
+            ("initrd", "initrd", "others", "2098-01-01T00:00:00Z"),
+            ("kernel", "kernel", "targets", "2100-01-01T00:00:00Z"),
+            ("rootfs.img", "rootfs", "targets", "2100-01-01T00:00:00Z"),
+        ];
+        assert_eq!(rvs.len(), expected.len());
+        for (rv, (name, content, role, expires)) in rvs.iter().zip(expected) {
+            assert_eq!(rv.name(), name);
+            assert_eq!(rv.hash_values()[0].alg(), "sha256");
+            assert_eq!(
+                rv.hash_values()[0].value(),
+                &hex(digest(&SHA256, content.as_bytes()).as_ref())
+            );
+            assert_eq!(rv.metadata()["role"], role);
+            assert_eq!(rv.metadata()["length"], content.len().to_string());
+            assert_eq!(rv.expired(), &expires.parse::<DateTime<Utc>>().unwrap());
+        }
+    }
+
+    #[test]
+    fn tuf_extractor_rejects() {
+        let cases: Vec<fn(&str, &mut Value)> = vec![
+            // Root version is not the next one
+            |role, v| {
+                if role == "root" {
+                    v["version"] = json!(3);
+                }
+            },
+            // Snapshot not matching the timestamp
+            |role, v| {
+                if role == "timestamp" {
+                    v["meta"]["snapshot.json"]["hashes"]["sha256"] = json!("00".repeat(32));
+                }
+            },
+            // Targets version not matching the snapshot
+            |role, v| {
+                if role == "snapshot" {
+                    v["meta"]["targets.json"]["version"] = json!(2);
+                }
+            },
+            // Delegated role not listed in the snapshot
+            |role, v| {
+                if role == "snapshot" {
+                    v["meta"].as_object_mut().unwrap().remove("others.json");
+                }
+            },
+            // Expired metadata
+            |role, v| {
+                if role == "firmware" {
+                    v["expires"] = json!("2000-01-01T00:00:00Z");
+                }
+            },
+            // Unsupported spec version
+            |role, v| {
+                if role == "targets" {
+                    v["spec_version"] = json!("2.0.0");
+                }
+            },
+        ];
+        for modify in cases {
+            let (provenance, e) = repository(modify);
+            assert!(e.verify_and_extract(&provenance.to_string()).is_err());
+        }
+
+        let (provenance, e) = repository(|_, _| {});
+
+        // Tampered timestamp
+        let mut p = provenance.clone();
+        let mut timestamp: Value = serde_json::from_str(p["timestamp"].as_str().unwrap()).unwrap();
+        timestamp["signed"]["version"] = json!(8);
+        p["timestamp"] = json!(timestamp.to_string());
+        assert!(e.verify_and_extract(&p.to_string()).is_err());
+
+        // New root not signed by the old root key
+        let mut p = provenance.clone();
+        let mut root: Value = serde_json::from_str(p["roots"][0].as_str().unwrap()).unwrap();
+        root["signatures"].as_array_mut().unwrap().remove(0);
+        p["roots"][0] = json!(root.to_string());
+        assert!(e.verify_and_extract(&p.to_string()).is_err());
+
+        // Missing delegated role
+        let mut p = provenance.clone();
+        p["delegations"].as_object_mut().unwrap().remove("others");
+        assert!(e.verify_and_extract(&p.to_string()).is_err());
+
+        // No pinned root
+        let e = TufExtractor::with_policy(Policy::default()).unwrap();
+        assert!(e.verify_and_extract(&provenance.to_string()).is_err());
+    }
+}
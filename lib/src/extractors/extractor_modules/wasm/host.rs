@@ -0,0 +1,145 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Host functions imported by WASM plugins from module `rvps`. They
+//! only read and write the linear memory of the calling plugin, and
+//! trap if a buffer is out of its bounds.
+
+use anyhow::{anyhow, Result};
+use log::debug;
+use ring::digest::{digest as ring_digest, SHA256, SHA384, SHA512};
+use wasmi::{core::Trap, Caller, Extern, Linker, Memory, StoreLimits};
+
+use crate::dsse::Verifier;
+
+/// Name of the module of the host functions
+pub const HOST_MODULE: &str = "rvps";
+
+/// State of a plugin instance kept by the host.
+/// * `limits`: resource limits of the plugin instance.
+pub struct HostState {
+    pub limits: StoreLimits,
+}
+
+/// Calculate the digest of `data` by the algorithm `alg`, one of
+/// `sha256`, `sha384` and `sha512`, or `None` if not supported.
+pub fn digest(alg: &str, data: &[u8]) -> Option<Vec<u8>> {
+    let algorithm = match alg {
+        "sha256" => &SHA256,
+        "sha384" => &SHA384,
+        "sha512" => &SHA512,
+        _ => return None,
+    };
+    Some(ring_digest(algorithm, data).as_ref().to_vec())
+}
+
+/// Whether `sig` is a valid signature of `message` by the PEM encoded
+/// public key, of any type supported by [`Verifier`].
+pub fn verify_signature(public_key: &[u8], message: &[u8], sig: &[u8]) -> bool {
+    std::str::from_utf8(public_key)
+        .map_err(|e| anyhow!(e))
+        .and_then(Verifier::from_pem)
+        .and_then(|verifier| verifier.verify_signature(message, sig))
+        .is_ok()
+}
+
+/// Get the exported memory of the calling plugin.
+fn memory(caller: &Caller<'_, HostState>) -> Result<Memory, Trap> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| Trap::new("Plugin does not export memory."))
+}
+
+/// Read `len` bytes at `ptr` from the memory of the calling plugin.
+/// The buffer is checked against the memory before anything is
+/// allocated by the host.
+fn read(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Result<Vec<u8>, Trap> {
+    let out_of_bounds = || Trap::new("Buffer is out of the plugin memory.");
+    let start = usize::try_from(ptr).map_err(|_| out_of_bounds())?;
+    let len = usize::try_from(len).map_err(|_| out_of_bounds())?;
+    let end = start.checked_add(len).ok_or_else(out_of_bounds)?;
+    memory(caller)?
+        .data(caller)
+        .get(start..end)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(out_of_bounds)
+}
+
+/// Write `data` at `ptr` to the memory of the calling plugin.
+fn write(caller: &mut Caller<'_, HostState>, ptr: i32, data: &[u8]) -> Result<(), Trap> {
+    memory(caller)?
+        .write(caller, ptr as u32 as usize, data)
+        .map_err(|e| Trap::new(e.to_string()))
+}
+
+/// Define the host functions in the linker.
+/// * `digest(alg_ptr, alg_len, data_ptr, data_len, out_ptr) -> i32`
+/// writes the digest of the data to `out_ptr`, which must have room
+/// for 64 bytes, and returns its length, or -1 if the algorithm is not
+/// supported.
+/// * `verify_signature(key_ptr, key_len, msg_ptr, msg_len, sig_ptr,
+/// sig_len) -> i32` returns 1 if the signature of the message is valid
+/// by the PEM encoded public key, or 0 otherwise.
+/// * `log(ptr, len)` logs the message at debug level.
+pub fn define(linker: &mut Linker<HostState>) -> Result<()> {
+    linker
+        .func_wrap(
+            HOST_MODULE,
+            "digest",
+            |mut caller: Caller<'_, HostState>,
+             alg_ptr: i32,
+             alg_len: i32,
+             data_ptr: i32,
+             data_len: i32,
+             out_ptr: i32|
+             -> Result<i32, Trap> {
+                let alg = read(&caller, alg_ptr, alg_len)?;
+                let data = read(&caller, data_ptr, data_len)?;
+                match digest(&String::from_utf8_lossy(&alg), &data) {
+                    Some(value) => {
+                        write(&mut caller, out_ptr, &value)?;
+                        Ok(value.len() as i32)
+                    }
+                    None => Ok(-1),
+                }
+            },
+        )
+        .map_err(|e| anyhow!("Define host function failed: {}", e))?;
+
+    linker
+        .func_wrap(
+            HOST_MODULE,
+            "verify_signature",
+            |caller: Caller<'_, HostState>,
+             key_ptr: i32,
+             key_len: i32,
+             msg_ptr: i32,
+             msg_len: i32,
+             sig_ptr: i32,
+             sig_len: i32|
+             -> Result<i32, Trap> {
+                let key = read(&caller, key_ptr, key_len)?;
+                let message = read(&caller, msg_ptr, msg_len)?;
+                let sig = read(&caller, sig_ptr, sig_len)?;
+                Ok(verify_signature(&key, &message, &sig) as i32)
+            },
+        )
+        .map_err(|e| anyhow!("Define host function failed: {}", e))?;
+
+    linker
+        .func_wrap(
+            HOST_MODULE,
+            "log",
+            |caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<(), Trap> {
+                let message = read(&caller, ptr, len)?;
+                debug!("WASM plugin: {}", String::from_utf8_lossy(&message));
+                Ok(())
+            },
+        )
+        .map_err(|e| anyhow!("Define host function failed: {}", e))?;
+
+    Ok(())
+}
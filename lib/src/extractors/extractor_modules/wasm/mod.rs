@@ -0,0 +1,434 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! # WASM Plugin Extractor
+//!
+//! This Extractor runs Extractors of other provenance formats compiled
+//! to WebAssembly, s.t. new formats can be supported without rebuilding
+//! the RVPS. A plugin runs in a fresh sandbox for each provenance,
+//! limited in memory and fuel, and can only reach the host through the
+//! functions in [`host`].
+
+pub mod host;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use wasmi::{Config as EngineConfig, Engine, Linker, Module, Store, StoreLimitsBuilder};
+
+use crate::reference_value::ReferenceValue;
+
+use self::host::HostState;
+
+use super::Extractor;
+
+/// File extension of the plugins
+pub const PLUGIN_EXTENSION: &str = "wasm";
+
+/// Config of the WASM plugins, s.t. the `wasm` section of the config
+/// file of RVPS.
+/// * `plugin_dir`: directory of the plugins. Each `<type>.wasm` in it
+/// is loaded as the Extractor of provenance type `<type>`.
+/// * `max_memory`: max size in bytes of the linear memory of a plugin.
+/// Defaults to 64 MiB.
+/// * `fuel`: fuel to process a provenance, roughly the number of
+/// instructions a plugin may execute. Defaults to 100,000,000.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub plugin_dir: PathBuf,
+    #[serde(default = "default_max_memory")]
+    pub max_memory: usize,
+    #[serde(default = "default_fuel")]
+    pub fuel: u64,
+}
+
+/// Use to set default max memory of Config
+fn default_max_memory() -> usize {
+    64 << 20
+}
+
+/// Use to set default fuel of Config
+fn default_fuel() -> u64 {
+    100_000_000
+}
+
+/// Output of a plugin, either the reference values, or why the
+/// provenance is rejected.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum Output {
+    ReferenceValues(Vec<ReferenceValue>),
+    Error { error: String },
+}
+
+/// A compiled plugin, shared by its Extractor instances.
+struct Plugin {
+    engine: Engine,
+    module: Module,
+    linker: Linker<HostState>,
+    max_memory: usize,
+    fuel: u64,
+}
+
+#[derive(Clone)]
+pub struct WasmExtractor {
+    plugin: Arc<Plugin>,
+}
+
+impl WasmExtractor {
+    /// Compile the plugin in WebAssembly binary format, limited by the
+    /// memory and fuel in the config.
+    pub fn new(wasm: &[u8], config: &Config) -> Result<Self> {
+        let mut engine_config = EngineConfig::default();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config);
+        let module =
+            Module::new(&engine, wasm).map_err(|e| anyhow!("Compile plugin failed: {}", e))?;
+        let mut linker = Linker::new(&engine);
+        host::define(&mut linker)?;
+
+        Ok(Self {
+            plugin: Arc::new(Plugin {
+                engine,
+                module,
+                linker,
+                max_memory: config.max_memory,
+                fuel: config.fuel,
+            }),
+        })
+    }
+
+    /// Read and compile the plugin file.
+    pub fn from_file<P: AsRef<Path>>(path: P, config: &Config) -> Result<Self> {
+        Self::new(&fs::read(path)?, config)
+    }
+
+    /// Run the plugin on the provenance in a new instance, and get the
+    /// output of the plugin.
+    fn run(&self, provenance: &[u8]) -> Result<Vec<u8>> {
+        let plugin = &self.plugin;
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(plugin.max_memory)
+            .memories(1)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&plugin.engine, HostState { limits });
+        store.limiter(|state| &mut state.limits);
+        store
+            .add_fuel(plugin.fuel)
+            .map_err(|e| anyhow!("Add fuel failed: {}", e))?;
+
+        let instance = plugin
+            .linker
+            .instantiate(&mut store, &plugin.module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|e| anyhow!("Instantiate plugin failed: {}", e))?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| anyhow!("Plugin does not export memory."))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&store, "alloc")
+            .map_err(|e| anyhow!("Plugin does not export alloc: {}", e))?;
+        let extract = instance
+            .get_typed_func::<(i32, i32), i64>(&store, "extract")
+            .map_err(|e| anyhow!("Plugin does not export extract: {}", e))?;
+
+        let len = i32::try_from(provenance.len())?;
+        let ptr = alloc
+            .call(&mut store, len)
+            .map_err(|e| anyhow!("Plugin failed: {}", e))?;
+        memory
+            .write(&mut store, ptr as u32 as usize, provenance)
+            .map_err(|e| anyhow!("Write provenance to plugin failed: {}", e))?;
+        let output = extract
+            .call(&mut store, (ptr, len))
+            .map_err(|e| anyhow!("Plugin failed: {}", e))? as u64;
+
+        let (ptr, len) = ((output >> 32) as usize, (output & 0xffff_ffff) as usize);
+        memory
+            .data(&store)
+            .get(ptr..ptr + len)
+            .map(|output| output.to_vec())
+            .ok_or_else(|| anyhow!("Output of plugin is out of its memory."))
+    }
+}
+
+impl Extractor for WasmExtractor {
+    /// WASM Plugin Extractor.
+    /// The provenance is passed to `extract` of the plugin, which
+    /// returns either the reference values, or why the provenance is
+    /// rejected, in JSON.
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+        let output = self.run(provenance.as_bytes())?;
+        match serde_json::from_slice(&output)? {
+            Output::ReferenceValues(rvs) => Ok(rvs),
+            Output::Error { error } => Err(anyhow!("Plugin rejected provenance: {}", error)),
+        }
+    }
+}
+
+/// Load the plugins in the directory of the config, named by their
+/// provenance types. A plugin that fails to compile is skipped.
+pub fn load_plugins_with_config(config: &Config) -> Result<Vec<(String, WasmExtractor)>> {
+    let mut plugins = Vec::new();
+    for entry in fs::read_dir(&config.plugin_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(PLUGIN_EXTENSION) {
+            continue;
+        }
+        let name = match path.file_stem().and_then(|s| s.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        match WasmExtractor::from_file(&path, config) {
+            Ok(extractor) => {
+                info!("Load WASM plugin {} from {:?}", name, path);
+                plugins.push((name, extractor));
+            }
+            Err(e) => warn!("Load WASM plugin {:?} failed: {}", path, e),
+        }
+    }
+    plugins.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(plugins)
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use ring::digest::{digest, SHA256};
+    use serde_json::json;
+
+    use crate::{
+        extractors::{extractor_modules::Extractor, Extractors, ExtractorsAPI},
+        Message, RVPSError,
+    };
+
+    use super::{load_plugins_with_config, Config, WasmExtractor};
+
+    /// Bump allocator growing the memory on demand, shared by the
+    /// test plugins
+    const ALLOC: &str = r#"
+        (memory (export "memory") 1)
+        (global $next (mut i32) (i32.const 4096))
+        (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (local.get $ptr) (local.get $len)))
+            (if (i32.gt_u (global.get $next) (i32.shl (memory.size) (i32.const 16)))
+                (then
+                    (if (i32.eq
+                            (memory.grow (i32.add
+                                (i32.shr_u
+                                    (i32.sub (global.get $next) (i32.shl (memory.size) (i32.const 16)))
+                                    (i32.const 16))
+                                (i32.const 1)))
+                            (i32.const -1))
+                        (then unreachable))))
+            (local.get $ptr))
+    "#;
+
+    /// Plugin returning the provenance as its output
+    fn echo() -> Vec<u8> {
+        wat::parse_str(format!(
+            r#"(module {}
+                (func (export "extract") (param $ptr i32) (param $len i32) (result i64)
+                    (i64.or
+                        (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                        (i64.extend_i32_u (local.get $len)))))"#,
+            ALLOC
+        ))
+        .unwrap()
+    }
+
+    /// Plugin returning a reference value of the SHA-256 digest of the
+    /// provenance, calculated by the host
+    fn sha256() -> Vec<u8> {
+        let prefix = r#"[{"name":"wasm","expired":"2030-01-01T00:00:00Z","hash-value":[{"alg":"sha256","value":""#;
+        let output = format!("{}{}\"}}]}}]", prefix, "0".repeat(64));
+        wat::parse_str(format!(
+            r#"(module
+                (import "rvps" "digest" (func $digest (param i32 i32 i32 i32 i32) (result i32)))
+                {alloc}
+                (data (i32.const 0) "sha256")
+                (data (i32.const 8) "0123456789abcdef")
+                (data (i32.const 128) "{output}")
+                (func (export "extract") (param $ptr i32) (param $len i32) (result i64)
+                    (local $i i32)
+                    (local $b i32)
+                    (if (i32.ne
+                            (call $digest (i32.const 0) (i32.const 6) (local.get $ptr) (local.get $len) (i32.const 32))
+                            (i32.const 32))
+                        (then unreachable))
+                    (loop $hex
+                        (local.set $b (i32.load8_u (i32.add (i32.const 32) (local.get $i))))
+                        (i32.store8
+                            (i32.add (i32.const {value}) (i32.shl (local.get $i) (i32.const 1)))
+                            (i32.load8_u (i32.add (i32.const 8) (i32.shr_u (local.get $b) (i32.const 4)))))
+                        (i32.store8
+                            (i32.add (i32.const {value_1}) (i32.shl (local.get $i) (i32.const 1)))
+                            (i32.load8_u (i32.add (i32.const 8) (i32.and (local.get $b) (i32.const 15)))))
+                        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                        (br_if $hex (i32.lt_u (local.get $i) (i32.const 32))))
+                    (i64.or (i64.shl (i64.const 128) (i64.const 32)) (i64.const {len}))))"#,
+            alloc = ALLOC,
+            output = output.replace('"', "\\\""),
+            value = 128 + prefix.len(),
+            value_1 = 128 + prefix.len() + 1,
+            len = output.len(),
+        ))
+        .unwrap()
+    }
+
+    /// Plugin that never returns
+    fn spin() -> Vec<u8> {
+        wat::parse_str(format!(
+            r#"(module {}
+                (func (export "extract") (param i32 i32) (result i64)
+                    (loop $spin (br $spin))
+                    (i64.const 0)))"#,
+            ALLOC
+        ))
+        .unwrap()
+    }
+
+    /// Plugin logging a buffer of `len` bytes at `ptr`
+    fn log(ptr: i32, len: i32) -> Vec<u8> {
+        wat::parse_str(format!(
+            r#"(module
+                (import "rvps" "log" (func $log (param i32 i32)))
+                {}
+                (func (export "extract") (param i32 i32) (result i64)
+                    (call $log (i32.const {}) (i32.const {}))
+                    (i64.const 0)))"#,
+            ALLOC, ptr, len
+        ))
+        .unwrap()
+    }
+
+    fn config() -> Config {
+        Config {
+            plugin_dir: "/nonexistent".into(),
+            max_memory: 1 << 20,
+            fuel: 10_000_000,
+        }
+    }
+
+    #[test]
+    fn wasm_extractor() {
+        let rvs = json!([{
+            "name": "foo",
+            "expired": "2030-01-01T00:00:00Z",
+            "hash-value": [{ "alg": "sha256", "value": "abcd" }],
+        }]);
+        let e = WasmExtractor::new(&echo(), &config()).unwrap();
+        let extracted = e.verify_and_extract(&rvs.to_string()).unwrap();
+        assert_eq!(extracted.len(), 1);
+        assert_eq!(extracted[0].name(), "foo");
+        assert_eq!(extracted[0].hash_values()[0].value(), "abcd");
+
+        // Rejected by the plugin
+        let rejected = json!({ "error": "bad signature" }).to_string();
+        let err = e.verify_and_extract(&rejected).unwrap_err();
+        assert!(err.to_string().contains("bad signature"));
+
+        // Digest calculated by the host function
+        let e = WasmExtractor::new(&sha256(), &config()).unwrap();
+        let extracted = e.verify_and_extract("provenance").unwrap();
+        let expected: String = digest(&SHA256, b"provenance")
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        assert_eq!(extracted[0].name(), "wasm");
+        assert_eq!(extracted[0].hash_values()[0].value(), &expected);
+    }
+
+    #[test]
+    fn wasm_extractor_limits() {
+        // Out of fuel
+        let e = WasmExtractor::new(&spin(), &config()).unwrap();
+        assert!(e.verify_and_extract("[]").is_err());
+
+        // Out of memory
+        let e = WasmExtractor::new(&echo(), &config()).unwrap();
+        let large = format!("[\"{}\"]", "a".repeat(2 << 20));
+        assert!(e.verify_and_extract(&large).is_err());
+
+        // Initial memory over the limit
+        let config = Config {
+            max_memory: 0,
+            ..config()
+        };
+        let e = WasmExtractor::new(&echo(), &config).unwrap();
+        assert!(e.verify_and_extract("[]").is_err());
+    }
+
+    #[test]
+    fn wasm_host_buffer_bounds() {
+        // Buffers out of the plugin memory trap before the host
+        // allocates anything
+        for (ptr, len) in [(0, -1), (-1, 1), (0, 65537), (65535, 2)] {
+            let e = WasmExtractor::new(&log(ptr, len), &config()).unwrap();
+            let err = e.verify_and_extract("[]").unwrap_err();
+            assert!(format!("{:#}", err).contains("out of the plugin memory"));
+        }
+    }
+
+    #[test]
+    fn wasm_load_plugins() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("echo.wasm"), echo()).unwrap();
+        fs::write(dir.path().join("broken.wasm"), b"not wasm").unwrap();
+        fs::write(dir.path().join("README"), b"not a plugin").unwrap();
+
+        let config = Config {
+            plugin_dir: dir.path().into(),
+            ..config()
+        };
+        let plugins = load_plugins_with_config(&config).unwrap();
+        assert_eq!(plugins.len(), 1);
+        assert_eq!(plugins[0].0, "echo");
+        assert!(plugins[0].1.verify_and_extract("[]").unwrap().is_empty());
+    }
+
+    #[test]
+    fn wasm_configure_plugins() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("echo.wasm"), echo()).unwrap();
+        fs::write(dir.path().join("in-toto.wasm"), echo()).unwrap();
+
+        let mut config = crate::Config {
+            wasm: Some(Config {
+                plugin_dir: dir.path().into(),
+                ..config()
+            }),
+            ..Default::default()
+        };
+        let mut e = Extractors::new();
+        e.configure(&config).unwrap();
+        assert!(e.process(Message::new("echo", "[]")).unwrap().is_empty());
+
+        // Built-in Extractors are not overridden
+        #[cfg(feature = "in-toto")]
+        assert!(e.process(Message::new("in-toto", "[]")).is_err());
+
+        // Plugins loaded before are dropped when reconfigured
+        config.wasm = None;
+        e.configure(&config).unwrap();
+        let err = e.process(Message::new("echo", "[]")).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RVPSError>(),
+            Some(&RVPSError::UnsupportedProvenanceType("echo".into()))
+        );
+    }
+}
@@ -0,0 +1,260 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Offline validation of the certificate chains of manifest signers,
+//! see RFC 5280, and verification of their signatures.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use ring::{
+    digest::{digest, SHA256, SHA384, SHA512},
+    signature::{self, UnparsedPublicKey, VerificationAlgorithm},
+};
+use x509_parser::{certificate::X509Certificate, prelude::FromDer, time::ASN1Time};
+
+/// Max length of a certificate chain
+const MAX_CHAIN_LENGTH: usize = 8;
+
+const RSA_ENCRYPTION_OID: &str = "1.2.840.113549.1.1.1";
+
+const EC_PUBLIC_KEY_OID: &str = "1.2.840.10045.2.1";
+
+const ANY_EXTENDED_KEY_USAGE_OID: &str = "2.5.29.37.0";
+
+/// Length of uncompressed P-256 and P-384 public keys
+const P256_PUBLIC_KEY_LEN: usize = 65;
+const P384_PUBLIC_KEY_LEN: usize = 97;
+
+/// Hash algorithm of a signature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hash {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl Hash {
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        let algorithm = match self {
+            Hash::Sha256 => &SHA256,
+            Hash::Sha384 => &SHA384,
+            Hash::Sha512 => &SHA512,
+        };
+        digest(algorithm, data).as_ref().to_vec()
+    }
+}
+
+/// Signature scheme of a manifest signature.
+/// * `RsaPkcs1`: RSASSA-PKCS1-v1_5, 2048 to 8192 bits.
+/// * `RsaPss`: RSASSA-PSS with MGF1 of the same hash, 2048 to 8192 bits.
+/// * `Ecdsa`: ECDSA over P-256 or P-384, either ASN.1 DER encoded or
+/// fixed-length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    RsaPkcs1(Hash),
+    RsaPss(Hash),
+    Ecdsa(Hash),
+}
+
+/// The verified signing certificate.
+/// * `subject`: subject of the certificate.
+/// * `not_after`: the earliest end of validity of the certificates in
+/// the chain, s.t. when the chain expires.
+pub struct SigningCertificate {
+    pub subject: String,
+    pub not_after: DateTime<Utc>,
+    key_algorithm: String,
+    public_key: Vec<u8>,
+}
+
+impl SigningCertificate {
+    /// Get the verification algorithm of the signature, by the scheme
+    /// and the public key.
+    fn algorithm(&self, scheme: Scheme, sig: &[u8]) -> Result<&'static dyn VerificationAlgorithm> {
+        use self::{Hash::*, Scheme::*};
+
+        let key_len = self.public_key.len();
+        Ok(match (self.key_algorithm.as_str(), scheme) {
+            (RSA_ENCRYPTION_OID, RsaPkcs1(Sha256)) => &signature::RSA_PKCS1_2048_8192_SHA256,
+            (RSA_ENCRYPTION_OID, RsaPkcs1(Sha384)) => &signature::RSA_PKCS1_2048_8192_SHA384,
+            (RSA_ENCRYPTION_OID, RsaPkcs1(Sha512)) => &signature::RSA_PKCS1_2048_8192_SHA512,
+            (RSA_ENCRYPTION_OID, RsaPss(Sha256)) => &signature::RSA_PSS_2048_8192_SHA256,
+            (RSA_ENCRYPTION_OID, RsaPss(Sha384)) => &signature::RSA_PSS_2048_8192_SHA384,
+            (RSA_ENCRYPTION_OID, RsaPss(Sha512)) => &signature::RSA_PSS_2048_8192_SHA512,
+            (EC_PUBLIC_KEY_OID, Ecdsa(Sha256)) if key_len == P256_PUBLIC_KEY_LEN => {
+                match sig.len() {
+                    64 => &signature::ECDSA_P256_SHA256_FIXED,
+                    _ => &signature::ECDSA_P256_SHA256_ASN1,
+                }
+            }
+            (EC_PUBLIC_KEY_OID, Ecdsa(Sha384)) if key_len == P256_PUBLIC_KEY_LEN => {
+                &signature::ECDSA_P256_SHA384_ASN1
+            }
+            (EC_PUBLIC_KEY_OID, Ecdsa(Sha256)) if key_len == P384_PUBLIC_KEY_LEN => {
+                &signature::ECDSA_P384_SHA256_ASN1
+            }
+            (EC_PUBLIC_KEY_OID, Ecdsa(Sha384)) if key_len == P384_PUBLIC_KEY_LEN => {
+                match sig.len() {
+                    96 => &signature::ECDSA_P384_SHA384_FIXED,
+                    _ => &signature::ECDSA_P384_SHA384_ASN1,
+                }
+            }
+            _ => {
+                return Err(anyhow!(
+                    "Signature scheme {:?} is not supported by the signing certificate.",
+                    scheme
+                ))
+            }
+        })
+    }
+
+    /// Verify the signature of the message by the signing certificate.
+    pub fn verify_signature(&self, scheme: Scheme, message: &[u8], sig: &[u8]) -> Result<()> {
+        UnparsedPublicKey::new(self.algorithm(scheme, sig)?, &self.public_key)
+            .verify(message, sig)
+            .map_err(|_| anyhow!("Verify manifest signature failed."))
+    }
+}
+
+/// Parse a DER encoded certificate.
+pub fn parse(der: &[u8]) -> Result<X509Certificate<'_>> {
+    let (_, certificate) =
+        X509Certificate::from_der(der).map_err(|e| anyhow!("Parse certificate failed: {}", e))?;
+    Ok(certificate)
+}
+
+/// Whether `issuer` issued `certificate`, and both are valid at `time`.
+fn issued_by(certificate: &X509Certificate, issuer: &X509Certificate, time: ASN1Time) -> bool {
+    certificate.issuer().as_raw() == issuer.subject().as_raw()
+        && issuer.validity().is_valid_at(time)
+        && certificate
+            .verify_signature(Some(issuer.public_key()))
+            .is_ok()
+}
+
+/// Get the OIDs of the extended key usages, or `None` if the extension
+/// is absent, s.t. all usages are allowed.
+fn extended_key_usages(certificate: &X509Certificate) -> Result<Option<Vec<String>>> {
+    let eku = match certificate.extended_key_usage()? {
+        Some(eku) => eku.value,
+        None => return Ok(None),
+    };
+    let mut usages: Vec<String> = eku.other.iter().map(|oid| oid.to_id_string()).collect();
+    for (present, oid) in [
+        (eku.any, ANY_EXTENDED_KEY_USAGE_OID),
+        (eku.server_auth, "1.3.6.1.5.5.7.3.1"),
+        (eku.client_auth, "1.3.6.1.5.5.7.3.2"),
+        (eku.code_signing, "1.3.6.1.5.5.7.3.3"),
+        (eku.email_protection, "1.3.6.1.5.5.7.3.4"),
+        (eku.time_stamping, "1.3.6.1.5.5.7.3.8"),
+        (eku.ocsp_signing, "1.3.6.1.5.5.7.3.9"),
+    ] {
+        if present {
+            usages.push(oid.to_string());
+        }
+    }
+    Ok(Some(usages))
+}
+
+/// Whether the CA may issue certificates for the required extended
+/// key usages, s.t. it has no EKU extension, or the extension has all
+/// of them or `anyExtendedKeyUsage`.
+fn ca_allows_usages(ca: &X509Certificate, required: &[String]) -> bool {
+    match extended_key_usages(ca) {
+        Ok(None) => true,
+        Ok(Some(usages)) => {
+            usages.iter().any(|u| u == ANY_EXTENDED_KEY_USAGE_OID)
+                || required.iter().all(|r| usages.contains(r))
+        }
+        Err(_) => false,
+    }
+}
+
+/// Whether the CA may issue a certificate with `depth` intermediate
+/// CAs below it, by its basic constraints and key usage.
+fn can_issue(ca: &X509Certificate, depth: usize) -> bool {
+    let path_len_ok = match ca.basic_constraints() {
+        Ok(Some(constraints)) if constraints.value.ca => constraints
+            .value
+            .path_len_constraint
+            .map(|len| depth <= len as usize)
+            .unwrap_or(true),
+        _ => false,
+    };
+    let key_usage_ok = match ca.key_usage() {
+        Ok(Some(key_usage)) => key_usage.value.key_cert_sign(),
+        Ok(None) => true,
+        Err(_) => false,
+    };
+    path_len_ok && key_usage_ok
+}
+
+/// Verify the chain of the DER encoded signing certificate `leaf` up
+/// to one of the trusted `roots` now, with the `intermediates` in any
+/// order. The signing certificate must have all the required extended
+/// key usages, and every CA in the chain must allow them.
+pub fn verify_chain(
+    leaf: &[u8],
+    intermediates: &[&[u8]],
+    roots: &[Vec<u8>],
+    required_usages: &[String],
+) -> Result<SigningCertificate> {
+    let time = ASN1Time::now();
+    let leaf = parse(leaf)?;
+    let intermediates = intermediates
+        .iter()
+        .map(|der| parse(der))
+        .collect::<Result<Vec<_>>>()?;
+    let roots = roots
+        .iter()
+        .map(|der| parse(der))
+        .collect::<Result<Vec<_>>>()?;
+
+    if !leaf.validity().is_valid_at(time) {
+        return Err(anyhow!("Signing certificate is not valid now."));
+    }
+    if !required_usages.is_empty() {
+        let usages = extended_key_usages(&leaf)?.unwrap_or_default();
+        if let Some(missing) = required_usages.iter().find(|r| !usages.contains(r)) {
+            return Err(anyhow!(
+                "Signing certificate does not have extended key usage {}.",
+                missing
+            ));
+        }
+    }
+    if let Some(key_usage) = leaf.key_usage()? {
+        if !key_usage.value.digital_signature() {
+            return Err(anyhow!("Signing certificate is not for digital signature."));
+        }
+    }
+
+    let mut not_after = leaf.validity().not_after.timestamp();
+    let mut current = &leaf;
+    for depth in 0..MAX_CHAIN_LENGTH {
+        let issued = |ca: &&X509Certificate| {
+            issued_by(current, ca, time)
+                && can_issue(ca, depth)
+                && ca_allows_usages(ca, required_usages)
+        };
+
+        if let Some(root) = roots.iter().find(issued) {
+            not_after = not_after.min(root.validity().not_after.timestamp());
+            return Ok(SigningCertificate {
+                subject: leaf.subject().to_string(),
+                not_after: Utc.timestamp(not_after, 0),
+                key_algorithm: leaf.public_key().algorithm.algorithm.to_id_string(),
+                public_key: leaf.public_key().subject_public_key.data.to_vec(),
+            });
+        }
+
+        current = intermediates
+            .iter()
+            .find(issued)
+            .ok_or_else(|| anyhow!("Certificate chain is not issued by a trusted root."))?;
+        not_after = not_after.min(current.validity().not_after.timestamp());
+    }
+
+    Err(anyhow!("Certificate chain is too long."))
+}
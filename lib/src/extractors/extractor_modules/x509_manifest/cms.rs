@@ -0,0 +1,370 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Verification of CMS SignedData, see RFC 5652, which is also the
+//! SignedData of PKCS#7. Only DER encoding is supported.
+
+use std::borrow::Cow;
+
+use anyhow::{anyhow, Result};
+use x509_parser::{der_parser::oid::Oid, extensions::ParsedExtension};
+
+use super::chain::{self, Hash, Scheme, SigningCertificate};
+
+const SIGNED_DATA_OID: &str = "1.2.840.113549.1.7.2";
+
+const CONTENT_TYPE_OID: &str = "1.2.840.113549.1.9.3";
+
+const MESSAGE_DIGEST_OID: &str = "1.2.840.113549.1.9.4";
+
+/// DER tags
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_CONTEXT_0: u8 = 0xa0;
+const TAG_CONTEXT_1: u8 = 0xa1;
+const TAG_CONTEXT_0_PRIMITIVE: u8 = 0x80;
+
+/// A DER encoded value.
+/// * `content`: the value without the tag and length.
+/// * `raw`: the whole encoding.
+struct Tlv<'a> {
+    tag: u8,
+    content: &'a [u8],
+    raw: &'a [u8],
+}
+
+/// A cursor over DER encoded values.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.0.first().copied()
+    }
+
+    fn next(&mut self) -> Result<Tlv<'a>> {
+        let truncated = || anyhow!("CMS SignedData is truncated.");
+        let input = self.0;
+        let tag = *input.first().ok_or_else(truncated)?;
+        if tag & 0x1f == 0x1f {
+            return Err(anyhow!("High tag number is not supported in CMS."));
+        }
+        let first = *input.get(1).ok_or_else(truncated)?;
+        let (len, header) = match first {
+            0x80 => return Err(anyhow!("Indefinite length is not supported in CMS.")),
+            0x00..=0x7f => (first as usize, 2),
+            _ => {
+                let n = (first & 0x7f) as usize;
+                if n > 4 {
+                    return Err(anyhow!("Length is too large in CMS."));
+                }
+                let bytes = input.get(2..2 + n).ok_or_else(truncated)?;
+                let len = bytes.iter().fold(0, |len, b| (len << 8) | *b as usize);
+                (len, 2 + n)
+            }
+        };
+        let end = header
+            .checked_add(len)
+            .filter(|end| *end <= input.len())
+            .ok_or_else(truncated)?;
+        self.0 = &input[end..];
+        Ok(Tlv {
+            tag,
+            content: &input[header..end],
+            raw: &input[..end],
+        })
+    }
+
+    /// Read a value of the tag.
+    fn expect(&mut self, tag: u8) -> Result<Tlv<'a>> {
+        let tlv = self.next()?;
+        match tlv.tag == tag {
+            true => Ok(tlv),
+            false => Err(anyhow!(
+                "Unexpected tag {:#04x} in CMS, expected {:#04x}.",
+                tlv.tag,
+                tag
+            )),
+        }
+    }
+
+    /// Read a value of the tag if it is the next one.
+    fn optional(&mut self, tag: u8) -> Result<Option<Tlv<'a>>> {
+        match self.peek() == Some(tag) {
+            true => Ok(Some(self.next()?)),
+            false => Ok(None),
+        }
+    }
+
+    fn oid(&mut self) -> Result<String> {
+        let content = self.expect(TAG_OID)?.content;
+        Ok(Oid::new(Cow::Borrowed(content)).to_id_string())
+    }
+
+    /// Read an AlgorithmIdentifier, and get its OID.
+    fn algorithm(&mut self) -> Result<String> {
+        Reader(self.expect(TAG_SEQUENCE)?.content).oid()
+    }
+}
+
+/// Identifier of the signing certificate of a signer.
+enum SignerId<'a> {
+    IssuerAndSerialNumber { issuer: &'a [u8], serial: &'a [u8] },
+    SubjectKeyIdentifier(&'a [u8]),
+}
+
+/// A SignerInfo.
+/// * `signed_attributes`: the whole encoding of the signed attributes.
+struct SignerInfo<'a> {
+    sid: SignerId<'a>,
+    digest_algorithm: String,
+    signed_attributes: Option<&'a [u8]>,
+    signature_algorithm: String,
+    signature: &'a [u8],
+}
+
+/// A SignedData.
+/// * `content`: the encapsulated content, `None` if detached.
+/// * `certificates`: DER encoded certificates.
+struct SignedData<'a> {
+    content_type: String,
+    content: Option<&'a [u8]>,
+    certificates: Vec<&'a [u8]>,
+    signers: Vec<SignerInfo<'a>>,
+}
+
+impl<'a> SignedData<'a> {
+    /// Parse a ContentInfo of SignedData.
+    fn parse(der: &'a [u8]) -> Result<Self> {
+        let mut content_info = Reader(Reader(der).expect(TAG_SEQUENCE)?.content);
+        if content_info.oid()? != SIGNED_DATA_OID {
+            return Err(anyhow!("CMS content is not SignedData."));
+        }
+        let explicit = content_info.expect(TAG_CONTEXT_0)?.content;
+        let mut signed_data = Reader(Reader(explicit).expect(TAG_SEQUENCE)?.content);
+
+        signed_data.expect(TAG_INTEGER)?;
+        signed_data.expect(TAG_SET)?;
+
+        let mut encapsulated = Reader(signed_data.expect(TAG_SEQUENCE)?.content);
+        let content_type = encapsulated.oid()?;
+        let content = match encapsulated.optional(TAG_CONTEXT_0)? {
+            Some(explicit) => Some(Reader(explicit.content).expect(TAG_OCTET_STRING)?.content),
+            None => None,
+        };
+
+        let mut certificates = Vec::new();
+        if let Some(set) = signed_data.optional(TAG_CONTEXT_0)? {
+            let mut set = Reader(set.content);
+            while !set.is_empty() {
+                // Other certificate formats are skipped
+                let certificate = set.next()?;
+                if certificate.tag == TAG_SEQUENCE {
+                    certificates.push(certificate.raw);
+                }
+            }
+        }
+        signed_data.optional(TAG_CONTEXT_1)?;
+
+        let mut signers = Vec::new();
+        let mut set = Reader(signed_data.expect(TAG_SET)?.content);
+        while !set.is_empty() {
+            signers.push(SignerInfo::parse(set.expect(TAG_SEQUENCE)?.content)?);
+        }
+
+        Ok(Self {
+            content_type,
+            content,
+            certificates,
+            signers,
+        })
+    }
+}
+
+impl<'a> SignerInfo<'a> {
+    fn parse(content: &'a [u8]) -> Result<Self> {
+        let mut signer = Reader(content);
+        signer.expect(TAG_INTEGER)?;
+        let sid = match signer.next()? {
+            Tlv {
+                tag: TAG_SEQUENCE,
+                content,
+                ..
+            } => {
+                let mut sid = Reader(content);
+                SignerId::IssuerAndSerialNumber {
+                    issuer: sid.expect(TAG_SEQUENCE)?.raw,
+                    serial: sid.expect(TAG_INTEGER)?.content,
+                }
+            }
+            Tlv {
+                tag: TAG_CONTEXT_0_PRIMITIVE,
+                content,
+                ..
+            } => SignerId::SubjectKeyIdentifier(content),
+            _ => return Err(anyhow!("Malformed CMS signer identifier.")),
+        };
+
+        Ok(Self {
+            sid,
+            digest_algorithm: signer.algorithm()?,
+            signed_attributes: signer.optional(TAG_CONTEXT_0)?.map(|a| a.raw),
+            signature_algorithm: signer.algorithm()?,
+            signature: signer.expect(TAG_OCTET_STRING)?.content,
+        })
+    }
+
+    /// Whether the DER encoded certificate is the one of the signer.
+    fn signed_by(&self, certificate: &[u8]) -> bool {
+        let certificate = match chain::parse(certificate) {
+            Ok(certificate) => certificate,
+            Err(_) => return false,
+        };
+        match self.sid {
+            SignerId::IssuerAndSerialNumber { issuer, serial } => {
+                certificate.issuer().as_raw() == issuer && certificate.raw_serial() == serial
+            }
+            SignerId::SubjectKeyIdentifier(id) => certificate.extensions().iter().any(|e| {
+                matches!(e.parsed_extension(), ParsedExtension::SubjectKeyIdentifier(k) if k.0 == id)
+            }),
+        }
+    }
+
+    /// Get the hash of the digest algorithm.
+    fn hash(&self) -> Result<Hash> {
+        match self.digest_algorithm.as_str() {
+            "2.16.840.1.101.3.4.2.1" => Ok(Hash::Sha256),
+            "2.16.840.1.101.3.4.2.2" => Ok(Hash::Sha384),
+            "2.16.840.1.101.3.4.2.3" => Ok(Hash::Sha512),
+            oid => Err(anyhow!("Unsupported CMS digest algorithm: {}", oid)),
+        }
+    }
+
+    /// Get the signature scheme, where the hash of the signature
+    /// algorithm must match the digest algorithm.
+    fn scheme(&self) -> Result<Scheme> {
+        let hash = self.hash()?;
+        let scheme = match self.signature_algorithm.as_str() {
+            "1.2.840.113549.1.1.1" => Scheme::RsaPkcs1(hash),
+            "1.2.840.113549.1.1.11" => Scheme::RsaPkcs1(Hash::Sha256),
+            "1.2.840.113549.1.1.12" => Scheme::RsaPkcs1(Hash::Sha384),
+            "1.2.840.113549.1.1.13" => Scheme::RsaPkcs1(Hash::Sha512),
+            "1.2.840.10045.2.1" => Scheme::Ecdsa(hash),
+            "1.2.840.10045.4.3.2" => Scheme::Ecdsa(Hash::Sha256),
+            "1.2.840.10045.4.3.3" => Scheme::Ecdsa(Hash::Sha384),
+            "1.2.840.10045.4.3.4" => Scheme::Ecdsa(Hash::Sha512),
+            oid => return Err(anyhow!("Unsupported CMS signature algorithm: {}", oid)),
+        };
+        match scheme {
+            Scheme::RsaPkcs1(h) | Scheme::Ecdsa(h) if h == hash => Ok(scheme),
+            _ => Err(anyhow!(
+                "CMS signature algorithm does not match the digest algorithm."
+            )),
+        }
+    }
+
+    /// Get the signed message, s.t. the content itself, or the signed
+    /// attributes if present, which must have the content type and the
+    /// digest of the content.
+    fn message(&self, content_type: &str, content: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        let attributes = match self.signed_attributes {
+            Some(attributes) => attributes,
+            None => return Ok(Cow::Borrowed(content)),
+        };
+
+        let (mut found_type, mut found_digest) = (false, false);
+        let mut reader = Reader(Reader(attributes).next()?.content);
+        while !reader.is_empty() {
+            let mut attribute = Reader(reader.expect(TAG_SEQUENCE)?.content);
+            let oid = attribute.oid()?;
+            let mut values = Reader(attribute.expect(TAG_SET)?.content);
+            match oid.as_str() {
+                CONTENT_TYPE_OID => {
+                    if values.oid()? != content_type || !values.is_empty() {
+                        return Err(anyhow!("CMS content type attribute does not match."));
+                    }
+                    found_type = true;
+                }
+                MESSAGE_DIGEST_OID => {
+                    let digest = values.expect(TAG_OCTET_STRING)?.content;
+                    if digest != self.hash()?.digest(content) || !values.is_empty() {
+                        return Err(anyhow!("CMS message digest does not match the content."));
+                    }
+                    found_digest = true;
+                }
+                _ => {}
+            }
+        }
+        if !found_type || !found_digest {
+            return Err(anyhow!(
+                "CMS signed attributes lack content type or message digest."
+            ));
+        }
+
+        // The signed attributes are signed as an explicit SET OF
+        let mut message = attributes.to_vec();
+        message[0] = TAG_SET;
+        Ok(Cow::Owned(message))
+    }
+
+    /// Verify the signature of the signer, and the chain of its signing
+    /// certificate.
+    fn verify(
+        &self,
+        signed_data: &SignedData<'a>,
+        content: &'a [u8],
+        roots: &[Vec<u8>],
+        required_usages: &[String],
+    ) -> Result<SigningCertificate> {
+        let (certificate, intermediates): (Vec<&[u8]>, Vec<&[u8]>) = signed_data
+            .certificates
+            .iter()
+            .partition(|c| self.signed_by(c));
+        let certificate = certificate
+            .first()
+            .ok_or_else(|| anyhow!("Signing certificate not found in CMS."))?;
+
+        let signing_certificate =
+            chain::verify_chain(certificate, &intermediates, roots, required_usages)?;
+        let message = self.message(&signed_data.content_type, content)?;
+        signing_certificate.verify_signature(self.scheme()?, &message, self.signature)?;
+        Ok(signing_certificate)
+    }
+}
+
+/// Verify the DER encoded CMS SignedData, with the certificates in it,
+/// up to one of the DER encoded trusted `roots`. The content is either
+/// encapsulated, or `detached`. If any of the signers is verified, the
+/// content and the signing certificate are returned.
+pub fn verify(
+    der: &[u8],
+    detached: Option<&[u8]>,
+    roots: &[Vec<u8>],
+    required_usages: &[String],
+) -> Result<(Vec<u8>, SigningCertificate)> {
+    let signed_data = SignedData::parse(der)?;
+    let content = match (signed_data.content, detached) {
+        (Some(content), None) | (None, Some(content)) => content,
+        (Some(_), Some(_)) => {
+            return Err(anyhow!("CMS content is both encapsulated and detached."))
+        }
+        (None, None) => return Err(anyhow!("CMS content is detached but not given.")),
+    };
+
+    let mut error = anyhow!("No signer in CMS.");
+    for signer in &signed_data.signers {
+        match signer.verify(&signed_data, content, roots, required_usages) {
+            Ok(certificate) => return Ok((content.to_vec(), certificate)),
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
@@ -0,0 +1,79 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Verification of JWS in compact serialization, see RFC 7515, signed
+//! by the certificate in the `x5c` header.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use super::chain::{self, Hash, Scheme, SigningCertificate};
+
+/// Protected header of the JWS.
+/// * `x5c`: base64 encoded DER certificates, starting with the
+/// signing certificate.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Header {
+    pub alg: String,
+    pub x5c: Vec<String>,
+    #[serde(default)]
+    pub crit: Vec<String>,
+}
+
+/// Get the signature scheme of a JWS algorithm.
+fn scheme(algorithm: &str) -> Result<Scheme> {
+    match algorithm {
+        "RS256" => Ok(Scheme::RsaPkcs1(Hash::Sha256)),
+        "RS384" => Ok(Scheme::RsaPkcs1(Hash::Sha384)),
+        "RS512" => Ok(Scheme::RsaPkcs1(Hash::Sha512)),
+        "PS256" => Ok(Scheme::RsaPss(Hash::Sha256)),
+        "PS384" => Ok(Scheme::RsaPss(Hash::Sha384)),
+        "PS512" => Ok(Scheme::RsaPss(Hash::Sha512)),
+        "ES256" => Ok(Scheme::Ecdsa(Hash::Sha256)),
+        "ES384" => Ok(Scheme::Ecdsa(Hash::Sha384)),
+        _ => Err(anyhow!("Unsupported JWS algorithm: {}", algorithm)),
+    }
+}
+
+fn decode(value: &str) -> Result<Vec<u8>> {
+    base64::decode_config(value, base64::URL_SAFE_NO_PAD)
+        .map_err(|e| anyhow!("Decode JWS failed: {}", e))
+}
+
+/// Verify the JWS with the certificate chain in its protected header,
+/// up to one of the DER encoded trusted `roots`, and get the payload
+/// and the signing certificate.
+pub fn verify(
+    jws: &str,
+    roots: &[Vec<u8>],
+    required_usages: &[String],
+) -> Result<(Vec<u8>, SigningCertificate)> {
+    let parts: Vec<&str> = jws.trim().split('.').collect();
+    let (protected, payload, signature) = match parts[..] {
+        [protected, payload, signature] => (protected, payload, signature),
+        _ => return Err(anyhow!("JWS is not in compact serialization.")),
+    };
+
+    let header: Header = serde_json::from_slice(&decode(protected)?)?;
+    if let Some(unknown) = header.crit.first() {
+        return Err(anyhow!("Unsupported critical header: {}", unknown));
+    }
+    let scheme = scheme(&header.alg)?;
+
+    let chain = header
+        .x5c
+        .iter()
+        .map(|c| base64::decode(c).map_err(|e| anyhow!("Decode certificate failed: {}", e)))
+        .collect::<Result<Vec<_>>>()?;
+    let (leaf, intermediates) = chain
+        .split_first()
+        .ok_or_else(|| anyhow!("Signing certificate not found."))?;
+    let intermediates: Vec<&[u8]> = intermediates.iter().map(|c| c.as_slice()).collect();
+    let certificate = chain::verify_chain(leaf, &intermediates, roots, required_usages)?;
+
+    let signing_input = format!("{}.{}", protected, payload);
+    certificate.verify_signature(scheme, signing_input.as_bytes(), &decode(signature)?)?;
+    Ok((decode(payload)?, certificate))
+}
@@ -0,0 +1,326 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! # X.509 Manifest Extractor
+//!
+//! This Extractor helps to verify a manifest of component digests
+//! signed by a code signing certificate, either by CMS/PKCS#7 or by
+//! JWS with `x5c`, whose chain is validated up to the trusted roots,
+//! and extract the digests of the components.
+
+pub mod chain;
+pub mod cms;
+pub mod jws;
+
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::reference_value::{ReferenceValue, REFERENCE_VALUE_VERSION};
+
+use super::{ConfigurableExtractor, Extractor};
+
+/// OID of the code signing extended key usage
+pub const CODE_SIGNING_OID: &str = "1.3.6.1.5.5.7.3.3";
+
+/// Format of the manifest signature.
+/// * `Cms`: base64 encoded DER CMS/PKCS#7 SignedData.
+/// * `Jws`: JWS in compact serialization.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Cms,
+    Jws,
+}
+
+/// Provenance of a signed manifest.
+/// * `signature`: the manifest signature in the `format`.
+/// * `manifest`: the base64 encoded manifest, only if it is detached
+/// from the CMS SignedData.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Provenance {
+    pub format: Format,
+    pub signature: String,
+    pub manifest: Option<String>,
+}
+
+/// A component in the manifest.
+/// * `digests`: hex encoded digests of the component, keyed by the
+/// algorithm, e.g. `sha256`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Component {
+    pub name: String,
+    pub version: Option<String>,
+    pub digests: BTreeMap<String, String>,
+}
+
+/// The signed manifest.
+/// * `expires`: optional expiry of the manifest.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Manifest {
+    pub expires: Option<DateTime<Utc>>,
+    pub components: Vec<Component>,
+}
+
+/// Policy of the X.509 Manifest Extractor, given by its section of
+/// the config file.
+/// * `trusted_roots`: paths of the PEM encoded trusted root
+/// certificates.
+/// * `extended_key_usages`: OIDs of the extended key usages that the
+/// signing certificate must have. Defaults to code signing.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Policy {
+    #[serde(default)]
+    pub trusted_roots: Vec<PathBuf>,
+    #[serde(default = "default_extended_key_usages")]
+    pub extended_key_usages: Vec<String>,
+}
+
+/// Use to set default extended key usages of Policy
+fn default_extended_key_usages() -> Vec<String> {
+    vec![CODE_SIGNING_OID.to_string()]
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            trusted_roots: Vec::new(),
+            extended_key_usages: default_extended_key_usages(),
+        }
+    }
+}
+
+pub struct X509ManifestExtractor {
+    roots: Vec<Vec<u8>>,
+    extended_key_usages: Vec<String>,
+}
+
+impl X509ManifestExtractor {
+    /// Create an X.509 Manifest Extractor with the given policy.
+    pub fn with_policy(policy: Policy) -> Result<Self> {
+        let mut roots = Vec::new();
+        for path in &policy.trusted_roots {
+            let pems = pem::parse_many(fs::read(path)?)?;
+            for pem in pems.into_iter().filter(|p| p.tag == "CERTIFICATE") {
+                chain::parse(&pem.contents)?;
+                roots.push(pem.contents);
+            }
+        }
+
+        Ok(Self {
+            roots,
+            extended_key_usages: policy.extended_key_usages,
+        })
+    }
+}
+
+impl ConfigurableExtractor for X509ManifestExtractor {
+    type Config = Policy;
+
+    fn with_config(config: Policy) -> Result<Self> {
+        Self::with_policy(config)
+    }
+}
+
+impl Extractor for X509ManifestExtractor {
+    /// X.509 Manifest Extractor.
+    /// The provenance is a manifest signed by CMS or JWS. A reference
+    /// value is generated for each component, named by its name, which
+    /// expires with the manifest, or with the certificate chain if it
+    /// expires earlier.
+    fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+        let provenance: Provenance = serde_json::from_str(provenance)?;
+        let (content, certificate) = match provenance.format {
+            Format::Cms => {
+                let der = base64::decode(provenance.signature.trim())?;
+                let detached = provenance.manifest.map(base64::decode).transpose()?;
+                cms::verify(
+                    &der,
+                    detached.as_deref(),
+                    &self.roots,
+                    &self.extended_key_usages,
+                )?
+            }
+            Format::Jws => {
+                if provenance.manifest.is_some() {
+                    return Err(anyhow!("Detached manifest is not supported by JWS."));
+                }
+                jws::verify(
+                    &provenance.signature,
+                    &self.roots,
+                    &self.extended_key_usages,
+                )?
+            }
+        };
+        let manifest: Manifest = serde_json::from_slice(&content)?;
+
+        let expired = match manifest.expires {
+            Some(expires) => expires.min(certificate.not_after),
+            None => certificate.not_after,
+        };
+        let mut rvs = Vec::new();
+        for component in &manifest.components {
+            if component.digests.is_empty() {
+                return Err(anyhow!("No digest of component {}.", component.name));
+            }
+            let mut rv = ReferenceValue::new()
+                .set_name(&component.name)
+                .set_version(REFERENCE_VALUE_VERSION)
+                .set_expired(expired)
+                .add_metadata("signer", &certificate.subject);
+            if let Some(version) = &component.version {
+                rv = rv.add_metadata("version", version);
+            }
+            for (alg, value) in &component.digests {
+                rv = rv.add_hash_value(alg.to_string(), value.to_lowercase());
+            }
+            rvs.push(rv);
+        }
+        Ok(rvs)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use chrono::{TimeZone, Utc};
+    use ring::digest::{digest, SHA256};
+    use serde_json::json;
+
+    use crate::extractors::extractor_modules::{artifact::hex, Extractor};
+
+    use super::{Policy, X509ManifestExtractor};
+
+    /// Test vectors generated by `<git-repo>/tests/x509-manifest/generate.py`
+    const TEST_VECTORS_DIR: &str = "../tests/x509-manifest";
+
+    fn read(name: &str) -> Vec<u8> {
+        fs::read(format!("{}/{}", TEST_VECTORS_DIR, name)).unwrap()
+    }
+
+    fn extractor(root: &str, extended_key_usages: &[&str]) -> X509ManifestExtractor {
+        X509ManifestExtractor::with_policy(Policy {
+            trusted_roots: vec![format!("{}/{}", TEST_VECTORS_DIR, root).into()],
+            extended_key_usages: extended_key_usages.iter().map(|u| u.to_string()).collect(),
+        })
+        .unwrap()
+    }
+
+    fn cms(name: &str, detached: bool) -> String {
+        let mut provenance = json!({
+            "format": "cms",
+            "signature": base64::encode(read(name)),
+        });
+        if detached {
+            provenance["manifest"] = json!(base64::encode(read("manifest.json")));
+        }
+        provenance.to_string()
+    }
+
+    fn jws(name: &str) -> String {
+        json!({
+            "format": "jws",
+            "signature": String::from_utf8(read(name)).unwrap(),
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn x509_manifest_extractor() {
+        let e = extractor("root.pem", &["1.3.6.1.5.5.7.3.3"]);
+        let ovmf = hex(digest(&SHA256, b"ovmf").as_ref());
+
+        for (provenance, signer, expired) in [
+            (
+                cms("manifest.p7s", false),
+                "CN=RVPS Test Firmware Signer",
+                Utc.ymd(2040, 1, 1),
+            ),
+            (
+                cms("manifest.detached.p7s", true),
+                "CN=RVPS Test Firmware RSA Signer",
+                Utc.ymd(2039, 1, 1),
+            ),
+            (
+                jws("manifest.jws"),
+                "CN=RVPS Test Firmware Signer",
+                Utc.ymd(2040, 1, 1),
+            ),
+        ] {
+            let rvs = e.verify_and_extract(&provenance).unwrap();
+            assert_eq!(rvs.len(), 2);
+            assert_eq!(rvs[0].name(), "ovmf");
+            assert_eq!(rvs[0].hash_values().len(), 2);
+            assert_eq!(rvs[0].hash_values()[0].alg(), "sha256");
+            assert_eq!(rvs[0].hash_values()[0].value(), &ovmf);
+            assert_eq!(rvs[0].metadata()["version"], "edk2-stable202208");
+            assert_eq!(rvs[0].metadata()["signer"], signer);
+            // Capped by the signing certificate, not the manifest
+            assert_eq!(rvs[0].expired(), &expired.and_hms(0, 0, 0));
+            assert_eq!(rvs[1].name(), "shim");
+        }
+
+        // No extended key usage required
+        let e = extractor("root.pem", &[]);
+        assert!(e.verify_and_extract(&jws("manifest.no-eku.jws")).is_ok());
+    }
+
+    #[test]
+    fn x509_manifest_extractor_rejects() {
+        let e = extractor("root.pem", &["1.3.6.1.5.5.7.3.3"]);
+
+        for name in [
+            // Signing certificate without code signing
+            "manifest.no-eku.jws",
+            // Expired signing certificate
+            "manifest.expired.jws",
+            // Issued by an untrusted root
+            "manifest.untrusted.jws",
+        ] {
+            assert!(e.verify_and_extract(&jws(name)).is_err());
+        }
+
+        // Detached manifest not given
+        assert!(e
+            .verify_and_extract(&cms("manifest.detached.p7s", false))
+            .is_err());
+
+        // Detached manifest modified
+        let provenance = json!({
+            "format": "cms",
+            "signature": base64::encode(read("manifest.detached.p7s")),
+            "manifest": base64::encode(b"{\"components\": []}"),
+        });
+        assert!(e.verify_and_extract(&provenance.to_string()).is_err());
+
+        // Tampered JWS payload
+        let signed = String::from_utf8(read("manifest.jws")).unwrap();
+        let parts: Vec<&str> = signed.trim().split('.').collect();
+        let payload = base64::encode_config(b"{\"components\": []}", base64::URL_SAFE_NO_PAD);
+        let provenance = json!({
+            "format": "jws",
+            "signature": format!("{}.{}.{}", parts[0], payload, parts[2]),
+        });
+        assert!(e.verify_and_extract(&provenance.to_string()).is_err());
+
+        // Tampered CMS content
+        let mut der = read("manifest.p7s");
+        let offset = der.windows(4).position(|w| w == b"ovmf").unwrap();
+        der[offset] = b'O';
+        let provenance = json!({ "format": "cms", "signature": base64::encode(&der) });
+        assert!(e.verify_and_extract(&provenance.to_string()).is_err());
+
+        // Untrusted root
+        let e = extractor("other_root.pem", &["1.3.6.1.5.5.7.3.3"]);
+        assert!(e.verify_and_extract(&cms("manifest.p7s", false)).is_err());
+
+        // Another extended key usage required
+        let e = extractor("root.pem", &["1.3.6.1.5.5.7.3.1"]);
+        assert!(e.verify_and_extract(&jws("manifest.jws")).is_err());
+    }
+}
@@ -9,11 +9,20 @@ pub mod extractor_modules;
 
 use anyhow::*;
 
-use std::collections::HashMap;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-use crate::{reference_value::ReferenceValue, Message};
+use crate::{
+    reference_value::ReferenceValue, report::VerificationReport, Config, Message, RVPSError,
+};
 
-use self::extractor_modules::{ExtractorInstance, ExtractorModuleList};
+use self::extractor_modules::{
+    ExtractorConfig, ExtractorInstance, ExtractorInstantiateFunc, ExtractorModuleList,
+};
+
+/// Configuration of the Extractors, keyed by provenance type.
+pub type ExtractorsConfig = BTreeMap<String, ExtractorConfig>;
 
 /// `Extractors` provides different kinds of `Extractor`s due to
 /// different provenance types, e.g. in-toto, etc.
@@ -28,8 +37,41 @@ pub trait ExtractorsAPI {
     /// Process the message, e.g. verifying
     /// and extracting the provenance inside the message due to
     /// type also inside the same message. If verification
-    /// succeeds, return the generated ReferenceValue.
-    fn process(&mut self, message: Message) -> Result<ReferenceValue>;
+    /// succeeds, return the generated ReferenceValues.
+    fn process(&mut self, message: Message) -> Result<Vec<ReferenceValue>> {
+        self.process_with_report(message).map(|(rvs, _)| rvs)
+    }
+
+    /// Process the message like `process`, and also return the report
+    /// of the verification. If the verification fails, the report is
+    /// attached to the error.
+    fn process_with_report(
+        &mut self,
+        message: Message,
+    ) -> Result<(Vec<ReferenceValue>, VerificationReport)>;
+
+    /// Get the capabilities of all the registered Extractors, sorted by
+    /// provenance type. Extractors are instantiated if not yet, and
+    /// those failing to be instantiated are reported as unavailable.
+    fn capabilities(&mut self) -> Result<Vec<ExtractorCapability>>;
+}
+
+/// Capability of an Extractor.
+/// * `name`: provenance type handled by the Extractor, s.t. `type` of
+/// the Message.
+/// * `payload_versions`: versions of the provenance format that the
+/// Extractor supports. Empty if the format is unversioned, s.t. the
+/// Extractor does not check any version of the provenance.
+/// * `available`: whether the Extractor can be instantiated, s.t. its
+/// provenance is accepted.
+/// * `error`: why the Extractor is unavailable.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ExtractorCapability {
+    pub name: String,
+    pub payload_versions: Vec<String>,
+    pub available: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 /// The struct `Extractors` is responsible for implementing
@@ -39,9 +81,16 @@ pub trait ExtractorsAPI {
 /// `extractors_instance_map` is another map that maps provenance type
 /// to the instancialized Extractor. The two map implement a
 /// "instantialization-on-demand" mechanism.
+/// `config` is the configuration of the Extractors, with which they
+/// are instantiated.
+/// `plugins` is the provenance types of the WASM plugins loaded by the
+/// configuration.
 pub struct Extractors {
     extractors_module_list: ExtractorModuleList,
     extractors_instance_map: HashMap<String, ExtractorInstance>,
+    config: ExtractorsConfig,
+    #[cfg(feature = "wasm")]
+    plugins: Vec<String>,
 }
 
 impl Default for Extractors {
@@ -57,6 +106,69 @@ impl Extractors {
         Extractors {
             extractors_module_list,
             extractors_instance_map,
+            config: ExtractorsConfig::new(),
+            #[cfg(feature = "wasm")]
+            plugins: Vec::new(),
+        }
+    }
+
+    /// Configure the Extractors with the config file, e.g. at startup.
+    /// Every configured Extractor is instantiated at once to validate its
+    /// configuration, and nothing is changed if any configuration is
+    /// invalid. The configuration is kept by provenance type, s.t. it
+    /// also applies to an Extractor overriding the configured one later.
+    /// The config replaces the one given before, s.t. the Extractors
+    /// configured before are instantiated again. Extractors registered
+    /// as instances can not be configured.
+    /// The WASM plugins of the config replace those loaded before.
+    pub fn configure(&mut self, config: &Config) -> Result<()> {
+        #[cfg(feature = "wasm")]
+        let plugins = match &config.wasm {
+            Some(wasm) => extractor_modules::wasm::load_plugins_with_config(wasm)
+                .context("Load WASM plugins failed")?,
+            None => Vec::new(),
+        };
+
+        let mut instances = HashMap::new();
+        for (extractor_name, extractor_config) in &config.extractors {
+            let instantiate_func = self.extractors_module_list.get_func(extractor_name)?;
+            let extractor_instance = (instantiate_func)(Some(extractor_config))
+                .context(RVPSError::InvalidExtractorConfig(extractor_name.clone()))?;
+            instances.insert(extractor_name.clone(), extractor_instance);
+        }
+
+        #[cfg(feature = "wasm")]
+        self.register_plugins(plugins);
+        let module_list = &self.extractors_module_list;
+        self.extractors_instance_map
+            .retain(|name, _| !module_list.contains(name));
+        self.extractors_instance_map.extend(instances);
+        self.config = config.extractors.clone();
+        Ok(())
+    }
+
+    /// Register the WASM plugins in place of those loaded before. A
+    /// plugin does not override the Extractor of the same provenance
+    /// type, and can not be configured.
+    #[cfg(feature = "wasm")]
+    fn register_plugins(&mut self, plugins: Vec<(String, extractor_modules::wasm::WasmExtractor)>) {
+        for name in std::mem::take(&mut self.plugins) {
+            self.unregister_extractor(&name);
+        }
+
+        for (name, extractor) in plugins {
+            let instantiate_func: ExtractorInstantiateFunc =
+                Box::new(move |config| -> Result<ExtractorInstance> {
+                    if config.is_some() {
+                        return Err(anyhow!("WASM plugins can not be configured."));
+                    }
+                    Ok(Box::new(extractor.clone()))
+                });
+            if let Err(e) = self.register_extractor(&name, instantiate_func) {
+                log::warn!("WASM plugin {} is not loaded: {}", name, e);
+                continue;
+            }
+            self.plugins.push(name);
         }
     }
 
@@ -67,18 +179,68 @@ impl Extractors {
             .insert(extractor_name, extractor_instance);
     }
 
+    /// Whether an `Extractor` is registered for `extractor_name`, either
+    /// by an instantiate function or by an instance.
+    fn is_registered(&self, extractor_name: &str) -> bool {
+        self.extractors_module_list.contains(extractor_name)
+            || self.extractors_instance_map.contains_key(extractor_name)
+    }
+
+    /// Register the instantiate function of an external `Extractor` for
+    /// the provenance type `extractor_name`. The `Extractor` will be
+    /// instantiated on the first provenance of the type. A built-in
+    /// `Extractor` can be overridden after it is unregistered.
+    pub fn register_extractor(
+        &mut self,
+        extractor_name: &str,
+        instantiate_func: ExtractorInstantiateFunc,
+    ) -> Result<()> {
+        if self.is_registered(extractor_name) {
+            return Err(RVPSError::DuplicateExtractor(extractor_name.into()).into());
+        }
+        self.extractors_module_list
+            .register(extractor_name, instantiate_func)
+    }
+
+    /// Register a ready instance of an external `Extractor` for the
+    /// provenance type `extractor_name`.
+    pub fn register_extractor_instance(
+        &mut self,
+        extractor_name: &str,
+        extractor_instance: ExtractorInstance,
+    ) -> Result<()> {
+        if self.is_registered(extractor_name) {
+            return Err(RVPSError::DuplicateExtractor(extractor_name.into()).into());
+        }
+        self.register_instance(extractor_name.to_string(), extractor_instance);
+        Ok(())
+    }
+
+    /// Unregister the `Extractor` for `extractor_name`, including its
+    /// instance if it has been instantiated, and return whether it was
+    /// registered. Its configuration is kept.
+    pub fn unregister_extractor(&mut self, extractor_name: &str) -> bool {
+        let func = self.extractors_module_list.unregister(extractor_name);
+        let instance = self.extractors_instance_map.remove(extractor_name);
+        func || instance.is_some()
+    }
+
     /// Instantiate an `Extractor` of given type `extractor_name`. This method will
     /// instantiate an `Extractor` instance and then register it.
     fn instantiate_extractor(&mut self, extractor_name: String) -> Result<()> {
         let instantiate_func = self.extractors_module_list.get_func(&extractor_name)?;
-        let extractor_instance = (instantiate_func)();
+        let extractor_instance = (instantiate_func)(self.config.get(&extractor_name))
+            .context(RVPSError::InvalidExtractorConfig(extractor_name.clone()))?;
         self.register_instance(extractor_name, extractor_instance);
         Ok(())
     }
 }
 
 impl ExtractorsAPI for Extractors {
-    fn process(&mut self, message: Message) -> Result<ReferenceValue> {
+    fn process_with_report(
+        &mut self,
+        message: Message,
+    ) -> Result<(Vec<ReferenceValue>, VerificationReport)> {
         let typ = message.typ;
 
         if self.extractors_instance_map.get_mut(&typ).is_none() {
@@ -89,18 +251,73 @@ impl ExtractorsAPI for Extractors {
             .get_mut(&typ)
             .ok_or_else(|| anyhow!("The Extractor instance does not existing!"))?;
 
-        extractor_instance.verify_and_extract(&message.payload)
+        let mut report = VerificationReport::new(&typ);
+        report.add_timestamp("verification", Utc::now());
+        let rvs = extractor_instance
+            .verify_and_extract_with_report(&message.payload, &mut report)
+            .map_err(|e| {
+                // The Extractor does not tell which rule failed
+                if report.failed_rules.is_empty() {
+                    report.add_failed_rule("verification", &format!("{:#}", e));
+                }
+                e.context(report.clone())
+                    .context(RVPSError::VerificationFailed)
+            })?;
+
+        report.verified = true;
+        report.reference_values = rvs.iter().map(|rv| rv.name().clone()).collect();
+        Ok((rvs, report))
+    }
+
+    fn capabilities(&mut self) -> Result<Vec<ExtractorCapability>> {
+        let names: BTreeSet<String> = self
+            .extractors_module_list
+            .names()
+            .chain(self.extractors_instance_map.keys())
+            .cloned()
+            .collect();
+
+        let mut capabilities = Vec::new();
+        for name in names {
+            // An Extractor failing to be instantiated, e.g. not configured,
+            // is reported as unavailable rather than failing the discovery
+            let error = if self.extractors_instance_map.contains_key(&name) {
+                None
+            } else {
+                self.instantiate_extractor(name.clone())
+                    .err()
+                    .map(|e| format!("{:#}", e))
+            };
+            let payload_versions = self
+                .extractors_instance_map
+                .get(&name)
+                .map(|extractor| extractor.payload_versions())
+                .unwrap_or_default();
+            capabilities.push(ExtractorCapability {
+                name,
+                payload_versions,
+                available: error.is_none(),
+                error,
+            });
+        }
+        Ok(capabilities)
     }
 }
 
 #[cfg(test)]
 mod test {
+    use anyhow::Result;
     use chrono::{TimeZone, Utc};
+    use serde::Deserialize;
+    use serde_json::json;
     use serial_test::serial;
 
     use crate::{
-        extractors::extractor_modules::in_toto::test::sha256_for_in_toto_test_artifact, Message,
-        ReferenceValue, MESSAGE_VERSION,
+        extractors::extractor_modules::{
+            configurable, configured, in_toto::test::sha256_for_in_toto_test_artifact,
+            ConfigurableExtractor, Extractor,
+        },
+        Config, Message, RVPSError, ReferenceValue, MESSAGE_VERSION,
     };
 
     use super::{
@@ -124,6 +341,184 @@ mod test {
             .set_version("0.1")
             .add_hash_value("sha256".into(), sha256_for_in_toto_test_artifact());
 
-        assert_eq!(res, rv);
+        assert_eq!(res, vec![rv]);
+    }
+
+    #[test]
+    #[serial]
+    fn extractors_registration() {
+        struct CountingExtractor(&'static str);
+
+        impl Extractor for CountingExtractor {
+            fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+                Ok(vec![ReferenceValue::new()
+                    .set_name(self.0)
+                    .add_metadata("provenance", provenance)])
+            }
+        }
+
+        let mut e = Extractors::new();
+        e.register_extractor(
+            "external",
+            Box::new(|_| Ok(Box::new(CountingExtractor("func")))),
+        )
+        .unwrap();
+        let res = e.process(Message::new("external", "foo")).unwrap();
+        assert_eq!(res[0].name(), "func");
+        assert_eq!(res[0].metadata()["provenance"], "foo");
+
+        // Duplicate names, whether instantiated or not
+        for name in ["external", "in-toto"] {
+            let err = e
+                .register_extractor_instance(name, Box::new(CountingExtractor("instance")))
+                .unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<RVPSError>(),
+                Some(&RVPSError::DuplicateExtractor(name.into()))
+            );
+        }
+
+        // Override a built-in Extractor
+        assert!(e.unregister_extractor("in-toto"));
+        assert!(!e.unregister_extractor("in-toto"));
+        e.register_extractor_instance("in-toto", Box::new(CountingExtractor("instance")))
+            .unwrap();
+        let res = e.process(Message::new("in-toto", "bar")).unwrap();
+        assert_eq!(res[0].name(), "instance");
+
+        assert!(e.unregister_extractor("external"));
+        let err = e.process(Message::new("external", "foo")).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RVPSError>(),
+            Some(&RVPSError::UnsupportedProvenanceType("external".into()))
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn extractors_configuration() {
+        #[derive(Deserialize)]
+        struct PrefixConfig {
+            prefix: String,
+        }
+
+        struct PrefixExtractor(String);
+
+        impl Extractor for PrefixExtractor {
+            fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+                Ok(vec![
+                    ReferenceValue::new().set_name(&format!("{}{}", self.0, provenance))
+                ])
+            }
+        }
+
+        impl ConfigurableExtractor for PrefixExtractor {
+            type Config = PrefixConfig;
+
+            fn with_config(config: PrefixConfig) -> Result<Self> {
+                if config.prefix.is_empty() {
+                    return Err(anyhow::anyhow!("Empty prefix"));
+                }
+                Ok(PrefixExtractor(config.prefix))
+            }
+        }
+
+        let mut e = Extractors::new();
+        e.register_extractor(
+            "prefix",
+            configurable(|| PrefixExtractor("default-".into())),
+        )
+        .unwrap();
+
+        let res = e.process(Message::new("prefix", "foo")).unwrap();
+        assert_eq!(res[0].name(), "default-foo");
+
+        let config: Config = serde_json::from_value(
+            json!({ "extractors": { "prefix": { "prefix": "configured-" } } }),
+        )
+        .unwrap();
+        e.configure(&config).unwrap();
+        let res = e.process(Message::new("prefix", "foo")).unwrap();
+        assert_eq!(res[0].name(), "configured-foo");
+
+        // Invalid configurations are rejected, keeping the valid one
+        for (config, err) in [
+            (
+                json!({ "prefix": { "prefix": "" } }),
+                RVPSError::InvalidExtractorConfig("prefix".into()),
+            ),
+            (
+                json!({ "prefix": { "suffix": "-bar" } }),
+                RVPSError::InvalidExtractorConfig("prefix".into()),
+            ),
+            (
+                json!({ "unknown": {} }),
+                RVPSError::UnsupportedProvenanceType("unknown".into()),
+            ),
+        ] {
+            let config: Config = serde_json::from_value(json!({ "extractors": config })).unwrap();
+            let res = e.configure(&config).unwrap_err();
+            assert_eq!(res.downcast_ref::<RVPSError>(), Some(&err));
+        }
+        let res = e.process(Message::new("prefix", "foo")).unwrap();
+        assert_eq!(res[0].name(), "configured-foo");
+
+        // The configuration applies to the overriding Extractor
+        assert!(e.unregister_extractor("prefix"));
+        e.register_extractor(
+            "prefix",
+            configurable(|| PrefixExtractor("default-".into())),
+        )
+        .unwrap();
+        let res = e.process(Message::new("prefix", "foo")).unwrap();
+        assert_eq!(res[0].name(), "configured-foo");
+
+        // A new config replaces the one given before
+        e.configure(&Config::default()).unwrap();
+        let res = e.process(Message::new("prefix", "foo")).unwrap();
+        assert_eq!(res[0].name(), "default-foo");
+
+        // An Extractor requiring configuration is unavailable without it
+        e.register_extractor("required", configured::<PrefixExtractor>())
+            .unwrap();
+        let err = e.process(Message::new("required", "foo")).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RVPSError>(),
+            Some(&RVPSError::InvalidExtractorConfig("required".into()))
+        );
+        let config: Config = serde_json::from_value(
+            json!({ "extractors": { "required": { "prefix": "required-" } } }),
+        )
+        .unwrap();
+        e.configure(&config).unwrap();
+        let res = e.process(Message::new("required", "foo")).unwrap();
+        assert_eq!(res[0].name(), "required-foo");
+    }
+
+    #[test]
+    #[serial]
+    fn extractors_errors() {
+        let mut e = Extractors::new();
+        let message = Message {
+            version: MESSAGE_VERSION.to_string(),
+            payload: generate_in_toto_provenance(),
+            typ: "unknown".into(),
+        };
+        let err = e.process(message).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RVPSError>(),
+            Some(&RVPSError::UnsupportedProvenanceType("unknown".into()))
+        );
+
+        let message = Message {
+            version: MESSAGE_VERSION.to_string(),
+            payload: "{}".into(),
+            typ: "in-toto".into(),
+        };
+        let err = e.process(message).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RVPSError>(),
+            Some(&RVPSError::VerificationFailed)
+        );
     }
 }
@@ -6,18 +6,32 @@
 #![allow(clippy::new_without_default)]
 
 pub mod cache;
+pub mod config;
+pub mod dsse;
+pub mod error;
 pub mod extractors;
 pub mod pre_processor;
 pub mod reference_value;
+pub mod report;
 
-use anyhow::{anyhow, Result};
+use std::path::Path;
+
+use anyhow::Result;
 use cache::Cache;
-use extractors::{Extractors, ExtractorsAPI};
+use extractors::{
+    extractor_modules::{ExtractorInstance, ExtractorInstantiateFunc},
+    ExtractorCapability, Extractors, ExtractorsAPI,
+};
+use log::warn;
 use pre_processor::{PreProcessor, PreProcessorAPI, Ware};
+use report::AuditLog;
 use serde::{Deserialize, Serialize};
 
+pub use config::{BroadcasterConfig, Config};
+pub use error::RVPSError;
 pub use pre_processor::ware;
 pub use reference_value::ReferenceValue;
+pub use report::VerificationReport;
 
 /// Default version of Message
 static MESSAGE_VERSION: &str = "0.1";
@@ -44,13 +58,70 @@ fn default_version() -> String {
     MESSAGE_VERSION.into()
 }
 
+impl Message {
+    /// Create a new Message of the default version.
+    pub fn new(typ: &str, payload: &str) -> Self {
+        Message {
+            version: MESSAGE_VERSION.into(),
+            payload: payload.into(),
+            typ: typ.into(),
+        }
+    }
+
+    /// Set version of the Message.
+    pub fn set_version(mut self, version: &str) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Get version of the Message.
+    pub fn version(&self) -> &String {
+        &self.version
+    }
+
+    /// Get provenance type of the Message.
+    pub fn typ(&self) -> &String {
+        &self.typ
+    }
+
+    /// Get payload of the Message.
+    pub fn payload(&self) -> &String {
+        &self.payload
+    }
+}
+
+/// Capabilities of the RVPS, for clients to know what it accepts.
+/// * `message_versions`: accepted versions of the Message.
+/// * `extractors`: the registered Extractors, with the payload versions
+/// each supports.
+/// * `cache`: name of the active Cache backend.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    pub message_versions: Vec<String>,
+    pub extractors: Vec<ExtractorCapability>,
+    pub cache: String,
+}
+
 /// The interfaces of Reference Value Provider Service
 /// * `verify_and_extract` is responsible for verify a message and
-/// store reference values from it.
+/// store reference values from it. The stored rvs will be returned,
+/// with the report of the verification. If the verification fails,
+/// the report is attached to the error, s.t.
+/// `e.downcast_ref::<VerificationReport>()`.
 /// * `get_rv` gets rv by the artifact's name.
+/// * `list_rvs` gets all the stored rvs.
+/// * `delete_rv` deletes rv by the artifact's name, and returns
+/// the deleted one if any.
+/// * `capabilities` reports what the RVPS accepts.
 pub trait RVPSAPI {
-    fn verify_and_extract(&mut self, message: Message) -> Result<()>;
+    fn verify_and_extract(
+        &mut self,
+        message: Message,
+    ) -> Result<(Vec<ReferenceValue>, VerificationReport)>;
     fn get_rv(&self, name: &str) -> Result<Option<ReferenceValue>>;
+    fn list_rvs(&self) -> Result<Vec<ReferenceValue>>;
+    fn delete_rv(&mut self, name: &str) -> Result<Option<ReferenceValue>>;
+    fn capabilities(&mut self) -> Result<Capabilities>;
 }
 
 /// The core of the RVPS, s.t. componants except communication componants.
@@ -58,6 +129,7 @@ pub struct Core<T: Cache> {
     pre_processor: PreProcessor,
     extractors: Extractors,
     cache: T,
+    audit_log: Option<AuditLog>,
 }
 
 impl<T: Cache> Core<T> {
@@ -71,6 +143,7 @@ impl<T: Cache> Core<T> {
             pre_processor,
             extractors,
             cache,
+            audit_log: None,
         }
     }
 
@@ -79,44 +152,129 @@ impl<T: Cache> Core<T> {
         self.pre_processor.add_ware(ware);
         self
     }
+
+    /// Store the reports of all the verifications, whether succeeded or
+    /// not, in the audit log file at `path`.
+    pub fn set_audit_log<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.audit_log = Some(AuditLog::open(path)?);
+        Ok(())
+    }
+
+    /// Configure the Extractors with their sections of the config file,
+    /// and load the WASM plugins of its `wasm` section. The configuration
+    /// is validated by instantiating the configured Extractors, and
+    /// nothing is changed if any configuration is invalid.
+    pub fn configure_extractors(&mut self, config: &Config) -> Result<()> {
+        self.extractors.configure(config)
+    }
+
+    /// Register the instantiate function of an external Extractor for
+    /// the provenance type `name`. Fails if the type is already
+    /// registered, unless it is unregistered first.
+    pub fn register_extractor(
+        &mut self,
+        name: &str,
+        instantiate_func: ExtractorInstantiateFunc,
+    ) -> Result<()> {
+        self.extractors.register_extractor(name, instantiate_func)
+    }
+
+    /// Register a ready instance of an external Extractor for the
+    /// provenance type `name`. Fails if the type is already registered,
+    /// unless it is unregistered first.
+    pub fn register_extractor_instance(
+        &mut self,
+        name: &str,
+        extractor_instance: ExtractorInstance,
+    ) -> Result<()> {
+        self.extractors
+            .register_extractor_instance(name, extractor_instance)
+    }
+
+    /// Unregister the Extractor for the provenance type `name`, e.g. to
+    /// override a built-in Extractor, and return whether it was
+    /// registered.
+    pub fn unregister_extractor(&mut self, name: &str) -> bool {
+        self.extractors.unregister_extractor(name)
+    }
 }
 
 impl<T: Cache> RVPSAPI for Core<T> {
-    fn verify_and_extract(&mut self, mut message: Message) -> Result<()> {
+    fn verify_and_extract(
+        &mut self,
+        mut message: Message,
+    ) -> Result<(Vec<ReferenceValue>, VerificationReport)> {
         // Judge the version field
         if message.version != MESSAGE_VERSION {
-            return Err(anyhow!(
-                "Version unmatched! Need {}, given {}.",
-                MESSAGE_VERSION,
-                message.version
-            ));
+            return Err(RVPSError::VersionUnmatched {
+                need: MESSAGE_VERSION.into(),
+                given: message.version,
+            }
+            .into());
         }
 
         self.pre_processor.process(&mut message)?;
 
-        let rv = self.extractors.process(message)?;
-        self.cache.set(rv.name().to_string(), rv)?;
-        Ok(())
+        let (rvs, report) = match self.extractors.process_with_report(message) {
+            Ok(res) => res,
+            Err(e) => {
+                if let (Some(audit_log), Some(report)) =
+                    (&mut self.audit_log, e.downcast_ref::<VerificationReport>())
+                {
+                    if let Err(audit_err) = audit_log.record(report) {
+                        warn!("Record the verification report failed: {}", audit_err);
+                    }
+                }
+                return Err(e);
+            }
+        };
+
+        // Nothing is stored unless the report is recorded
+        if let Some(audit_log) = &mut self.audit_log {
+            audit_log.record(&report)?;
+        }
+        for rv in &rvs {
+            self.cache.set(rv.name().to_string(), rv.clone())?;
+        }
+        Ok((rvs, report))
     }
 
     fn get_rv(&self, name: &str) -> Result<Option<ReferenceValue>> {
         self.cache.get(name)
     }
+
+    fn list_rvs(&self) -> Result<Vec<ReferenceValue>> {
+        self.cache.get_all()
+    }
+
+    fn delete_rv(&mut self, name: &str) -> Result<Option<ReferenceValue>> {
+        self.cache.delete(name)
+    }
+
+    fn capabilities(&mut self) -> Result<Capabilities> {
+        Ok(Capabilities {
+            message_versions: vec![MESSAGE_VERSION.into()],
+            extractors: self.extractors.capabilities()?,
+            cache: self.cache.name().into(),
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use anyhow::Result;
     use chrono::{TimeZone, Utc};
     use log::Level;
     use serial_test::serial;
 
     use crate::{
         cache::simple::SimpleCache,
-        extractors::extractor_modules::in_toto::test::{
-            generate_in_toto_provenance, sha256_for_in_toto_test_artifact,
+        extractors::extractor_modules::{
+            in_toto::test::{generate_in_toto_provenance, sha256_for_in_toto_test_artifact},
+            Extractor,
         },
         pre_processor::ware::log::LogWare,
-        Core, Message, ReferenceValue, MESSAGE_VERSION, RVPSAPI,
+        Core, Message, RVPSError, ReferenceValue, VerificationReport, MESSAGE_VERSION, RVPSAPI,
     };
 
     extern crate testing_logger;
@@ -137,7 +295,185 @@ mod tests {
             .set_version("0.1")
             .add_hash_value("sha256".into(), sha256_for_in_toto_test_artifact());
         let res = core.get_rv("foo.tar.gz").unwrap();
-        assert_eq!(res, Some(rv));
+        assert_eq!(res, Some(rv.clone()));
+
+        let all = core.list_rvs().unwrap();
+        assert_eq!(all, vec![rv.clone()]);
+
+        let deleted = core.delete_rv("foo.tar.gz").unwrap();
+        assert_eq!(deleted, Some(rv));
+        assert_eq!(core.get_rv("foo.tar.gz").unwrap(), None);
+        assert_eq!(core.delete_rv("foo.tar.gz").unwrap(), None);
+    }
+
+    #[test]
+    #[serial]
+    fn test_core_with_external_extractor() {
+        struct FixedExtractor;
+
+        impl Extractor for FixedExtractor {
+            fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+                Ok(vec![ReferenceValue::new()
+                    .set_name(provenance)
+                    .set_expired(Utc.ymd(1970, 1, 1).and_hms(0, 0, 0))])
+            }
+        }
+
+        let mut core = Core::new(SimpleCache::new());
+        core.register_extractor("fixed", Box::new(|_| Ok(Box::new(FixedExtractor))))
+            .unwrap();
+        core.verify_and_extract(Message::new("fixed", "foo"))
+            .unwrap();
+        assert!(core.get_rv("foo").unwrap().is_some());
+
+        // Override the built-in in-toto Extractor
+        let err = core
+            .register_extractor_instance("in-toto", Box::new(FixedExtractor))
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RVPSError>(),
+            Some(&RVPSError::DuplicateExtractor("in-toto".into()))
+        );
+        assert!(core.unregister_extractor("in-toto"));
+        core.register_extractor_instance("in-toto", Box::new(FixedExtractor))
+            .unwrap();
+        core.verify_and_extract(Message::new("in-toto", "bar"))
+            .unwrap();
+        assert!(core.get_rv("bar").unwrap().is_some());
+    }
+
+    #[test]
+    #[serial]
+    fn test_core_verification_report() {
+        struct SignedExtractor;
+
+        impl Extractor for SignedExtractor {
+            fn verify_and_extract(&self, provenance: &str) -> Result<Vec<ReferenceValue>> {
+                self.verify_and_extract_with_report(
+                    provenance,
+                    &mut VerificationReport::new("signed"),
+                )
+            }
+
+            fn verify_and_extract_with_report(
+                &self,
+                provenance: &str,
+                report: &mut VerificationReport,
+            ) -> Result<Vec<ReferenceValue>> {
+                report.add_signer("alice");
+                report.check(
+                    "signature",
+                    match provenance {
+                        "bad" => Err(anyhow::anyhow!("Bad signature")),
+                        _ => Ok(()),
+                    },
+                )?;
+                Ok(vec![ReferenceValue::new()
+                    .set_name(provenance)
+                    .set_expired(Utc.ymd(1970, 1, 1).and_hms(0, 0, 0))])
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let audit_path = dir.path().join("audit.log");
+        let mut core = Core::new(SimpleCache::new());
+        core.set_audit_log(&audit_path).unwrap();
+        core.register_extractor_instance("signed", Box::new(SignedExtractor))
+            .unwrap();
+
+        let (rvs, report) = core
+            .verify_and_extract(Message::new("signed", "foo"))
+            .unwrap();
+        assert_eq!(rvs.len(), 1);
+        assert!(report.verified);
+        assert_eq!(report.provenance_type, "signed");
+        assert_eq!(report.signers, vec!["alice"]);
+        assert_eq!(report.reference_values, vec!["foo"]);
+        assert!(report.timestamps.contains_key("verification"));
+        assert!(report.failed_rules.is_empty());
+
+        let err = core
+            .verify_and_extract(Message::new("signed", "bad"))
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RVPSError>(),
+            Some(&RVPSError::VerificationFailed)
+        );
+        let failed = err.downcast_ref::<VerificationReport>().unwrap().clone();
+        assert!(!failed.verified);
+        assert_eq!(failed.failed_rules.len(), 1);
+        assert_eq!(failed.failed_rules[0].rule, "signature");
+        assert_eq!(failed.failed_rules[0].reason, "Bad signature");
+        assert_eq!(core.get_rv("bad").unwrap(), None);
+
+        // Extractors not reporting fail as a whole
+        let err = core
+            .verify_and_extract(Message::new("in-toto", "{}"))
+            .unwrap_err();
+        let unreported = err.downcast_ref::<VerificationReport>().unwrap().clone();
+        assert_eq!(unreported.failed_rules[0].rule, "verification");
+
+        let audited: Vec<VerificationReport> = std::fs::read_to_string(&audit_path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(audited, vec![report, failed, unreported]);
+    }
+
+    #[test]
+    #[serial]
+    fn test_core_capabilities() {
+        struct VersionedExtractor;
+
+        impl Extractor for VersionedExtractor {
+            fn verify_and_extract(&self, _provenance: &str) -> Result<Vec<ReferenceValue>> {
+                Ok(Vec::new())
+            }
+
+            fn payload_versions(&self) -> Vec<String> {
+                vec!["1.0".into()]
+            }
+        }
+
+        let mut core = Core::new(SimpleCache::new());
+        core.register_extractor_instance("versioned", Box::new(VersionedExtractor))
+            .unwrap();
+        core.register_extractor("broken", Box::new(|_| Err(anyhow::anyhow!("Bad plugin."))))
+            .unwrap();
+        let capabilities = core.capabilities().unwrap();
+        assert_eq!(capabilities.message_versions, vec![MESSAGE_VERSION]);
+        assert_eq!(capabilities.cache, "simple");
+
+        let names: Vec<&str> = capabilities
+            .extractors
+            .iter()
+            .map(|extractor| extractor.name.as_str())
+            .collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted);
+
+        let versioned = capabilities
+            .extractors
+            .iter()
+            .find(|extractor| extractor.name == "versioned")
+            .unwrap();
+        assert_eq!(versioned.payload_versions, vec!["1.0"]);
+        assert!(versioned.available);
+        let broken = capabilities
+            .extractors
+            .iter()
+            .find(|extractor| extractor.name == "broken")
+            .unwrap();
+        assert!(!broken.available);
+        assert!(broken.error.as_ref().unwrap().contains("Bad plugin."));
+        let in_toto = capabilities
+            .extractors
+            .iter()
+            .find(|extractor| extractor.name == "in-toto")
+            .unwrap();
+        assert_eq!(in_toto.payload_versions, vec!["0.9"]);
     }
 
     #[test]
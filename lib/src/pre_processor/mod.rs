@@ -16,8 +16,9 @@ use anyhow::*;
 /// A Ware loaded in Pre-Processor will process all the messages passing
 /// through the Pre-Processor. A series of Wares organized in order can
 /// process all the messages in need before they are consumed by the
-/// Extractors.
-pub trait Ware {
+/// Extractors. Wares must be `Send + Sync`, s.t. a `Core` can be
+/// shared among threads, e.g. by a server.
+pub trait Ware: Send + Sync {
     fn handle(
         &self,
         message: &mut Message,
@@ -5,12 +5,20 @@
 
 //! reference value for RVPS
 
+use std::collections::BTreeMap;
+
+use anyhow::Result;
 use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::dsse::{Envelope, Signer, Verifier};
+
 /// Default version of ReferenceValue
 pub const REFERENCE_VALUE_VERSION: &str = "0.1";
 
+/// Payload type of a DSSE envelope which carries a ReferenceValue
+pub const REFERENCE_VALUE_PAYLOAD_TYPE: &str = "application/vnd.rvps.reference-value+json";
+
 /// A HashValuePair stores a hash algorithm name
 /// and relative artifact's hash value due to
 /// the algorithm.
@@ -44,10 +52,14 @@ fn primitive_date_time_from_str<'de, D: Deserializer<'de>>(
     }
     let s = s.unwrap();
 
-    let ndt = NaiveDateTime::parse_from_str(&s, "%Y-%m-%dT%H:%M:%SZ")
-        .map_err(|err| serde::de::Error::custom::<String>(err.to_string()))?;
-
-    Ok(DateTime::<Utc>::from_utc(ndt, Utc))
+    // Fall back to RFC3339, which is used when serializing, s.t.
+    // fractional seconds and offsets are also accepted.
+    match NaiveDateTime::parse_from_str(&s, "%Y-%m-%dT%H:%M:%SZ") {
+        Ok(ndt) => Ok(DateTime::<Utc>::from_utc(ndt, Utc)),
+        Err(_) => DateTime::parse_from_rfc3339(&s)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|err| serde::de::Error::custom::<String>(err.to_string())),
+    }
 }
 
 /// Define Reference Value.
@@ -59,6 +71,8 @@ fn primitive_date_time_from_str<'de, D: Deserializer<'de>>(
 /// * `expired`: expired time for this reference value.
 /// * `hash_value`: A set of key-value pairs, each indicates a hash
 /// algorithm and its relative hash value for the artifact.
+/// * `metadata`: optional information about the artifact carried from
+/// the provenance, e.g. its supplier or version.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct ReferenceValue {
     #[serde(default = "default_version")]
@@ -68,6 +82,8 @@ pub struct ReferenceValue {
     expired: DateTime<Utc>,
     #[serde(rename = "hash-value")]
     hash_value: Vec<HashValuePair>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
 }
 
 /// Set the default version for ReferenceValue
@@ -82,6 +98,7 @@ impl ReferenceValue {
             name: String::new(),
             expired: Utc::now(),
             hash_value: Vec::new(),
+            metadata: BTreeMap::new(),
         }
     }
 
@@ -128,6 +145,33 @@ impl ReferenceValue {
     pub fn name(&self) -> &String {
         &self.name
     }
+
+    /// Add a metadata entry of the artifact, e.g. `supplier`.
+    pub fn add_metadata(mut self, key: &str, value: &str) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Get metadata of the ReferenceValue.
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Sign the ReferenceValue and wrap it into a DSSE envelope.
+    pub fn sign(&self, signer: &Signer) -> Result<Envelope> {
+        let payload = serde_json::to_vec(self)?;
+        Ok(signer.sign(REFERENCE_VALUE_PAYLOAD_TYPE, &payload))
+    }
+
+    /// Verify a signed message published by RVPS, s.t. a JSON
+    /// encoded DSSE envelope, and get the ReferenceValue inside.
+    /// Subscribers (e.g. Attestation Service) should use this
+    /// to authenticate the received reference values.
+    pub fn from_signed_message(message: &str, verifier: &Verifier) -> Result<Self> {
+        let envelope: Envelope = serde_json::from_str(message)?;
+        let payload = verifier.verify(&envelope, REFERENCE_VALUE_PAYLOAD_TYPE)?;
+        Ok(serde_json::from_slice(&payload)?)
+    }
 }
 
 #[cfg(test)]
@@ -135,6 +179,8 @@ mod test {
     use chrono::{TimeZone, Utc};
     use serde_json::json;
 
+    use crate::dsse::{test::generate_ed25519_private_key_pem, Signer, Verifier};
+
     use super::ReferenceValue;
 
     #[test]
@@ -182,4 +228,53 @@ mod test {
         let deserialized_rf: ReferenceValue = serde_json::from_str(&rv_json).unwrap();
         assert_eq!(deserialized_rf, rv);
     }
+
+    #[test]
+    fn reference_value_signed_message() {
+        let rv = ReferenceValue::new()
+            .set_version("1.0")
+            .set_name("artifact")
+            .set_expired(Utc.ymd(1970, 1, 1).and_hms(0, 0, 0))
+            .add_hash_value("sha512".into(), "123".into());
+
+        let signer = Signer::from_pem(&generate_ed25519_private_key_pem()).unwrap();
+        let verifier = Verifier::from_pem(&signer.public_key_pem()).unwrap();
+        let message = serde_json::to_string(&rv.sign(&signer).unwrap()).unwrap();
+
+        let verified_rv = ReferenceValue::from_signed_message(&message, &verifier).unwrap();
+        assert_eq!(verified_rv, rv);
+
+        // A plain reference value is not accepted
+        let plain = serde_json::to_string(&rv).unwrap();
+        assert!(ReferenceValue::from_signed_message(&plain, &verifier).is_err());
+    }
+
+    #[test]
+    fn reference_value_metadata() {
+        let rv = ReferenceValue::new()
+            .set_name("artifact")
+            .set_expired(Utc.ymd(1970, 1, 1).and_hms(0, 0, 0))
+            .add_hash_value("sha256".into(), "123".into())
+            .add_metadata("supplier", "Organization: Alibaba Cloud")
+            .add_metadata("version", "1.0.0");
+
+        let serialized_rf = serde_json::to_value(&rv).unwrap();
+        assert_eq!(
+            serialized_rf["metadata"],
+            json!({
+                "supplier": "Organization: Alibaba Cloud",
+                "version": "1.0.0"
+            })
+        );
+        let deserialized_rf: ReferenceValue = serde_json::from_value(serialized_rf).unwrap();
+        assert_eq!(deserialized_rf, rv);
+    }
+
+    #[test]
+    fn reference_value_roundtrip_fractional_seconds() {
+        let rv = ReferenceValue::new().set_name("artifact");
+        let rv_json = serde_json::to_string(&rv).unwrap();
+        let deserialized_rf: ReferenceValue = serde_json::from_str(&rv_json).unwrap();
+        assert_eq!(deserialized_rf, rv);
+    }
 }
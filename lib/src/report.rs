@@ -0,0 +1,204 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Verification reports of provenance, telling how a provenance is
+//! verified, and audit logs to store them.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Result of a step or an inspection of the supply chain, e.g. a step
+/// of an in-toto layout.
+/// * `name`: name of the step.
+/// * `kind`: kind of the step, e.g. `step` or `inspection`.
+/// * `passed`: whether the step passed the verification.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StepReport {
+    pub name: String,
+    pub kind: String,
+    pub passed: bool,
+}
+
+/// A rule of the verification which the provenance failed.
+/// * `rule`: name of the rule, e.g. `signature` or `builder-id`.
+/// * `reason`: why the rule failed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FailedRule {
+    pub rule: String,
+    pub reason: String,
+}
+
+/// Report of verifying a provenance. Extractors record the details they
+/// know, and the others are left empty.
+/// * `provenance_type`: provenance type of the Message.
+/// * `verified`: whether the provenance is verified.
+/// * `signers`: ids of the keys or identities which signed the provenance.
+/// * `steps`: results of the steps of the supply chain.
+/// * `timestamps`: times related to the provenance, e.g. `verification` for
+/// the time of the verification, or when the provenance expires.
+/// * `warnings`: problems not failing the verification.
+/// * `failed_rules`: rules failed by the provenance.
+/// * `reference_values`: names of the extracted reference values.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct VerificationReport {
+    pub provenance_type: String,
+    pub verified: bool,
+    #[serde(default)]
+    pub signers: Vec<String>,
+    #[serde(default)]
+    pub steps: Vec<StepReport>,
+    #[serde(default)]
+    pub timestamps: BTreeMap<String, DateTime<Utc>>,
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    #[serde(default)]
+    pub failed_rules: Vec<FailedRule>,
+    #[serde(default)]
+    pub reference_values: Vec<String>,
+}
+
+impl VerificationReport {
+    /// Create an empty report of a provenance not verified yet.
+    pub fn new(provenance_type: &str) -> Self {
+        VerificationReport {
+            provenance_type: provenance_type.into(),
+            verified: false,
+            signers: Vec::new(),
+            steps: Vec::new(),
+            timestamps: BTreeMap::new(),
+            warnings: Vec::new(),
+            failed_rules: Vec::new(),
+            reference_values: Vec::new(),
+        }
+    }
+
+    /// Record a signer of the provenance, once for each.
+    pub fn add_signer(&mut self, signer: &str) {
+        if !self.signers.iter().any(|s| s == signer) {
+            self.signers.push(signer.into());
+        }
+    }
+
+    /// Record the result of a step.
+    pub fn add_step(&mut self, name: &str, kind: &str, passed: bool) {
+        self.steps.push(StepReport {
+            name: name.into(),
+            kind: kind.into(),
+            passed,
+        });
+    }
+
+    /// Record a time related to the provenance.
+    pub fn add_timestamp(&mut self, name: &str, time: DateTime<Utc>) {
+        self.timestamps.insert(name.into(), time);
+    }
+
+    /// Record a warning.
+    pub fn add_warning(&mut self, warning: &str) {
+        self.warnings.push(warning.into());
+    }
+
+    /// Record a failed rule and why it failed.
+    pub fn add_failed_rule(&mut self, rule: &str, reason: &str) {
+        self.failed_rules.push(FailedRule {
+            rule: rule.into(),
+            reason: reason.into(),
+        });
+    }
+
+    /// Record the failed rule if `res` is an error, s.t. the rule is
+    /// checked by `res`, and pass `res` through.
+    pub fn check<T>(&mut self, rule: &str, res: Result<T>) -> Result<T> {
+        if let Err(e) = &res {
+            self.add_failed_rule(rule, &format!("{:#}", e));
+        }
+        res
+    }
+}
+
+/// A report is attached to the error of a failed verification, and is
+/// got by `e.downcast_ref::<VerificationReport>()`.
+impl fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rules: Vec<&str> = self.failed_rules.iter().map(|r| r.rule.as_str()).collect();
+        write!(f, "Failed rules: [{}]", rules.join(", "))
+    }
+}
+
+/// `AuditLog` stores verification reports by appending them to a file,
+/// one JSON object in a line.
+pub struct AuditLog {
+    file: File,
+}
+
+impl AuditLog {
+    /// Open the audit log file, which is created if not existing.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(AuditLog { file })
+    }
+
+    /// Append a report to the audit log.
+    pub fn record(&mut self, report: &VerificationReport) -> Result<()> {
+        let mut line = serde_json::to_vec(report)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use anyhow::anyhow;
+    use chrono::{TimeZone, Utc};
+
+    use super::{AuditLog, VerificationReport};
+
+    #[test]
+    fn audit_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+
+        let mut verified = VerificationReport::new("slsa");
+        verified.verified = true;
+        verified.add_signer("alice");
+        verified.add_signer("alice");
+        verified.add_step("build", "step", true);
+        verified.add_timestamp("verified", Utc.ymd(2022, 1, 1).and_hms(0, 0, 0));
+        verified.reference_values.push("foo.tar.gz".into());
+        assert_eq!(verified.signers, vec!["alice"]);
+
+        let mut failed = VerificationReport::new("slsa");
+        assert!(failed
+            .check::<()>("builder-id", Err(anyhow!("Builder bar is not trusted.")))
+            .is_err());
+        assert!(failed.check("signature", Ok(())).is_ok());
+        assert_eq!(failed.failed_rules.len(), 1);
+        assert_eq!(failed.to_string(), "Failed rules: [builder-id]");
+
+        // Reports are appended to the existing log
+        AuditLog::open(&path).unwrap().record(&verified).unwrap();
+        AuditLog::open(&path).unwrap().record(&failed).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        let reports: Vec<VerificationReport> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(reports, vec![verified, failed]);
+    }
+}
@@ -0,0 +1,97 @@
+#!/bin/bash
+# Generate the signed checksum test vectors in this directory.
+# * SHA256SUMS: the checksum file, in the format of sha256sum.
+# * openpgp-{ed25519,rsa}.asc: trusted OpenPGP public keys, and
+#   SHA256SUMS.{ed25519,rsa}.asc the detached signatures by them.
+# * minisign.pub: a trusted minisign public key, SHA256SUMS.minisig
+#   the prehashed signature by it and SHA256SUMS.legacy.minisig the
+#   legacy one.
+# * openpgp-expiring.asc: an OpenPGP key expiring in 2099, and
+#   SHA256SUMS.expiring.asc its signature expiring in 2098, and
+#   SHA256SUMS.sig-expired.asc its signature expired in 2020.
+# * openpgp-{expired,revoked}.asc: an OpenPGP key expired in 2020 and a
+#   revoked one, and SHA256SUMS.{expired,revoked}.asc the signatures
+#   by them.
+#
+# Requires gpg and python3-cryptography.
+set -e
+cd "$(dirname "$0")"
+
+cat > SHA256SUMS <<SUMS
+$(printf 'rvps-0.1.0-x86_64.tar.gz' | sha256sum | cut -d' ' -f1)  rvps-0.1.0-x86_64.tar.gz
+$(printf 'rvps-0.1.0-aarch64.tar.gz' | sha256sum | cut -d' ' -f1) *rvps-0.1.0-aarch64.tar.gz
+$(printf 'rvps-0.1.0.tar.gz' | sha256sum | cut -d' ' -f1)  rvps-0.1.0.tar.gz
+SUMS
+
+GNUPGHOME=$(mktemp -d)
+export GNUPGHOME
+trap 'gpgconf --kill gpg-agent; rm -rf "$GNUPGHOME"' EXIT
+
+for key in ed25519 rsa; do
+    case $key in
+    ed25519) algo=ed25519 ;;
+    rsa) algo=rsa3072 ;;
+    esac
+    gpg --batch --passphrase '' --quick-gen-key "RVPS Test <$key@example.com>" "$algo" sign never
+    gpg --armor --export "$key@example.com" > "openpgp-$key.asc"
+    gpg --batch --yes --armor --digest-algo SHA256 --local-user "$key@example.com" \
+        --output "SHA256SUMS.$key.asc" --detach-sign SHA256SUMS
+done
+
+# Keys and signatures with validity periods, as if made in 2020
+past=(--faked-system-time 20200101T000000 --ignore-time-conflict)
+gpg --batch --passphrase '' "${past[@]}" \
+    --quick-gen-key "RVPS Test <expiring@example.com>" ed25519 sign 20990101T000000
+gpg --armor --export expiring@example.com > openpgp-expiring.asc
+gpg --batch --yes --armor --local-user expiring@example.com --default-sig-expire 20980101T000000 \
+    --output SHA256SUMS.expiring.asc --detach-sign SHA256SUMS
+gpg --batch --yes --armor --local-user expiring@example.com "${past[@]}" --default-sig-expire 1d \
+    --output SHA256SUMS.sig-expired.asc --detach-sign SHA256SUMS
+
+gpg --batch --passphrase '' "${past[@]}" \
+    --quick-gen-key "RVPS Test <expired@example.com>" ed25519 sign 1y
+gpg --armor --export expired@example.com > openpgp-expired.asc
+gpg --batch --yes --armor --local-user expired@example.com "${past[@]}" \
+    --output SHA256SUMS.expired.asc --detach-sign SHA256SUMS
+
+gpg --batch --passphrase '' --quick-gen-key "RVPS Test <revoked@example.com>" ed25519 sign never
+gpg --batch --yes --armor --local-user revoked@example.com \
+    --output SHA256SUMS.revoked.asc --detach-sign SHA256SUMS
+fingerprint=$(gpg --with-colons --list-keys revoked@example.com | awk -F: '/^fpr/ { print $10; exit }')
+sed 's/^:-----BEGIN/-----BEGIN/' "$GNUPGHOME/openpgp-revocs.d/$fingerprint.rev" | gpg --import
+gpg --armor --export revoked@example.com > openpgp-revoked.asc
+
+python3 - <<'PY'
+import base64
+import hashlib
+import os
+
+from cryptography.hazmat.primitives.asymmetric.ed25519 import Ed25519PrivateKey
+from cryptography.hazmat.primitives.serialization import Encoding, PublicFormat
+
+key = Ed25519PrivateKey.generate()
+keyid = os.urandom(8)
+public_key = key.public_key().public_bytes(Encoding.Raw, PublicFormat.Raw)
+
+with open("minisign.pub", "w") as f:
+    f.write("untrusted comment: minisign public key %s\n" % keyid[::-1].hex().upper())
+    f.write(base64.b64encode(b"Ed" + keyid + public_key).decode() + "\n")
+
+with open("SHA256SUMS", "rb") as f:
+    content = f.read()
+
+
+def minisign(algorithm, message, output):
+    signature = key.sign(message)
+    trusted_comment = "timestamp:1661990400\tfile:SHA256SUMS"
+    global_signature = key.sign(signature + trusted_comment.encode())
+    with open(output, "w") as f:
+        f.write("untrusted comment: signature from minisign secret key\n")
+        f.write(base64.b64encode(algorithm + keyid + signature).decode() + "\n")
+        f.write("trusted comment: %s\n" % trusted_comment)
+        f.write(base64.b64encode(global_signature).decode() + "\n")
+
+
+minisign(b"ED", hashlib.blake2b(content).digest(), "SHA256SUMS.minisig")
+minisign(b"Ed", content, "SHA256SUMS.legacy.minisig")
+PY
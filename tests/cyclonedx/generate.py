@@ -0,0 +1,96 @@
+#!/usr/bin/env python3
+# Generate the CycloneDX test vectors in this directory.
+# * es256.pub: the trusted public key.
+# * bom.json: a CycloneDX 1.5 BOM, and bom.json.sig the base64 encoded
+#   detached signature over it.
+# * bom.signed.json: the BOM with an embedded JSF signature.
+#
+# Requires python3-cryptography.
+
+import base64
+import copy
+import json
+import os
+
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.hazmat.primitives.asymmetric import ec
+from cryptography.hazmat.primitives.asymmetric.utils import decode_dss_signature
+
+os.chdir(os.path.dirname(os.path.abspath(__file__)))
+
+BOM = {
+    "bomFormat": "CycloneDX",
+    "specVersion": "1.5",
+    "serialNumber": "urn:uuid:3e671687-395b-41f5-a30f-a58921a69b79",
+    "version": 1,
+    "metadata": {
+        "component": {
+            "type": "container",
+            "bom-ref": "rvps-image",
+            "name": "rvps",
+            "version": "0.1.0",
+            "supplier": {"name": "Alibaba Cloud"},
+            "hashes": [{"alg": "SHA-256", "content": "aa" * 32}],
+        }
+    },
+    "components": [
+        {
+            "type": "library",
+            "bom-ref": "pkg:cargo/ring@0.16.20",
+            "name": "ring",
+            "version": "0.16.20",
+            "purl": "pkg:cargo/ring@0.16.20",
+            "hashes": [
+                {"alg": "SHA-256", "content": "bb" * 32},
+                {"alg": "SHA-512", "content": "cc" * 64},
+                {"alg": "MD5", "content": "dd" * 16},
+            ],
+        },
+        {
+            "type": "application",
+            "name": "busybox",
+            "version": "1.35.0",
+            "components": [
+                {
+                    "type": "file",
+                    "name": "/bin/sh",
+                    "hashes": [{"alg": "SHA3-256", "content": "ee" * 32}],
+                }
+            ],
+        },
+    ],
+}
+
+
+def canonical_json(value):
+    """JSON Canonicalization Scheme (RFC 8785) of ASCII keys and integers"""
+    return json.dumps(value, sort_keys=True, separators=(",", ":"), ensure_ascii=False).encode()
+
+
+def b64url(data):
+    return base64.urlsafe_b64encode(data).rstrip(b"=").decode()
+
+
+key = ec.generate_private_key(ec.SECP256R1())
+with open("es256.pub", "wb") as f:
+    f.write(
+        key.public_key().public_bytes(
+            serialization.Encoding.PEM, serialization.PublicFormat.SubjectPublicKeyInfo
+        )
+    )
+
+# Detached signature over the BOM file
+content = json.dumps(BOM, indent=4).encode() + b"\n"
+with open("bom.json", "wb") as f:
+    f.write(content)
+with open("bom.json.sig", "w") as f:
+    f.write(base64.b64encode(key.sign(content, ec.ECDSA(hashes.SHA256()))).decode() + "\n")
+
+# Embedded JSF signature, over the canonical BOM without the value
+signed = copy.deepcopy(BOM)
+signed["signature"] = {"algorithm": "ES256"}
+r, s = decode_dss_signature(key.sign(canonical_json(signed), ec.ECDSA(hashes.SHA256())))
+signed["signature"]["value"] = b64url(r.to_bytes(32, "big") + s.to_bytes(32, "big"))
+with open("bom.signed.json", "w") as f:
+    json.dump(signed, f, indent=4)
+    f.write("\n")
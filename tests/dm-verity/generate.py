@@ -0,0 +1,80 @@
+#!/usr/bin/env python3
+# Generate the dm-verity test vectors in this directory, in the same
+# way as veritysetup of cryptsetup.
+# * rootfs.img: a random "filesystem" image of 100 KiB.
+# * hash_tree.img: the hash device of rootfs.img by
+#   `veritysetup format --hash sha256 --data-block-size 1024
+#   --hash-block-size 512 --salt <SALT>`, with the superblock.
+# * measurements.json: the expected root hashes with several
+#   parameters.
+
+import hashlib
+import json
+import os
+import random
+import struct
+import uuid
+
+os.chdir(os.path.dirname(os.path.abspath(__file__)))
+
+random.seed(43)
+IMAGE = bytes(random.getrandbits(8) for _ in range(100 * 1024))
+SALT = hashlib.sha256(b"rvps").hexdigest()
+
+
+def build(alg, data_block_size, hash_block_size, salt, hash_type):
+    """Get the root hash and the levels of the hash tree, from bottom."""
+    salt = bytes.fromhex(salt)
+    digest_size = hashlib.new(alg).digest_size
+    digest_size_full = 1 << (digest_size - 1).bit_length()
+    hashes_per_block = 1 << ((hash_block_size // digest_size_full).bit_length() - 1)
+    stride = digest_size_full if hash_type == 1 else digest_size
+
+    def h(block):
+        if hash_type == 1:
+            return hashlib.new(alg, salt + block).digest()
+        return hashlib.new(alg, block + salt).digest()
+
+    blocks = [IMAGE[i:i + data_block_size] for i in range(0, len(IMAGE), data_block_size)]
+    levels = []
+    while len(blocks) > 1:
+        hashes = [h(b) for b in blocks]
+        blocks = []
+        for i in range(0, len(hashes), hashes_per_block):
+            block = b"".join(d.ljust(stride, b"\0") for d in hashes[i:i + hashes_per_block])
+            blocks.append(block.ljust(hash_block_size, b"\0"))
+        levels.append(blocks)
+    return h(blocks[0]).hex(), levels
+
+
+def superblock(alg, data_block_size, hash_block_size, salt, hash_type):
+    salt = bytes.fromhex(salt)
+    sb = b"verity\0\0" + struct.pack("<II", 1, hash_type)
+    sb += uuid.UUID("7f0d8b2a-5d43-4ad4-9a57-2c4f0f6d0c43").bytes
+    sb += alg.encode().ljust(32, b"\0")
+    sb += struct.pack("<IIQH", data_block_size, hash_block_size, len(IMAGE) // data_block_size, len(salt))
+    sb += bytes(6) + salt.ljust(256, b"\0")
+    return sb.ljust(512, b"\0")
+
+
+PARAMS = {
+    "sha256-1024-512": ("sha256", 1024, 512, SALT, 1),
+    "sha1-1024-1024-v0": ("sha1", 1024, 1024, "", 0),
+    "sha512-4096-4096": ("sha512", 4096, 4096, SALT, 1),
+}
+
+measurements = {"salt": SALT}
+for name, params in PARAMS.items():
+    measurements[name], levels = build(*params)
+    if name == "sha256-1024-512":
+        hash_block_size = params[2]
+        tree = superblock(*params).ljust(hash_block_size, b"\0")
+        tree += b"".join(b"".join(level) for level in reversed(levels))
+        with open("hash_tree.img", "wb") as f:
+            f.write(tree)
+
+with open("rootfs.img", "wb") as f:
+    f.write(IMAGE)
+with open("measurements.json", "w") as f:
+    json.dump(measurements, f, indent=2)
+    f.write("\n")
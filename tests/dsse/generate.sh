@@ -0,0 +1,52 @@
+#!/bin/bash
+# Generate the DSSE test vectors in this directory.
+# Each <key>.pub is a trusted public key, and <key>.json is a DSSE
+# envelope of payload.json signed by <key>. multi.json is signed by
+# all the keys.
+set -e
+cd "$(dirname "$0")"
+
+PAYLOAD_TYPE="application/vnd.in-toto+json"
+KEYS="ed25519 ecdsa-p256 ecdsa-p384 rsa-pss"
+
+openssl genpkey -algorithm ed25519 -out ed25519.key
+openssl genpkey -algorithm ec -pkeyopt ec_paramgen_curve:P-256 -out ecdsa-p256.key
+openssl genpkey -algorithm ec -pkeyopt ec_paramgen_curve:P-384 -out ecdsa-p384.key
+openssl genpkey -algorithm rsa -pkeyopt rsa_keygen_bits:2048 -out rsa-pss.key
+
+# PAE(type, body) = "DSSEv1" SP LEN(type) SP type SP LEN(body) SP body
+printf 'DSSEv1 %d %s %d ' "${#PAYLOAD_TYPE}" "$PAYLOAD_TYPE" "$(wc -c < payload.json)" > pae.bin
+cat payload.json >> pae.bin
+
+sign() {
+    case $1 in
+    ed25519) openssl pkeyutl -sign -rawin -inkey ed25519.key -in pae.bin ;;
+    ecdsa-p256) openssl dgst -sha256 -sign ecdsa-p256.key pae.bin ;;
+    ecdsa-p384) openssl dgst -sha384 -sign ecdsa-p384.key pae.bin ;;
+    rsa-pss) openssl dgst -sha256 -sign rsa-pss.key -sigopt rsa_padding_mode:pss \
+        -sigopt rsa_pss_saltlen:digest pae.bin ;;
+    esac
+}
+
+signature() {
+    local keyid sig
+    keyid=$(openssl pkey -in "$1.key" -pubout -outform der | sha256sum | cut -d' ' -f1)
+    sig=$(sign "$1" | base64 -w0)
+    printf '{"keyid":"%s","sig":"%s"}' "$keyid" "$sig"
+}
+
+envelope() {
+    printf '{"payloadType":"%s","payload":"%s","signatures":[%s]}\n' \
+        "$PAYLOAD_TYPE" "$(base64 -w0 payload.json)" "$1"
+}
+
+signatures=()
+for key in $KEYS; do
+    openssl pkey -in "$key.key" -pubout -out "$key.pub"
+    sig=$(signature "$key")
+    envelope "$sig" > "$key.json"
+    signatures+=("$sig")
+done
+envelope "$(IFS=,; echo "${signatures[*]}")" > multi.json
+
+rm -f pae.bin *.key
@@ -0,0 +1,63 @@
+#!/usr/bin/env python3
+# Generate the IMA test vectors in this directory.
+# * ascii_runtime_measurements and binary_runtime_measurements: the
+#   same measurement list of a reference system, in both formats, with
+#   ima-ng and ima-sig entries, a violation and an ima-buf entry.
+
+import hashlib
+import os
+import random
+import struct
+
+os.chdir(os.path.dirname(os.path.abspath(__file__)))
+
+random.seed(42)
+
+
+def rand(n):
+    return bytes(random.getrandbits(8) for _ in range(n))
+
+
+def field(data):
+    return struct.pack("<I", len(data)) + data
+
+
+# template, algorithm, digest, path or buffer, signature
+ENTRIES = [
+    ("ima-ng", "sha256", hashlib.sha256(b"boot_aggregate").digest(), "boot_aggregate", None),
+    ("ima-ng", "sha256", hashlib.sha256(b"bash").digest(), "/usr/bin/bash", None),
+    ("ima-sig", "sha256", hashlib.sha256(b"libc").digest(), "/usr/lib64/libc.so.6",
+     bytes.fromhex("030204") + rand(4) + struct.pack(">H", 64) + rand(64)),
+    ("ima-sig", "sha256", hashlib.sha256(b"config").digest(), "/etc/app/my config.conf", b""),
+    ("ima-ng", "sha1", hashlib.sha1(b"ls").digest(), "/usr/bin/ls", None),
+    # Violation, s.t. the file was opened for write while measured
+    ("ima-ng", "sha256", bytes(32), "/var/log/messages", None),
+    ("ima-buf", "sha256", hashlib.sha256(b"console=ttyS0").digest(), "kexec-cmdline", b"console=ttyS0"),
+    # The same file measured again after an update
+    ("ima-ng", "sha256", hashlib.sha256(b"bash-2").digest(), "/usr/bin/bash", None),
+    ("ima-ng", "sha512", hashlib.sha512(b"bash").digest(), "/usr/bin/bash", None),
+    ("ima-ng", "sha256", hashlib.sha256(b"tmp").digest(), "/tmp/run.sh", None),
+]
+
+ascii_list = ""
+binary_list = b""
+for template, alg, digest, name, extra in ENTRIES:
+    data = field(alg.encode() + b":\0" + digest) + field(name.encode() + b"\0")
+    if extra is not None:
+        data += field(extra)
+
+    violation = not any(digest)
+    template_digest = bytes(20) if violation else hashlib.sha1(data).digest()
+
+    binary_list += struct.pack("<I", 10) + template_digest
+    binary_list += field(template.encode()) + field(data)
+
+    line = "10 %s %s %s:%s %s" % (template_digest.hex(), template, alg, digest.hex(), name)
+    if extra:
+        line += " " + extra.hex()
+    ascii_list += line + "\n"
+
+with open("ascii_runtime_measurements", "w") as f:
+    f.write(ascii_list)
+with open("binary_runtime_measurements", "wb") as f:
+    f.write(binary_list)
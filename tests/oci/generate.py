@@ -0,0 +1,182 @@
+#!/usr/bin/env python3
+# Generate the OCI test vectors in this directory.
+# * index.json: a multi-arch image index of manifest-{amd64,arm64}.json.
+# * cosign.pub: the trusted cosign public key, and index.cosign.{payload,sig}
+#   the cosign signature of the index.
+# * notation_root.pem: the trusted notation root certificate, and
+#   manifest-amd64.jws the notation signature of the amd64 manifest.
+#
+# Requires python3-cryptography.
+
+import base64
+import datetime
+import hashlib
+import json
+import os
+
+from cryptography import x509
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.hazmat.primitives.asymmetric import ec
+from cryptography.hazmat.primitives.asymmetric.utils import decode_dss_signature
+from cryptography.x509.oid import ExtendedKeyUsageOID, NameOID
+
+os.chdir(os.path.dirname(os.path.abspath(__file__)))
+
+REPOSITORY = "registry.example.com/rvps/app"
+
+
+def dump(value):
+    return (json.dumps(value, indent=2) + "\n").encode()
+
+
+def digest(content):
+    return "sha256:" + hashlib.sha256(content).hexdigest()
+
+
+def b64url(data):
+    return base64.urlsafe_b64encode(data).rstrip(b"=").decode()
+
+
+def manifest(arch):
+    config = dump({"architecture": arch, "os": "linux", "rootfs": {"type": "layers"}})
+    layers = [("%s-layer-%d" % (arch, i)).encode() for i in range(2)]
+    return dump(
+        {
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "config": {
+                "mediaType": "application/vnd.oci.image.config.v1+json",
+                "digest": digest(config),
+                "size": len(config),
+            },
+            "layers": [
+                {
+                    "mediaType": "application/vnd.oci.image.layer.v1.tar+gzip",
+                    "digest": digest(layer),
+                    "size": len(layer),
+                }
+                for layer in layers
+            ],
+        }
+    )
+
+
+manifests = {arch: manifest(arch) for arch in ["amd64", "arm64"]}
+for arch, content in manifests.items():
+    with open("manifest-%s.json" % arch, "wb") as f:
+        f.write(content)
+
+index = dump(
+    {
+        "schemaVersion": 2,
+        "mediaType": "application/vnd.oci.image.index.v1+json",
+        "manifests": [
+            {
+                "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "digest": digest(content),
+                "size": len(content),
+                "platform": {"architecture": arch, "os": "linux"},
+            }
+            for arch, content in manifests.items()
+        ],
+    }
+)
+with open("index.json", "wb") as f:
+    f.write(index)
+
+# cosign, s.t. a simple signing payload signed by a key
+cosign_key = ec.generate_private_key(ec.SECP256R1())
+with open("cosign.pub", "wb") as f:
+    f.write(
+        cosign_key.public_key().public_bytes(
+            serialization.Encoding.PEM, serialization.PublicFormat.SubjectPublicKeyInfo
+        )
+    )
+payload = json.dumps(
+    {
+        "critical": {
+            "identity": {"docker-reference": REPOSITORY},
+            "image": {"docker-manifest-digest": digest(index)},
+            "type": "cosign container image signature",
+        },
+        "optional": None,
+    },
+    separators=(",", ":"),
+).encode()
+with open("index.cosign.payload", "wb") as f:
+    f.write(payload)
+with open("index.cosign.sig", "w") as f:
+    f.write(base64.b64encode(cosign_key.sign(payload, ec.ECDSA(hashes.SHA256()))).decode())
+
+# notation, s.t. a JWS signed by a certificate
+root_key = ec.generate_private_key(ec.SECP256R1())
+root_name = x509.Name([x509.NameAttribute(NameOID.COMMON_NAME, "RVPS Test Root")])
+root = (
+    x509.CertificateBuilder()
+    .subject_name(root_name)
+    .issuer_name(root_name)
+    .public_key(root_key.public_key())
+    .serial_number(x509.random_serial_number())
+    .not_valid_before(datetime.datetime(2022, 1, 1))
+    .not_valid_after(datetime.datetime(2042, 1, 1))
+    .add_extension(x509.BasicConstraints(ca=True, path_length=0), critical=True)
+    .sign(root_key, hashes.SHA256())
+)
+leaf_key = ec.generate_private_key(ec.SECP256R1())
+leaf = (
+    x509.CertificateBuilder()
+    .subject_name(x509.Name([x509.NameAttribute(NameOID.COMMON_NAME, "RVPS Test Signer")]))
+    .issuer_name(root_name)
+    .public_key(leaf_key.public_key())
+    .serial_number(x509.random_serial_number())
+    .not_valid_before(datetime.datetime(2022, 1, 1))
+    .not_valid_after(datetime.datetime(2042, 1, 1))
+    .add_extension(x509.BasicConstraints(ca=False, path_length=None), critical=True)
+    .add_extension(x509.ExtendedKeyUsage([ExtendedKeyUsageOID.CODE_SIGNING]), critical=False)
+    .sign(root_key, hashes.SHA256())
+)
+with open("notation_root.pem", "wb") as f:
+    f.write(root.public_bytes(serialization.Encoding.PEM))
+
+amd64 = manifests["amd64"]
+protected = b64url(
+    json.dumps(
+        {
+            "alg": "ES256",
+            "crit": ["io.cncf.notary.signingScheme", "io.cncf.notary.expiry"],
+            "cty": "application/vnd.cncf.notary.payload.v1+json",
+            "io.cncf.notary.expiry": "2032-09-01T00:00:00Z",
+            "io.cncf.notary.signingScheme": "notary.x509",
+            "io.cncf.notary.signingTime": "2022-09-01T00:00:00Z",
+        }
+    ).encode()
+)
+payload = b64url(
+    json.dumps(
+        {
+            "targetArtifact": {
+                "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "digest": digest(amd64),
+                "size": len(amd64),
+            }
+        }
+    ).encode()
+)
+r, s = decode_dss_signature(
+    leaf_key.sign((protected + "." + payload).encode(), ec.ECDSA(hashes.SHA256()))
+)
+jws = {
+    "payload": payload,
+    "protected": protected,
+    "header": {
+        "x5c": [
+            base64.b64encode(c.public_bytes(serialization.Encoding.DER)).decode()
+            for c in [leaf, root]
+        ],
+        "io.cncf.notary.signingAgent": "generate.py",
+    },
+    "signature": b64url(r.to_bytes(32, "big") + s.to_bytes(32, "big")),
+}
+with open("manifest-amd64.jws", "w") as f:
+    json.dump(jws, f, indent=2)
+    f.write("\n")
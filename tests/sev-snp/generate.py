@@ -0,0 +1,242 @@
+#!/usr/bin/env python3
+# Generate the SEV-SNP test vectors in this directory.
+# * ovmf.fd: a minimal firmware with the OVMF footer table and the SEV
+#   metadata, but no code.
+# * vmlinuz, initrd: the kernel and initrd, which are random bytes.
+# * measurements.json: the expected launch measurements of them, by
+#   the same algorithm as sev-snp-measure.
+
+import ctypes
+import hashlib
+import json
+import os
+import random
+import struct
+import uuid
+
+os.chdir(os.path.dirname(os.path.abspath(__file__)))
+
+FOUR_GB = 0x100000000
+PAGE_SIZE = 4096
+VMSA_GPA = 0xFFFFFFFFF000
+
+OVMF_TABLE_FOOTER_GUID = "96b582de-1fb2-45f7-baea-a366c55a082d"
+SEV_HASH_TABLE_RV_GUID = "7255371f-3a3b-4b04-927b-1da6efa8d454"
+SEV_ES_RESET_BLOCK_GUID = "00f771de-1a7e-4fcb-890e-68c77e2fb44e"
+OVMF_SEV_METADATA_GUID = "dc886566-984a-4798-a75e-5585a7bf67cc"
+
+SEV_HASH_TABLE_HEADER_GUID = "9438d606-4f22-4cc9-b479-a793d411fd21"
+SEV_KERNEL_ENTRY_GUID = "4de79437-abd2-427f-b835-d5b172d2045b"
+SEV_INITRD_ENTRY_GUID = "44baf731-3a2f-4bd7-9af1-41e29169781d"
+SEV_CMDLINE_ENTRY_GUID = "97d02dd8-bd20-4c94-aa78-e7714d36ab2a"
+
+SNP_SEC_MEMORY, SNP_SECRETS, CPUID, SNP_KERNEL_HASHES = 1, 2, 3, 0x10
+
+PAGE_TYPE_NORMAL, PAGE_TYPE_VMSA, PAGE_TYPE_ZERO = 1, 2, 3
+PAGE_TYPE_SECRETS, PAGE_TYPE_CPUID = 5, 6
+
+RESET_EIP = 0x80B004
+HASH_TABLE_GPA = 0x80CC00
+METADATA = [
+    (0x800000, 0x9000, SNP_SEC_MEMORY),
+    (0x809000, 0x1000, SNP_SECRETS),
+    (0x80A000, 0x1000, CPUID),
+    (0x80B000, 0x1000, SNP_SEC_MEMORY),
+    (0x80C000, 0x1000, SNP_KERNEL_HASHES),
+]
+
+
+def guid(value):
+    return uuid.UUID(value).bytes_le
+
+
+def table_entry(guid_str, data):
+    return data + struct.pack("<H", len(data) + 18) + guid(guid_str)
+
+
+def generate_ovmf(size):
+    metadata = b"".join(struct.pack("<III", *item) for item in METADATA)
+    metadata = struct.pack("<4sIII", b"ASEV", 16 + len(metadata), 1, len(METADATA)) + metadata
+
+    # The metadata is at 0x1000 from the start of the firmware
+    metadata_offset = 0x1000
+    table = (
+        table_entry(SEV_ES_RESET_BLOCK_GUID, struct.pack("<I", RESET_EIP))
+        + table_entry(SEV_HASH_TABLE_RV_GUID, struct.pack("<II", HASH_TABLE_GPA, 0x400))
+        + table_entry(OVMF_SEV_METADATA_GUID, struct.pack("<I", size - metadata_offset))
+    )
+    footer = table + struct.pack("<H", len(table) + 18) + guid(OVMF_TABLE_FOOTER_GUID)
+
+    rng = random.Random(0)
+    data = bytearray(rng.randbytes(size))
+    data[metadata_offset:metadata_offset + len(metadata)] = metadata
+    end = size - 32
+    data[end - len(footer):end] = footer
+    return bytes(data)
+
+
+class VmcbSeg(ctypes.LittleEndianStructure):
+    _pack_ = 1
+    _fields_ = [
+        ("selector", ctypes.c_uint16),
+        ("attrib", ctypes.c_uint16),
+        ("limit", ctypes.c_uint32),
+        ("base", ctypes.c_uint64),
+    ]
+
+
+class SevEsSaveArea(ctypes.LittleEndianStructure):
+    _pack_ = 1
+    _fields_ = [(name, VmcbSeg) for name in
+                ["es", "cs", "ss", "ds", "fs", "gs", "gdtr", "ldtr", "idtr", "tr"]] + [
+        ("reserved_1", ctypes.c_uint8 * 0x30),
+        ("efer", ctypes.c_uint64),
+        ("reserved_2", ctypes.c_uint8 * 0x70),
+        ("cr4", ctypes.c_uint64),
+        ("reserved_3", ctypes.c_uint64),
+        ("cr0", ctypes.c_uint64),
+        ("dr7", ctypes.c_uint64),
+        ("dr6", ctypes.c_uint64),
+        ("rflags", ctypes.c_uint64),
+        ("rip", ctypes.c_uint64),
+        ("reserved_4", ctypes.c_uint8 * 0xE8),
+        ("g_pat", ctypes.c_uint64),
+        ("reserved_5", ctypes.c_uint8 * 0xA0),
+        ("rdx", ctypes.c_uint64),
+        ("reserved_6", ctypes.c_uint8 * 0x98),
+        ("sev_features", ctypes.c_uint64),
+        ("reserved_7", ctypes.c_uint8 * 0x30),
+        ("xcr0", ctypes.c_uint64),
+        ("reserved_8", ctypes.c_uint8 * 0x18),
+        ("mxcsr", ctypes.c_uint32),
+        ("reserved_9", ctypes.c_uint8 * 4),
+        ("x87_fcw", ctypes.c_uint16),
+        ("reserved_10", ctypes.c_uint8 * (PAGE_SIZE - 0x412)),
+    ]
+
+
+assert SevEsSaveArea.efer.offset == 0xD0
+assert SevEsSaveArea.rip.offset == 0x178
+assert SevEsSaveArea.g_pat.offset == 0x268
+assert SevEsSaveArea.rdx.offset == 0x310
+assert SevEsSaveArea.sev_features.offset == 0x3B0
+assert SevEsSaveArea.xcr0.offset == 0x3E8
+assert SevEsSaveArea.x87_fcw.offset == 0x410
+assert ctypes.sizeof(SevEsSaveArea) == PAGE_SIZE
+
+
+def vmsa_page(eip, sev_features, vcpu_sig):
+    return bytes(SevEsSaveArea(
+        es=VmcbSeg(0, 0x93, 0xFFFF, 0),
+        cs=VmcbSeg(0xF000, 0x9B, 0xFFFF, eip & 0xFFFF0000),
+        ss=VmcbSeg(0, 0x93, 0xFFFF, 0),
+        ds=VmcbSeg(0, 0x93, 0xFFFF, 0),
+        fs=VmcbSeg(0, 0x93, 0xFFFF, 0),
+        gs=VmcbSeg(0, 0x93, 0xFFFF, 0),
+        gdtr=VmcbSeg(0, 0, 0xFFFF, 0),
+        idtr=VmcbSeg(0, 0, 0xFFFF, 0),
+        ldtr=VmcbSeg(0, 0x82, 0xFFFF, 0),
+        tr=VmcbSeg(0, 0x8B, 0xFFFF, 0),
+        efer=0x1000,
+        cr4=0x40,
+        cr0=0x10,
+        dr7=0x400,
+        dr6=0xFFFF0FF0,
+        rflags=0x2,
+        rip=eip & 0xFFFF,
+        g_pat=0x7040600070406,
+        rdx=vcpu_sig,
+        sev_features=sev_features,
+        xcr0=0x1,
+        mxcsr=0x1F80,
+        x87_fcw=0x37F,
+    ))
+
+
+class Gctx:
+    def __init__(self):
+        self.ld = bytes(48)
+
+    def update(self, page_type, gpa, contents):
+        page_info = self.ld + contents + struct.pack("<HBBBBBBQ", 0x70, page_type, 0, 0, 0, 0, 0, gpa)
+        assert len(page_info) == 0x70
+        self.ld = hashlib.sha384(page_info).digest()
+
+    def update_normal_pages(self, gpa, data):
+        for offset in range(0, len(data), PAGE_SIZE):
+            page = data[offset:offset + PAGE_SIZE]
+            self.update(PAGE_TYPE_NORMAL, gpa + offset, hashlib.sha384(page).digest())
+
+    def update_zero_pages(self, gpa, size):
+        for offset in range(0, size, PAGE_SIZE):
+            self.update(PAGE_TYPE_ZERO, gpa + offset, bytes(48))
+
+
+def hash_table_page(kernel, initrd, cmdline):
+    def entry(guid_str, content):
+        return guid(guid_str) + struct.pack("<H", 50) + hashlib.sha256(content).digest()
+
+    cmdline = cmdline.encode() + b"\0" if cmdline else b"\0"
+    table = (entry(SEV_CMDLINE_ENTRY_GUID, cmdline)
+             + entry(SEV_INITRD_ENTRY_GUID, initrd or b"")
+             + entry(SEV_KERNEL_ENTRY_GUID, kernel))
+    table = guid(SEV_HASH_TABLE_HEADER_GUID) + struct.pack("<H", 18 + len(table)) + table
+    table += bytes(-len(table) % 16)
+    offset = HASH_TABLE_GPA & (PAGE_SIZE - 1)
+    return bytes(offset) + table + bytes(PAGE_SIZE - offset - len(table))
+
+
+def launch_digest(ovmf, vcpus, vcpu_sig, kernel=None, initrd=None, cmdline=None):
+    gctx = Gctx()
+    gctx.update_normal_pages(FOUR_GB - len(ovmf), ovmf)
+    for gpa, size, section_type in METADATA:
+        if section_type == SNP_SEC_MEMORY:
+            gctx.update_zero_pages(gpa, size)
+        elif section_type == SNP_SECRETS:
+            gctx.update(PAGE_TYPE_SECRETS, gpa, bytes(48))
+        elif section_type == CPUID:
+            gctx.update(PAGE_TYPE_CPUID, gpa, bytes(48))
+        elif kernel is not None:
+            gctx.update_normal_pages(gpa, hash_table_page(kernel, initrd, cmdline))
+        else:
+            gctx.update_zero_pages(gpa, size)
+
+    for i in range(vcpus):
+        eip = 0xFFFFFFF0 if i == 0 else RESET_EIP
+        page = vmsa_page(eip, 0x1, vcpu_sig)
+        gctx.update(PAGE_TYPE_VMSA, VMSA_GPA, hashlib.sha384(page).digest())
+    return gctx.ld.hex()
+
+
+EPYC_MILAN = 0xA00F11
+EPYC_V4 = 0x800F12
+
+ovmf = generate_ovmf(0x10000)
+rng = random.Random(1)
+kernel = rng.randbytes(0x3000)
+initrd = rng.randbytes(0x1800)
+cmdline = "console=ttyS0 root=/dev/vda"
+
+with open("ovmf.fd", "wb") as f:
+    f.write(ovmf)
+with open("vmlinuz", "wb") as f:
+    f.write(kernel)
+with open("initrd", "wb") as f:
+    f.write(initrd)
+
+measurements = {
+    "ovmf": {
+        "sha384": hashlib.sha384(ovmf).hexdigest(),
+        "EPYC-Milan-1": launch_digest(ovmf, 1, EPYC_MILAN),
+    },
+    "kernel": {
+        "sha256": hashlib.sha256(kernel).hexdigest(),
+        "initrd-sha256": hashlib.sha256(initrd).hexdigest(),
+        "cmdline": cmdline,
+        "EPYC-Milan-4": launch_digest(ovmf, 4, EPYC_MILAN, kernel, initrd, cmdline),
+        "EPYC-v4-2": launch_digest(ovmf, 2, EPYC_V4, kernel, initrd, cmdline),
+    },
+}
+with open("measurements.json", "w") as f:
+    json.dump(measurements, f, indent=2)
+    f.write("\n")
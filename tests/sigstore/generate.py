@@ -0,0 +1,236 @@
+#!/usr/bin/env python3
+# Generate the Sigstore test vectors in this directory, using a fake
+# Fulcio CA and a fake Rekor log.
+# * fulcio_root.pem: the trusted Fulcio root certificate.
+# * rekor.pub: the trusted Rekor public key.
+# * provenance.json: a Sigstore bundle of artifact.txt, with its name.
+#
+# Requires python3-cryptography.
+
+import base64
+import datetime
+import hashlib
+import json
+import os
+
+from cryptography import x509
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.hazmat.primitives.asymmetric import ec
+from cryptography.x509.oid import ExtendedKeyUsageOID, NameOID, ObjectIdentifier
+
+os.chdir(os.path.dirname(os.path.abspath(__file__)))
+
+ARTIFACT = "artifact.txt"
+IDENTITY = "alice@example.com"
+ISSUER = "https://accounts.example.com"
+NOW = datetime.datetime(2022, 9, 1, tzinfo=datetime.timezone.utc)
+INTEGRATED_TIME = int((NOW + datetime.timedelta(minutes=1)).timestamp())
+LOG_INDEX = 1
+TREE_SIZE = 5
+
+# Fulcio OIDC issuer extensions
+OIDC_ISSUER_V1 = ObjectIdentifier("1.3.6.1.4.1.57264.1.1")
+OIDC_ISSUER_V2 = ObjectIdentifier("1.3.6.1.4.1.57264.1.8")
+
+
+def b64(data):
+    return base64.b64encode(data).decode()
+
+
+def canonical_json(value):
+    return json.dumps(value, sort_keys=True, separators=(",", ":")).encode()
+
+
+def name(common_name):
+    return x509.Name(
+        [
+            x509.NameAttribute(NameOID.ORGANIZATION_NAME, "sigstore.dev"),
+            x509.NameAttribute(NameOID.COMMON_NAME, common_name),
+        ]
+    )
+
+
+def ca_certificate(key, subject, issuer, issuer_key, path_length):
+    return (
+        x509.CertificateBuilder()
+        .subject_name(subject)
+        .issuer_name(issuer)
+        .public_key(key.public_key())
+        .serial_number(x509.random_serial_number())
+        .not_valid_before(datetime.datetime(2022, 1, 1))
+        .not_valid_after(datetime.datetime(2032, 1, 1))
+        .add_extension(x509.BasicConstraints(ca=True, path_length=path_length), critical=True)
+        .add_extension(
+            x509.KeyUsage(False, False, False, False, False, True, True, False, False),
+            critical=True,
+        )
+        .sign(issuer_key, hashes.SHA384())
+    )
+
+
+# Fulcio CA
+root_key = ec.generate_private_key(ec.SECP384R1())
+root = ca_certificate(root_key, name("sigstore"), name("sigstore"), root_key, 1)
+intermediate_key = ec.generate_private_key(ec.SECP384R1())
+intermediate = ca_certificate(
+    intermediate_key, name("sigstore-intermediate"), root.subject, root_key, 0
+)
+
+# Short-lived signing certificate
+signing_key = ec.generate_private_key(ec.SECP256R1())
+leaf = (
+    x509.CertificateBuilder()
+    .subject_name(x509.Name([]))
+    .issuer_name(intermediate.subject)
+    .public_key(signing_key.public_key())
+    .serial_number(x509.random_serial_number())
+    .not_valid_before(NOW)
+    .not_valid_after(NOW + datetime.timedelta(minutes=10))
+    .add_extension(
+        x509.SubjectAlternativeName([x509.RFC822Name(IDENTITY)]), critical=True
+    )
+    .add_extension(x509.ExtendedKeyUsage([ExtendedKeyUsageOID.CODE_SIGNING]), critical=False)
+    .add_extension(
+        x509.KeyUsage(True, False, False, False, False, False, False, False, False),
+        critical=True,
+    )
+    .add_extension(
+        x509.UnrecognizedExtension(OIDC_ISSUER_V1, ISSUER.encode()), critical=False
+    )
+    .add_extension(
+        # DER UTF8String
+        x509.UnrecognizedExtension(
+            OIDC_ISSUER_V2, bytes([0x0C, len(ISSUER)]) + ISSUER.encode()
+        ),
+        critical=False,
+    )
+    .sign(intermediate_key, hashes.SHA384())
+)
+
+# Sign the artifact
+with open(ARTIFACT, "rb") as f:
+    artifact = f.read()
+digest = hashlib.sha256(artifact).digest()
+signature = signing_key.sign(artifact, ec.ECDSA(hashes.SHA256()))
+
+# Rekor entry
+rekor_key = ec.generate_private_key(ec.SECP256R1())
+rekor_spki = rekor_key.public_key().public_bytes(
+    serialization.Encoding.DER, serialization.PublicFormat.SubjectPublicKeyInfo
+)
+log_id = hashlib.sha256(rekor_spki).digest()
+leaf_pem = leaf.public_bytes(serialization.Encoding.PEM)
+body = canonical_json(
+    {
+        "apiVersion": "0.0.1",
+        "kind": "hashedrekord",
+        "spec": {
+            "data": {"hash": {"algorithm": "sha256", "value": digest.hex()}},
+            "signature": {
+                "content": b64(signature),
+                "publicKey": {"content": b64(leaf_pem)},
+            },
+        },
+    }
+)
+
+# Signed Entry Timestamp
+set_payload = canonical_json(
+    {
+        "body": b64(body),
+        "integratedTime": INTEGRATED_TIME,
+        "logID": log_id.hex(),
+        "logIndex": LOG_INDEX,
+    }
+)
+signed_entry_timestamp = rekor_key.sign(set_payload, ec.ECDSA(hashes.SHA256()))
+
+
+# RFC 6962 Merkle tree
+def leaf_hash(data):
+    return hashlib.sha256(b"\x00" + data).digest()
+
+
+def node_hash(left, right):
+    return hashlib.sha256(b"\x01" + left + right).digest()
+
+
+def split(n):
+    k = 1
+    while k * 2 < n:
+        k *= 2
+    return k
+
+
+def tree_hash(leaves):
+    if len(leaves) == 1:
+        return leaf_hash(leaves[0])
+    k = split(len(leaves))
+    return node_hash(tree_hash(leaves[:k]), tree_hash(leaves[k:]))
+
+
+def audit_path(m, leaves):
+    if len(leaves) == 1:
+        return []
+    k = split(len(leaves))
+    if m < k:
+        return audit_path(m, leaves[:k]) + [tree_hash(leaves[k:])]
+    return audit_path(m - k, leaves[k:]) + [tree_hash(leaves[:k])]
+
+
+leaves = [b"entry-%d" % i for i in range(TREE_SIZE)]
+leaves[LOG_INDEX] = body
+root_hash = tree_hash(leaves)
+hashes_ = audit_path(LOG_INDEX, leaves)
+
+# Checkpoint, a signed note of the tree head
+note = "rekor.example.com - 1193050959916656506\n%d\n%s\n" % (TREE_SIZE, b64(root_hash))
+note_signature = log_id[:4] + rekor_key.sign(note.encode(), ec.ECDSA(hashes.SHA256()))
+checkpoint = note + "\n— rekor.example.com " + b64(note_signature) + "\n"
+
+bundle = {
+    "mediaType": "application/vnd.dev.sigstore.bundle+json;version=0.1",
+    "verificationMaterial": {
+        "x509CertificateChain": {
+            "certificates": [
+                {"rawBytes": b64(leaf.public_bytes(serialization.Encoding.DER))},
+                {"rawBytes": b64(intermediate.public_bytes(serialization.Encoding.DER))},
+            ]
+        },
+        "tlogEntries": [
+            {
+                "logIndex": str(LOG_INDEX),
+                "logId": {"keyId": b64(log_id)},
+                "kindVersion": {"kind": "hashedrekord", "version": "0.0.1"},
+                "integratedTime": str(INTEGRATED_TIME),
+                "inclusionPromise": {"signedEntryTimestamp": b64(signed_entry_timestamp)},
+                "inclusionProof": {
+                    "logIndex": str(LOG_INDEX),
+                    "rootHash": b64(root_hash),
+                    "treeSize": str(TREE_SIZE),
+                    "hashes": [b64(h) for h in hashes_],
+                    "checkpoint": {"envelope": checkpoint},
+                },
+                "canonicalizedBody": b64(body),
+            }
+        ],
+    },
+    "messageSignature": {
+        "messageDigest": {"algorithm": "SHA2_256", "digest": b64(digest)},
+        "signature": b64(signature),
+    },
+}
+
+with open("provenance.json", "w") as f:
+    json.dump({"artifact": ARTIFACT, "bundle": bundle}, f, indent=4)
+    f.write("\n")
+
+with open("fulcio_root.pem", "wb") as f:
+    f.write(root.public_bytes(serialization.Encoding.PEM))
+
+with open("rekor.pub", "wb") as f:
+    f.write(
+        rekor_key.public_key().public_bytes(
+            serialization.Encoding.PEM, serialization.PublicFormat.SubjectPublicKeyInfo
+        )
+    )
@@ -0,0 +1,119 @@
+#!/usr/bin/env python3
+# Generate the TCG event log test vectors in this directory.
+# * eventlog.bin: a TCG2 crypto-agile event log of a synthetic boot
+#   with shim, grub and kernel, in sha1, sha256, sha384 and sm3_256
+#   banks, where sm3_256 is not supported by the Extractor.
+# * measurements.json: the expected PCRs of each supported bank, and
+#   the digests of the boot applications.
+
+import hashlib
+import json
+import os
+import random
+import struct
+
+os.chdir(os.path.dirname(os.path.abspath(__file__)))
+
+EV_NO_ACTION = 0x3
+EV_SEPARATOR = 0x4
+EV_S_CRTM_VERSION = 0x8
+EV_IPL = 0xD
+EV_EFI_BOOT_SERVICES_APPLICATION = 0x80000003
+EV_EFI_ACTION = 0x80000007
+EV_EFI_PLATFORM_FIRMWARE_BLOB = 0x80000008
+EV_EFI_VARIABLE_DRIVER_CONFIG = 0x800000E0
+
+# TPM algorithm ID, digest size, hashlib name
+BANKS = [
+    (0x0004, 20, "sha1"),
+    (0x000B, 32, "sha256"),
+    (0x000C, 48, "sha384"),
+    (0x0012, 32, None),
+]
+
+random.seed(41)
+
+
+def rand(n):
+    return bytes(random.getrandbits(8) for _ in range(n))
+
+
+def spec_id_event():
+    algorithms = b"".join(struct.pack("<HH", alg, size) for alg, size, _ in BANKS)
+    event = b"Spec ID Event03\0" + struct.pack("<IBBBBI", 0, 0, 2, 0, 2, len(BANKS))
+    event += algorithms + b"\0"
+    return struct.pack("<II", 0, EV_NO_ACTION) + bytes(20) + struct.pack("<I", len(event)) + event
+
+
+def digests_of(data):
+    # The unsupported bank gets random digests, which are never checked
+    return [hashlib.new(name, data).digest() if name else rand(size) for _, size, name in BANKS]
+
+
+def event2(pcr, event_type, digests, data):
+    out = struct.pack("<III", pcr, event_type, len(BANKS))
+    for (alg, _, _), digest in zip(BANKS, digests):
+        out += struct.pack("<H", alg) + digest
+    return out + struct.pack("<I", len(data)) + data
+
+
+def utf16(s):
+    return (s + "\0").encode("utf-16-le")
+
+
+log = spec_id_event()
+events = []  # (pcr, type, digests)
+
+
+def add(pcr, event_type, data, digests=None):
+    global log
+    digests = digests or digests_of(data)
+    log += event2(pcr, event_type, digests, data)
+    if event_type != EV_NO_ACTION:
+        events.append((pcr, event_type, digests))
+    return digests
+
+
+# Startup locality 3, s.t. PCR 0 starts from 0...03
+add(0, EV_NO_ACTION, b"StartupLocality\0\x03", [bytes(size) for _, size, _ in BANKS])
+add(0, EV_S_CRTM_VERSION, utf16("1.0"))
+add(0, EV_EFI_PLATFORM_FIRMWARE_BLOB, struct.pack("<QQ", 0xFFC00000, 0x400000), digests_of(rand(64)))
+add(7, EV_EFI_VARIABLE_DRIVER_CONFIG, rand(40))
+for pcr in range(8):
+    add(pcr, EV_SEPARATOR, bytes(4))
+add(4, EV_EFI_ACTION, b"Calling EFI Application from Boot Option")
+
+applications = {}
+for name in ["shim", "grub", "kernel"]:
+    # The digest of an application is its Authenticode hash, which is
+    # not related to the event data, s.t. the device path
+    applications[name] = add(4, EV_EFI_BOOT_SERVICES_APPLICATION, rand(32), digests_of(rand(256)))
+    if name == "grub":
+        add(8, EV_IPL, b"grub_cmd: linux /vmlinuz root=/dev/vda\0")
+        add(9, EV_IPL, b"/vmlinuz\0")
+add(5, EV_EFI_ACTION, b"Exit Boot Services Invocation")
+add(5, EV_EFI_ACTION, b"Exit Boot Services Returned")
+
+with open("eventlog.bin", "wb") as f:
+    f.write(log)
+
+pcrs = {}
+for index, (_, size, name) in enumerate(BANKS):
+    if name is None:
+        continue
+    for pcr, _, digests in events:
+        init = bytes(size - 1) + (b"\x03" if pcr == 0 else b"\0")
+        value = pcrs.setdefault(str(pcr), {}).get(name)
+        value = bytes.fromhex(value) if value else init
+        pcrs[str(pcr)][name] = hashlib.new(name, value + digests[index]).hexdigest()
+
+measurements = {
+    "pcrs": dict(sorted(pcrs.items(), key=lambda p: int(p[0]))),
+    "events": {
+        name: {bank: digests[i].hex() for i, (_, _, bank) in enumerate(BANKS) if bank}
+        for name, digests in applications.items()
+    },
+}
+with open("measurements.json", "w") as f:
+    json.dump(measurements, f, indent=2)
+    f.write("\n")
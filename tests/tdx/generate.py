@@ -0,0 +1,192 @@
+#!/usr/bin/env python3
+# Generate the TDX test vectors in this directory.
+# * tdvf.fd: a minimal TDVF with the OVMF footer table and the TDX
+#   metadata, but no code.
+# * vmlinuz: a minimal PE image with a certificate table, and initrd
+#   random bytes.
+# * launch.json: the launch description of them, to be signed by the
+#   tests, and measurements.json the expected MRTD and RTMRs.
+
+import hashlib
+import json
+import os
+import random
+import struct
+import uuid
+
+os.chdir(os.path.dirname(os.path.abspath(__file__)))
+
+PAGE_SIZE = 4096
+FOUR_GB = 0x100000000
+
+OVMF_TABLE_FOOTER_GUID = "96b582de-1fb2-45f7-baea-a366c55a082d"
+TDX_METADATA_OFFSET_GUID = "e47a6535-984a-4798-865e-4685a7bf8ec2"
+TDX_METADATA_GUID = "e9eaf9f3-168e-44d5-a8eb-7f4d8738f6ae"
+
+BFV, CFV, TD_HOB, TEMP_MEM, PERM_MEM = 0, 1, 2, 3, 4
+MR_EXTEND, PAGE_AUG = 1, 2
+
+TDVF_SIZE = 0x10000
+# data offset, raw size, GPA, memory size, type, attributes
+SECTIONS = [
+    (0x0, 0x4000, FOUR_GB - TDVF_SIZE, 0x4000, CFV, 0),
+    (0x4000, 0xC000, FOUR_GB - 0xC000, 0xC000, BFV, MR_EXTEND),
+    (0, 0, 0x809000, 0x2000, TD_HOB, 0),
+    (0, 0, 0x80B000, 0x3000, TEMP_MEM, 0),
+    (0, 0, 0x900000, 0x100000, PERM_MEM, 0),
+    (0, 0, 0xA00000, 0x1000, TEMP_MEM, PAGE_AUG),
+]
+
+
+def guid(value):
+    return uuid.UUID(value).bytes_le
+
+
+def generate_tdvf():
+    rng = random.Random(0)
+    data = bytearray(rng.randbytes(TDVF_SIZE))
+
+    sections = b"".join(struct.pack("<IIQQII", *s) for s in SECTIONS)
+    descriptor = struct.pack("<4sIII", b"TDVF", 16 + len(sections), 1, len(SECTIONS)) + sections
+    descriptor_offset = 0x5010
+    data[descriptor_offset - 16:descriptor_offset] = guid(TDX_METADATA_GUID)
+    data[descriptor_offset:descriptor_offset + len(descriptor)] = descriptor
+
+    entry = struct.pack("<I", TDVF_SIZE - descriptor_offset)
+    table = entry + struct.pack("<H", len(entry) + 18) + guid(TDX_METADATA_OFFSET_GUID)
+    footer = table + struct.pack("<H", len(table) + 18) + guid(OVMF_TABLE_FOOTER_GUID)
+    end = TDVF_SIZE - 32
+    data[end - len(footer):end] = footer
+    return bytes(data)
+
+
+def mrtd(tdvf):
+    h = hashlib.sha384()
+    for data_offset, raw_size, gpa, size, section_type, attributes in SECTIONS:
+        if section_type == PERM_MEM or attributes & PAGE_AUG:
+            continue
+        content = tdvf[data_offset:data_offset + raw_size] + bytes(size - raw_size)
+        for offset in range(0, size, PAGE_SIZE):
+            h.update(b"MEM.PAGE.ADD".ljust(16, b"\0") + struct.pack("<Q", gpa + offset) + bytes(104))
+            if attributes & MR_EXTEND:
+                for chunk in range(offset, offset + PAGE_SIZE, 256):
+                    h.update(b"MR.EXTEND".ljust(16, b"\0") + struct.pack("<Q", gpa + chunk) + bytes(104))
+                    h.update(content[chunk:chunk + 256])
+    return h.hexdigest()
+
+
+def generate_pe():
+    rng = random.Random(1)
+    # Sections are listed out of the order in the file
+    text = (0x400, 0x600)
+    data = (0x200, 0x200)
+    headers_size = 0x200
+    trailer = rng.randbytes(0x100)
+    certificate = rng.randbytes(0x80)
+    cert_offset = 0xA00 + len(trailer)
+
+    image = bytearray(rng.randbytes(cert_offset + len(certificate)))
+    image[0:2] = b"MZ"
+    image[0x3C:0x40] = struct.pack("<I", 0x80)
+    image[0x80:0x84] = b"PE\0\0"
+    # COFF header: x86_64, 2 sections, PE32+ optional header with 16 directories
+    image[0x84:0x98] = struct.pack("<HHIIIHH", 0x8664, 2, 0, 0, 0, 240, 0x22)
+    opt = 0x98
+    image[opt:opt + 2] = struct.pack("<H", 0x20B)
+    image[opt + 60:opt + 64] = struct.pack("<I", headers_size)
+    image[opt + 108:opt + 112] = struct.pack("<I", 16)
+    image[opt + 112 + 4 * 8:opt + 112 + 5 * 8] = struct.pack("<II", cert_offset, len(certificate))
+    table = opt + 240
+    for i, (pointer, size) in enumerate([text, data]):
+        name = [b".text", b".data"][i].ljust(8, b"\0")
+        header = name + struct.pack("<IIIIIIHHI", size, 0x1000 * (i + 1), size, pointer, 0, 0, 0, 0, 0)
+        image[table + 40 * i:table + 40 * (i + 1)] = header
+    image[cert_offset:] = certificate
+    return bytes(image)
+
+
+def authenticode(image):
+    opt = struct.unpack_from("<I", image, 0x3C)[0] + 24
+    checksum = opt + 64
+    cert_dir = opt + 112 + 4 * 8
+    headers_size = struct.unpack_from("<I", image, opt + 60)[0]
+    cert_size = struct.unpack_from("<I", image, cert_dir + 4)[0]
+
+    h = hashlib.sha384()
+    h.update(image[:checksum])
+    h.update(image[checksum + 4:cert_dir])
+    h.update(image[cert_dir + 8:headers_size])
+    hashed = headers_size
+    table = opt + struct.unpack_from("<H", image, opt - 4)[0]
+    sections = [struct.unpack_from("<II", image, table + 40 * i + 16) for i in range(2)]
+    for size, pointer in sorted(sections, key=lambda s: s[1]):
+        h.update(image[pointer:pointer + size])
+        hashed += size
+    h.update(image[hashed:len(image) - cert_size])
+    return h.digest()
+
+
+def sha384(data):
+    return hashlib.sha384(data).digest()
+
+
+def replay(events):
+    rtmr = bytes(48)
+    for event in events:
+        rtmr = sha384(rtmr + event)
+    return rtmr.hex()
+
+
+tdvf = generate_tdvf()
+kernel = generate_pe()
+initrd = random.Random(2).randbytes(0x1800)
+cmdline = "console=hvc0 root=/dev/vda initrd=initrd"
+
+with open("tdvf.fd", "wb") as f:
+    f.write(tdvf)
+with open("vmlinuz", "wb") as f:
+    f.write(kernel)
+with open("initrd", "wb") as f:
+    f.write(initrd)
+
+launch = {
+    "name": "td-guest",
+    "tdvf": "sha384:" + hashlib.sha384(tdvf).hexdigest(),
+    "kernel": "sha384:" + hashlib.sha384(kernel).hexdigest(),
+    "initrd": "sha256:" + hashlib.sha256(initrd).hexdigest(),
+    "cmdline": cmdline,
+    "rtmrs": {
+        "0": [{"type": "digest", "value": hashlib.sha384(b"td-hob").hexdigest()}, {"type": "separator"}],
+        "1": [
+            {"type": "kernel"},
+            {"type": "action", "value": "Calling EFI Application from Boot Option"},
+            {"type": "separator"},
+            {"type": "action", "value": "Exit Boot Services Invocation"},
+            {"type": "action", "value": "Exit Boot Services Returned"},
+        ],
+        "2": [{"type": "cmdline"}, {"type": "initrd"}],
+    },
+}
+with open("launch.json", "w") as f:
+    json.dump(launch, f, indent=2)
+    f.write("\n")
+
+separator = sha384(bytes(4))
+measurements = {
+    "mrtd": mrtd(tdvf),
+    "rtmr0": replay([sha384(b"td-hob"), separator]),
+    "rtmr1": replay([
+        authenticode(kernel),
+        sha384(b"Calling EFI Application from Boot Option"),
+        separator,
+        sha384(b"Exit Boot Services Invocation"),
+        sha384(b"Exit Boot Services Returned"),
+    ]),
+    "rtmr2": replay([
+        sha384((cmdline + "\0").encode("utf-16-le")),
+        sha384(initrd),
+    ]),
+}
+with open("measurements.json", "w") as f:
+    json.dump(measurements, f, indent=2)
+    f.write("\n")
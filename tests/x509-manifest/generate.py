@@ -0,0 +1,163 @@
+#!/usr/bin/env python3
+# Generate the X.509 manifest test vectors in this directory.
+# * root.pem: the trusted root CA, which issues an intermediate CA, which
+#   issues the code signing certificates. other_root.pem is an untrusted root.
+# * manifest.json: the manifest of component digests.
+# * manifest.p7s: CMS SignedData of the manifest, attached, by an ECDSA P-256
+#   certificate, with the intermediate CA.
+# * manifest.detached.p7s: CMS SignedData of the manifest, detached, by an RSA
+#   certificate with SHA-384, with the intermediate CA.
+# * manifest.jws: JWS of the manifest in compact serialization, by the ECDSA
+#   P-256 certificate, with the chain in x5c.
+# * manifest.no-eku.jws: JWS by a certificate without the code signing EKU.
+# * manifest.expired.jws: JWS by an expired certificate.
+# * manifest.untrusted.jws: JWS by a certificate issued by other_root.pem.
+#
+# Requires python3-cryptography.
+
+import base64
+import datetime
+import hashlib
+import json
+import os
+
+from cryptography import x509
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.hazmat.primitives.asymmetric import ec, rsa
+from cryptography.hazmat.primitives.asymmetric.utils import decode_dss_signature
+from cryptography.hazmat.primitives.serialization import pkcs7
+from cryptography.x509.oid import ExtendedKeyUsageOID, NameOID
+
+os.chdir(os.path.dirname(os.path.abspath(__file__)))
+
+
+def name(common_name):
+    return x509.Name([x509.NameAttribute(NameOID.COMMON_NAME, common_name)])
+
+
+def certificate(subject, key, issuer, issuer_key, ca, not_after, eku=True, path_length=0):
+    builder = (
+        x509.CertificateBuilder()
+        .subject_name(name(subject))
+        .issuer_name(name(issuer))
+        .public_key(key.public_key())
+        .serial_number(x509.random_serial_number())
+        .not_valid_before(datetime.datetime(2022, 1, 1))
+        .not_valid_after(not_after)
+        .add_extension(x509.BasicConstraints(ca=ca, path_length=path_length if ca else None), critical=True)
+    )
+    if ca:
+        builder = builder.add_extension(
+            x509.KeyUsage(False, False, False, False, False, True, True, False, False),
+            critical=True,
+        )
+    elif eku:
+        builder = builder.add_extension(
+            x509.ExtendedKeyUsage([ExtendedKeyUsageOID.CODE_SIGNING]), critical=False
+        )
+    return builder.sign(issuer_key, hashes.SHA256())
+
+
+def pem(cert):
+    return cert.public_bytes(serialization.Encoding.PEM)
+
+
+def der(cert):
+    return cert.public_bytes(serialization.Encoding.DER)
+
+
+def b64url(data):
+    return base64.urlsafe_b64encode(data).rstrip(b"=").decode()
+
+
+root_key = ec.generate_private_key(ec.SECP384R1())
+root = certificate("RVPS Test Root", root_key, "RVPS Test Root", root_key, True,
+                   datetime.datetime(2042, 1, 1), path_length=1)
+ca_key = ec.generate_private_key(ec.SECP256R1())
+ca = certificate("RVPS Test Firmware CA", ca_key, "RVPS Test Root", root_key, True,
+                 datetime.datetime(2041, 1, 1))
+ec_key = ec.generate_private_key(ec.SECP256R1())
+ec_leaf = certificate("RVPS Test Firmware Signer", ec_key, "RVPS Test Firmware CA", ca_key,
+                      False, datetime.datetime(2040, 1, 1))
+rsa_key = rsa.generate_private_key(65537, 2048)
+rsa_leaf = certificate("RVPS Test Firmware RSA Signer", rsa_key, "RVPS Test Firmware CA",
+                       ca_key, False, datetime.datetime(2039, 1, 1))
+
+other_root_key = ec.generate_private_key(ec.SECP256R1())
+other_root = certificate("RVPS Other Root", other_root_key, "RVPS Other Root",
+                         other_root_key, True, datetime.datetime(2042, 1, 1))
+
+with open("root.pem", "wb") as f:
+    f.write(pem(root))
+with open("other_root.pem", "wb") as f:
+    f.write(pem(other_root))
+
+manifest = (json.dumps(
+    {
+        "expires": "2045-01-01T00:00:00Z",
+        "components": [
+            {
+                "name": "ovmf",
+                "version": "edk2-stable202208",
+                "digests": {
+                    "sha256": hashlib.sha256(b"ovmf").hexdigest(),
+                    "sha384": hashlib.sha384(b"ovmf").hexdigest(),
+                },
+            },
+            {
+                "name": "shim",
+                "digests": {"sha256": hashlib.sha256(b"shim").hexdigest()},
+            },
+        ],
+    },
+    indent=2,
+) + "\n").encode()
+with open("manifest.json", "wb") as f:
+    f.write(manifest)
+
+options = [pkcs7.PKCS7Options.Binary]
+with open("manifest.p7s", "wb") as f:
+    f.write(
+        pkcs7.PKCS7SignatureBuilder()
+        .set_data(manifest)
+        .add_signer(ec_leaf, ec_key, hashes.SHA256())
+        .add_certificate(ca)
+        .sign(serialization.Encoding.DER, options)
+    )
+with open("manifest.detached.p7s", "wb") as f:
+    f.write(
+        pkcs7.PKCS7SignatureBuilder()
+        .set_data(manifest)
+        .add_signer(rsa_leaf, rsa_key, hashes.SHA384())
+        .add_certificate(ca)
+        .sign(serialization.Encoding.DER, options + [pkcs7.PKCS7Options.DetachedSignature])
+    )
+
+
+def jws(key, chain):
+    protected = b64url(json.dumps({
+        "alg": "ES256",
+        "cty": "application/json",
+        "x5c": [base64.b64encode(der(c)).decode() for c in chain],
+    }).encode())
+    payload = b64url(manifest)
+    r, s = decode_dss_signature(
+        key.sign((protected + "." + payload).encode(), ec.ECDSA(hashes.SHA256()))
+    )
+    return "%s.%s.%s\n" % (protected, payload, b64url(r.to_bytes(32, "big") + s.to_bytes(32, "big")))
+
+
+no_eku = certificate("RVPS Test Server", ec_key, "RVPS Test Firmware CA", ca_key, False,
+                     datetime.datetime(2040, 1, 1), eku=False)
+expired = certificate("RVPS Test Expired Signer", ec_key, "RVPS Test Firmware CA", ca_key,
+                      False, datetime.datetime(2023, 1, 1))
+untrusted = certificate("RVPS Test Firmware Signer", ec_key, "RVPS Other Root",
+                        other_root_key, False, datetime.datetime(2040, 1, 1))
+for suffix, chain in [
+    ("", [ec_leaf, ca]),
+    (".no-eku", [no_eku, ca]),
+    (".expired", [expired, ca]),
+    (".untrusted", [untrusted]),
+]:
+    with open("manifest%s.jws" % suffix, "w") as f:
+        f.write(jws(ec_key, chain))